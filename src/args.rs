@@ -1,7 +1,163 @@
-#[derive(clap::ValueEnum, Clone)]
+use serde::Deserialize;
+#[cfg(all(feature = "cli", feature = "toml"))]
+use std::fs;
+#[cfg(feature = "cli")]
+use std::path::PathBuf;
+
+#[cfg(feature = "cli")]
+#[cfg(all(feature = "cli", feature = "toml"))]
+use anyhow::Context;
+#[cfg(feature = "cli")]
+use anyhow::Result;
+
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
 pub enum InputFormat {
     #[cfg(feature = "yaml")]
     Yaml,
     #[cfg(feature = "toml")]
     Toml,
 }
+
+impl InputFormat {
+    /// Maps a file extension to the matching enabled input format
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(InputFormat::Yaml),
+            #[cfg(feature = "toml")]
+            "toml" => Some(InputFormat::Toml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[cfg(feature = "unbound")]
+    Unbound,
+    #[cfg(feature = "nsd")]
+    Nsd,
+}
+
+/// External program that checks the generated zone files
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum ZoneChecker {
+    #[default]
+    NsdCheckzone,
+    NamedCheckzone,
+    Kzonecheck,
+}
+
+/// Checker of the server itself, run on the generated config before it is
+/// written
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfChecker {
+    UnboundCheckconf,
+    NsdCheckconf,
+}
+
+/// Default command-line settings read from `config.toml`
+#[cfg(feature = "cli")]
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CliConfig {
+    pub input: Option<String>,
+    pub input_format: Option<InputFormat>,
+    pub output: Option<String>,
+    pub output_format: Option<OutputFormat>,
+    pub serial: Option<String>,
+    pub no_serial_save: Option<bool>,
+    pub serial_strategy: Option<crate::serial::SerialStrategy>,
+    pub serial_from_git: Option<crate::serial::GitSerial>,
+    pub serial_format: Option<crate::serial::SerialFormat>,
+    pub lock_file: Option<String>,
+    pub lock_timeout: Option<u64>,
+    pub max_zone_records: Option<usize>,
+    pub max_zone_size: Option<usize>,
+    pub unbound_column_width: Option<usize>,
+    pub nsd_column_width: Option<usize>,
+    pub nsd_file_template: Option<String>,
+    pub nsd_zone_dir: Option<String>,
+    pub nsd_conf_dir: Option<String>,
+    pub nsd_split_size: Option<usize>,
+    pub prune_fragments: Option<bool>,
+    pub unbound_private_domains: Option<bool>,
+    pub annotate: Option<bool>,
+    pub reproducible: Option<bool>,
+    /// Checks the zone files with this program after writing them
+    pub verify: Option<ZoneChecker>,
+    /// Checks the generated server config with this program before writing it
+    pub check_with: Option<ConfChecker>,
+}
+
+#[cfg(feature = "cli")]
+impl CliConfig {
+    /// Location of the per-user config: `$XDG_CONFIG_HOME/zonefile-rs/config.toml`,
+    /// falling back to `~/.config/zonefile-rs/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("zonefile-rs").join("config.toml"))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("Cannot read config file {}", path.display()))?;
+        toml::from_str(&raw).map_err(|e| {
+            crate::error::ParseError::new(format!("{}: {}", path.display(), e.message())).into()
+        })
+    }
+
+    #[cfg(not(feature = "toml"))]
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        anyhow::bail!(
+            "{}: reading the config file requires the 'toml' feature",
+            path.display()
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "cli", feature = "toml"))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_load_cli_config() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "input = \"zones.toml\"\ninput-format = \"toml\"\nserial = \"/var/lib/zonefile/serial\"\nunbound-column-width = 60"
+        )
+        .unwrap();
+
+        let config = CliConfig::load(file.path()).unwrap();
+        assert_eq!(config.input.as_deref(), Some("zones.toml"));
+        assert!(matches!(config.input_format, Some(InputFormat::Toml)));
+        assert_eq!(config.serial.as_deref(), Some("/var/lib/zonefile/serial"));
+        assert_eq!(config.unbound_column_width, Some(60));
+        assert_eq!(config.nsd_column_width, None);
+    }
+
+    #[test]
+    fn test_load_cli_config_unknown_key() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "inptu = \"zones.toml\"").unwrap();
+
+        let err = CliConfig::load(file.path()).unwrap_err();
+        assert!(err.to_string().contains("inptu"), "{err}");
+    }
+}
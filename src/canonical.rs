@@ -0,0 +1,389 @@
+use indexmap::IndexMap;
+
+use crate::parser::{
+    CnameEntry, Content, DelegateEntry, HostEntry, HostValue, MxEntry, NameserverEntry,
+    ReverseValue, SingleOrVecValue, StringOrTableValue, ZoneBaseEntry, ZoneWithoutName, Zones,
+};
+
+/// Rewrites a config into its canonical form: zones as a map, hosts, CNAMEs
+/// and SRV records sorted by name, one-element lists as single values and
+/// objects without optional fields in their short string form.
+pub fn canonicalize(content: Content) -> Content {
+    Content {
+        vars: content.vars,
+        origins: content.origins,
+        dropped_zones: content.dropped_zones,
+        defaults: content.defaults.map(|mut defaults| {
+            defaults.mx = defaults.mx.map(|mx| single(mx.to_vec()));
+            defaults.nameserver = defaults.nameserver.map(|ns| single(ns.to_vec()));
+            defaults
+        }),
+        groups: content.groups.map(|groups| {
+            groups
+                .into_iter()
+                .map(|(name, mut group)| {
+                    group.base = canonical_base(group.base);
+                    group.mx = group.mx.map(canonical_mx);
+                    (name, group)
+                })
+                .collect()
+        }),
+        reverse: content.reverse.map(canonical_reverse),
+        unbound: content.unbound,
+        #[cfg(feature = "nsd")]
+        tsig: content.tsig,
+        lint: content.lint,
+        secondary: content.secondary.map(|secondary| {
+            single(
+                secondary
+                    .to_vec()
+                    .into_iter()
+                    .map(|mut entry| {
+                        entry.masters = single(entry.masters.to_vec());
+                        entry
+                    })
+                    .collect(),
+            )
+        }),
+        zone: content.zone.map(|zones| {
+            Zones::Map(
+                zones
+                    .into_vec()
+                    .into_iter()
+                    .map(|zone| {
+                        let (name, zone) = zone.split_name();
+                        (name, canonical_zone(zone))
+                    })
+                    .collect(),
+            )
+        }),
+    }
+}
+
+fn canonical_zone(mut zone: ZoneWithoutName) -> ZoneWithoutName {
+    zone.base = canonical_base(zone.base);
+    zone.mx = zone.mx.map(canonical_mx);
+    zone.hosts = zone.hosts.map(|hosts| {
+        sorted(hosts)
+            .into_iter()
+            .map(|(name, host)| (name, canonical_host(host)))
+            .collect()
+    });
+    zone.cname = zone.cname.map(|cname| {
+        sorted(cname)
+            .into_iter()
+            .map(|(name, cname)| match cname {
+                StringOrTableValue::Table(CnameEntry {
+                    target,
+                    ttl: None,
+                    comment: None,
+                    not_before: None,
+                    not_after: None,
+                    disabled: None,
+                    outputs: None,
+                }) => (name, StringOrTableValue::Entry(target)),
+                StringOrTableValue::Table(mut entry) => {
+                    entry.outputs = entry.outputs.map(|outputs| single(outputs.to_vec()));
+                    (name, StringOrTableValue::Table(entry))
+                }
+                cname => (name, cname),
+            })
+            .collect()
+    });
+    zone.tags = zone.tags.map(|tags| single(tags.to_vec()));
+    zone.outputs = zone.outputs.map(|outputs| single(outputs.to_vec()));
+    zone.pool = zone.pool.map(|pool| {
+        sorted(pool)
+            .into_iter()
+            .map(|(name, members)| (name, single(members.to_vec())))
+            .collect()
+    });
+    zone.services = zone.services.map(|services| {
+        sorted(services)
+            .into_iter()
+            .map(|(service, hosts)| (service, single(hosts.to_vec())))
+            .collect()
+    });
+    zone.srv = zone.srv.map(|mut srv| {
+        srv.0 = sorted(srv.0)
+            .into_iter()
+            .map(|(name, mut entry)| {
+                entry.outputs = entry.outputs.map(|outputs| single(outputs.to_vec()));
+                (name, entry)
+            })
+            .collect();
+        srv
+    });
+    zone.delegate = zone.delegate.map(|delegate| {
+        sorted(delegate)
+            .into_iter()
+            .map(|(child, ns)| (child, canonical_delegate(ns)))
+            .collect()
+    });
+    zone
+}
+
+fn canonical_delegate(
+    ns: SingleOrVecValue<StringOrTableValue<DelegateEntry>>,
+) -> SingleOrVecValue<StringOrTableValue<DelegateEntry>> {
+    single(
+        ns.to_vec()
+            .into_iter()
+            .map(|ns| match ns {
+                StringOrTableValue::Table(DelegateEntry {
+                    name,
+                    ip: None,
+                    ttl: None,
+                }) => StringOrTableValue::Entry(name),
+                StringOrTableValue::Table(mut entry) => {
+                    entry.ip = entry.ip.map(|ip| single(ip.to_vec()));
+                    StringOrTableValue::Table(entry)
+                }
+                ns => ns,
+            })
+            .collect(),
+    )
+}
+
+fn canonical_mx(
+    mx: SingleOrVecValue<StringOrTableValue<MxEntry>>,
+) -> SingleOrVecValue<StringOrTableValue<MxEntry>> {
+    single(
+        mx.to_vec()
+            .into_iter()
+            .map(|mx| match mx {
+                StringOrTableValue::Table(MxEntry {
+                    name,
+                    prio: None,
+                    ttl: None,
+                    comment: None,
+                    not_before: None,
+                    not_after: None,
+                }) => StringOrTableValue::Entry(name),
+                mx => mx,
+            })
+            .collect(),
+    )
+}
+
+fn canonical_host(host: HostValue) -> HostValue {
+    let entry = match host {
+        HostValue::Ip(ip) => return HostValue::Ip(single(ip.to_vec())),
+        HostValue::Entry(entry) => *entry,
+    };
+    match entry {
+        HostEntry {
+            ip: Some(ip),
+            ip6_suffix: None,
+            eui64: None,
+            alias: None,
+            same_as: None,
+            ttl: None,
+            with_ptr: None,
+            ptr_policy: None,
+            alias_mode: None,
+            ptr_primary: None,
+            acme_delegate: None,
+            comment: None,
+            not_before: None,
+            not_after: None,
+            disabled: None,
+            tags: None,
+            outputs: None,
+        } => HostValue::Ip(single(ip.to_vec())),
+        mut entry => {
+            entry.ip = entry.ip.map(|ip| single(ip.to_vec()));
+            entry.alias = entry.alias.map(|alias| single(alias.to_vec()));
+            entry.tags = entry.tags.map(|tags| single(tags.to_vec()));
+            entry.outputs = entry.outputs.map(|outputs| single(outputs.to_vec()));
+            HostValue::Entry(Box::new(entry))
+        }
+    }
+}
+
+fn canonical_base(mut base: ZoneBaseEntry) -> ZoneBaseEntry {
+    base.nameserver = base.nameserver.map(canonical_nameserver);
+    base
+}
+
+fn canonical_nameserver(
+    ns: SingleOrVecValue<StringOrTableValue<NameserverEntry>>,
+) -> SingleOrVecValue<StringOrTableValue<NameserverEntry>> {
+    single(
+        ns.to_vec()
+            .into_iter()
+            .map(|ns| match ns {
+                StringOrTableValue::Table(t) if t.ttl.is_none() => {
+                    StringOrTableValue::Entry(t.name)
+                }
+                ns => ns,
+            })
+            .collect(),
+    )
+}
+
+/// Reverse networks without overrides are written as a plain list
+fn canonical_reverse(reverse: ReverseValue) -> ReverseValue {
+    let entries = reverse.into_entries();
+    if entries.values().all(|entry| {
+        entry.exclude.is_none()
+            && entry.delegate.is_none()
+            && entry.fill_ptr.is_none()
+            && is_empty(&entry.base)
+    }) {
+        ReverseValue::Net(single(entries.into_keys().collect()))
+    } else {
+        ReverseValue::Entry(
+            entries
+                .into_iter()
+                .map(|(net, mut entry)| {
+                    entry.base = canonical_base(entry.base);
+                    entry.exclude = entry.exclude.map(|nets| single(nets.to_vec()));
+                    entry.delegate = entry.delegate.map(|delegate| {
+                        delegate
+                            .into_iter()
+                            .map(|(child, ns)| (child, canonical_nameserver(ns)))
+                            .collect()
+                    });
+                    (net, entry)
+                })
+                .collect(),
+        )
+    }
+}
+
+fn is_empty(base: &ZoneBaseEntry) -> bool {
+    base.serial.is_none()
+        && base.email.is_none()
+        && base.expire.is_none()
+        && base.nameserver.is_none()
+        && base.nrc_ttl.is_none()
+        && base.refresh.is_none()
+        && base.retry.is_none()
+        && base.ttl.is_none()
+        && base.nsd.is_none()
+}
+
+fn single<T>(mut values: Vec<T>) -> SingleOrVecValue<T> {
+    if values.len() == 1 {
+        SingleOrVecValue::Single(values.remove(0))
+    } else {
+        SingleOrVecValue::Multiple(values)
+    }
+}
+
+fn sorted<V>(mut map: IndexMap<String, V>) -> IndexMap<String, V> {
+    map.sort_keys();
+    map
+}
+
+/// Whether a YAML or TOML config has comments, which the canonical form
+/// cannot keep. A `#` starts a comment at the beginning of a line or after
+/// whitespace, unless it is inside a quoted string.
+pub fn has_comments(content: &str) -> bool {
+    content.lines().any(|line| {
+        let mut quote = None;
+        let mut escaped = false;
+        let mut previous = ' ';
+        for c in line.chars() {
+            match quote {
+                Some('"') if escaped => escaped = false,
+                Some('"') if c == '\\' => escaped = true,
+                Some(q) if c == q => quote = None,
+                Some(_) => (),
+                None if c == '#' && previous.is_whitespace() => return true,
+                // A quote only opens a string at the start of a value, not in
+                // the middle of a plain one like `it's`
+                None if matches!(c, '"' | '\'')
+                    && (previous.is_whitespace() || "[{,:=".contains(previous)) =>
+                {
+                    quote = Some(c)
+                }
+                None => (),
+            }
+            previous = c;
+        }
+        false
+    })
+}
+
+#[cfg(test)]
+#[cfg(feature = "yaml")]
+mod tests {
+    use super::*;
+    use crate::args::InputFormat;
+    use crate::parser::{format_config, parse_config};
+
+    fn fmt(yaml: &str) -> String {
+        let content = parse_config(yaml, InputFormat::Yaml).unwrap();
+        format_config(&canonicalize(content), InputFormat::Yaml).unwrap()
+    }
+
+    #[test]
+    fn test_canonicalize_zone() {
+        let yaml = r#"
+zone:
+  - name: example.com
+    nameserver: [{name: ns1.example.com.}]
+    mx: [mail]
+    hosts:
+      www: [192.168.1.2]
+      api: {ip: 192.168.1.3}
+      db: {ip: [192.168.1.4], ttl: 60}
+    cname:
+      web: {target: www}
+      alt: www
+"#;
+        assert_eq!(
+            fmt(yaml),
+            "zone:
+  example.com:
+    nameserver: ns1.example.com.
+    mx: mail
+    hosts:
+      api: 192.168.1.3
+      db:
+        ip: 192.168.1.4
+        ttl: 60
+      www: 192.168.1.2
+    cname:
+      alt: www
+      web: www
+"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_reverse() {
+        assert_eq!(
+            fmt("reverse:\n  192.168.1.0/24: {}\n"),
+            "reverse: 192.168.1.0/24\n"
+        );
+        assert_eq!(
+            fmt("reverse:\n  192.168.1.0/24: {ttl: 60}\n"),
+            "reverse:\n  192.168.1.0/24:\n    ttl: 60\n"
+        );
+        assert_eq!(
+            fmt("reverse:\n  fd00::/64: {ttl: 60}\n"),
+            "reverse:\n  fd00::/64:\n    ttl: 60\n"
+        );
+    }
+
+    #[test]
+    fn test_has_comments() {
+        assert!(has_comments("# zones\nzone: {}\n"));
+        assert!(has_comments("zone:\n  example.com: {} # internal\n"));
+        assert!(has_comments("ttl = 60 # seconds\n"));
+        assert!(!has_comments("zone: {}\n"));
+        assert!(!has_comments("text: 'v=1 #x'\nother: \"a \\\" #b\"\n"));
+        assert!(!has_comments("url: http://example.com/#top\n"));
+        assert!(has_comments("text: it's here # note\n"));
+    }
+
+    #[test]
+    fn test_canonicalize_is_idempotent() {
+        let yaml = std::fs::read_to_string("zones.yaml").unwrap();
+        let once = fmt(&yaml);
+        assert_eq!(fmt(&once), once);
+    }
+}
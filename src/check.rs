@@ -0,0 +1,816 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
+
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::parser::{ForwardZone, ZoneBase, ZoneSet};
+use crate::record::Record;
+use crate::stats::zone_sizes;
+
+/// How a check failure is treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Reported, but the zones are still generated
+    Warning,
+    /// Stops the run
+    Error,
+}
+
+/// A problem found by a check of the resolved zones
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// Name of the check that produced the finding, e.g. `mx-target`
+    pub rule: &'static str,
+    pub zone: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.zone, self.message)
+    }
+}
+
+/// Name that owns a record of a forward zone. NS and MX records are owned by
+/// the zone apex, generated PTRs by the host they point to.
+pub fn owner<'a>(zone: &'a ForwardZone, record: &Record<'a>) -> &'a str {
+    match record {
+        Record::Ns(_) | Record::Mx(_) => &zone.base.name,
+        Record::Delegation(r) => &r.name,
+        Record::Address(r) => &r.name,
+        Record::Cname(r) => &r.name,
+        Record::Srv(r) => &r.name,
+        Record::Txt(r) => &r.name,
+        Record::Ptr(r) => &r.name,
+    }
+}
+
+/// Names defined across all forward zones, lowercased
+struct Names {
+    zones: Vec<String>,
+    addresses: HashSet<String>,
+    cnames: HashSet<String>,
+}
+
+impl Names {
+    fn collect(forward: &[ForwardZone]) -> Self {
+        let lower = |name: &str| name.to_ascii_lowercase();
+        Names {
+            zones: forward.iter().map(|z| lower(&z.base.name)).collect(),
+            addresses: forward
+                .iter()
+                .flat_map(|z| &z.hosts)
+                .map(|h| lower(&h.name))
+                .collect(),
+            cnames: forward
+                .iter()
+                .flat_map(|z| &z.cname)
+                .map(|c| lower(&c.name))
+                .collect(),
+        }
+    }
+
+    fn is_cname(&self, name: &str) -> bool {
+        self.cnames.contains(&name.to_ascii_lowercase())
+    }
+
+    /// Whether the name has an address record, directly or through a wildcard
+    fn has_address(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        if self.addresses.contains(&name) {
+            return true;
+        }
+        let mut parent = name.as_str();
+        while let Some((_, rest)) = parent.split_once('.') {
+            if self.addresses.contains(&format!("*.{rest}")) {
+                return true;
+            }
+            parent = rest;
+        }
+        false
+    }
+
+    /// Whether the name belongs to one of the configured zones
+    fn is_managed(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        self.zones
+            .iter()
+            .any(|zone| name == *zone || name.ends_with(&format!(".{zone}")))
+    }
+}
+
+/// Checks that MX targets inside a configured zone have an A or AAAA record and
+/// that no MX target is a CNAME (RFC 2181, section 10.3). Missing address
+/// records are reported with `missing`, so targets that are served from
+/// elsewhere can be demoted to warnings. Targets outside all configured zones
+/// are not checked.
+pub fn mx_targets(zones: &ZoneSet, missing: Severity) -> Vec<Finding> {
+    let names = Names::collect(&zones.forward);
+    let mut findings = Vec::new();
+    for zone in &zones.forward {
+        for mx in &zone.mx {
+            let target = &mx.name;
+            let finding = |severity, message| Finding {
+                rule: "mx-target",
+                zone: zone.base.name.clone(),
+                severity,
+                message,
+            };
+            if names.is_cname(target) {
+                findings.push(finding(
+                    Severity::Error,
+                    format!("MX target {target} is a CNAME"),
+                ));
+            } else if names.is_managed(target) && !names.has_address(target) {
+                findings.push(finding(
+                    missing,
+                    format!("MX target {target} has no A or AAAA record"),
+                ));
+            }
+        }
+    }
+    findings
+}
+
+/// Checks that no SRV target is a CNAME (RFC 2782) and warns about targets
+/// inside a configured zone without an A or AAAA record. The target `.` means
+/// the service is not provided and is always accepted.
+pub fn srv_targets(zones: &ZoneSet) -> Vec<Finding> {
+    let names = Names::collect(&zones.forward);
+    let mut findings = Vec::new();
+    for zone in &zones.forward {
+        for srv in zone.srv.iter().filter(|srv| srv.target != ".") {
+            let (target, name) = (&srv.target, &srv.name);
+            let finding = |severity, message| Finding {
+                rule: "srv-target",
+                zone: zone.base.name.clone(),
+                severity,
+                message,
+            };
+            if names.is_cname(target) {
+                findings.push(finding(
+                    Severity::Error,
+                    format!("SRV target {target} of {name} is a CNAME"),
+                ));
+            } else if names.is_managed(target) && !names.has_address(target) {
+                findings.push(finding(
+                    Severity::Warning,
+                    format!("SRV target {target} of {name} has no A or AAAA record"),
+                ));
+            }
+        }
+    }
+    findings
+}
+
+/// Checks that no nameserver of a zone is a CNAME (RFC 2181, section 10.3)
+/// and warns about nameservers inside a configured zone without an A or AAAA
+/// record, which resolvers could not reach. Nameservers outside all
+/// configured zones, like vanity names or those of a provider, are not
+/// checked, but a zone all of whose nameservers are is reported, since
+/// nothing here keeps their addresses right.
+pub fn ns_targets(zones: &ZoneSet) -> Vec<Finding> {
+    let names = Names::collect(&zones.forward);
+    let bases = zones
+        .forward
+        .iter()
+        .map(|zone| &zone.base)
+        .chain(zones.reverse.iter().map(|zone| &zone.base));
+    let mut findings = Vec::new();
+    for base in bases {
+        let finding = |severity, message| Finding {
+            rule: "ns-target",
+            zone: base.name.clone(),
+            severity,
+            message,
+        };
+        for target in base.nameserver.iter().map(|ns| &ns.name) {
+            if names.is_cname(target) {
+                findings.push(finding(
+                    Severity::Error,
+                    format!("Nameserver {target} is a CNAME"),
+                ));
+            } else if names.is_managed(target) && !names.has_address(target) {
+                findings.push(finding(
+                    Severity::Warning,
+                    format!("Nameserver {target} has no A or AAAA record"),
+                ));
+            }
+        }
+        if base.nameserver.iter().all(|ns| !names.is_managed(&ns.name)) {
+            findings.push(finding(
+                Severity::Warning,
+                "All nameservers lie outside the configured zones".to_string(),
+            ));
+        }
+    }
+    findings
+}
+
+/// Nameservers outside all configured zones that `lookup` cannot resolve,
+/// e.g. through live DNS. Each name is looked up once.
+pub fn unresolved_nameservers(
+    zones: &ZoneSet,
+    lookup: impl Fn(&str) -> Result<(), String>,
+) -> Vec<Finding> {
+    let names = Names::collect(&zones.forward);
+    let bases = zones
+        .forward
+        .iter()
+        .map(|zone| &zone.base)
+        .chain(zones.reverse.iter().map(|zone| &zone.base));
+    let mut failed: IndexMap<String, Option<String>> = IndexMap::new();
+    let mut findings = Vec::new();
+    for base in bases {
+        for target in base.nameserver.iter().map(|ns| &ns.name) {
+            if names.is_managed(target) {
+                continue;
+            }
+            let error = failed
+                .entry(target.clone())
+                .or_insert_with(|| lookup(target).err());
+            if let Some(error) = error {
+                findings.push(Finding {
+                    rule: "ns-resolve",
+                    zone: base.name.clone(),
+                    severity: Severity::Error,
+                    message: format!("Nameserver {target} does not resolve: {error}"),
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// Recommended range of a SOA timer in seconds and where it is recommended
+struct TimerRange {
+    name: &'static str,
+    range: RangeInclusive<u32>,
+    reference: &'static str,
+}
+
+const TIMER_RANGES: [TimerRange; 4] = [
+    TimerRange {
+        name: "refresh",
+        range: 1200..=43200,
+        reference: "RFC 1912",
+    },
+    TimerRange {
+        name: "retry",
+        range: 180..=43200,
+        reference: "RFC 1912",
+    },
+    TimerRange {
+        name: "expire",
+        range: 1209600..=2419200,
+        reference: "RFC 1912",
+    },
+    TimerRange {
+        name: "nrc-ttl",
+        range: 300..=86400,
+        reference: "RFC 2308",
+    },
+];
+
+/// Warns about SOA timers outside of the recommended ranges, one finding per
+/// timer. The relations between the timers are enforced while resolving.
+pub fn soa_timers(zones: &ZoneSet) -> Vec<Finding> {
+    let bases = zones
+        .forward
+        .iter()
+        .map(|z| &z.base)
+        .chain(zones.reverse.iter().map(|z| &z.base));
+
+    let mut findings = Vec::new();
+    for base in bases {
+        let ZoneBase {
+            refresh,
+            retry,
+            expire,
+            nrc_ttl,
+            ..
+        } = *base;
+        for (timer, value) in TIMER_RANGES.iter().zip([refresh, retry, expire, nrc_ttl]) {
+            if !timer.range.contains(&value) {
+                findings.push(Finding {
+                    rule: "soa-timers",
+                    zone: base.name.clone(),
+                    severity: Severity::Warning,
+                    message: format!(
+                        "{} {value} is outside the range {}-{} recommended by {}",
+                        timer.name,
+                        timer.range.start(),
+                        timer.range.end(),
+                        timer.reference
+                    ),
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// RFC 1918 IPv4 or unique local (RFC 4193) IPv6 address
+fn is_private(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private(),
+        IpAddr::V6(ip) => ip.is_unique_local(),
+    }
+}
+
+/// Warns about private addresses in zones marked `public`, which would leak
+/// the internal network layout
+pub fn private_addresses(zones: &ZoneSet) -> Vec<Finding> {
+    zones
+        .forward
+        .iter()
+        .filter(|zone| zone.public)
+        .flat_map(|zone| {
+            zone.hosts
+                .iter()
+                .filter(|host| is_private(&host.ip))
+                .map(|host| Finding {
+                    rule: "private-address",
+                    zone: zone.base.name.clone(),
+                    severity: Severity::Warning,
+                    message: format!("{} has the private address {}", host.name, host.ip),
+                })
+        })
+        .collect()
+}
+
+/// Special-use domain (RFC 6761) and why zones below it are a problem, if they are
+struct SpecialUse {
+    suffix: &'static str,
+    problem: Option<&'static str>,
+}
+
+const SPECIAL_USE: [SpecialUse; 7] = [
+    SpecialUse {
+        suffix: "home.arpa.",
+        problem: None,
+    },
+    SpecialUse {
+        suffix: "internal.",
+        problem: None,
+    },
+    SpecialUse {
+        suffix: "test.",
+        problem: None,
+    },
+    SpecialUse {
+        suffix: "local.",
+        problem: Some("collides with multicast DNS (RFC 6762)"),
+    },
+    SpecialUse {
+        suffix: "localhost.",
+        problem: Some("is reserved for the loopback address (RFC 6761)"),
+    },
+    SpecialUse {
+        suffix: "invalid.",
+        problem: Some("never resolves (RFC 6761)"),
+    },
+    SpecialUse {
+        suffix: "onion.",
+        problem: Some("is only resolved by Tor (RFC 7686)"),
+    },
+];
+
+fn find_special_use(name: &str) -> Option<&'static SpecialUse> {
+    let name = name.to_ascii_lowercase();
+    SPECIAL_USE
+        .iter()
+        .find(|domain| name == domain.suffix || name.ends_with(&format!(".{}", domain.suffix)))
+}
+
+/// Special-use domain like `home.arpa.` a fully qualified name belongs to
+pub fn special_use_domain(name: &str) -> Option<&'static str> {
+    find_special_use(name).map(|domain| domain.suffix)
+}
+
+/// Warns about zones below special-use domains that do not work like regular
+/// DNS, e.g. `.local`, which clients resolve with multicast DNS
+pub fn special_use(zones: &ZoneSet) -> Vec<Finding> {
+    zones
+        .forward
+        .iter()
+        .filter_map(|zone| {
+            let domain = find_special_use(&zone.base.name)?;
+            Some(Finding {
+                rule: "special-use",
+                zone: zone.base.name.clone(),
+                severity: Severity::Warning,
+                message: format!(
+                    "Zone is below .{}, which {}",
+                    domain.suffix.trim_end_matches('.'),
+                    domain.problem?
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Warns about names defined in more than one zone, e.g. a host `www.iot` in
+/// `example.com` and a host `www` in `iot.example.com`. Which definition is
+/// answered depends on the zone the server picks, so one of them is shadowed.
+/// The finding is reported for the first zone defining the name.
+pub fn cross_zone_duplicates(zones: &ZoneSet) -> Vec<Finding> {
+    let mut sites: IndexMap<String, IndexMap<&str, Vec<&str>>> = IndexMap::new();
+    for zone in &zones.forward {
+        for record in zone.records() {
+            if matches!(record, Record::Ns(_) | Record::Mx(_) | Record::Ptr(_)) {
+                continue;
+            }
+            let name = owner(zone, &record);
+            let types = sites
+                .entry(name.to_ascii_lowercase())
+                .or_default()
+                .entry(&zone.base.name)
+                .or_default();
+            if !types.contains(&record.record_type()) {
+                types.push(record.record_type());
+            }
+        }
+    }
+
+    sites
+        .into_iter()
+        .filter(|(_, zones)| zones.len() > 1)
+        .map(|(name, zones)| {
+            let (first, _) = zones.first().expect("defined in two zones");
+            let sites: Vec<String> = zones
+                .iter()
+                .map(|(zone, types)| format!("{zone} ({})", types.join(", ")))
+                .collect();
+            Finding {
+                rule: "cross-zone-duplicate",
+                zone: first.to_string(),
+                severity: Severity::Warning,
+                message: format!("{name} is defined in {}", sites.join(" and ")),
+            }
+        })
+        .collect()
+}
+
+/// TTLs outside this range are reported by `ttl_advice`
+const ADVISED_TTL: RangeInclusive<u32> = 60..=604800;
+
+/// Warns about record TTLs below a minute, which mostly add query load, and
+/// above a week, which make changes take very long to reach the resolvers
+pub fn ttl_advice(zones: &ZoneSet) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for zone in &zones.forward {
+        // Generated PTRs share the TTL of their address record
+        let records = zone.records().filter(|r| !matches!(r, Record::Ptr(_)));
+        for record in records.filter(|r| !ADVISED_TTL.contains(&r.ttl())) {
+            let advice = if record.ttl() < *ADVISED_TTL.start() {
+                "below a minute, which mostly adds query load"
+            } else {
+                "above a week, which delays changes"
+            };
+            findings.push(Finding {
+                rule: "ttl-advice",
+                zone: zone.base.name.clone(),
+                severity: Severity::Warning,
+                message: format!(
+                    "TTL {} of {} {} is {advice}",
+                    record.ttl(),
+                    owner(zone, &record),
+                    record.record_type()
+                ),
+            });
+        }
+    }
+    findings
+}
+
+/// Upper bounds for the size of a single zone
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZoneLimits {
+    pub max_records: Option<usize>,
+    /// Estimated zone file size in bytes
+    pub max_bytes: Option<usize>,
+}
+
+/// Fails zones that exceed the limits, e.g. because an import source
+/// suddenly produced far more records than usual
+pub fn zone_limits(zones: &ZoneSet, limits: ZoneLimits) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for size in zone_sizes(&zones.forward, &zones.reverse) {
+        let finding = |message| Finding {
+            rule: "zone-size",
+            zone: size.zone.clone(),
+            severity: Severity::Error,
+            message,
+        };
+        if let Some(max) = limits.max_records.filter(|max| size.records > *max) {
+            findings.push(finding(format!(
+                "{} records exceed the limit of {max}",
+                size.records
+            )));
+        }
+        if let Some(max) = limits.max_bytes.filter(|max| size.bytes > *max) {
+            findings.push(finding(format!(
+                "Estimated size of {} bytes exceeds the limit of {max}",
+                size.bytes
+            )));
+        }
+    }
+    findings
+}
+
+/// Compares two serials with RFC 1982 serial number arithmetic. `None` if the
+/// serials are exactly 2^31 apart, where the order is undefined.
+pub fn serial_cmp(a: u32, b: u32) -> Option<Ordering> {
+    const HALF: u32 = 1 << 31;
+    match b.wrapping_sub(a) {
+        0 => Some(Ordering::Equal),
+        HALF => None,
+        distance if distance < HALF => Some(Ordering::Less),
+        _ => Some(Ordering::Greater),
+    }
+}
+
+/// Checks that explicitly set zone serials do not move backwards from the
+/// previously saved serial. Secondaries ignore a zone whose serial is not
+/// greater than the one they have, so the update would silently get lost.
+pub fn serial_backwards(zones: &ZoneSet, previous: u32, severity: Severity) -> Vec<Finding> {
+    let bases = zones
+        .forward
+        .iter()
+        .map(|z| &z.base)
+        .chain(zones.reverse.iter().map(|z| &z.base));
+    bases
+        .filter(|base| base.serial != zones.serial)
+        .filter(|base| matches!(serial_cmp(base.serial, previous), Some(Ordering::Less) | None))
+        .map(|base| Finding {
+            rule: "serial-backwards",
+            zone: base.name.clone(),
+            severity,
+            message: format!(
+                "Serial {} is behind the saved serial {previous}, secondaries would ignore the update",
+                base.serial
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[cfg(feature = "yaml")]
+mod tests {
+    use super::*;
+    use crate::args::InputFormat;
+    use crate::parser::parse;
+
+    fn zones(zone: &str) -> ZoneSet {
+        let yaml = format!(
+            "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n{zone}"
+        );
+        parse(&yaml, 2025060100, InputFormat::Yaml).unwrap()
+    }
+
+    #[test]
+    fn test_mx_targets_resolved() {
+        let zones = zones(
+            "    mx: [mail, mx.example.net., host.apps]\n    hosts:\n      mail: 192.168.1.3\n      \"*.apps\": 192.168.1.4\n",
+        );
+        assert!(mx_targets(&zones, Severity::Error).is_empty());
+    }
+
+    #[test]
+    fn test_mx_target_missing() {
+        let zones = zones("    mx: MAIL\n");
+        let findings = mx_targets(&zones, Severity::Warning);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(
+            findings[0].to_string(),
+            "example.com.: MX target mail.example.com. has no A or AAAA record"
+        );
+    }
+
+    #[test]
+    fn test_mx_target_cname() {
+        let zones = zones(
+            "    mx: mail\n    hosts:\n      server: 192.168.1.3\n    cname:\n      mail: server\n",
+        );
+        let findings = mx_targets(&zones, Severity::Warning);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert!(findings[0].message.contains("is a CNAME"));
+    }
+
+    #[test]
+    fn test_srv_targets() {
+        let zones = zones(
+            "    hosts:\n      server: 192.168.1.3\n    cname:\n      alias: server\n    srv:\n      _http._tcp:\n        port: 80\n        target: server\n      _ldap._tcp:\n        port: 389\n        target: alias\n      _imap._tcp:\n        port: 0\n        target: .\n      _xmpp._tcp:\n        port: 5222\n        target: chat\n",
+        );
+        let findings: Vec<(Severity, String)> = srv_targets(&zones)
+            .into_iter()
+            .map(|f| (f.severity, f.message))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    Severity::Error,
+                    "SRV target alias.example.com. of _ldap._tcp.example.com. is a CNAME"
+                        .to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "SRV target chat.example.com. of _xmpp._tcp.example.com. has no A or AAAA record"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ns_targets() {
+        assert!(ns_targets(&zones("    hosts:\n      ns1: 192.168.1.1\n")).is_empty());
+
+        let zones = zones(
+            "    nameserver: [ns1, ns2, 'ns.example.net. 1d']\n    hosts:\n      server: 192.168.1.3\n    cname:\n      ns2: server\n",
+        );
+        let findings: Vec<(Severity, String)> = ns_targets(&zones)
+            .into_iter()
+            .map(|f| (f.severity, f.message))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    Severity::Warning,
+                    "Nameserver ns1.example.com. has no A or AAAA record".to_string()
+                ),
+                (
+                    Severity::Error,
+                    "Nameserver ns2.example.com. is a CNAME".to_string()
+                ),
+            ]
+        );
+        assert_eq!(zones.forward[0].base.nameserver[2].ttl, 86400);
+    }
+
+    #[test]
+    fn test_vanity_nameservers() {
+        let zones = zones("    nameserver: [ns1.example.net., ns2.example.org.]\n");
+        let messages: Vec<String> = ns_targets(&zones).iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            messages,
+            ["example.com.: All nameservers lie outside the configured zones"]
+        );
+
+        let lookups = std::cell::RefCell::new(Vec::new());
+        let findings = unresolved_nameservers(&zones, |name| {
+            lookups.borrow_mut().push(name.to_string());
+            match name {
+                "ns1.example.net." => Ok(()),
+                _ => Err("no address".to_string()),
+            }
+        });
+        assert_eq!(
+            lookups.into_inner(),
+            ["ns1.example.net.", "ns2.example.org."]
+        );
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(
+            findings[0].message,
+            "Nameserver ns2.example.org. does not resolve: no address"
+        );
+    }
+
+    #[test]
+    fn test_soa_timers() {
+        assert!(soa_timers(&zones("    ttl: 3600\n")).is_empty());
+
+        let zones = zones("    refresh: 600\n    retry: 60\n    expire: 86400\n");
+        let messages: Vec<String> = soa_timers(&zones).iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "example.com.: refresh 600 is outside the range 1200-43200 recommended by RFC 1912",
+                "example.com.: retry 60 is outside the range 180-43200 recommended by RFC 1912",
+                "example.com.: expire 86400 is outside the range 1209600-2419200 recommended by RFC 1912",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_private_addresses() {
+        let hosts = "    hosts:\n      www: [203.0.113.5, 2001:db8::5]\n      db: [10.0.0.5, fd00::5]\n      intra: 172.16.0.1\n";
+        assert!(private_addresses(&zones(hosts)).is_empty());
+
+        let findings = private_addresses(&zones(&format!("    public: true\n{hosts}")));
+        let messages: Vec<String> = findings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "example.com.: db.example.com. has the private address 10.0.0.5",
+                "example.com.: db.example.com. has the private address fd00::5",
+                "example.com.: intra.example.com. has the private address 172.16.0.1",
+            ]
+        );
+        assert!(findings.iter().all(|f| f.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn test_special_use() {
+        assert_eq!(special_use_domain("nas.Home.Arpa."), Some("home.arpa."));
+        assert_eq!(special_use_domain("internal."), Some("internal."));
+        assert_eq!(special_use_domain("notinternal."), None);
+
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  office.local: {}\n  lab.home.arpa: {}\n  example.com: {}\n";
+        let zones = parse(yaml, 2025060100, InputFormat::Yaml).unwrap();
+        let messages: Vec<String> = special_use(&zones)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            messages,
+            ["office.local.: Zone is below .local, which collides with multicast DNS (RFC 6762)"]
+        );
+    }
+
+    #[test]
+    fn test_cross_zone_duplicates() {
+        let zones = zones(
+            "    hosts:\n      www.iot: 192.168.1.2\n      mail: 192.168.1.3\n  iot.example.com:\n    hosts:\n      WWW: [192.168.2.2, 2001:db8::2]\n    cname:\n      cam: mail.example.com.\n",
+        );
+        let messages: Vec<String> = cross_zone_duplicates(&zones)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            messages,
+            ["example.com.: www.iot.example.com. is defined in example.com. (A) and iot.example.com. (A, AAAA)"]
+        );
+    }
+
+    #[test]
+    fn test_zone_limits() {
+        let zones = zones("    hosts:\n      www: [192.168.1.2, 192.168.1.3]\n");
+        assert!(zone_limits(&zones, ZoneLimits::default()).is_empty());
+
+        let limits = ZoneLimits {
+            max_records: Some(3),
+            max_bytes: Some(100),
+        };
+        let messages: Vec<String> = zone_limits(&zones, limits)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "example.com.: 4 records exceed the limit of 3",
+                "example.com.: Estimated size of 218 bytes exceeds the limit of 100",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serial_cmp() {
+        assert_eq!(serial_cmp(1, 2), Some(Ordering::Less));
+        assert_eq!(serial_cmp(2, 2), Some(Ordering::Equal));
+        assert_eq!(serial_cmp(u32::MAX, 1), Some(Ordering::Less));
+        assert_eq!(serial_cmp(1, u32::MAX), Some(Ordering::Greater));
+        assert_eq!(serial_cmp(0, 1 << 31), None);
+    }
+
+    #[test]
+    fn test_serial_backwards() {
+        let zones = zones("    serial: 2025010101\n  newer.example.com:\n    serial: 2025070101\n");
+        assert!(serial_backwards(&zones, 2025010101, Severity::Error).is_empty());
+
+        let findings = serial_backwards(&zones, 2025060100, Severity::Error);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].to_string(),
+            "example.com.: Serial 2025010101 is behind the saved serial 2025060100, secondaries would ignore the update"
+        );
+    }
+
+    #[test]
+    fn test_ttl_advice() {
+        let zones = zones(
+            "    ttl: 30\n    hosts:\n      www:\n        ip: 192.168.1.2\n        ttl: 3600\n      static:\n        ip: 192.168.1.3\n        ttl: 1209600\n",
+        );
+        let messages: Vec<String> = ttl_advice(&zones).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "example.com.: TTL 30 of example.com. NS is below a minute, which mostly adds query load",
+                "example.com.: TTL 1209600 of static.example.com. A is above a week, which delays changes",
+            ]
+        );
+    }
+}
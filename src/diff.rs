@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::parser::ZoneSet;
+use crate::report::{record_rows, RecordRow};
+
+/// A record present in both zone sets with a different TTL or, for records
+/// that exist once per name (SOA, CNAME), different data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordChange {
+    pub old: RecordRow,
+    pub new: RecordRow,
+}
+
+/// Record changes of one zone. A zone that only exists on one side has all its
+/// records added or removed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ZoneChanges {
+    pub zone: String,
+    pub added: Vec<RecordRow>,
+    pub removed: Vec<RecordRow>,
+    pub changed: Vec<RecordChange>,
+}
+
+impl ZoneChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Semantic difference between two zone sets, ordered by zone name
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ZoneDiff {
+    /// Zones with at least one change
+    pub zones: Vec<ZoneChanges>,
+}
+
+impl ZoneDiff {
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+}
+
+fn unique_per_name(record_type: &str) -> bool {
+    matches!(record_type, "SOA" | "CNAME")
+}
+
+fn same_record(a: &RecordRow, b: &RecordRow) -> bool {
+    a.name == b.name
+        && a.record_type == b.record_type
+        && (unique_per_name(a.record_type) || a.data == b.data)
+}
+
+fn by_zone(zones: &ZoneSet) -> BTreeMap<String, Vec<RecordRow>> {
+    let mut map: BTreeMap<String, Vec<RecordRow>> = BTreeMap::new();
+    for row in record_rows(&zones.forward, &zones.reverse) {
+        map.entry(row.zone.clone()).or_default().push(row);
+    }
+    map
+}
+
+fn diff_zone(zone: String, old: Vec<RecordRow>, mut new: Vec<RecordRow>) -> ZoneChanges {
+    let mut changes = ZoneChanges {
+        zone,
+        ..Default::default()
+    };
+    for old_row in old {
+        match new.iter().position(|row| same_record(&old_row, row)) {
+            Some(i) => {
+                let new_row = new.remove(i);
+                if new_row != old_row {
+                    changes.changed.push(RecordChange {
+                        old: old_row,
+                        new: new_row,
+                    });
+                }
+            }
+            None => changes.removed.push(old_row),
+        }
+    }
+    changes.added = new;
+    changes
+}
+
+/// Compares the resolved records of two zone sets, e.g. before and after a
+/// config change
+pub fn diff(old: &ZoneSet, new: &ZoneSet) -> ZoneDiff {
+    let mut old_zones = by_zone(old);
+    let mut new_zones = by_zone(new);
+
+    let mut names: Vec<String> = old_zones.keys().chain(new_zones.keys()).cloned().collect();
+    names.sort();
+    names.dedup();
+
+    let zones = names
+        .into_iter()
+        .map(|name| {
+            let old_rows = old_zones.remove(&name).unwrap_or_default();
+            let new_rows = new_zones.remove(&name).unwrap_or_default();
+            diff_zone(name, old_rows, new_rows)
+        })
+        .filter(|changes| !changes.is_empty())
+        .collect();
+    ZoneDiff { zones }
+}
+
+#[cfg(test)]
+#[cfg(feature = "yaml")]
+mod tests {
+    use super::*;
+    use crate::args::InputFormat;
+    use crate::parser::parse;
+
+    const BASE: &str = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n";
+
+    #[test]
+    fn test_diff_records() {
+        let old = parse(
+            &format!("{BASE}    hosts:\n      www: 192.168.1.2\n      db: 192.168.1.4\n    cname:\n      web: www\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        let new = parse(
+            &format!("{BASE}    hosts:\n      www: 192.168.1.2\n      mail: 192.168.1.3\n    cname:\n      web: mail\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+
+        let result = diff(&old, &new);
+        assert_eq!(result.zones.len(), 1);
+        let zone = &result.zones[0];
+        assert_eq!(zone.zone, "example.com.");
+        assert_eq!(zone.added.len(), 1);
+        assert_eq!(zone.added[0].name, "mail.example.com.");
+        assert_eq!(zone.removed.len(), 1);
+        assert_eq!(zone.removed[0].name, "db.example.com.");
+        assert_eq!(zone.changed.len(), 1);
+        assert_eq!(zone.changed[0].old.data, "www.example.com.");
+        assert_eq!(zone.changed[0].new.data, "mail.example.com.");
+    }
+
+    #[test]
+    fn test_diff_serial_and_new_zone() {
+        let old = parse(BASE, 2025060100, InputFormat::Yaml).unwrap();
+        assert!(diff(&old, &old).is_empty());
+
+        let new = parse(
+            &format!("{BASE}  example.org: {{}}\n"),
+            2025060101,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        let result = diff(&old, &new);
+        let names: Vec<&str> = result.zones.iter().map(|z| z.zone.as_str()).collect();
+        assert_eq!(names, ["example.com.", "example.org."]);
+        assert_eq!(result.zones[0].changed[0].old.record_type, "SOA");
+        assert_eq!(result.zones[1].added.len(), 2);
+        assert!(result.zones[1].removed.is_empty());
+    }
+}
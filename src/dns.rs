@@ -0,0 +1,436 @@
+use anyhow::{bail, Context, Result};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const TYPE_SOA: u16 = 6;
+const TYPE_DNSKEY: u16 = 48;
+const TYPE_AXFR: u16 = 252;
+const CLASS_IN: u16 = 1;
+
+/// Address of a DNS server given as `host`, `ip` or `ip:port`, port 53 by default
+pub fn server_addr(server: &str) -> Result<SocketAddr> {
+    if let Ok(addr) = server.parse() {
+        return Ok(addr);
+    }
+    let host = server.trim_start_matches('[').trim_end_matches(']');
+    (host, 53)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .with_context(|| format!("Invalid DNS server '{server}'"))
+}
+
+/// Looks `name` up through the resolver of the system, e.g. to check that a
+/// nameserver outside the configured zones exists. The trailing dot is left
+/// off, `/etc/hosts` has none.
+pub fn lookup_host(name: &str) -> Result<(), String> {
+    match (name.trim_end_matches('.'), 53)
+        .to_socket_addrs()
+        .map(|mut addrs| addrs.next())
+    {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err("no address".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Queries `server` for the SOA serial of `zone`. `None` if the server does
+/// not serve the zone.
+pub fn query_soa_serial(server: SocketAddr, zone: &str, timeout: Duration) -> Result<Option<u32>> {
+    let id = query_id();
+    let query = query(id, zone, TYPE_SOA)?;
+
+    let local: SocketAddr = if server.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.send_to(&query, server)?;
+
+    let mut buf = [0u8; 4096];
+    loop {
+        let (len, from) = socket
+            .recv_from(&mut buf)
+            .with_context(|| format!("No answer from {server} for the SOA of {zone}"))?;
+        // Ignore stray packets, e.g. late answers to an earlier query
+        if from == server && buf[..len].starts_with(&id.to_be_bytes()) {
+            return soa_serial(&buf[..len])
+                .with_context(|| format!("Invalid answer from {server} for the SOA of {zone}"));
+        }
+    }
+}
+
+/// DNSKEY records at the apex of `zone`, transferred from `server` by AXFR.
+/// Returns their RDATA in wire format.
+pub fn transfer_dnskeys(server: SocketAddr, zone: &str, timeout: Duration) -> Result<Vec<Vec<u8>>> {
+    let id = query_id();
+    let query = query(id, zone, TYPE_AXFR)?;
+    let mut stream = TcpStream::connect_timeout(&server, timeout)
+        .with_context(|| format!("Cannot connect to {server} to transfer {zone}"))?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    // Messages over TCP are preceded by their length
+    stream.write_all(&(query.len() as u16).to_be_bytes())?;
+    stream.write_all(&query)?;
+
+    let mut transfer = Transfer::default();
+    while transfer.soa_records < 2 {
+        let mut len = [0u8; 2];
+        stream
+            .read_exact(&mut len)
+            .with_context(|| format!("Transfer of {zone} from {server} ended early"))?;
+        let mut message = vec![0u8; u16::from_be_bytes(len) as usize];
+        stream
+            .read_exact(&mut message)
+            .with_context(|| format!("Transfer of {zone} from {server} ended early"))?;
+        transfer
+            .read(&message, id, zone)
+            .with_context(|| format!("Invalid transfer of {zone} from {server}"))?;
+    }
+    Ok(transfer.dnskeys)
+}
+
+/// Records of a zone transfer read so far. The transfer starts and ends with
+/// the SOA record.
+#[derive(Default)]
+struct Transfer {
+    soa_records: usize,
+    dnskeys: Vec<Vec<u8>>,
+}
+
+impl Transfer {
+    /// Reads the answer section of one message of the transfer
+    fn read(&mut self, message: &[u8], id: u16, zone: &str) -> Result<()> {
+        let header = message.get(..12).context("Truncated header")?;
+        if header[..2] != id.to_be_bytes() {
+            bail!("Answer to another query");
+        }
+        match header[3] & 0x0f {
+            0 => (),
+            5 | 9 => bail!("The server refused the transfer"),
+            rcode => bail!("Server failed with response code {rcode}"),
+        }
+        let questions = u16::from_be_bytes([header[4], header[5]]);
+        let answers = u16::from_be_bytes([header[6], header[7]]);
+        if answers == 0 {
+            bail!("Message without records");
+        }
+
+        let mut pos = 12;
+        for _ in 0..questions {
+            pos = skip_name(message, pos)? + 4;
+        }
+        for _ in 0..answers {
+            let (owner, next) = read_name(message, pos)?;
+            let record = message.get(next..next + 10).context("Truncated record")?;
+            let record_type = u16::from_be_bytes([record[0], record[1]]);
+            let length = u16::from_be_bytes([record[8], record[9]]) as usize;
+            pos = next + 10;
+            let rdata = message.get(pos..pos + length).context("Truncated record")?;
+            pos += length;
+            if self.soa_records == 0 && record_type != TYPE_SOA {
+                bail!("The transfer does not start with the SOA record");
+            }
+            match record_type {
+                TYPE_SOA => self.soa_records += 1,
+                TYPE_DNSKEY if owner.eq_ignore_ascii_case(zone) => {
+                    self.dnskeys.push(rdata.to_vec())
+                }
+                _ => (),
+            }
+            if self.soa_records == 2 {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ID of a new query, to tell its answer from stray packets
+fn query_id() -> u16 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.subsec_nanos() as u16)
+        .unwrap_or(0)
+}
+
+/// Non-recursive query for the records of `record_type` of `zone`
+fn query(id: u16, zone: &str, record_type: u16) -> Result<Vec<u8>> {
+    let mut packet = Vec::with_capacity(64);
+    packet.extend(id.to_be_bytes());
+    // Flags, then one question and no other records
+    packet.extend([0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in zone.split('.').filter(|label| !label.is_empty()) {
+        if label.len() > 63 {
+            bail!("Label '{label}' of {zone} is longer than 63 characters");
+        }
+        packet.push(label.len() as u8);
+        packet.extend(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend(record_type.to_be_bytes());
+    packet.extend(CLASS_IN.to_be_bytes());
+    Ok(packet)
+}
+
+/// Serial of the first SOA record in the answer section
+fn soa_serial(response: &[u8]) -> Result<Option<u32>> {
+    let header = response.get(..12).context("Truncated header")?;
+    match header[3] & 0x0f {
+        0 => (),
+        // NXDOMAIN, REFUSED and NOTAUTH: the server does not serve the zone
+        3 | 5 | 9 => return Ok(None),
+        rcode => bail!("Server failed with response code {rcode}"),
+    }
+    let questions = u16::from_be_bytes([header[4], header[5]]);
+    let answers = u16::from_be_bytes([header[6], header[7]]);
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(response, pos)? + 4;
+    }
+    for _ in 0..answers {
+        pos = skip_name(response, pos)?;
+        let record = response.get(pos..pos + 10).context("Truncated record")?;
+        let record_type = u16::from_be_bytes([record[0], record[1]]);
+        let length = u16::from_be_bytes([record[8], record[9]]) as usize;
+        pos += 10;
+        if record_type == TYPE_SOA {
+            // MNAME and RNAME precede the serial
+            let serial_pos = skip_name(response, skip_name(response, pos)?)?;
+            let serial = response
+                .get(serial_pos..serial_pos + 4)
+                .context("Truncated SOA record")?;
+            return Ok(Some(u32::from_be_bytes([
+                serial[0], serial[1], serial[2], serial[3],
+            ])));
+        }
+        pos += length;
+    }
+    Ok(None)
+}
+
+/// Position after the (possibly compressed) name starting at `pos`
+fn skip_name(packet: &[u8], mut pos: usize) -> Result<usize> {
+    loop {
+        let len = *packet.get(pos).context("Truncated name")?;
+        match len {
+            0 => return Ok(pos + 1),
+            len if len & 0xc0 == 0xc0 => return Ok(pos + 2),
+            len => pos += 1 + len as usize,
+        }
+    }
+}
+
+/// The (possibly compressed) name starting at `pos` with a trailing dot,
+/// and the position after it
+fn read_name(packet: &[u8], mut pos: usize) -> Result<(String, usize)> {
+    let mut name = String::new();
+    let mut end = None;
+    // Pointers must point before the labels read so far, so they cannot loop
+    let mut limit = pos;
+    loop {
+        let len = *packet.get(pos).context("Truncated name")?;
+        match len {
+            0 => {
+                if name.is_empty() {
+                    name.push('.');
+                }
+                return Ok((name, end.unwrap_or(pos + 1)));
+            }
+            len if len & 0xc0 == 0xc0 => {
+                let low = *packet.get(pos + 1).context("Truncated name")?;
+                let target = u16::from_be_bytes([len & 0x3f, low]) as usize;
+                if target >= limit {
+                    bail!("Invalid name compression");
+                }
+                end.get_or_insert(pos + 2);
+                limit = target;
+                pos = target;
+            }
+            len if len & 0xc0 != 0 => bail!("Invalid label type"),
+            len => {
+                let label = packet
+                    .get(pos + 1..pos + 1 + len as usize)
+                    .context("Truncated name")?;
+                name.push_str(&String::from_utf8_lossy(label));
+                name.push('.');
+                pos += 1 + len as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answer to `query` with one SOA record, its names compressed
+    fn soa_response(query: &[u8], serial: u32) -> Vec<u8> {
+        let mut response = query.to_vec();
+        // QR and AA set, one answer
+        response[2] = 0x84;
+        response[7] = 1;
+        response.extend([0xc0, 12]);
+        response.extend(TYPE_SOA.to_be_bytes());
+        response.extend(CLASS_IN.to_be_bytes());
+        response.extend(3600u32.to_be_bytes());
+        let mut rdata = vec![3, b'n', b's', b'1', 0xc0, 12];
+        rdata.extend([0xc0, 12]);
+        rdata.extend(serial.to_be_bytes());
+        rdata.extend([0; 16]);
+        response.extend((rdata.len() as u16).to_be_bytes());
+        response.extend(rdata);
+        response
+    }
+
+    #[test]
+    fn test_query() {
+        let query = query(0x1234, "example.com.", TYPE_SOA).unwrap();
+        assert_eq!(&query[..2], &[0x12, 0x34]);
+        assert_eq!(&query[12..], b"\x07example\x03com\x00\x00\x06\x00\x01");
+    }
+
+    #[test]
+    fn test_soa_serial() {
+        let query = query(1, "example.com.", TYPE_SOA).unwrap();
+        let response = soa_response(&query, 2025060105);
+        assert_eq!(soa_serial(&response).unwrap(), Some(2025060105));
+
+        let mut refused = query.clone();
+        refused[3] = 5;
+        assert_eq!(soa_serial(&refused).unwrap(), None);
+        let mut failed = query.clone();
+        failed[3] = 2;
+        assert!(soa_serial(&failed).is_err());
+        assert!(soa_serial(&response[..response.len() - 20]).is_err());
+    }
+
+    #[test]
+    fn test_query_soa_serial() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let responder = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (len, from) = server.recv_from(&mut buf).unwrap();
+            server
+                .send_to(&soa_response(&buf[..len], 2025070100), from)
+                .unwrap();
+        });
+        let serial = query_soa_serial(addr, "example.com.", Duration::from_secs(5)).unwrap();
+        assert_eq!(serial, Some(2025070100));
+        responder.join().unwrap();
+    }
+
+    /// Appends a record with the owner name at `owner` to a message
+    fn push_record(message: &mut Vec<u8>, owner: &[u8], record_type: u16, rdata: &[u8]) {
+        message.extend(owner);
+        message.extend(record_type.to_be_bytes());
+        message.extend(CLASS_IN.to_be_bytes());
+        message.extend(3600u32.to_be_bytes());
+        message.extend((rdata.len() as u16).to_be_bytes());
+        message.extend(rdata);
+        message[7] += 1;
+    }
+
+    /// Transfer of example.com. in two messages, with a DNSKEY record at the
+    /// apex and one below it
+    fn axfr_messages(query: &[u8]) -> Vec<Vec<u8>> {
+        let mut first = query.to_vec();
+        first[2] = 0x84;
+        let soa = soa_response(query, 2025070100)[query.len() + 12..].to_vec();
+        push_record(&mut first, &[0xc0, 12], TYPE_SOA, &soa);
+        push_record(&mut first, &[0xc0, 12], TYPE_DNSKEY, &[1, 1, 3, 15, 0xaa]);
+        push_record(&mut first, b"\x03www\xc0\x0c", 1, &[192, 0, 2, 1]);
+        let mut second = query.to_vec();
+        second[2] = 0x84;
+        push_record(
+            &mut second,
+            b"\x03sub\xc0\x0c",
+            TYPE_DNSKEY,
+            &[1, 0, 3, 15, 0xbb],
+        );
+        push_record(&mut second, &[0xc0, 12], TYPE_DNSKEY, &[1, 0, 3, 15, 0xcc]);
+        push_record(&mut second, &[0xc0, 12], TYPE_SOA, &soa);
+        vec![first, second]
+    }
+
+    #[test]
+    fn test_read_name() {
+        let packet = b"\x07example\x03com\x00\x03www\xc0\x00\x00";
+        assert_eq!(read_name(packet, 0).unwrap(), ("example.com.".into(), 13));
+        assert_eq!(
+            read_name(packet, 13).unwrap(),
+            ("www.example.com.".into(), 19)
+        );
+        assert_eq!(read_name(packet, 19).unwrap(), (".".into(), 20));
+        // Pointers to themselves or forward could loop
+        assert!(read_name(b"\xc0\x00", 0).is_err());
+        assert!(read_name(b"\x01a\xc0\x00", 0).is_err());
+        assert!(read_name(b"\x07exam", 0).is_err());
+    }
+
+    #[test]
+    fn test_transfer() {
+        let query = query(7, "example.com.", TYPE_AXFR).unwrap();
+        let mut transfer = Transfer::default();
+        for message in axfr_messages(&query) {
+            transfer.read(&message, 7, "example.com.").unwrap();
+        }
+        assert_eq!(transfer.soa_records, 2);
+        assert_eq!(
+            transfer.dnskeys,
+            [vec![1, 1, 3, 15, 0xaa], vec![1, 0, 3, 15, 0xcc]]
+        );
+
+        let mut refused = query.clone();
+        refused[3] = 5;
+        let err = Transfer::default()
+            .read(&refused, 7, "example.com.")
+            .unwrap_err();
+        assert!(err.to_string().contains("refused"), "{err}");
+        let second = &axfr_messages(&query)[1];
+        assert!(Transfer::default().read(second, 7, "example.com.").is_err());
+    }
+
+    #[test]
+    fn test_transfer_dnskeys() {
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let responder = std::thread::spawn(move || {
+            let (mut stream, _) = server.accept().unwrap();
+            let mut len = [0u8; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut query = vec![0u8; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut query).unwrap();
+            for message in axfr_messages(&query) {
+                stream
+                    .write_all(&(message.len() as u16).to_be_bytes())
+                    .unwrap();
+                stream.write_all(&message).unwrap();
+            }
+        });
+        let keys = transfer_dnskeys(addr, "example.com.", Duration::from_secs(5)).unwrap();
+        assert_eq!(keys.len(), 2);
+        responder.join().unwrap();
+    }
+
+    #[test]
+    fn test_server_addr() {
+        assert_eq!(
+            server_addr("192.0.2.53").unwrap(),
+            "192.0.2.53:53".parse().unwrap()
+        );
+        assert_eq!(
+            server_addr("[2001:db8::53]:5353").unwrap(),
+            "[2001:db8::53]:5353".parse().unwrap()
+        );
+        assert_eq!(
+            server_addr("2001:db8::53").unwrap(),
+            "[2001:db8::53]:53".parse().unwrap()
+        );
+    }
+}
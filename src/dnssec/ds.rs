@@ -0,0 +1,136 @@
+//! DS records of key signing keys, handed to the parent zone's registrar
+//! (RFC 4034, section 5)
+
+use std::fmt;
+
+use sha2::{Digest, Sha256, Sha384};
+
+use super::keys::DnsKey;
+
+/// Hash of the DS record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DigestType {
+    /// SHA-256, digest type 2, which every validator supports
+    Sha256,
+    /// SHA-384, digest type 4
+    Sha384,
+}
+
+impl DigestType {
+    /// Number of the digest type in the DS record
+    pub fn number(self) -> u8 {
+        match self {
+            DigestType::Sha256 => 2,
+            DigestType::Sha384 => 4,
+        }
+    }
+
+    fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DigestType::Sha256 => Sha256::digest(data).to_vec(),
+            DigestType::Sha384 => Sha384::digest(data).to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DsRecord {
+    pub zone: String,
+    pub tag: u16,
+    pub algorithm: u8,
+    pub digest_type: DigestType,
+    pub digest: Vec<u8>,
+}
+
+impl DsRecord {
+    /// DS record of `key`: the digest of its owner name and DNSKEY RDATA
+    pub fn of(key: &DnsKey, digest_type: DigestType) -> DsRecord {
+        let mut data = Vec::new();
+        for label in key.zone.split('.').filter(|label| !label.is_empty()) {
+            data.push(label.len() as u8);
+            data.extend(label.to_ascii_lowercase().bytes());
+        }
+        data.push(0);
+        data.extend(key.rdata());
+        DsRecord {
+            zone: key.zone.clone(),
+            tag: key.tag(),
+            algorithm: key.algorithm,
+            digest_type,
+            digest: digest_type.digest(&data),
+        }
+    }
+
+    /// RDATA in wire format, also that of the CDS record
+    pub fn rdata(&self) -> Vec<u8> {
+        let mut rdata = self.tag.to_be_bytes().to_vec();
+        rdata.extend([self.algorithm, self.digest_type.number()]);
+        rdata.extend(&self.digest);
+        rdata
+    }
+
+    /// RDATA in zone file syntax
+    pub fn data(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.tag,
+            self.algorithm,
+            self.digest_type.number(),
+            hex::encode(&self.digest)
+        )
+    }
+}
+
+impl fmt::Display for DsRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} IN DS {}", self.zone, self.data())
+    }
+}
+
+/// Keys the parent needs DS records of: the key signing keys, or all keys
+/// of a zone without a key signing key, as those sign the DNSKEY records
+pub fn ds_keys(keys: &[DnsKey]) -> Vec<&DnsKey> {
+    if keys.iter().any(DnsKey::is_ksk) {
+        keys.iter().filter(|key| key.is_ksk()).collect()
+    } else {
+        keys.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dnssec::keys::parse_dnskeys;
+
+    #[test]
+    fn test_ds_rfc8080() {
+        let keys = parse_dnskeys(
+            "example.com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=",
+        )
+        .unwrap();
+        assert_eq!(
+            DsRecord::of(&keys[0], DigestType::Sha256).to_string(),
+            "example.com. IN DS 3613 15 2 \
+             3aa5ab37efce57f737fc1627013fee07bdf241bd10f3b1964ab55c78e79a304b"
+        );
+        let ds = DsRecord::of(&keys[0], DigestType::Sha384);
+        assert_eq!(ds.digest.len(), 48);
+        assert_eq!(ds.rdata()[..4], [0x0e, 0x1d, 15, 4]);
+        assert_eq!(ds.rdata().len(), 52);
+        assert!(ds.to_string().starts_with("example.com. IN DS 3613 15 4 "));
+    }
+
+    #[test]
+    fn test_ds_keys() {
+        let key = |flags| DnsKey {
+            zone: "example.com.".to_string(),
+            flags,
+            algorithm: 15,
+            public_key: vec![flags as u8; 32],
+        };
+        let keys = [key(256), key(257)];
+        assert_eq!(ds_keys(&keys), [&keys[1]]);
+        assert_eq!(ds_keys(&keys[..1]), [&keys[0]]);
+    }
+}
@@ -0,0 +1,716 @@
+//! Ed25519 zone keys in the BIND key file format: `K<zone>+015+<tag>.key`
+//! holds the DNSKEY record and the timing of the key in comments,
+//! `K<zone>+015+<tag>.private` the seed
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{ed25519::signature, Signature, Signer, SigningKey};
+
+use super::{format_time, parse_time};
+
+/// DNSSEC algorithm number of Ed25519
+pub const ALGORITHM: u8 = 15;
+/// DNSKEY flags of a zone signing key
+pub const FLAGS_ZSK: u16 = 256;
+/// DNSKEY flags of a key signing key, the zone key flag plus SEP
+pub const FLAGS_KSK: u16 = 257;
+
+pub struct Key {
+    /// Zone the key belongs to, with the trailing dot
+    pub zone: String,
+    pub flags: u16,
+    pub tag: u16,
+    pub timing: Timing,
+    signing: SigningKey,
+}
+
+/// Points in time of a key's life in seconds since the epoch, as in the
+/// comments of BIND key files. A key is published in the DNSKEY records
+/// until `delete` and signs from `activate` until `inactive`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timing {
+    pub created: Option<i64>,
+    pub activate: Option<i64>,
+    pub inactive: Option<i64>,
+    pub delete: Option<i64>,
+}
+
+impl Timing {
+    pub fn is_published(&self, now: i64) -> bool {
+        self.delete.is_none_or(|delete| now < delete)
+    }
+
+    pub fn is_active(&self, now: i64) -> bool {
+        self.is_published(now)
+            && self.activate.is_none_or(|activate| activate <= now)
+            && self.inactive.is_none_or(|inactive| now < inactive)
+    }
+
+    /// State of the key at `now`: published (not signing yet), active,
+    /// inactive (published only) or deleted
+    pub fn state(&self, now: i64) -> &'static str {
+        if !self.is_published(now) {
+            "deleted"
+        } else if self.is_active(now) {
+            "active"
+        } else if self.activate.is_some_and(|activate| now < activate) {
+            "published"
+        } else {
+            "inactive"
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, Option<i64>); 4] {
+        [
+            ("Created", self.created),
+            ("Activate", self.activate),
+            ("Inactive", self.inactive),
+            ("Delete", self.delete),
+        ]
+    }
+
+    /// Timing from the comments of a `.key` file, e.g. `; Activate: 20250101000000`
+    fn parse(content: &str) -> Result<Timing> {
+        let mut timing = Timing::default();
+        for line in content.lines() {
+            let Some((name, value)) = line
+                .trim_start()
+                .strip_prefix(';')
+                .and_then(|comment| comment.split_once(':'))
+            else {
+                continue;
+            };
+            let field = match name.trim() {
+                "Created" => &mut timing.created,
+                "Activate" => &mut timing.activate,
+                "Inactive" => &mut timing.inactive,
+                "Delete" => &mut timing.delete,
+                _ => continue,
+            };
+            let value = value.split_whitespace().next().unwrap_or_default();
+            *field =
+                Some(parse_time(value).with_context(|| format!("Invalid {} time", name.trim()))?);
+        }
+        Ok(timing)
+    }
+}
+
+impl Key {
+    pub fn from_seed(zone: &str, flags: u16, seed: [u8; 32]) -> Key {
+        let signing = SigningKey::from_bytes(&seed);
+        let dnskey = DnsKey {
+            zone: zone.to_string(),
+            flags,
+            algorithm: ALGORITHM,
+            public_key: signing.verifying_key().to_bytes().to_vec(),
+        };
+        Key {
+            zone: dnskey.zone.clone(),
+            flags,
+            tag: dnskey.tag(),
+            timing: Timing::default(),
+            signing,
+        }
+    }
+
+    /// New key with a random seed from the operating system
+    pub fn generate(zone: &str, flags: u16) -> Result<Key> {
+        let mut seed = [0u8; 32];
+        fs::File::open("/dev/urandom")
+            .and_then(|mut random| random.read_exact(&mut seed))
+            .context("Cannot read random bytes for a new key")?;
+        Ok(Key::from_seed(zone, flags, seed))
+    }
+
+    /// Key signing keys (SEP flag set) sign the DNSKEY records
+    pub fn is_ksk(&self) -> bool {
+        self.flags & 1 == 1
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing.verifying_key().to_bytes()
+    }
+
+    /// RDATA of the DNSKEY record in wire format
+    pub fn rdata(&self) -> Vec<u8> {
+        self.dnskey().rdata()
+    }
+
+    /// Public part of the key
+    pub fn dnskey(&self) -> DnsKey {
+        DnsKey {
+            zone: self.zone.clone(),
+            flags: self.flags,
+            algorithm: ALGORITHM,
+            public_key: self.public_key().to_vec(),
+        }
+    }
+
+    /// File name of the key without extension, e.g. `Kexample.com.+015+03613`
+    pub fn file_stem(&self) -> String {
+        format!("K{}+{:03}+{:05}", self.zone, ALGORITHM, self.tag)
+    }
+
+    /// Content of the `.key` file
+    pub fn key_file(&self) -> String {
+        let role = if self.is_ksk() { "key" } else { "zone" };
+        let mut content = format!(
+            "; This is a {role}-signing key, keyid {}, for {}\n",
+            self.tag, self.zone
+        );
+        for (name, time) in self.timing.fields() {
+            if let Some(time) = time {
+                content += &format!("; {name}: {}\n", format_time(time));
+            }
+        }
+        content += &format!(
+            "{} IN DNSKEY {} 3 {ALGORITHM} {}\n",
+            self.zone,
+            self.flags,
+            BASE64.encode(self.public_key())
+        );
+        content
+    }
+
+    /// Content of the `.private` file
+    pub fn private_file(&self) -> String {
+        format!(
+            "Private-key-format: v1.3\nAlgorithm: {ALGORITHM} (ED25519)\nPrivateKey: {}\n",
+            BASE64.encode(self.signing.to_bytes())
+        )
+    }
+
+    /// Writes the `.key` and `.private` files to `dir`, the private one only
+    /// readable by the owner. Returns the path of the `.key` file.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create key directory {}", dir.display()))?;
+        let stem = self.file_stem();
+        let private = dir.join(format!("{stem}.private"));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(
+            &mut options
+                .open(&private)
+                .with_context(|| format!("Cannot create {}", private.display()))?,
+            self.private_file().as_bytes(),
+        )?;
+        self.save_timing(dir)
+    }
+
+    /// Rewrites the `.key` file in `dir`, e.g. after the timing changed.
+    /// Returns its path.
+    pub fn save_timing(&self, dir: &Path) -> Result<PathBuf> {
+        let public = dir.join(format!("{}.key", self.file_stem()));
+        fs::write(&public, self.key_file())
+            .with_context(|| format!("Cannot write {}", public.display()))?;
+        Ok(public)
+    }
+}
+
+/// Signs the RRsets of the zone for its RRSIG records
+impl Signer<Signature> for Key {
+    fn try_sign(&self, message: &[u8]) -> Result<Signature, signature::Error> {
+        self.signing.try_sign(message)
+    }
+}
+
+/// Table of keys with their role and timing at `now`
+pub fn format_keys(keys: &[Key], now: i64) -> String {
+    let width = keys
+        .iter()
+        .map(|key| key.zone.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let time = |time: Option<i64>| match time {
+        Some(time) => {
+            let t = format_time(time);
+            format!(
+                "{}-{}-{} {}:{}",
+                &t[..4],
+                &t[4..6],
+                &t[6..8],
+                &t[8..10],
+                &t[10..12]
+            )
+        }
+        None => "-".to_string(),
+    };
+    let mut output = format!(
+        "{:width$}  TAG    ROLE  STATE      {:16}  {:16}  {}\n",
+        "ZONE", "ACTIVATE", "INACTIVE", "DELETE"
+    );
+    for key in keys {
+        output += &format!(
+            "{:width$}  {:05}  {}   {:9}  {:16}  {:16}  {}\n",
+            key.zone,
+            key.tag,
+            if key.is_ksk() { "KSK" } else { "ZSK" },
+            key.timing.state(now),
+            time(key.timing.activate),
+            time(key.timing.inactive),
+            time(key.timing.delete),
+        );
+    }
+    output
+}
+
+/// Key tag of a DNSKEY record (RFC 4034, Appendix B)
+pub fn key_tag(rdata: &[u8]) -> u16 {
+    let mut sum = 0u32;
+    for (i, byte) in rdata.iter().enumerate() {
+        sum += if i % 2 == 0 {
+            u32::from(*byte) << 8
+        } else {
+            u32::from(*byte)
+        };
+    }
+    sum += sum >> 16;
+    sum as u16
+}
+
+/// Public part of a key, as in a DNSKEY record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsKey {
+    /// Owner of the record, the zone
+    pub zone: String,
+    pub flags: u16,
+    pub algorithm: u8,
+    pub public_key: Vec<u8>,
+}
+
+impl DnsKey {
+    /// Key of the DNSKEY record of `zone` with this RDATA in wire format
+    pub fn from_rdata(zone: &str, rdata: &[u8]) -> Result<DnsKey> {
+        let [flags_high, flags_low, protocol, algorithm, public_key @ ..] = rdata else {
+            bail!("Truncated DNSKEY record of {zone}");
+        };
+        if *protocol != 3 {
+            bail!("DNSKEY record of {zone} has protocol {protocol} instead of 3");
+        }
+        if public_key.is_empty() {
+            bail!("DNSKEY record of {zone} has no public key");
+        }
+        Ok(DnsKey {
+            zone: zone.to_string(),
+            flags: u16::from_be_bytes([*flags_high, *flags_low]),
+            algorithm: *algorithm,
+            public_key: public_key.to_vec(),
+        })
+    }
+
+    /// RDATA of the DNSKEY record in wire format
+    pub fn rdata(&self) -> Vec<u8> {
+        let mut rdata = self.flags.to_be_bytes().to_vec();
+        rdata.extend([3, self.algorithm]);
+        rdata.extend(&self.public_key);
+        rdata
+    }
+
+    pub fn tag(&self) -> u16 {
+        key_tag(&self.rdata())
+    }
+
+    /// RDATA in zone file syntax
+    pub fn data(&self) -> String {
+        format!(
+            "{} 3 {} {}",
+            self.flags,
+            self.algorithm,
+            BASE64.encode(&self.public_key)
+        )
+    }
+
+    pub fn is_ksk(&self) -> bool {
+        self.flags & 1 == 1
+    }
+}
+
+/// `.key` files of `zone` in `dir` with the algorithm from their name. A
+/// missing `dir` holds no keys.
+fn key_files(dir: &Path, zone: &str) -> Result<Vec<(PathBuf, u8)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Cannot read key directory {}", dir.display()))
+        }
+    };
+    let prefix = format!("k{}+", zone.to_ascii_lowercase());
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let algorithm = name
+            .to_ascii_lowercase()
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".key"))
+            .and_then(|rest| rest.split_once('+'))
+            .and_then(|(alg, _)| alg.parse::<u8>().ok());
+        if let Some(algorithm) = algorithm {
+            files.push((path, algorithm));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Reads all keys of `zone` from `dir`, sorted by key tag. Fails on keys of
+/// other algorithms, as the zone could not be signed completely.
+pub fn load_keys(dir: &Path, zone: &str) -> Result<Vec<Key>> {
+    let mut keys = Vec::new();
+    for (path, algorithm) in key_files(dir, zone)? {
+        if algorithm != ALGORITHM {
+            bail!(
+                "Key {} uses algorithm {algorithm}, only Ed25519 ({ALGORITHM}) is supported",
+                path.display()
+            );
+        }
+        keys.push(read_key(&path, zone)?);
+    }
+    keys.sort_by_key(|key| key.tag);
+    Ok(keys)
+}
+
+/// Reads the public keys of `zone` from the `.key` files in `dir`, of any
+/// algorithm, sorted by key tag
+pub fn load_public_keys(dir: &Path, zone: &str) -> Result<Vec<DnsKey>> {
+    let mut keys = Vec::new();
+    for (path, _) in key_files(dir, zone)? {
+        keys.push(read_dnskey(&path, Some(zone))?);
+    }
+    keys.sort_by_key(DnsKey::tag);
+    Ok(keys)
+}
+
+/// Zones with keys in `dir`, sorted by name
+pub fn key_zones(dir: &Path) -> Result<Vec<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Cannot read key directory {}", dir.display()))
+        }
+    };
+    let mut zones = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let zone = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix('K'))
+            .and_then(|name| name.strip_suffix(".key"))
+            .and_then(|name| name.rsplitn(3, '+').nth(2));
+        if let Some(zone) = zone.filter(|zone| zone.ends_with('.')) {
+            zones.push(zone.to_string());
+        }
+    }
+    zones.sort();
+    zones.dedup();
+    Ok(zones)
+}
+
+/// Reads the DNSKEY record of a `.key` file, which must belong to `zone` if
+/// given
+pub fn read_dnskey(path: &Path, zone: Option<&str>) -> Result<DnsKey> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    parse_key_file(path, &content, zone)
+}
+
+fn parse_key_file(path: &Path, content: &str, zone: Option<&str>) -> Result<DnsKey> {
+    let key = match parse_dnskeys(content)?.as_slice() {
+        [key] => key.clone(),
+        [] => bail!("No DNSKEY record in {}", path.display()),
+        _ => bail!("More than one DNSKEY record in {}", path.display()),
+    };
+    if let Some(zone) = zone.filter(|zone| !key.zone.eq_ignore_ascii_case(zone)) {
+        bail!(
+            "Key file {} holds a key of {} instead of {zone}",
+            path.display(),
+            key.zone
+        );
+    }
+    if key.flags & 256 == 0 {
+        bail!("Key {} has no zone key flag (256)", path.display());
+    }
+    Ok(key)
+}
+
+/// Reads a `.key` file and the `.private` file next to it
+fn read_key(path: &Path, zone: &str) -> Result<Key> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let dnskey = parse_key_file(path, &content, Some(zone))?;
+    if dnskey.algorithm != ALGORITHM || dnskey.public_key.len() != 32 {
+        bail!("Key {} is no Ed25519 key", path.display());
+    }
+
+    let private = path.with_extension("private");
+    let private_content = fs::read_to_string(&private)
+        .with_context(|| format!("Cannot read private key {}", private.display()))?;
+    let seed = parse_private(&private_content)
+        .with_context(|| format!("Invalid private key file {}", private.display()))?;
+
+    let mut key = Key::from_seed(zone, dnskey.flags, seed);
+    key.timing =
+        Timing::parse(&content).with_context(|| format!("Invalid key file {}", path.display()))?;
+    if key.public_key().as_slice() != dnskey.public_key {
+        bail!(
+            "Private key {} does not belong to {}",
+            private.display(),
+            path.display()
+        );
+    }
+    Ok(key)
+}
+
+/// DNSKEY records in zone file syntax, e.g. of a `.key` file or the answer
+/// of `dig DNSKEY`. Other records are skipped, records may continue over
+/// several lines in parentheses.
+pub fn parse_dnskeys(content: &str) -> Result<Vec<DnsKey>> {
+    let mut keys = Vec::new();
+    let mut tokens = Vec::new();
+    let mut depth = 0i32;
+    for line in content.lines() {
+        let line = line.split(';').next().unwrap_or_default();
+        depth += line.matches('(').count() as i32 - line.matches(')').count() as i32;
+        tokens.extend(
+            line.split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                .filter(|token| !token.is_empty()),
+        );
+        if depth <= 0 {
+            keys.extend(parse_dnskey(&tokens)?);
+            tokens.clear();
+            depth = 0;
+        }
+    }
+    keys.extend(parse_dnskey(&tokens)?);
+    Ok(keys)
+}
+
+/// The key of one record, `None` if it is no DNSKEY record
+fn parse_dnskey(tokens: &[&str]) -> Result<Option<DnsKey>> {
+    let Some((owner, rest)) = tokens.split_first() else {
+        return Ok(None);
+    };
+    // TTL and class may precede the type
+    let mut fields = rest.iter().copied().skip_while(|t| {
+        t.bytes().all(|b| b.is_ascii_digit())
+            || ["IN", "CH", "HS"]
+                .iter()
+                .any(|class| t.eq_ignore_ascii_case(class))
+    });
+    if !fields
+        .next()
+        .is_some_and(|t| t.eq_ignore_ascii_case("DNSKEY"))
+    {
+        return Ok(None);
+    }
+    let fields: Vec<&str> = fields.collect();
+    let [flags, protocol, algorithm, key @ ..] = fields.as_slice() else {
+        bail!("Incomplete DNSKEY record of {owner}");
+    };
+    if *protocol != "3" {
+        bail!("DNSKEY record of {owner} has protocol {protocol} instead of 3");
+    }
+    Ok(Some(DnsKey {
+        zone: owner.to_string(),
+        flags: flags
+            .parse()
+            .with_context(|| format!("Invalid flags {flags} of the key of {owner}"))?,
+        algorithm: algorithm
+            .parse()
+            .with_context(|| format!("Invalid algorithm {algorithm} of the key of {owner}"))?,
+        public_key: BASE64
+            .decode(key.concat())
+            .with_context(|| format!("Invalid public key of {owner}"))?,
+    }))
+}
+
+/// Seed of a `.private` file
+fn parse_private(content: &str) -> Result<[u8; 32]> {
+    let mut seed = None;
+    for line in content.lines() {
+        match line.split_once(':').map(|(k, v)| (k.trim(), v.trim())) {
+            Some(("Algorithm", value)) if !value.starts_with("15") => {
+                bail!("Algorithm {value} instead of 15 (ED25519)")
+            }
+            Some(("PrivateKey", value)) => seed = Some(BASE64.decode(value)?),
+            _ => (),
+        }
+    }
+    let seed = seed.context("No PrivateKey")?;
+    match seed.try_into() {
+        Ok(seed) => Ok(seed),
+        Err(seed) => bail!("Private key has {} bytes instead of 32", seed.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Key of RFC 8080, section 6.1
+    const RFC8080_PRIVATE: &str = "Private-key-format: v1.2\n\
+        Algorithm: 15 (ED25519)\n\
+        PrivateKey: ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI=\n";
+
+    #[test]
+    fn test_rfc8080_key() {
+        let seed = parse_private(RFC8080_PRIVATE).unwrap();
+        let key = Key::from_seed("example.com.", FLAGS_KSK, seed);
+        assert_eq!(
+            BASE64.encode(key.public_key()),
+            "l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4="
+        );
+        assert_eq!(key.tag, 3613);
+        assert_eq!(key.file_stem(), "Kexample.com.+015+03613");
+    }
+
+    #[test]
+    fn test_dnskey_rdata() {
+        let seed = parse_private(RFC8080_PRIVATE).unwrap();
+        let key = Key::from_seed("example.com.", FLAGS_KSK, seed).dnskey();
+        assert_eq!(
+            DnsKey::from_rdata("example.com.", &key.rdata()).unwrap(),
+            key
+        );
+        assert_eq!(
+            key.data(),
+            "257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4="
+        );
+        assert!(DnsKey::from_rdata("example.com.", &[1, 1, 3]).is_err());
+        assert!(DnsKey::from_rdata("example.com.", &[1, 1, 2, 15, 0]).is_err());
+    }
+
+    #[test]
+    fn test_load_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let seed = parse_private(RFC8080_PRIVATE).unwrap();
+        let key = Key::from_seed("example.com.", FLAGS_KSK, seed);
+        key.save(dir.path()).unwrap();
+        Key::generate("example.com.", FLAGS_ZSK)
+            .unwrap()
+            .save(dir.path())
+            .unwrap();
+        Key::generate("other.com.", FLAGS_ZSK)
+            .unwrap()
+            .save(dir.path())
+            .unwrap();
+
+        let keys = load_keys(dir.path(), "example.com.").unwrap();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.iter().any(|k| k.tag == 3613 && k.is_ksk()));
+        assert!(keys.iter().any(|k| !k.is_ksk()));
+        assert!(load_keys(dir.path(), "missing.com.").unwrap().is_empty());
+
+        // A private key that does not match its public key
+        let private = dir.path().join("Kexample.com.+015+03613.private");
+        fs::remove_file(&private).unwrap();
+        fs::write(
+            &private,
+            Key::generate("example.com.", FLAGS_KSK)
+                .unwrap()
+                .private_file(),
+        )
+        .unwrap();
+        let err = load_keys(dir.path(), "example.com.").err().unwrap();
+        assert!(err.to_string().contains("does not belong"), "{err}");
+
+        fs::write(dir.path().join("Kother.com.+013+12345.key"), "").unwrap();
+        let err = load_keys(dir.path(), "other.com.").err().unwrap();
+        assert!(err.to_string().contains("algorithm 13"), "{err}");
+        assert_eq!(
+            key_zones(dir.path()).unwrap(),
+            ["example.com.", "other.com."]
+        );
+    }
+
+    #[test]
+    fn test_signature_verifies() {
+        let dir = tempfile::tempdir().unwrap();
+        Key::generate("example.com.", FLAGS_ZSK)
+            .unwrap()
+            .save(dir.path())
+            .unwrap();
+        let key = load_keys(dir.path(), "example.com.").unwrap().remove(0);
+        let public = key.dnskey().public_key.try_into().unwrap();
+        let verifying = ed25519_dalek::VerifyingKey::from_bytes(&public).unwrap();
+        let signature = key.sign(b"example.com. RRset");
+        assert!(verifying
+            .verify_strict(b"example.com. RRset", &signature)
+            .is_ok());
+        assert!(verifying
+            .verify_strict(b"example.org. RRset", &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_dnskeys() {
+        let content = "; This is a key-signing key, keyid 3613, for example.com.\n\
+            example.com. 3600 IN DNSKEY 257 3 15 (\n\
+            l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4= ) ; KSK\n\
+            example.com. 3600 IN RRSIG DNSKEY 15 2 3600 20150819220000 20150729220000 3613 example.com. AAAA\n\
+            example.org. DNSKEY 256 3 13 AQID\n";
+        let keys = parse_dnskeys(content).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].zone, "example.com.");
+        assert_eq!(keys[0].flags, 257);
+        assert_eq!(keys[0].algorithm, 15);
+        assert_eq!(keys[0].tag(), 3613);
+        assert_eq!(keys[1].algorithm, 13);
+        assert_eq!(keys[1].public_key, [1, 2, 3]);
+
+        let err = parse_dnskeys("example.com. IN DNSKEY 257 2 15 AQID").unwrap_err();
+        assert!(err.to_string().contains("protocol 2"), "{err}");
+        assert!(parse_dnskeys("example.com. IN DNSKEY 257 3").is_err());
+    }
+
+    #[test]
+    fn test_timing() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut key = Key::from_seed("example.com.", FLAGS_ZSK, [1; 32]);
+        key.timing = Timing {
+            created: Some(1760486400),
+            activate: Some(1760572800),
+            inactive: Some(1761177600),
+            delete: Some(1761782400),
+        };
+        key.save(dir.path()).unwrap();
+        let content = fs::read_to_string(dir.path().join(format!("{}.key", key.file_stem())));
+        assert!(content.unwrap().contains("\n; Activate: 20251016000000\n"));
+        let loaded = load_keys(dir.path(), "example.com.").unwrap();
+        assert_eq!(loaded[0].timing, key.timing);
+
+        for (now, state) in [
+            (1760486400, "published"),
+            (1760572800, "active"),
+            (1761177600, "inactive"),
+            (1761782400, "deleted"),
+        ] {
+            assert_eq!(key.timing.state(now), state);
+        }
+        assert!(Timing::default().is_active(0));
+        assert!(Timing::parse("; Delete: 2025").is_err());
+
+        let table = format_keys(&loaded, 1760572800);
+        assert!(
+            table.lines().nth(1).unwrap().starts_with(&format!(
+                "example.com.  {:05}  ZSK   active     2025-10-16 00:00  2025-10-23 00:00",
+                key.tag
+            )),
+            "{table}"
+        );
+    }
+}
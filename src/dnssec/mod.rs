@@ -0,0 +1,579 @@
+//! DNSSEC signing of the generated zones with Ed25519 keys: DNSKEY records,
+//! an NSEC chain and RRSIG records over every authoritative RRset
+
+pub mod ds;
+pub mod keys;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+
+use anyhow::{bail, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::Signer;
+
+pub use ds::{ds_keys, DigestType, DsRecord};
+pub use keys::{load_keys, DnsKey, Key};
+
+use crate::check::owner;
+use crate::parser::{ForwardZone, ReverseZone, ZoneBase};
+use crate::record::Record;
+use crate::transform::ip_name;
+use crate::validation::is_wildcard;
+
+const TYPE_NS: u16 = 2;
+const TYPE_SOA: u16 = 6;
+const TYPE_RRSIG: u16 = 46;
+const TYPE_NSEC: u16 = 47;
+const TYPE_DNSKEY: u16 = 48;
+const TYPE_CDS: u16 = 59;
+const TYPE_CDNSKEY: u16 = 60;
+const CLASS_IN: u16 = 1;
+
+/// Time span the signatures are valid in, in seconds since the epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub inception: i64,
+    pub expiration: i64,
+}
+
+/// A record added by signing, written after the records of the zone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedRecord {
+    pub owner: String,
+    pub ttl: u32,
+    pub record_type: &'static str,
+    pub data: String,
+}
+
+/// Everything signing adds to a zone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedZone {
+    /// DNSKEY, CDS, CDNSKEY, NSEC and RRSIG records
+    pub records: Vec<SignedRecord>,
+    /// SHA-256 DS records of the key signing keys, for the parent zone
+    pub ds: Vec<DsRecord>,
+}
+
+/// Records of one type at one name, RDATA in wire format and canonical order
+struct RrSet {
+    ttl: u32,
+    rdata: BTreeSet<Vec<u8>>,
+}
+
+/// All RRsets of one owner name
+struct Node {
+    owner: String,
+    sets: BTreeMap<u16, RrSet>,
+}
+
+/// Signs a forward zone with the keys active at `now`, publishing all keys
+/// not deleted yet
+pub fn sign_forward(
+    zone: &ForwardZone,
+    keys: &[Key],
+    window: Window,
+    now: i64,
+) -> Result<SignedZone> {
+    let records = zone
+        .records()
+        .filter(|r| !matches!(r, Record::Ptr(_)))
+        .map(|r| (owner(zone, &r).to_string(), r));
+    sign(&zone.base, records, keys, window, now)
+}
+
+/// Signs a reverse zone like [`sign_forward`]
+pub fn sign_reverse(
+    zone: &ReverseZone,
+    keys: &[Key],
+    window: Window,
+    now: i64,
+) -> Result<SignedZone> {
+    let records = zone.records().map(|r| {
+        let owner = match r {
+            Record::Delegation(d) => d.name.clone(),
+            Record::Ptr(p) => format!("{}.{}", ip_name(&p.ip, zone.split), zone.base.name),
+            _ => zone.base.name.clone(),
+        };
+        (owner, r)
+    });
+    sign(&zone.base, records, keys, window, now)
+}
+
+/// DNSKEY records of a zone signed by an external signer, e.g. with an HSM,
+/// published without signing the zone
+pub fn external_keys(base: &ZoneBase, keys: &[DnsKey]) -> SignedZone {
+    let records = keys
+        .iter()
+        .map(|key| SignedRecord {
+            owner: base.name.clone(),
+            ttl: base.ttl,
+            record_type: "DNSKEY",
+            data: key.data(),
+        })
+        .collect();
+    let ds = ds_keys(keys)
+        .into_iter()
+        .map(|key| DsRecord::of(key, DigestType::Sha256))
+        .collect();
+    SignedZone { records, ds }
+}
+
+fn sign<'a>(
+    base: &ZoneBase,
+    records: impl Iterator<Item = (String, Record<'a>)>,
+    keys: &[Key],
+    window: Window,
+    now: i64,
+) -> Result<SignedZone> {
+    let keys: Vec<&Key> = keys
+        .iter()
+        .filter(|key| key.timing.is_published(now))
+        .collect();
+    let active: Vec<&Key> = keys
+        .iter()
+        .copied()
+        .filter(|key| key.timing.is_active(now))
+        .collect();
+    if active.is_empty() {
+        bail!("No active keys to sign {}", base.name);
+    }
+    let apex = base.name.as_str();
+    let mut nodes: BTreeMap<Vec<Vec<u8>>, Node> = BTreeMap::new();
+    let mut add = |owner: &str, rtype: u16, ttl: u32, rdata: Vec<u8>| {
+        let node = nodes.entry(canonical_key(owner)).or_insert_with(|| Node {
+            owner: owner.to_string(),
+            sets: BTreeMap::new(),
+        });
+        let set = node.sets.entry(rtype).or_insert(RrSet {
+            ttl,
+            rdata: BTreeSet::new(),
+        });
+        set.ttl = set.ttl.min(ttl);
+        set.rdata.insert(rdata);
+    };
+
+    let mut soa = name_wire(base.primary_nameserver()?, true);
+    soa.extend(name_wire(&base.email, true));
+    for value in [
+        base.serial,
+        base.refresh,
+        base.retry,
+        base.expire,
+        base.nrc_ttl,
+    ] {
+        soa.extend(value.to_be_bytes());
+    }
+    add(apex, TYPE_SOA, base.ttl, soa);
+    for key in &keys {
+        add(apex, TYPE_DNSKEY, base.ttl, key.rdata());
+    }
+    // CDS and CDNSKEY records tell parents that support them which DS
+    // records to hold (RFC 7344), so key rollovers need no registrar
+    let dnskeys: Vec<_> = keys.iter().map(|key| key.dnskey()).collect();
+    let sep_keys = ds_keys(&dnskeys);
+    let ds: Vec<DsRecord> = sep_keys
+        .iter()
+        .map(|key| DsRecord::of(key, DigestType::Sha256))
+        .collect();
+    for record in &ds {
+        add(apex, TYPE_CDS, base.ttl, record.rdata());
+    }
+    for key in &sep_keys {
+        add(apex, TYPE_CDNSKEY, base.ttl, key.rdata());
+    }
+    for (owner, record) in records {
+        if !is_below(&owner, apex) {
+            bail!("{owner} is not part of zone {apex}");
+        }
+        let (rtype, rdata) = rdata(&record);
+        add(&owner, rtype, record.ttl(), rdata);
+    }
+    // Names below a delegation, like the glue of its nameservers, belong to
+    // the child zone and are neither signed nor part of the NSEC chain
+    let cuts: Vec<Vec<Vec<u8>>> = nodes
+        .iter()
+        .filter(|(_, node)| node.owner != apex && node.sets.contains_key(&TYPE_NS))
+        .map(|(key, _)| key.clone())
+        .collect();
+    nodes.retain(|key, _| {
+        !cuts
+            .iter()
+            .any(|cut| key.len() > cut.len() && key.starts_with(cut))
+    });
+
+    let mut signed = Vec::new();
+    for key in &dnskeys {
+        signed.push(SignedRecord {
+            owner: apex.to_string(),
+            ttl: base.ttl,
+            record_type: "DNSKEY",
+            data: key.data(),
+        });
+    }
+    for record in &ds {
+        signed.push(SignedRecord {
+            owner: apex.to_string(),
+            ttl: base.ttl,
+            record_type: "CDS",
+            data: record.data(),
+        });
+    }
+    for key in &sep_keys {
+        signed.push(SignedRecord {
+            owner: apex.to_string(),
+            ttl: base.ttl,
+            record_type: "CDNSKEY",
+            data: key.data(),
+        });
+    }
+
+    // Key signing keys sign the DNSKEY, CDS and CDNSKEY records, zone
+    // signing keys the rest.
+    // Without one of the two kinds, the others sign everything.
+    let (ksks, zsks): (Vec<&Key>, Vec<&Key>) = active.iter().partition(|key| key.is_ksk());
+    let dnskey_signers = if ksks.is_empty() { &zsks } else { &ksks };
+    let signers = if zsks.is_empty() { &ksks } else { &zsks };
+
+    // NSEC records link every name to the next in canonical order (RFC 4034)
+    let nsec_ttl = base.nrc_ttl.min(base.ttl);
+    let owners: Vec<String> = nodes.values().map(|node| node.owner.clone()).collect();
+    for (i, node) in nodes.values_mut().enumerate() {
+        let next = &owners[(i + 1) % owners.len()];
+        let mut types: BTreeSet<u16> = node.sets.keys().copied().collect();
+        types.extend([TYPE_RRSIG, TYPE_NSEC]);
+        let mut rdata = name_wire(next, false);
+        rdata.extend(type_bitmap(&types));
+        let type_names: Vec<String> = types.iter().map(|t| type_name(*t)).collect();
+        node.sets.insert(
+            TYPE_NSEC,
+            RrSet {
+                ttl: nsec_ttl,
+                rdata: BTreeSet::from([rdata]),
+            },
+        );
+        signed.push(SignedRecord {
+            owner: node.owner.clone(),
+            ttl: nsec_ttl,
+            record_type: "NSEC",
+            data: format!("{next} {}", type_names.join(" ")),
+        });
+
+        // NS records of a delegation belong to the child zone
+        let delegation = node.owner != apex && node.sets.contains_key(&TYPE_NS);
+        for (&rtype, set) in &node.sets {
+            if delegation && rtype == TYPE_NS {
+                continue;
+            }
+            let keys = if matches!(rtype, TYPE_DNSKEY | TYPE_CDS | TYPE_CDNSKEY) {
+                dnskey_signers
+            } else {
+                signers
+            };
+            for key in keys {
+                signed.push(rrsig(key, &node.owner, rtype, set, apex, window));
+            }
+        }
+    }
+    Ok(SignedZone {
+        records: signed,
+        ds,
+    })
+}
+
+/// RRSIG record of `key` over one RRset (RFC 4034, section 3)
+fn rrsig(
+    key: &Key,
+    owner: &str,
+    rtype: u16,
+    set: &RrSet,
+    apex: &str,
+    window: Window,
+) -> SignedRecord {
+    // A wildcard's signature covers the name without the asterisk
+    let labels = name_labels(owner).count() - usize::from(is_wildcard(owner));
+    let mut data = rtype.to_be_bytes().to_vec();
+    data.extend([keys::ALGORITHM, labels as u8]);
+    data.extend(set.ttl.to_be_bytes());
+    data.extend((window.expiration as u32).to_be_bytes());
+    data.extend((window.inception as u32).to_be_bytes());
+    data.extend(key.tag.to_be_bytes());
+    data.extend(name_wire(apex, true));
+
+    let owner_wire = name_wire(owner, true);
+    for rdata in &set.rdata {
+        data.extend(&owner_wire);
+        data.extend(rtype.to_be_bytes());
+        data.extend(CLASS_IN.to_be_bytes());
+        data.extend(set.ttl.to_be_bytes());
+        data.extend((rdata.len() as u16).to_be_bytes());
+        data.extend(rdata);
+    }
+
+    SignedRecord {
+        owner: owner.to_string(),
+        ttl: set.ttl,
+        record_type: "RRSIG",
+        data: format!(
+            "{} {} {labels} {} {} {} {} {apex} {}",
+            type_name(rtype),
+            keys::ALGORITHM,
+            set.ttl,
+            format_time(window.expiration),
+            format_time(window.inception),
+            key.tag,
+            BASE64.encode(key.sign(&data).to_bytes())
+        ),
+    }
+}
+
+/// Type and RDATA of a record in wire format, names in canonical form
+fn rdata(record: &Record) -> (u16, Vec<u8>) {
+    let name = |name: &str| name_wire(name, true);
+    match record {
+        Record::Ns(r) => (TYPE_NS, name(&r.name)),
+        Record::Delegation(r) => (TYPE_NS, name(&r.target)),
+        Record::Mx(r) => {
+            let mut rdata = r.prio.to_be_bytes().to_vec();
+            rdata.extend(name(&r.name));
+            (15, rdata)
+        }
+        Record::Address(r) => match r.ip {
+            IpAddr::V4(ip) => (1, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (28, ip.octets().to_vec()),
+        },
+        Record::Cname(r) => (5, name(&r.target)),
+        Record::Srv(r) => {
+            let mut rdata = Vec::new();
+            for value in [r.prio, r.weight, r.port] {
+                rdata.extend(value.to_be_bytes());
+            }
+            rdata.extend(name(&r.target));
+            (33, rdata)
+        }
+        // Character strings of at most 255 octets, each with its length
+        Record::Txt(r) => {
+            let mut rdata = Vec::new();
+            for string in r.strings() {
+                rdata.push(string.len() as u8);
+                rdata.extend(string.as_bytes());
+            }
+            (16, rdata)
+        }
+        Record::Ptr(r) => (12, name(&r.name)),
+    }
+}
+
+fn name_labels(name: &str) -> impl DoubleEndedIterator<Item = &str> {
+    name.split('.').filter(|label| !label.is_empty())
+}
+
+/// Name in wire format, lowercased for the canonical form
+fn name_wire(name: &str, lowercase: bool) -> Vec<u8> {
+    let mut wire = Vec::with_capacity(name.len() + 2);
+    for label in name_labels(name) {
+        wire.push(label.len() as u8);
+        if lowercase {
+            wire.extend(label.to_ascii_lowercase().bytes());
+        } else {
+            wire.extend(label.bytes());
+        }
+    }
+    wire.push(0);
+    wire
+}
+
+/// Sort key of the canonical name order: labels from the root, lowercased
+fn canonical_key(name: &str) -> Vec<Vec<u8>> {
+    name_labels(name)
+        .rev()
+        .map(|label| label.to_ascii_lowercase().into_bytes())
+        .collect()
+}
+
+fn is_below(name: &str, apex: &str) -> bool {
+    canonical_key(name).starts_with(&canonical_key(apex))
+}
+
+/// Type bit maps field of an NSEC record
+fn type_bitmap(types: &BTreeSet<u16>) -> Vec<u8> {
+    let mut windows: BTreeMap<u8, Vec<u8>> = BTreeMap::new();
+    for &rtype in types {
+        let bitmap = windows.entry((rtype >> 8) as u8).or_default();
+        let byte = (rtype & 0xff) as usize / 8;
+        if bitmap.len() <= byte {
+            bitmap.resize(byte + 1, 0);
+        }
+        bitmap[byte] |= 0x80 >> (rtype % 8);
+    }
+    let mut field = Vec::new();
+    for (window, bitmap) in windows {
+        field.extend([window, bitmap.len() as u8]);
+        field.extend(bitmap);
+    }
+    field
+}
+
+fn type_name(rtype: u16) -> String {
+    match rtype {
+        1 => "A".to_string(),
+        2 => "NS".to_string(),
+        5 => "CNAME".to_string(),
+        6 => "SOA".to_string(),
+        12 => "PTR".to_string(),
+        15 => "MX".to_string(),
+        28 => "AAAA".to_string(),
+        16 => "TXT".to_string(),
+        33 => "SRV".to_string(),
+        46 => "RRSIG".to_string(),
+        47 => "NSEC".to_string(),
+        48 => "DNSKEY".to_string(),
+        59 => "CDS".to_string(),
+        60 => "CDNSKEY".to_string(),
+        other => format!("TYPE{other}"),
+    }
+}
+
+/// Signature time as YYYYMMDDHHmmSS in UTC
+pub(crate) fn format_time(time: i64) -> String {
+    let days = time.div_euclid(86400);
+    let secs = time.rem_euclid(86400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}{:02}{:02}{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Seconds since the epoch of a YYYYMMDDHHmmSS time in UTC
+pub(crate) fn parse_time(text: &str) -> Result<i64> {
+    if text.len() != 14 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("Time {text} is not in the form YYYYMMDDHHmmSS");
+    }
+    let field = |range: std::ops::Range<usize>| text[range].parse::<i64>().unwrap_or_default();
+    let (year, month, day) = (field(0..4), field(4..6), field(6..8));
+    let (hour, minute, second) = (field(8..10), field(10..12), field(12..14));
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        bail!("Time {text} is out of range");
+    }
+    // Days since 1970-01-01 of a civil date (Howard Hinnant's algorithm)
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    Ok(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::MxRecord;
+
+    fn rfc8080_key() -> Key {
+        let seed = BASE64
+            .decode("ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI=")
+            .unwrap();
+        Key::from_seed("example.com.", keys::FLAGS_KSK, seed.try_into().unwrap())
+    }
+
+    #[test]
+    fn test_rrsig_rfc8080() {
+        // Example 1 of RFC 8080, section 6.1
+        let mx = MxRecord {
+            name: "mail.example.com.".to_string(),
+            ttl: 3600,
+            prio: 10,
+            comment: None,
+        };
+        let (rtype, rdata) = rdata(&Record::Mx(&mx));
+        let set = RrSet {
+            ttl: 3600,
+            rdata: BTreeSet::from([rdata]),
+        };
+        let window = Window {
+            inception: 1438207200,
+            expiration: 1440021600,
+        };
+        let record = rrsig(
+            &rfc8080_key(),
+            "example.com.",
+            rtype,
+            &set,
+            "example.com.",
+            window,
+        );
+        assert_eq!(
+            record.data,
+            "MX 15 2 3600 20150819220000 20150729220000 3613 example.com. \
+             oL9krJun7xfBOIWcGHi7mag5/hdZrKWw15jPGrHpjQeRAvTdszaPD+QLs3fx8A4M3e23mRZ9VrbpMngwcrqNAg=="
+        );
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let mut names = vec![
+            "z.example.",
+            "example.",
+            "*.z.example.",
+            "yljkjljk.a.example.",
+            "a.example.",
+            "Z.a.example.",
+            "zABC.a.EXAMPLE.",
+            "\\200.z.example.",
+        ];
+        names.sort_by_key(|name| canonical_key(name));
+        assert_eq!(
+            names,
+            [
+                "example.",
+                "a.example.",
+                "yljkjljk.a.example.",
+                "Z.a.example.",
+                "zABC.a.EXAMPLE.",
+                "z.example.",
+                "*.z.example.",
+                "\\200.z.example.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_bitmap() {
+        // A MX RRSIG NSEC TYPE1234, from RFC 4034, section 4.3
+        let types = BTreeSet::from([1, 15, 46, 47, 1234]);
+        assert_eq!(
+            hex::encode(type_bitmap(&types)),
+            format!("0006400100000003041b{}20", "00".repeat(26))
+        );
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "19700101000000");
+        assert_eq!(format_time(1440021600), "20150819220000");
+        assert_eq!(format_time(951782400), "20000229000000");
+        for time in [0, 1440021600, 951782400, 4102444799] {
+            assert_eq!(parse_time(&format_time(time)).unwrap(), time);
+        }
+        assert!(parse_time("2015081922000").is_err());
+        assert!(parse_time("20151319220000").is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt;
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+#[cfg(feature = "cli")]
+use miette::{GraphicalReportHandler, GraphicalTheme};
+use thiserror::Error;
+
+/// The input could not be read as YAML/TOML or did not match the config schema
+#[derive(Debug, Error, Diagnostic)]
+#[error("{message}")]
+pub struct ParseError {
+    pub message: String,
+    #[source_code]
+    pub source_code: Option<NamedSource<String>>,
+    #[label("{}", label.as_deref().unwrap_or("here"))]
+    pub span: Option<SourceSpan>,
+    pub label: Option<String>,
+}
+
+impl ParseError {
+    pub fn new(message: impl Into<String>) -> Self {
+        ParseError {
+            message: message.into(),
+            source_code: None,
+            span: None,
+            label: None,
+        }
+    }
+
+    /// Attaches the offending part of the input so it can be shown as a snippet
+    pub fn with_source(mut self, name: &str, source: &str, span: SourceSpan) -> Self {
+        self.source_code = Some(NamedSource::new(name, source.to_string()));
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// 1-based line and column where the span starts in the source
+    pub fn position(&self) -> Option<(usize, usize)> {
+        let source = self.source_code.as_ref()?.inner();
+        Some(line_col(source, self.span?.offset()))
+    }
+}
+
+/// Independent errors collected so they can be reported together instead of
+/// stopping at the first one
+#[derive(Debug, Default)]
+pub struct Errors(Vec<(Option<String>, anyhow::Error)>);
+
+impl Errors {
+    /// Keeps the error of a failed step and returns the value of a successful one
+    pub fn check<T>(&mut self, result: anyhow::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.push(None, err);
+                None
+            }
+        }
+    }
+
+    /// Adds an error that happened at `location`, e.g. a zone name. The
+    /// errors of a nested collection keep their own location if they have one.
+    pub fn push(&mut self, location: Option<&str>, err: anyhow::Error) {
+        match err.downcast::<Errors>() {
+            Ok(nested) => {
+                for (inner, err) in nested.0 {
+                    self.0
+                        .push((inner.or_else(|| location.map(str::to_string)), err));
+                }
+            }
+            Err(err) => self.0.push((location.map(str::to_string), err)),
+        }
+    }
+
+    /// Marks an error with its location, for errors that are collected later
+    pub fn at(location: &str, err: anyhow::Error) -> anyhow::Error {
+        let mut errors = Errors::default();
+        errors.push(Some(location), err);
+        errors.into()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Succeeds if nothing failed. A single error is returned as it is.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        match self.0.len() {
+            0 => Ok(()),
+            1 => Err(self.0.remove(0).1),
+            _ => Err(self.into()),
+        }
+    }
+}
+
+impl fmt::Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let located = |(location, err): &(Option<String>, anyhow::Error)| match location {
+            Some(location) => format!("{location}: {err}"),
+            None => err.to_string(),
+        };
+        if let [error] = self.0.as_slice() {
+            return write!(f, "{}", located(error));
+        }
+        write!(f, "{} errors:", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n- {}", located(error))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Errors {}
+
+/// Replaces the message of an error while keeping it recognizable as a parse error
+pub fn reword(err: anyhow::Error, msg: String) -> anyhow::Error {
+    match err.downcast::<ParseError>() {
+        Ok(mut err) => {
+            err.message = msg;
+            err.into()
+        }
+        Err(_) => anyhow::Error::msg(msg),
+    }
+}
+
+/// Byte offset of a 1-based line and column
+pub fn offset_of(source: &str, line: usize, column: usize) -> Option<usize> {
+    let start: usize = source
+        .split_inclusive('\n')
+        .take(line.checked_sub(1)?)
+        .map(str::len)
+        .sum();
+    let text = source[start..].lines().next().unwrap_or("");
+    let column = text
+        .char_indices()
+        .nth(column.checked_sub(1)?)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    Some(start + column)
+}
+
+/// 1-based line and column of a byte offset
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+/// Span from `offset` to the end of the value starting there
+pub fn token_span(source: &str, offset: usize) -> SourceSpan {
+    let offset = offset.min(source.len());
+    let len = source[offset..]
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim_end()
+        .len();
+    (offset, len).into()
+}
+
+/// Formats an error for the terminal. Parse errors that know their position
+/// are shown with the annotated source snippet.
+#[cfg(feature = "cli")]
+pub fn render_error(err: &anyhow::Error, color: bool) -> String {
+    if let Some(parse_err) = err.downcast_ref::<ParseError>() {
+        if parse_err.source_code.is_some() {
+            let theme = if color {
+                GraphicalTheme::unicode()
+            } else {
+                GraphicalTheme::unicode_nocolor()
+            };
+            let mut output = String::new();
+            if GraphicalReportHandler::new_themed(theme)
+                .render_report(&mut output, parse_err)
+                .is_ok()
+            {
+                return format!("Error:\n{output}");
+            }
+        }
+    }
+    format!("Error: {err:?}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reword_keeps_parse_error() {
+        let err = reword(
+            ParseError::new("bad")
+                .with_source("input", "a: b", (3, 1).into())
+                .into(),
+            "file: bad".to_string(),
+        );
+        assert_eq!(err.to_string(), "file: bad");
+        let parse_err = err.downcast_ref::<ParseError>().unwrap();
+        assert!(parse_err.source_code.is_some());
+
+        let err = reword(anyhow::anyhow!("invalid"), "file: invalid".to_string());
+        assert!(!err.is::<ParseError>());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut errors = Errors::default();
+        assert_eq!(errors.check(Ok::<_, anyhow::Error>(1)), Some(1));
+        assert!(errors.is_empty());
+        errors.check::<()>(Err(anyhow::anyhow!("Email is required")));
+        let err = errors.finish().unwrap_err();
+        assert_eq!(err.to_string(), "Email is required");
+
+        let mut nested = Errors::default();
+        nested.push(None, anyhow::anyhow!("Email is required"));
+        nested.push(Some("b.example."), anyhow::anyhow!("Duplicate"));
+        let mut errors = Errors::default();
+        errors.push(Some("a.example."), nested.into());
+        let located = Errors::at("c.example.", anyhow::anyhow!("Email is required"));
+        assert_eq!(located.to_string(), "c.example.: Email is required");
+        errors.push(None, located);
+        errors.push(None, anyhow::anyhow!("Reverse zone networks overlap"));
+        assert_eq!(
+            errors.finish().unwrap_err().to_string(),
+            "4 errors:\n- a.example.: Email is required\n- b.example.: Duplicate\n- c.example.: Email is required\n- Reverse zone networks overlap"
+        );
+    }
+
+    #[test]
+    fn test_offsets() {
+        let source = "zone:\n  example.com:\n    hosts: [\n";
+        assert_eq!(offset_of(source, 3, 12), Some(32));
+        assert_eq!(line_col(source, 32), (3, 12));
+        assert_eq!(offset_of(source, 0, 1), None);
+        assert_eq!(token_span(source, 32), (32, 1).into());
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_render_error_snippet() {
+        let source = "zone:\n  example.com:\n    hosts: 1\n";
+        let err: anyhow::Error = ParseError::new("YAML parse error")
+            .with_source("zones.yaml", source, token_span(source, 32))
+            .with_label("expected a map")
+            .into();
+        let output = render_error(&err, false);
+        assert!(output.contains("zones.yaml:3:12"), "{output}");
+        assert!(output.contains("hosts: 1"), "{output}");
+        assert!(output.contains("expected a map"), "{output}");
+    }
+}
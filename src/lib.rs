@@ -5,10 +5,37 @@ compile_error!("At least one of the features 'yaml' or 'toml' must be enabled");
 compile_error!("At least one of the features 'nsd' or 'unbound' must be enabled");
 
 pub mod args;
+pub mod canonical;
+pub mod check;
 pub mod constants;
+pub mod diff;
+#[cfg(feature = "cli")]
+pub mod dns;
+#[cfg(feature = "dnssec")]
+pub mod dnssec;
+pub mod error;
+pub mod lint;
+#[cfg(feature = "cli")]
+pub mod lock;
+pub mod origin;
 pub mod output;
 pub mod parser;
+pub mod prelude;
 pub mod record;
+pub mod report;
+#[cfg(feature = "cli")]
 pub mod serial;
+#[cfg(feature = "source")]
+pub mod source;
+pub mod stats;
+pub mod tags;
+#[cfg(feature = "template")]
+pub mod template;
 pub mod transform;
+#[cfg(feature = "nsd")]
+pub mod tsig;
 pub mod validation;
+pub mod vars;
+#[cfg(feature = "cli")]
+pub mod verify;
+pub mod window;
@@ -0,0 +1,430 @@
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::check::{
+    cross_zone_duplicates, mx_targets, owner, private_addresses, soa_timers, special_use,
+    srv_targets, ttl_advice, Finding, Severity,
+};
+use crate::parser::ZoneSet;
+
+/// Severity a rule is reported with, or `off` to disable it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Warning,
+    Error,
+}
+
+/// Setting of one rule: just the level, or a table with level and threshold
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum RuleConfig {
+    Level(Level),
+    #[serde(rename_all = "kebab-case")]
+    Table {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        severity: Option<Level>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<u32>,
+    },
+}
+
+/// The `lint` section of the config
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub rules: IndexMap<String, RuleConfig>,
+}
+
+/// A lint rule with the level it has unless configured otherwise
+pub struct Rule {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// `None` keeps the severity of each finding
+    default: Option<Level>,
+}
+
+/// All rules in the order they are run
+pub const RULES: [Rule; 9] = [
+    Rule {
+        name: "mx-target",
+        description: "MX targets have an A/AAAA record and are no CNAME",
+        default: None,
+    },
+    Rule {
+        name: "srv-target",
+        description: "SRV targets have an A/AAAA record and are no CNAME",
+        default: None,
+    },
+    Rule {
+        name: "soa-timers",
+        description: "SOA timers are within the RFC 1912/2308 ranges",
+        default: None,
+    },
+    Rule {
+        name: "private-address",
+        description: "Zones marked `public` have no RFC 1918/ULA addresses",
+        default: None,
+    },
+    Rule {
+        name: "special-use",
+        description: "No zone is below a special-use domain like .local",
+        default: None,
+    },
+    Rule {
+        name: "ttl-advice",
+        description: "Record TTLs are between a minute and a week",
+        default: None,
+    },
+    Rule {
+        name: "cross-zone-duplicate",
+        description: "No name is defined in more than one zone",
+        default: Some(Level::Off),
+    },
+    Rule {
+        name: "min-ttl",
+        description: "No record TTL is below `value` seconds",
+        default: Some(Level::Off),
+    },
+    Rule {
+        name: "min-nameservers",
+        description: "Every zone has at least `value` NS records",
+        default: Some(Level::Off),
+    },
+];
+
+impl LintConfig {
+    fn setting(&self, rule: &Rule) -> (Option<Level>, Option<u32>) {
+        match self.rules.get(rule.name) {
+            Some(RuleConfig::Level(level)) => (Some(*level), None),
+            // Configuring a value enables a rule that is off by default
+            Some(RuleConfig::Table { severity, value }) => (
+                severity.or(rule.default.filter(|level| *level != Level::Off)),
+                *value,
+            ),
+            None => (rule.default, None),
+        }
+    }
+
+    /// Sets a rule to the level of a `RULE=LEVEL` override from the command
+    /// line, keeping the `value` of a threshold rule
+    pub fn set_rule(&mut self, setting: &str) -> Result<()> {
+        let Some((name, level)) = setting.split_once('=') else {
+            bail!("Invalid --rule '{setting}', expected RULE=LEVEL");
+        };
+        let level = match level {
+            "off" => Level::Off,
+            "warning" => Level::Warning,
+            "error" => Level::Error,
+            _ => bail!("Invalid level '{level}' of --rule {name}, expected off, warning or error"),
+        };
+        match self.rules.get_mut(name) {
+            Some(RuleConfig::Table { severity, .. }) => *severity = Some(level),
+            Some(rule) => *rule = RuleConfig::Level(level),
+            None => {
+                self.rules
+                    .insert(name.to_string(), RuleConfig::Level(level));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn min_ttl(zones: &ZoneSet, min: u32) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for zone in &zones.forward {
+        let low = zone.records().filter(|r| r.ttl() < min);
+        for record in low {
+            let name = owner(zone, &record);
+            findings.push(Finding {
+                rule: "min-ttl",
+                zone: zone.base.name.clone(),
+                severity: Severity::Error,
+                message: format!(
+                    "{} record of {name} has TTL {} below {min}",
+                    record.record_type(),
+                    record.ttl()
+                ),
+            });
+        }
+    }
+    findings
+}
+
+fn min_nameservers(zones: &ZoneSet, min: u32) -> Vec<Finding> {
+    let bases = zones
+        .forward
+        .iter()
+        .map(|z| &z.base)
+        .chain(zones.reverse.iter().map(|z| &z.base));
+    bases
+        .filter(|base| base.nameserver.len() < min as usize)
+        .map(|base| Finding {
+            rule: "min-nameservers",
+            zone: base.name.clone(),
+            severity: Severity::Error,
+            message: format!(
+                "{} NS records, expected at least {min}",
+                base.nameserver.len()
+            ),
+        })
+        .collect()
+}
+
+/// Runs every enabled rule and returns the findings with their configured
+/// severity. Fails for unknown rule names and for threshold rules without a
+/// `value`.
+pub fn lint(zones: &ZoneSet, config: &LintConfig) -> Result<Vec<Finding>> {
+    if let Some(name) = config
+        .rules
+        .keys()
+        .find(|name| !RULES.iter().any(|rule| rule.name == name.as_str()))
+    {
+        bail!("Unknown lint rule '{name}'");
+    }
+
+    let mut findings = Vec::new();
+    for rule in &RULES {
+        let (level, value) = config.setting(rule);
+        if level == Some(Level::Off) {
+            continue;
+        }
+        let threshold = || match value {
+            Some(value) => Ok(value),
+            None => bail!("Lint rule '{}' needs a value", rule.name),
+        };
+        let mut rule_findings = match rule.name {
+            "mx-target" => mx_targets(zones, Severity::Error),
+            "srv-target" => srv_targets(zones),
+            "soa-timers" => soa_timers(zones),
+            "private-address" => private_addresses(zones),
+            "special-use" => special_use(zones),
+            "ttl-advice" => ttl_advice(zones),
+            "cross-zone-duplicate" => cross_zone_duplicates(zones),
+            "min-ttl" => min_ttl(zones, threshold()?),
+            "min-nameservers" => min_nameservers(zones, threshold()?),
+            _ => unreachable!("every rule is handled"),
+        };
+        if let Some(level) = level {
+            let severity = match level {
+                Level::Error => Severity::Error,
+                _ => Severity::Warning,
+            };
+            for finding in &mut rule_findings {
+                finding.severity = severity;
+            }
+        }
+        findings.extend(rule_findings);
+    }
+    Ok(findings)
+}
+
+/// Formats the findings grouped by zone, followed by a summary line
+pub fn format_lint(findings: &[Finding]) -> String {
+    let mut output = String::new();
+    let mut zones: IndexMap<&str, Vec<&Finding>> = IndexMap::new();
+    for finding in findings {
+        zones.entry(&finding.zone).or_default().push(finding);
+    }
+    for (zone, zone_findings) in &zones {
+        writeln!(output, "{zone}").unwrap();
+        for finding in zone_findings {
+            let severity = match finding.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            writeln!(
+                output,
+                "  {severity:<7}  {:<20}  {}",
+                finding.rule, finding.message
+            )
+            .unwrap();
+        }
+    }
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    writeln!(
+        output,
+        "{errors} errors, {} warnings in {} zones",
+        findings.len() - errors,
+        zones.len()
+    )
+    .unwrap();
+    output
+}
+
+/// First line of the config that names the zone on its own rather than as
+/// part of a host or email, 1-based
+fn zone_line(source: &str, zone: &str) -> Option<usize> {
+    let zone = zone.trim_end_matches('.').to_ascii_lowercase();
+    let names_zone = |line: &str| {
+        line.match_indices(&zone).any(|(start, _)| {
+            let before = line[..start].chars().next_back();
+            let after = line[start + zone.len()..].chars().next();
+            !before.is_some_and(|c| c.is_alphanumeric() || ".-@".contains(c))
+                && after.is_none_or(|c| ":\"']".contains(c))
+        })
+    };
+    source
+        .lines()
+        .position(|line| names_zone(&line.to_ascii_lowercase()))
+        .map(|index| index + 1)
+}
+
+/// Formats the findings as a SARIF 2.1.0 log for code scanning tools. With
+/// the path and text of the input file, every finding points to the line
+/// where its zone is defined.
+pub fn format_sarif(findings: &[Finding], input: Option<(&str, &str)>) -> String {
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|rule| json!({ "id": rule.name, "shortDescription": { "text": rule.description } }))
+        .collect();
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            let level = match finding.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            let mut result = json!({
+                "ruleId": finding.rule,
+                "level": level,
+                "message": { "text": finding.to_string() },
+            });
+            if let Some((path, source)) = input {
+                let mut location = json!({ "artifactLocation": { "uri": path } });
+                if let Some(line) = zone_line(source, &finding.zone) {
+                    location["region"] = json!({ "startLine": line });
+                }
+                result["locations"] = json!([{ "physicalLocation": location }]);
+            }
+            result
+        })
+        .collect();
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "zonefile-rs",
+                    "informationUri": "https://github.com/isnogudus/zonefile-rs",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&log).expect("SARIF logs are always serializable")
+}
+
+#[cfg(test)]
+#[cfg(feature = "yaml")]
+mod tests {
+    use super::*;
+    use crate::args::InputFormat;
+    use crate::parser::parse;
+
+    fn zones() -> ZoneSet {
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    mx: mail\n    hosts:\n      www:\n        ip: 192.168.1.2\n        ttl: 60\n";
+        parse(yaml, 2025060100, InputFormat::Yaml).unwrap()
+    }
+
+    fn config(toml: &str) -> LintConfig {
+        serde_json::from_value(serde_json::json!({ "rules": serde_json::from_str::<serde_json::Value>(toml).unwrap() })).unwrap()
+    }
+
+    #[test]
+    fn test_lint_defaults() {
+        let findings = lint(&zones(), &LintConfig::default()).unwrap();
+        let rules: Vec<&str> = findings.iter().map(|f| f.rule).collect();
+        assert_eq!(rules, ["mx-target"]);
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_lint_configured_rules() {
+        let config = config(
+            r#"{"mx-target": "warning", "min-ttl": {"severity": "error", "value": 300}, "min-nameservers": {"value": 2}}"#,
+        );
+        let findings = lint(&zones(), &config).unwrap();
+        let found: Vec<(&str, Severity)> = findings.iter().map(|f| (f.rule, f.severity)).collect();
+        assert_eq!(
+            found,
+            [
+                ("mx-target", Severity::Warning),
+                ("min-ttl", Severity::Error),
+                ("min-ttl", Severity::Error),
+                ("min-nameservers", Severity::Error),
+            ]
+        );
+        assert!(format_lint(&findings).ends_with("3 errors, 1 warnings in 1 zones\n"));
+    }
+
+    #[test]
+    fn test_lint_invalid_config() {
+        let err = lint(&zones(), &config(r#"{"max-ttl": "error"}"#)).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown lint rule 'max-ttl'");
+        let err = lint(&zones(), &config(r#"{"min-ttl": "error"}"#)).unwrap_err();
+        assert_eq!(err.to_string(), "Lint rule 'min-ttl' needs a value");
+    }
+
+    #[test]
+    fn test_set_rule() {
+        let mut config = config(r#"{"mx-target": "off", "min-ttl": {"value": 300}}"#);
+        config.set_rule("mx-target=warning").unwrap();
+        config.set_rule("min-ttl=warning").unwrap();
+        config.set_rule("soa-timers=off").unwrap();
+        assert_eq!(config.rules["mx-target"], RuleConfig::Level(Level::Warning));
+        assert_eq!(
+            config.rules["min-ttl"],
+            RuleConfig::Table {
+                severity: Some(Level::Warning),
+                value: Some(300)
+            }
+        );
+        assert_eq!(config.rules["soa-timers"], RuleConfig::Level(Level::Off));
+
+        let err = config.set_rule("mx-target").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid --rule 'mx-target', expected RULE=LEVEL"
+        );
+        let err = config.set_rule("mx-target=info").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid level 'info' of --rule mx-target, expected off, warning or error"
+        );
+    }
+
+    #[test]
+    fn test_format_sarif() {
+        let source = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    mx: mail\n";
+        let zones = parse(source, 2025060100, InputFormat::Yaml).unwrap();
+        let findings = lint(&zones, &LintConfig::default()).unwrap();
+        let sarif: Value =
+            serde_json::from_str(&format_sarif(&findings, Some(("zones.yaml", source)))).unwrap();
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "mx-target");
+        assert_eq!(result["level"], "error");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "zones.yaml");
+        assert_eq!(location["region"]["startLine"], 5);
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"][0]["id"],
+            "mx-target"
+        );
+
+        let sarif: Value = serde_json::from_str(&format_sarif(&findings, None)).unwrap();
+        assert!(sarif["runs"][0]["results"][0].get("locations").is_none());
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// First pause between attempts to take a held lock, doubled up to `MAX_BACKOFF`
+const FIRST_BACKOFF: Duration = Duration::from_millis(50);
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Advisory lock held for the duration of a run, released when dropped
+#[derive(Debug)]
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// Takes an exclusive lock on `path`, creating the file if needed. While
+    /// another process holds the lock, retries with backoff for up to `wait`.
+    pub fn acquire(path: &Path, wait: Duration) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Cannot open lock file {}", path.display()))?;
+
+        let deadline = Instant::now() + wait;
+        let mut backoff = FIRST_BACKOFF;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(RunLock { _file: file }),
+                Err(TryLockError::WouldBlock) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(
+                            anyhow::Error::new(io::Error::from(io::ErrorKind::WouldBlock)).context(
+                                format!("{} is locked by another running instance", path.display()),
+                            ),
+                        );
+                    }
+                    sleep(backoff.min(deadline - now));
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Cannot lock {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".serial.lock");
+
+        let lock = RunLock::acquire(&path, Duration::ZERO).unwrap();
+        let err = RunLock::acquire(&path, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("locked"), "{err}");
+
+        drop(lock);
+        assert!(RunLock::acquire(&path, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn test_lock_waits_for_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".serial.lock");
+
+        let lock = RunLock::acquire(&path, Duration::ZERO).unwrap();
+        let start = Instant::now();
+        assert!(RunLock::acquire(&path, Duration::from_millis(200)).is_err());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        let release = std::thread::spawn(move || {
+            sleep(Duration::from_millis(100));
+            drop(lock);
+        });
+        assert!(RunLock::acquire(&path, Duration::from_secs(10)).is_ok());
+        release.join().unwrap();
+    }
+}
@@ -1,101 +1,1791 @@
-use anyhow::Result;
-use clap::Parser;
+#[cfg(feature = "nsd")]
+use anyhow::Context;
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+#[cfg(feature = "dnssec")]
+use std::collections::HashMap;
 use std::fs;
-use std::io::Read;
-use std::path::Path;
-use zonefile_rs::args::InputFormat;
+#[cfg(feature = "unbound")]
+use std::fs::File;
+#[cfg(feature = "unbound")]
+use std::io::BufWriter;
+#[cfg(any(feature = "unbound", feature = "nsd"))]
+use std::io::Write;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use zonefile_rs::args::{CliConfig, ConfChecker, InputFormat, OutputFormat, ZoneChecker};
+use zonefile_rs::canonical::{canonicalize, has_comments};
+use zonefile_rs::check::{
+    mx_targets, ns_targets, private_addresses, serial_backwards, serial_cmp, soa_timers,
+    special_use, srv_targets, ttl_advice, unresolved_nameservers, zone_limits, Finding, Severity,
+    ZoneLimits,
+};
+#[cfg(feature = "dnssec")]
+use zonefile_rs::dns::transfer_dnskeys;
+use zonefile_rs::dns::{lookup_host, query_soa_serial, server_addr};
+#[cfg(feature = "dnssec")]
+use zonefile_rs::dnssec::{
+    ds_keys, external_keys,
+    keys::{
+        format_keys, key_zones, load_public_keys, parse_dnskeys, read_dnskey, FLAGS_KSK, FLAGS_ZSK,
+    },
+    load_keys, sign_forward, sign_reverse, DigestType, DnsKey, DsRecord, Key, Window,
+};
+use zonefile_rs::error::{render_error, ParseError};
+use zonefile_rs::lint::{format_lint, format_sarif, lint};
+use zonefile_rs::lock::RunLock;
+use zonefile_rs::origin::annotate;
+use zonefile_rs::verify::{check_config, verify_zones};
+use zonefile_rs::window::{apply_windows, Date};
 
+#[cfg(feature = "dnssec")]
+use zonefile_rs::output::nsd::write_signatures;
 #[cfg(feature = "nsd")]
-use zonefile_rs::output::nsd::write_nsd;
+use zonefile_rs::output::nsd::{
+    fragment_path, render_conf_fragments, render_zones_conf, split_zone, stale_fragments,
+    write_forward_zone, write_reverse_zone, zone_path, ZoneLayout,
+};
+#[cfg(feature = "tar")]
+use zonefile_rs::output::tar::{archive_change, gzip, render_tar, write_archive};
 #[cfg(feature = "unbound")]
-use zonefile_rs::output::unbound::generate_unbound;
-use zonefile_rs::parser::parse;
-use zonefile_rs::serial::{calc_serial, load_serial, save_serial};
+use zonefile_rs::output::unbound::{write_private_domains, write_unbound};
+use zonefile_rs::output::{write_files, Backup, FileChange, Header, OutputFile, StagedFile};
+#[cfg(feature = "unbound")]
+use zonefile_rs::output::{CountingWriter, UNBOUND_COLUMN_WIDTH};
+#[cfg(feature = "nsd")]
+use zonefile_rs::output::{NSD_COLUMN_WIDTH, NSD_FILE_TEMPLATE, NSD_ZONE_DIR};
+use zonefile_rs::parser::{
+    config_files, format_config, parse_config, parse_dir_config, resolve, Content, ZoneSet,
+};
+#[cfg(feature = "nsd")]
+use zonefile_rs::parser::{ForwardZone, ReverseZone, ZoneBase};
+use zonefile_rs::report::{
+    dump_records, explain_zone, format_explained, list_zones, name_matches, query_records,
+    record_rows,
+};
+#[cfg(feature = "dnssec")]
+use zonefile_rs::serial::SignedState;
+use zonefile_rs::serial::{
+    calc_serial_with, git_serial, reference_time, GitSerial, SerialFormat, SerialState,
+    SerialStrategy,
+};
+use zonefile_rs::stats::Stats;
+use zonefile_rs::tags::{filter_outputs, filter_tags, TagFilter};
+#[cfg(feature = "template")]
+use zonefile_rs::template::parse_template;
+#[cfg(feature = "nsd")]
+use zonefile_rs::tsig::{TsigConfig, TsigKey};
 
 // Default input format based on available features
 #[cfg(feature = "yaml")]
-const DEFAULT_INPUT_FORMAT: &str = "yaml";
+const DEFAULT_INPUT_FORMAT: InputFormat = InputFormat::Yaml;
 
 #[cfg(all(feature = "toml", not(feature = "yaml")))]
-const DEFAULT_INPUT_FORMAT: &str = "toml";
+const DEFAULT_INPUT_FORMAT: InputFormat = InputFormat::Toml;
 
 // Default output format based on available features
 #[cfg(feature = "unbound")]
-const DEFAULT_OUTPUT_FORMAT: &str = "unbound";
+const DEFAULT_OUTPUT_FORMAT: OutputFormat = OutputFormat::Unbound;
 
 #[cfg(all(feature = "nsd", not(feature = "unbound")))]
-const DEFAULT_OUTPUT_FORMAT: &str = "nsd";
+const DEFAULT_OUTPUT_FORMAT: OutputFormat = OutputFormat::Nsd;
+
+const DEFAULT_SERIAL_FILE: &str = ".serial";
+const DEFAULT_LOCK_TIMEOUT: u64 = 10;
+const DNS_TIMEOUT: Duration = Duration::from_secs(3);
+#[cfg(feature = "dnssec")]
+const DEFAULT_SIGN_VALIDITY: u32 = 30;
+
+// Exit codes
+const EXIT_PARSE: u8 = 1;
+const EXIT_VALIDATION: u8 = 2;
+const EXIT_IO: u8 = 3;
+const EXIT_CHANGES: u8 = 4;
+const EXIT_USAGE: u8 = 64;
 
 #[derive(Parser)]
 #[command(name = "zonefile-rs")]
 #[command(about = "Generate DNS zone files from TOML or YAML configuration")]
 #[command(version)]
 struct Cli {
-    /// Input file (default: stdin)
-    #[arg(short, long, value_name = "FILE")]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Input file or directory of *.yaml/*.toml files (default: stdin)
+    #[arg(short, long, value_name = "FILE", global = true)]
     input: Option<String>,
 
-    /// Input format: yaml or toml
-    #[arg(short = 'I', long, value_name = "FORMAT", default_value = DEFAULT_INPUT_FORMAT)]
-    input_format: InputFormat,
+    /// Input format: yaml or toml [default: yaml]
+    #[arg(short = 'I', long, value_name = "FORMAT", global = true)]
+    input_format: Option<InputFormat>,
 
     /// Output file or directory
     #[arg(short, long, value_name = "PATH")]
     output: Option<String>,
 
-    /// Output format: unbound or nsd
-    #[arg(short = 'O', long, value_name = "FORMAT", default_value = DEFAULT_OUTPUT_FORMAT)]
-    output_format: OutputFormat,
+    /// Output format: unbound or nsd [default: unbound]
+    #[arg(short = 'O', long, value_name = "FORMAT")]
+    output_format: Option<OutputFormat>,
+
+    /// Render the input as a Jinja template before parsing
+    #[cfg(feature = "template")]
+    #[arg(short, long, global = true)]
+    template: bool,
+
+    /// Run the whole pipeline but only report which files would change
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Back up replaced output files: `--backup` keeps one `.bak` copy,
+    /// `--backup=N` keeps the N newest timestamped copies
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    backup: Option<Option<u64>>,
+
+    /// Check the zone files with an external checker before they are written
+    /// and fail if it rejects any: nsd-checkzone (default), named-checkzone
+    /// or kzonecheck
+    #[arg(long, value_name = "CHECKER", num_args = 0..=1, require_equals = true)]
+    verify: Option<Option<ZoneChecker>>,
+
+    /// Check the generated server config with the checker of the server,
+    /// unbound-checkconf or nsd-checkconf, and write nothing if it is rejected.
+    /// nsd-checkconf checks zones.conf only, not the zone files (see --verify)
+    #[arg(long, value_name = "CHECKER")]
+    check_with: Option<ConfChecker>,
+
+    /// Fail on warnings too, e.g. in CI
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Report MX targets without an A/AAAA record as warnings instead of errors,
+    /// e.g. for mail hosts served from elsewhere
+    #[arg(long, global = true)]
+    warn_mx_targets: bool,
+
+    /// Look up the nameservers outside the configured zones in live DNS and
+    /// fail if one does not resolve
+    #[arg(long, global = true)]
+    resolve_nameservers: bool,
+
+    /// Report explicit zone serials behind the saved serial as warnings
+    /// instead of errors
+    #[arg(long, global = true)]
+    warn_serial_backwards: bool,
+
+    /// Fail if a zone has more records than this
+    #[arg(long, value_name = "N", global = true)]
+    max_zone_records: Option<usize>,
+
+    /// Fail if the estimated zone file size of a zone exceeds this many bytes
+    #[arg(long, value_name = "BYTES", global = true)]
+    max_zone_size: Option<usize>,
+
+    /// Add Unbound `private-domain` and `domain-insecure` lines for zones
+    /// below special-use domains like home.arpa
+    #[arg(long)]
+    unbound_private_domains: bool,
+
+    /// Note the config file and line of every record next to it in the output
+    #[arg(long)]
+    annotate: bool,
+
+    /// Leave out the header with the version, time and input hash of the run,
+    /// so the same input always gives the same files
+    #[arg(long)]
+    reproducible: bool,
+
+    /// Only generate the zones and hosts tagged with TAG (repeatable)
+    #[arg(long, value_name = "TAG", global = true)]
+    include_tag: Vec<String>,
+
+    /// Leave out the zones and hosts tagged with TAG (repeatable)
+    #[arg(long, value_name = "TAG", global = true)]
+    exclude_tag: Vec<String>,
+
+    /// Only write the forward or the reverse zones, leaving the files and
+    /// serials of the others alone
+    #[arg(long, value_name = "KIND")]
+    only: Option<ZoneKind>,
+
+    /// Sign the NSD zone files with the Ed25519 keys in this directory
+    /// (K<zone>+015+<tag>.key and .private), zones without keys stay unsigned
+    #[cfg(feature = "dnssec")]
+    #[arg(long, value_name = "KEYDIR")]
+    sign: Option<PathBuf>,
+
+    /// Create a key for every zone without one, signing both its keys and
+    /// its records
+    #[cfg(feature = "dnssec")]
+    #[arg(long, requires = "sign")]
+    sign_generate: bool,
+
+    /// Days the signatures are valid for [default: 30]
+    #[cfg(feature = "dnssec")]
+    #[arg(long, value_name = "DAYS", requires = "sign",
+          value_parser = clap::value_parser!(u32).range(2..))]
+    sign_validity: Option<u32>,
+
+    /// Publish the DNSKEY records of a zone signed by an external signer,
+    /// e.g. with an HSM, leaving the signing to it: ZONE=FILE with the records
+    /// in zone file syntax or ZONE=axfr:SERVER to transfer them (repeatable)
+    #[cfg(feature = "dnssec")]
+    #[arg(long, value_name = "ZONE=SOURCE")]
+    import_dnskey: Vec<String>,
+
+    /// Print a summary of zones, records and bytes changed to stderr
+    #[arg(long)]
+    stats: bool,
+
+    /// Print the summary as JSON to stderr
+    #[arg(long)]
+    stats_json: bool,
+
+    /// Serial number file [default: .serial]
+    #[arg(short, long, value_name = "FILE", global = true)]
+    serial: Option<String>,
+
+    /// Never write the serial file, e.g. for previews, CI or read-only deployments
+    #[arg(long, global = true)]
+    no_serial_save: bool,
+
+    /// How new serials are generated: date (YYYYMMDDnn), unixtime or counter
+    /// [default: date]
+    #[arg(long, value_name = "STRATEGY", global = true)]
+    serial_strategy: Option<SerialStrategy>,
+
+    /// Layout of date serials, e.g. YYMMDDnnnn [default: YYYYMMDDnn]
+    #[arg(long, value_name = "LAYOUT", global = true)]
+    serial_format: Option<SerialFormat>,
+
+    /// Use at least this serial, e.g. to move past serials handed out before
+    #[arg(long, value_name = "N", global = true)]
+    serial_at_least: Option<u32>,
+
+    /// Query this server for the live serial of each zone and continue from
+    /// there if it is ahead of the serial file, e.g. after restoring a backup
+    #[arg(long, value_name = "SERVER", global = true)]
+    serial_from_server: Option<String>,
 
-    /// Serial number file
-    #[arg(short, long, value_name = "FILE", default_value = ".serial")]
-    serial: String,
+    /// Derive the serial from the git history of the input instead:
+    /// date (of the last commit) or count (of commits)
+    #[arg(long, value_name = "MODE", global = true)]
+    serial_from_git: Option<GitSerial>,
+
+    /// Date (YYYY-MM-DD) the serial is based on [default: $SOURCE_DATE_EPOCH or today]
+    #[arg(long, value_name = "DATE", global = true)]
+    now: Option<NaiveDate>,
+
+    /// Lock file guarding against concurrent runs [default: <serial file>.lock,
+    /// none with --no-serial-save]
+    #[arg(long, value_name = "FILE")]
+    lock_file: Option<String>,
+
+    /// Seconds to wait for a running instance to release the lock [default: 10]
+    #[arg(long, value_name = "SECONDS")]
+    lock_timeout: Option<u64>,
+
+    /// Config file with default settings [default: ~/.config/zonefile-rs/config.toml]
+    #[arg(short, long, value_name = "FILE", global = true)]
+    config: Option<String>,
+
+    /// Column the record types of the Unbound config are aligned at [default: 46]
+    #[arg(long, value_name = "N", global = true)]
+    unbound_column_width: Option<usize>,
+
+    /// Column the record types of the NSD zone files are aligned at [default: 32]
+    #[arg(long, value_name = "N", global = true)]
+    nsd_column_width: Option<usize>,
+
+    /// Name of the NSD zone files, `{zone}` with and `{name}` without the
+    /// trailing dot of the zone name, e.g. `db.{name}` [default: {zone}zone]
+    #[arg(long, value_name = "TEMPLATE", global = true)]
+    nsd_file_template: Option<String>,
+
+    /// Directory of the NSD zone files below the output directory, as
+    /// zones.conf and $INCLUDE name them [default: master]
+    #[arg(long, value_name = "DIR", global = true)]
+    nsd_zone_dir: Option<String>,
+
+    /// Split NSD zone files larger than BYTES into part files pulled in with
+    /// $INCLUDE, by the first letter of the names or the /24 of reverse zones
+    #[arg(long, value_name = "BYTES")]
+    nsd_split_size: Option<usize>,
+
+    /// Write the zone entries of zones.conf as one fragment per zone into DIR
+    /// instead, for an `include:` next to hand-managed NSD config
+    #[arg(long, value_name = "DIR")]
+    nsd_conf_dir: Option<String>,
+
+    /// Delete the fragments in the --nsd-conf-dir of zones no longer in the
+    /// config
+    #[arg(long)]
+    prune_fragments: bool,
+
+    /// Pack zones.conf and the zone files into a tar archive at FILE instead
+    /// of writing them to the output directory, `-` for stdout
+    #[cfg(feature = "tar")]
+    #[arg(long, value_name = "FILE")]
+    tar: Option<String>,
+
+    /// Compress the tar archive with gzip, the default for a FILE ending in
+    /// .gz or .tgz
+    #[cfg(feature = "tar")]
+    #[arg(long, requires = "tar")]
+    gzip: bool,
 }
 
-#[derive(clap::ValueEnum, Clone)]
-enum OutputFormat {
-    #[cfg(feature = "unbound")]
-    Unbound,
-    #[cfg(feature = "nsd")]
-    Nsd,
+impl Cli {
+    /// Fills every setting not given on the command line from the config file
+    fn apply_config(&mut self) -> Result<()> {
+        let config = match &self.config {
+            Some(path) => CliConfig::load(Path::new(path))?,
+            None => match CliConfig::default_path().filter(|p| p.is_file()) {
+                Some(path) => CliConfig::load(&path)?,
+                None => CliConfig::default(),
+            },
+        };
+
+        self.input = self.input.take().or(config.input);
+        self.input_format = self.input_format.take().or(config.input_format);
+        self.output = self.output.take().or(config.output);
+        self.output_format = self.output_format.take().or(config.output_format);
+        self.serial = self.serial.take().or(config.serial);
+        self.no_serial_save |= config.no_serial_save.unwrap_or(false);
+        self.serial_strategy = self.serial_strategy.or(config.serial_strategy);
+        self.serial_from_git = self.serial_from_git.or(config.serial_from_git);
+        self.serial_format = self.serial_format.take().or(config.serial_format);
+        self.lock_file = self.lock_file.take().or(config.lock_file);
+        self.lock_timeout = self.lock_timeout.or(config.lock_timeout);
+        self.max_zone_records = self.max_zone_records.or(config.max_zone_records);
+        self.max_zone_size = self.max_zone_size.or(config.max_zone_size);
+        self.unbound_column_width = self.unbound_column_width.or(config.unbound_column_width);
+        self.nsd_column_width = self.nsd_column_width.or(config.nsd_column_width);
+        self.nsd_file_template = self.nsd_file_template.take().or(config.nsd_file_template);
+        self.nsd_zone_dir = self.nsd_zone_dir.take().or(config.nsd_zone_dir);
+        self.nsd_conf_dir = self.nsd_conf_dir.take().or(config.nsd_conf_dir);
+        self.nsd_split_size = self.nsd_split_size.or(config.nsd_split_size);
+        self.prune_fragments |= config.prune_fragments.unwrap_or(false);
+        self.unbound_private_domains |= config.unbound_private_domains.unwrap_or(false);
+        self.annotate |= config.annotate.unwrap_or(false);
+        self.reproducible |= config.reproducible.unwrap_or(false);
+        self.verify = self.verify.take().or(config.verify.map(Some));
+        self.check_with = self.check_with.or(config.check_with);
+        Ok(())
+    }
+
+    fn input_format(&self) -> InputFormat {
+        self.input_format.clone().unwrap_or(DEFAULT_INPUT_FORMAT)
+    }
+
+    fn backup(&self) -> Option<Backup> {
+        self.backup.map(|keep| match keep {
+            Some(keep) => Backup::Timestamped(keep as usize),
+            None => Backup::Simple,
+        })
+    }
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// List all zones with serial, TTL and record counts without writing output
+    List,
+    /// Print the resolved records as a table
+    Dump {
+        /// Only show records of this zone
+        #[arg(long, value_name = "NAME")]
+        zone: Option<String>,
 
-    let path = Path::new(&cli.serial);
-    let old_serial = load_serial(path);
-    let serial = calc_serial(old_serial);
+        /// Only show records of this type (e.g. A, AAAA, MX, PTR)
+        #[arg(long = "type", value_name = "TYPE")]
+        record_type: Option<String>,
+    },
+    /// Show every record that would exist for a name, including its PTRs
+    Query {
+        /// Fully qualified name to look up
+        name: String,
+    },
+    /// Show the effective SOA, TTL, NS and MX values of a zone and where they come from
+    Explain {
+        /// Name of a forward or reverse zone
+        zone: String,
+    },
+    /// Print the input config converted to another format
+    Convert {
+        /// Target format: yaml or toml
+        #[arg(long, value_name = "FORMAT")]
+        to: InputFormat,
+    },
+    /// Rewrite the input config in canonical form (stdin is written to stdout).
+    /// Comments are not kept
+    Fmt {
+        /// Only check whether the input is formatted, exit with 4 if not
+        #[arg(long)]
+        check: bool,
+        /// Rewrite the input file even though it has comments, dropping them
+        #[arg(long)]
+        force: bool,
+    },
+    /// Run the lint rules configured in the `lint` section of the input and
+    /// print a report, exit with 2 if any rule reports an error
+    Lint {
+        /// Report format: text or sarif (for code scanning tools)
+        #[arg(long, value_name = "FORMAT", default_value = "text")]
+        format: LintFormat,
 
-    let content = match cli.input {
-        Some(file) => fs::read_to_string(file)?,
+        /// Set a rule to off, warning or error for this run, overriding the
+        /// `lint` section, may be repeated
+        #[arg(long, value_name = "RULE=LEVEL")]
+        rule: Vec<String>,
+    },
+    /// Print the DS records of key signing keys for the parent zone, from
+    /// .key files, the keys of zones in a key directory, or DNSKEY records
+    /// on stdin (e.g. from dig)
+    #[cfg(feature = "dnssec")]
+    Ds {
+        /// .key files or zone names
+        #[arg(value_name = "KEY|ZONE")]
+        keys: Vec<String>,
+
+        /// Directory with the keys of the zones given by name [default: --sign]
+        #[arg(long, value_name = "KEYDIR")]
+        key_dir: Option<PathBuf>,
+
+        /// Digest of the DS records, may be repeated
+        #[arg(long, value_name = "TYPE", default_value = "sha256")]
+        digest: Vec<DigestType>,
+    },
+    /// Create, roll over and list the DNSSEC keys of the zones
+    #[cfg(feature = "dnssec")]
+    Keys {
+        #[command(subcommand)]
+        action: KeysCommand,
+
+        /// Key directory [default: --sign]
+        #[arg(long, value_name = "KEYDIR", global = true)]
+        key_dir: Option<PathBuf>,
+    },
+}
+
+#[cfg(feature = "dnssec")]
+#[derive(Subcommand)]
+enum KeysCommand {
+    /// Create a key signing and a zone signing key for every zone without
+    /// keys, all zones of the config if none are given
+    Generate {
+        /// Zone names
+        zones: Vec<String>,
+    },
+    /// Replace the zone signing key of a zone, or its key signing key. The
+    /// old key signs during the overlap and stays published for another one.
+    Rotate {
+        /// Zone name
+        zone: String,
+
+        /// Replace the key signing key, whose DS record the parent holds
+        #[arg(long)]
+        ksk: bool,
+
+        /// Days both keys are published, at least the longest TTL of the zone
+        #[arg(long, value_name = "DAYS", default_value_t = 2,
+              value_parser = clap::value_parser!(u32).range(1..))]
+        overlap: u32,
+    },
+    /// List the keys with their role, state and timing
+    List {
+        /// Zone names, all zones of the key directory if none are given
+        zones: Vec<String>,
+    },
+}
+
+#[derive(Clone, clap::ValueEnum)]
+enum LintFormat {
+    Text,
+    Sarif,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ZoneKind {
+    Forward,
+    Reverse,
+}
+
+/// Reads the input file, or stdin if no file is given
+fn read_input(input: Option<&str>) -> Result<String> {
+    match input {
+        Some(file) => Ok(fs::read_to_string(file)?),
         None => {
             let mut buffer = String::new();
             std::io::stdin().read_to_string(&mut buffer)?;
-            buffer
+            Ok(buffer)
+        }
+    }
+}
+
+fn convert(cli: &Cli, to: InputFormat) -> Result<ExitCode> {
+    print!("{}", format_config(&load_config(cli)?, to)?);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn format(cli: &Cli, check: bool, force: bool) -> Result<ExitCode> {
+    #[cfg(feature = "template")]
+    if cli.template {
+        bail!("fmt cannot be used with --template");
+    }
+    let input = cli.input.as_deref();
+    if input.is_some_and(|i| Path::new(i).is_dir()) {
+        bail!("fmt needs a single input file, not a directory");
+    }
+    // Without -I the extension of the input file tells its format
+    let input_format = cli
+        .input_format
+        .clone()
+        .or_else(|| {
+            let extension = Path::new(input?).extension()?.to_str()?;
+            InputFormat::from_extension(extension)
+        })
+        .unwrap_or(DEFAULT_INPUT_FORMAT);
+    let content = read_input(input)?;
+    let config = parse_config(&content, input_format.clone())?;
+    let formatted = format_config(&canonicalize(config), input_format)?;
+
+    let Some(path) = input else {
+        print!("{formatted}");
+        return Ok(ExitCode::SUCCESS);
+    };
+    if formatted == content {
+        return Ok(ExitCode::SUCCESS);
+    }
+    if check || cli.dry_run {
+        println!("would reformat {path}");
+        return Ok(ExitCode::from(EXIT_CHANGES));
+    }
+    if has_comments(&content) && !force {
+        bail!("{path} has comments, which fmt cannot keep; use --force to rewrite it without them");
+    }
+    let file = OutputFile {
+        path: path.into(),
+        content: formatted,
+    };
+    write_files(&[file], cli.backup())?;
+    eprintln!("reformatted {path}");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn parse_input(content: &str, cli: &Cli) -> Result<Content> {
+    #[cfg(feature = "template")]
+    if cli.template {
+        return parse_template(content, cli.input_format());
+    }
+    parse_config(content, cli.input_format())
+}
+
+/// Keeps the warnings in the zone set to print them after generation. Fails
+/// with all errors at once if there are any. With `strict`, every warning,
+/// including those found while resolving, counts as an error.
+fn report_findings(zones: &mut ZoneSet, mut findings: Vec<Finding>, strict: bool) -> Result<()> {
+    findings.splice(0..0, zones.warnings.drain(..));
+    let (errors, warnings): (Vec<_>, Vec<_>) = findings
+        .into_iter()
+        .partition(|f| strict || f.severity == Severity::Error);
+    zones.warnings = warnings;
+    if !errors.is_empty() {
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        bail!("{}", errors.join("\n"));
+    }
+    Ok(())
+}
+
+fn print_warnings(zones: &ZoneSet) {
+    for warning in &zones.warnings {
+        eprintln!("Warning: {warning}");
+    }
+}
+
+/// Writes the Unbound config, followed by the special-use domain settings if
+/// enabled
+#[cfg(feature = "unbound")]
+fn write_unbound_config(
+    mut out: &mut dyn Write,
+    zones: &ZoneSet,
+    column_width: usize,
+    private_domains: bool,
+) -> Result<()> {
+    write_unbound(
+        &mut out,
+        &zones.forward,
+        &zones.reverse,
+        &zones.unbound,
+        column_width,
+    )?;
+    if private_domains {
+        // Stub zones of delegations close the first server clause
+        let delegates = zones.forward.iter().any(|zone| !zone.delegation.is_empty())
+            || zones.reverse.iter().any(|zone| !zone.delegation.is_empty());
+        if delegates {
+            writeln!(out, "server:")?;
+        }
+        write_private_domains(&mut out, &zones.forward)?;
+    }
+    Ok(())
+}
+
+/// Prints the DS records of the given .key files and zones, or of the
+/// DNSKEY records on stdin. Of zones and stdin only the key signing keys
+/// count, unless there are none.
+#[cfg(feature = "dnssec")]
+fn print_ds(
+    cli: &Cli,
+    inputs: &[String],
+    key_dir: Option<&Path>,
+    digests: &[DigestType],
+) -> Result<ExitCode> {
+    let mut keys = Vec::new();
+    if inputs.is_empty() {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content)?;
+        let found = parse_dnskeys(&content)?;
+        if found.is_empty() {
+            bail!("No DNSKEY records on stdin");
+        }
+        keys.extend(ds_keys(&found).into_iter().cloned());
+    }
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_file() {
+            keys.push(read_dnskey(path, None)?);
+            continue;
+        }
+        let Some(dir) = key_dir.or(cli.sign.as_deref()) else {
+            bail!("{input} is not a file, zones given by name need --key-dir");
+        };
+        let zone = fqdn(input);
+        let found = load_public_keys(dir, &zone)?;
+        if found.is_empty() {
+            bail!("No keys for {zone} in {}", dir.display());
+        }
+        keys.extend(ds_keys(&found).into_iter().cloned());
+    }
+    for key in &keys {
+        for digest in digests {
+            println!("{}", DsRecord::of(key, *digest));
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Zone name with the trailing dot
+#[cfg(feature = "dnssec")]
+fn fqdn(name: &str) -> String {
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.")
+    }
+}
+
+/// Runs the `keys` subcommands on the key directory
+#[cfg(feature = "dnssec")]
+fn manage_keys(cli: &Cli, action: &KeysCommand, key_dir: Option<&Path>) -> Result<ExitCode> {
+    let Some(dir) = key_dir.or(cli.sign.as_deref()) else {
+        bail!("keys needs --key-dir or --sign");
+    };
+    let now = reference_time(cli.now)?.timestamp();
+    match action {
+        KeysCommand::Generate { zones } => {
+            let zones: Vec<String> = if zones.is_empty() {
+                let set = resolve(load_config(cli)?, 0)?;
+                let names = set.forward.iter().map(|zone| zone.base.name.clone());
+                names
+                    .chain(set.reverse.iter().map(|zone| zone.base.name.clone()))
+                    .collect()
+            } else {
+                zones.iter().map(|zone| fqdn(zone)).collect()
+            };
+            for zone in zones {
+                let keys = load_keys(dir, &zone)?;
+                if keys.iter().any(|key| key.timing.is_published(now)) {
+                    eprintln!("{zone} has keys already");
+                    continue;
+                }
+                let ksk = new_key(cli, dir, &zone, FLAGS_KSK, now, None)?;
+                new_key(cli, dir, &zone, FLAGS_ZSK, now, None)?;
+                println!("{}", DsRecord::of(&ksk.dnskey(), DigestType::Sha256));
+            }
+        }
+        KeysCommand::Rotate { zone, ksk, overlap } => {
+            let zone = fqdn(zone);
+            let overlap = i64::from(*overlap) * 86400;
+            let role = if *ksk {
+                "key signing key"
+            } else {
+                "zone signing key"
+            };
+            let mut old: Vec<Key> = load_keys(dir, &zone)?
+                .into_iter()
+                .filter(|key| key.is_ksk() == *ksk && key.timing.is_published(now))
+                .collect();
+            if let Some(key) = old.iter().find(|key| key.timing.inactive.is_some()) {
+                bail!("The {role} {} of {zone} is being replaced already", key.tag);
+            }
+            if !old.iter().any(|key| key.timing.is_active(now)) {
+                bail!("{zone} has no active {role} to replace");
+            }
+            // A new zone signing key is published before it signs, so
+            // resolvers know it before they see its signatures. A new key
+            // signing key signs right away, next to the old one, until the
+            // parent has its DS record.
+            let flags = if *ksk { FLAGS_KSK } else { FLAGS_ZSK };
+            let activate = (!*ksk).then_some(now + overlap);
+            let new = new_key(cli, dir, &zone, flags, now, activate)?;
+            for key in &mut old {
+                key.timing.inactive = Some(now + overlap);
+                key.timing.delete = Some(now + if *ksk { overlap } else { 2 * overlap });
+                if cli.dry_run {
+                    println!("would retire key {}", key.file_stem());
+                } else {
+                    let path = key.save_timing(dir)?;
+                    eprintln!("{:<9}  {}", "update", path.display());
+                }
+            }
+            if *ksk {
+                eprintln!(
+                    "Replace the DS records of {zone} at the parent within {} days by:",
+                    overlap / 86400
+                );
+                println!("{}", DsRecord::of(&new.dnskey(), DigestType::Sha256));
+            }
+        }
+        KeysCommand::List { zones } => {
+            let zones = if zones.is_empty() {
+                key_zones(dir)?
+            } else {
+                zones.iter().map(|zone| fqdn(zone)).collect()
+            };
+            let mut keys = Vec::new();
+            for zone in zones {
+                keys.extend(load_keys(dir, &zone)?);
+            }
+            print!("{}", format_keys(&keys, now));
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Creates a key in `dir`, unless in a dry run
+#[cfg(feature = "dnssec")]
+fn new_key(
+    cli: &Cli,
+    dir: &Path,
+    zone: &str,
+    flags: u16,
+    now: i64,
+    activate: Option<i64>,
+) -> Result<Key> {
+    let mut key = Key::generate(zone, flags)?;
+    key.timing.created = Some(now);
+    key.timing.activate = activate;
+    if cli.dry_run {
+        println!("would create key {}", key.file_stem());
+    } else {
+        let path = key.save(dir)?;
+        eprintln!("{:<9}  {}", "create", path.display());
+    }
+    Ok(key)
+}
+
+/// Keys and signature window of every zone to sign
+#[cfg(feature = "dnssec")]
+type Signing = HashMap<String, (Vec<Key>, Window)>;
+
+/// Loads the keys of every zone and picks the window of its signatures. The
+/// previous window is kept while it is valid for at least half the validity,
+/// so unchanged zones keep identical signatures. Zones signed anew that
+/// would keep their serial get the `generated` one, or the secondaries
+/// would not pick the new signatures up.
+#[cfg(feature = "dnssec")]
+fn prepare_signing(
+    cli: &Cli,
+    key_dir: &Path,
+    zones: &mut ZoneSet,
+    state: &SerialState,
+    generated: &[String],
+) -> Result<Signing> {
+    let output_format = cli.output_format.clone().unwrap_or(DEFAULT_OUTPUT_FORMAT);
+    if !matches!(output_format, OutputFormat::Nsd) {
+        bail!("--sign signs zone files, which only the NSD output has");
+    }
+    let now = reference_time(cli.now)?.timestamp();
+    let validity = i64::from(cli.sign_validity.unwrap_or(DEFAULT_SIGN_VALIDITY)) * 86400;
+    let serial = zones.serial;
+    let bases = zones
+        .forward
+        .iter_mut()
+        .map(|zone| &mut zone.base)
+        .chain(zones.reverse.iter_mut().map(|zone| &mut zone.base));
+
+    let mut signing = Signing::new();
+    for base in bases {
+        let mut keys = load_keys(key_dir, &base.name)?;
+        keys.retain(|key| key.timing.is_published(now));
+        if keys.is_empty() && cli.sign_generate {
+            let key = Key::generate(&base.name, FLAGS_KSK)?;
+            if cli.dry_run {
+                println!("would create key {}", key.file_stem());
+            } else {
+                let path = key.save(key_dir)?;
+                eprintln!("{:<9}  {}", "create", path.display());
+            }
+            keys.push(key);
+        }
+        if keys.is_empty() {
+            continue;
+        }
+        let (tags, published) = key_tags(&keys, now);
+        let previous = state.reusable_signatures(
+            &base.name,
+            base.serial,
+            &tags,
+            &published,
+            now,
+            validity / 2,
+        );
+        let window = match previous {
+            Some(signed) => Window {
+                inception: signed.inception,
+                expiration: signed.expiration,
+            },
+            None => {
+                if generated.contains(&base.name) {
+                    base.serial = serial;
+                }
+                // An hour back for secondaries and validators with a slow clock
+                Window {
+                    inception: now - 3600,
+                    expiration: now + validity,
+                }
+            }
+        };
+        signing.insert(base.name.clone(), (keys, window));
+    }
+    if signing.is_empty() {
+        bail!("No keys for any zone in {}", key_dir.display());
+    }
+    Ok(signing)
+}
+
+/// Tags of the keys signing at `now` and of those only published
+#[cfg(feature = "dnssec")]
+fn key_tags(keys: &[Key], now: i64) -> (Vec<u16>, Vec<u16>) {
+    let (active, published): (Vec<&Key>, Vec<&Key>) =
+        keys.iter().partition(|key| key.timing.is_active(now));
+    let tags = |keys: Vec<&Key>| keys.iter().map(|key| key.tag).collect();
+    (tags(active), tags(published))
+}
+
+/// A zone of the NSD output
+#[cfg(feature = "nsd")]
+#[derive(Clone, Copy)]
+enum NsdZone<'a> {
+    Forward(&'a ForwardZone),
+    Reverse(&'a ReverseZone),
+}
+
+#[cfg(feature = "nsd")]
+impl NsdZone<'_> {
+    fn base(&self) -> &ZoneBase {
+        match self {
+            NsdZone::Forward(zone) => &zone.base,
+            NsdZone::Reverse(zone) => &zone.base,
+        }
+    }
+}
+
+/// Writes the zone files of the NSD output, each with the DNSKEY, NSEC and
+/// RRSIG records of its zone if it is signed or has imported keys
+#[cfg(feature = "nsd")]
+struct ZoneWriter<'a> {
+    output_dir: &'a Path,
+    layout: ZoneLayout<'a>,
+    column_width: usize,
+    header: Option<&'a Header>,
+    #[cfg(feature = "dnssec")]
+    signing: &'a Signing,
+    #[cfg(feature = "dnssec")]
+    imports: &'a Imports,
+    #[cfg(feature = "dnssec")]
+    now: i64,
+}
+
+#[cfg(feature = "nsd")]
+impl ZoneWriter<'_> {
+    fn path(&self, zone: NsdZone) -> PathBuf {
+        zone_path(self.output_dir, self.layout, &zone.base().name)
+    }
+
+    /// Writes the records of a zone, followed by its signatures and keys
+    fn write(&self, mut out: &mut dyn Write, zone: NsdZone) -> Result<()> {
+        match zone {
+            NsdZone::Forward(zone) => write_forward_zone(&mut out, zone, self.column_width)?,
+            NsdZone::Reverse(zone) => write_reverse_zone(&mut out, zone, self.column_width)?,
+        }
+        #[cfg(feature = "dnssec")]
+        {
+            let base = zone.base();
+            if let Some((keys, window)) = self.signing.get(&base.name) {
+                let signed = match zone {
+                    NsdZone::Forward(zone) => sign_forward(zone, keys, *window, self.now)?,
+                    NsdZone::Reverse(zone) => sign_reverse(zone, keys, *window, self.now)?,
+                };
+                write_signatures(&mut out, base, &signed, self.column_width)?;
+            }
+            if let Some(keys) = self.imports.get(&base.name) {
+                let imported = external_keys(base, keys);
+                write_signatures(&mut out, base, &imported, self.column_width)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the files of a zone with their headers: the zone file, split
+    /// into part files if it is larger than `split_size` bytes. Splitting
+    /// after signing keeps the signatures with their records.
+    fn files(&self, zone: NsdZone, split_size: Option<usize>) -> Result<Vec<OutputFile>> {
+        let mut content = Vec::new();
+        self.write(&mut content, zone)?;
+        let file = OutputFile {
+            path: self.path(zone),
+            content: String::from_utf8(content)?,
+        };
+        let base = zone.base();
+        let mut files = match split_size {
+            Some(size) if file.content.len() > size => {
+                let reverse = matches!(zone, NsdZone::Reverse(_));
+                split_zone(file, base, reverse, self.layout)
+            }
+            _ => vec![file],
+        };
+        if let Some(header) = self.header {
+            header.prepend(&mut files, base.serial, |_| Some(base.serial));
+        }
+        Ok(files)
+    }
+
+    /// Stages the files of a zone. Without `split_size` the zone is streamed
+    /// straight into its temporary file, a zone to split is rendered first.
+    fn stage(
+        &self,
+        zone: NsdZone,
+        split_size: Option<usize>,
+        dry_run: bool,
+    ) -> Result<Vec<StagedFile>> {
+        if split_size.is_some() {
+            let files = self.files(zone, split_size)?;
+            return files
+                .iter()
+                .map(|file| StagedFile::of(file, dry_run))
+                .collect();
+        }
+        let header = self
+            .header
+            .map(|header| header.render(";", zone.base().serial));
+        let staged = StagedFile::create(self.path(zone), header.as_deref(), dry_run, |out| {
+            self.write(out, zone)
+        })?;
+        Ok(vec![staged])
+    }
+}
+
+/// DNSKEY records of every zone signed by an external signer
+#[cfg(feature = "dnssec")]
+type Imports = HashMap<String, Vec<DnsKey>>;
+
+/// Reads the DNSKEY records of `--import-dnskey`. Zones whose key set changed
+/// since the last run get the `generated` serial, so the signer and the
+/// secondaries pick up the new keys.
+#[cfg(feature = "dnssec")]
+fn prepare_imports(
+    cli: &Cli,
+    zones: &mut ZoneSet,
+    state: &SerialState,
+    generated: &[String],
+    signing: &Signing,
+    skipped: &[String],
+) -> Result<Imports> {
+    let mut imports = Imports::new();
+    let output_format = cli.output_format.clone().unwrap_or(DEFAULT_OUTPUT_FORMAT);
+    if !cli.import_dnskey.is_empty() && !matches!(output_format, OutputFormat::Nsd) {
+        bail!("--import-dnskey publishes keys in zone files, which only the NSD output has");
+    }
+    for import in &cli.import_dnskey {
+        let Some((zone, source)) = import.split_once('=') else {
+            bail!("Invalid --import-dnskey '{import}', expected ZONE=FILE or ZONE=axfr:SERVER");
+        };
+        let zone = fqdn(zone);
+        if skipped.iter().any(|name| name.eq_ignore_ascii_case(&zone)) {
+            continue;
+        }
+        let Some(base) = zones
+            .forward
+            .iter()
+            .map(|zone| &zone.base)
+            .chain(zones.reverse.iter().map(|zone| &zone.base))
+            .find(|base| base.name.eq_ignore_ascii_case(&zone))
+        else {
+            bail!("No zone {zone} to import DNSKEY records for");
+        };
+        if signing.contains_key(&base.name) {
+            bail!("{zone} has keys for --sign, it cannot be signed externally too");
+        }
+        if imports.contains_key(&base.name) {
+            bail!("DNSKEY records of {zone} are imported twice");
+        }
+        let keys = match source.strip_prefix("axfr:") {
+            Some(server) => transfer_dnskeys(server_addr(server)?, &base.name, DNS_TIMEOUT)?
+                .iter()
+                .map(|rdata| DnsKey::from_rdata(&base.name, rdata))
+                .collect::<Result<Vec<_>>>()?,
+            None => {
+                let content = fs::read_to_string(source)
+                    .with_context(|| format!("Cannot read DNSKEY records from {source}"))?;
+                let mut keys = parse_dnskeys(&content)
+                    .with_context(|| format!("Invalid DNSKEY records in {source}"))?;
+                keys.retain(|key| fqdn(&key.zone).eq_ignore_ascii_case(&base.name));
+                keys
+            }
+        };
+        if keys.is_empty() {
+            bail!("No DNSKEY records of {zone} in {source}");
+        }
+        imports.insert(base.name.clone(), keys);
+    }
+
+    // Also zones whose keys are no longer imported
+    let serial = zones.serial;
+    let bases = zones
+        .forward
+        .iter_mut()
+        .map(|zone| &mut zone.base)
+        .chain(zones.reverse.iter_mut().map(|zone| &mut zone.base));
+    for base in bases {
+        let tags = imports.get(&base.name).map(|keys| import_tags(keys));
+        if state.imported_keys(&base.name) != tags.unwrap_or_default()
+            && generated.contains(&base.name)
+        {
+            base.serial = serial;
+        }
+    }
+    Ok(imports)
+}
+
+/// Sorted tags of imported keys
+#[cfg(feature = "dnssec")]
+fn import_tags(keys: &[DnsKey]) -> Vec<u16> {
+    let mut tags: Vec<u16> = keys.iter().map(DnsKey::tag).collect();
+    tags.sort_unstable();
+    tags
+}
+
+/// Loads the TSIG keys of the `tsig` section, creating the missing ones
+#[cfg(feature = "nsd")]
+fn prepare_tsig(config: &TsigConfig, dry_run: bool) -> Result<Vec<TsigKey>> {
+    let mut keys = Vec::new();
+    for (name, secondaries) in &config.keys {
+        let mut key = match TsigKey::load(&config.key_dir, name)? {
+            Some(key) => key,
+            None => {
+                let key = TsigKey::generate(name, config.algorithm)?;
+                if dry_run {
+                    println!("would create TSIG key {name}");
+                } else {
+                    let path = key.save(&config.key_dir)?;
+                    eprintln!("{:<9}  {}", "create", path.display());
+                }
+                key
+            }
+        };
+        key.secondaries = secondaries.clone();
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+/// Reads the raw config from a directory, a file or stdin
+fn load_config(cli: &Cli) -> Result<Content> {
+    load_input(cli).map(|(content, _)| content)
+}
+
+/// Config of the input and the hex SHA-256 of the input as read, the files of
+/// a directory one after another
+fn load_input(cli: &Cli) -> Result<(Content, String)> {
+    match cli.input.as_deref() {
+        Some(dir) if Path::new(dir).is_dir() => {
+            #[cfg(feature = "template")]
+            if cli.template {
+                bail!("--template cannot be used with a directory input");
+            }
+            let mut raw = Vec::new();
+            for path in config_files(Path::new(dir))? {
+                raw.extend(fs::read(path)?);
+            }
+            Ok((
+                parse_dir_config(Path::new(dir))?,
+                hex::encode(Sha256::digest(&raw)),
+            ))
+        }
+        input => {
+            let raw = read_input(input)?;
+            let mut content = parse_input(&raw, cli)?;
+            if let Some(file) = input {
+                content.origins.set_file(file);
+            }
+            Ok((content, hex::encode(Sha256::digest(raw.as_bytes()))))
+        }
+    }
+}
+
+fn run(mut cli: Cli) -> Result<ExitCode> {
+    cli.apply_config()?;
+
+    match &cli.command {
+        Some(Command::Convert { to }) => return convert(&cli, to.clone()),
+        Some(Command::Fmt { check, force }) => return format(&cli, *check, *force),
+        #[cfg(feature = "dnssec")]
+        Some(Command::Ds {
+            keys,
+            key_dir,
+            digest,
+        }) => return print_ds(&cli, keys, key_dir.as_deref(), digest),
+        #[cfg(feature = "dnssec")]
+        Some(Command::Keys { action, key_dir }) => {
+            return manage_keys(&cli, action, key_dir.as_deref())
+        }
+        _ => (),
+    }
+
+    let serial_file = cli
+        .serial
+        .clone()
+        .unwrap_or(DEFAULT_SERIAL_FILE.to_string());
+    let path = Path::new(&serial_file);
+
+    let _lock = run_lock(&cli, &serial_file)?;
+
+    let (mut config, input_hash) = load_input(&cli)?;
+    #[cfg(feature = "nsd")]
+    let tsig = config.tsig.take();
+    let mut lint_config = config.lint.take().unwrap_or_default();
+    let filter = TagFilter {
+        include: cli.include_tag.clone(),
+        exclude: cli.exclude_tag.clone(),
+    };
+    filter_tags(&mut config, &filter);
+    let output_format = cli.output_format.clone().unwrap_or(DEFAULT_OUTPUT_FORMAT);
+    filter_outputs(&mut config, output_format.into());
+    let today: Date = reference_time(cli.now)?
+        .date_naive()
+        .to_string()
+        .parse()
+        .map_err(|e: String| anyhow!(e))?;
+    let outside_windows = apply_windows(&mut config, today)?;
+    let mut state = SerialState::load(path)?;
+    if let Some(server) = &cli.serial_from_server {
+        let server = server_addr(server)?;
+        let zones = resolve(config.clone(), state.serial)?;
+        let names = zones.forward.iter().map(|zone| &zone.base.name);
+        for name in names.chain(zones.reverse.iter().map(|zone| &zone.base.name)) {
+            let Some(live) = query_soa_serial(server, name, DNS_TIMEOUT)? else {
+                continue;
+            };
+            if state.fast_forward(name, live) {
+                eprintln!("{name}: serial {live} on {server} is ahead of the serial file");
+            }
+        }
+    }
+    let old_serial = state.serial;
+    let strategy = cli.serial_strategy.unwrap_or_default();
+    let format = cli.serial_format.clone().unwrap_or_default();
+    let serial = match cli.serial_from_git {
+        Some(mode) => {
+            let Some(input) = &cli.input else {
+                bail!("--serial-from-git needs an input file or directory");
+            };
+            let serial = git_serial(Path::new(input), mode, &format)?;
+            if old_serial != 0 && serial_cmp(serial, old_serial) == Some(Ordering::Less) {
+                bail!("Serial {serial} from git is behind the saved serial {old_serial}");
+            }
+            serial
+        }
+        None => calc_serial_with(old_serial, strategy, &format, reference_time(cli.now)?)?,
+    };
+    let serial = match cli.serial_at_least {
+        Some(floor) if serial_cmp(serial, floor) == Some(Ordering::Less) => floor,
+        _ => serial,
+    };
+
+    let mut zones = resolve(config.clone(), serial)?;
+    zones.source = cli.input.as_ref().map(PathBuf::from);
+    zones.warnings.extend(outside_windows);
+    if cli.annotate {
+        annotate(&mut zones, &config)?;
+    }
+
+    if let Some(Command::Lint { format, rule }) = &cli.command {
+        for setting in rule {
+            lint_config.set_rule(setting)?;
+        }
+        let mut findings = std::mem::take(&mut zones.warnings);
+        findings.extend(lint(&zones, &lint_config)?);
+        match format {
+            LintFormat::Text => print!("{}", format_lint(&findings)),
+            LintFormat::Sarif => {
+                // Findings point into the input file; stdin and directories have no lines to show
+                let file = cli.input.as_deref().filter(|i| Path::new(i).is_file());
+                let source = file.map(fs::read_to_string).transpose()?;
+                let input = file.zip(source.as_deref());
+                println!("{}", format_sarif(&findings, input));
+            }
+        }
+        if findings
+            .iter()
+            .any(|f| cli.strict || f.severity == Severity::Error)
+        {
+            return Ok(ExitCode::from(EXIT_VALIDATION));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let missing_mx = if cli.warn_mx_targets {
+        Severity::Warning
+    } else {
+        Severity::Error
+    };
+    let mut findings = mx_targets(&zones, missing_mx);
+    findings.extend(srv_targets(&zones));
+    findings.extend(ns_targets(&zones));
+    if cli.resolve_nameservers {
+        findings.extend(unresolved_nameservers(&zones, lookup_host));
+    }
+    findings.extend(soa_timers(&zones));
+    findings.extend(private_addresses(&zones));
+    findings.extend(special_use(&zones));
+    findings.extend(ttl_advice(&zones));
+    if old_serial != 0 {
+        let severity = if cli.warn_serial_backwards {
+            Severity::Warning
+        } else {
+            Severity::Error
+        };
+        findings.extend(serial_backwards(&zones, old_serial, severity));
+    }
+    findings.extend(zone_limits(
+        &zones,
+        ZoneLimits {
+            max_records: cli.max_zone_records,
+            max_bytes: cli.max_zone_size,
+        },
+    ));
+    report_findings(&mut zones, findings, cli.strict)?;
+
+    // Zones whose serial signing may renew, before some get their old one back
+    #[cfg(feature = "dnssec")]
+    let generated: Vec<String> = zones
+        .forward
+        .iter()
+        .map(|zone| &zone.base)
+        .chain(zones.reverse.iter().map(|zone| &zone.base))
+        .filter(|base| base.serial == zones.serial && cli.serial_from_git.is_none())
+        .map(|base| base.name.clone())
+        .collect();
+
+    // Only after the serial checks, which look at explicit serials. Git serials
+    // must not depend on the state of earlier runs.
+    if cli.serial_from_git.is_none() {
+        state.keep_unchanged(&mut zones)?;
+    }
+
+    if let Some(command) = cli.command {
+        match command {
+            Command::List => print!("{}", list_zones(&zones.forward, &zones.reverse)),
+            Command::Dump { zone, record_type } => {
+                let rows: Vec<_> = record_rows(&zones.forward, &zones.reverse)
+                    .into_iter()
+                    .filter(|r| zone.as_ref().is_none_or(|z| name_matches(&r.zone, z)))
+                    .filter(|r| {
+                        record_type
+                            .as_ref()
+                            .is_none_or(|t| r.record_type.eq_ignore_ascii_case(t))
+                    })
+                    .collect();
+                print!("{}", dump_records(&rows));
+            }
+            Command::Query { name } => {
+                let rows = query_records(record_rows(&zones.forward, &zones.reverse), &name);
+                if rows.is_empty() {
+                    bail!("No records found for {name}");
+                }
+                print!("{}", dump_records(&rows));
+            }
+            Command::Explain { zone } => {
+                let Some(items) = explain_zone(config, &zones.forward, &zones.reverse, &zone)
+                else {
+                    bail!("Zone {zone} not found");
+                };
+                print!("{}", format_explained(&items));
+            }
+            Command::Convert { .. } | Command::Fmt { .. } | Command::Lint { .. } => {
+                unreachable!("handled before parsing")
+            }
+            #[cfg(feature = "dnssec")]
+            Command::Ds { .. } | Command::Keys { .. } => unreachable!("handled before parsing"),
         }
+        print_warnings(&zones);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Zones left out keep their state, their files are not written
+    let skipped: Vec<String> = match cli.only {
+        Some(ZoneKind::Forward) => zones.reverse.drain(..).map(|z| z.base.name).collect(),
+        Some(ZoneKind::Reverse) => zones.forward.drain(..).map(|z| z.base.name).collect(),
+        None => Vec::new(),
     };
 
-    let (forward, reverse) = parse(content.as_str(), serial, cli.input_format)?;
-    match cli.output_format {
+    #[cfg(feature = "dnssec")]
+    let signing = match &cli.sign {
+        Some(key_dir) => prepare_signing(&cli, key_dir, &mut zones, &state, &generated)?,
+        None => Signing::new(),
+    };
+    #[cfg(feature = "dnssec")]
+    let imports = prepare_imports(&cli, &mut zones, &state, &generated, &signing, &skipped)?;
+
+    let mut stats = Stats::collect(&zones.forward, &zones.reverse);
+
+    let header = if cli.reproducible {
+        None
+    } else {
+        Some(Header {
+            generated: reference_time(cli.now)?
+                .format("%Y-%m-%dT%H:%M:%SZ")
+                .to_string(),
+            input: cli.input.clone().unwrap_or("stdin".to_string()),
+            input_hash,
+        })
+    };
+    let backup = cli.backup();
+    // Zone and path of every zone file, for --verify, which checks them below
+    // the NSD config directory the $INCLUDE paths are relative to
+    let zone_files: Vec<(&str, PathBuf)>;
+    let config_dir: PathBuf;
+    // The packed files of --tar, which then are not written themselves
+    #[cfg(feature = "tar")]
+    let mut archive: Option<Vec<u8>> = None;
+    // Fragments of --prune-fragments to delete
+    #[cfg(feature = "nsd")]
+    let mut stale: Vec<PathBuf> = Vec::new();
+    // Files in their temporary files, which replace them once all are complete
+    let staged: Vec<StagedFile> = match cli.output_format.unwrap_or(DEFAULT_OUTPUT_FORMAT) {
         #[cfg(feature = "unbound")]
         OutputFormat::Unbound => {
-            let output = generate_unbound(&forward, &reverse);
-            match cli.output {
-                Some(path) => {
-                    let path = Path::new(&path);
-                    fs::write(path, output)?;
+            let column_width = cli.unbound_column_width.unwrap_or(UNBOUND_COLUMN_WIDTH);
+            let private_domains = cli.unbound_private_domains;
+            if cli.verify.is_some() {
+                bail!("--verify checks zone files, which only the NSD output has");
+            }
+            #[cfg(feature = "tar")]
+            if cli.tar.is_some() {
+                bail!("--tar packs the NSD output, the Unbound config is a single file");
+            }
+            zone_files = Vec::new();
+            config_dir = PathBuf::new();
+            let checker = cli.check_with;
+            if let Some(checker) = checker.filter(|c| *c != ConfChecker::UnboundCheckconf) {
+                bail!("--check-with {} checks the NSD output", checker.program());
+            }
+            let header = header.map(|header| header.render("#", zones.serial));
+            let write_config = |out: &mut dyn Write| {
+                write_unbound_config(out, &zones, column_width, private_domains)
+            };
+            let target = cli.output.clone().unwrap_or("stdout".to_string());
+            // The checker reads the written temporary file. Without one the
+            // config is rendered once to check and print it.
+            let checked = match checker {
+                Some(checker) if cli.output.is_none() || cli.dry_run => {
+                    let mut config = header.clone().unwrap_or_default().into_bytes();
+                    write_config(&mut config)?;
+                    check_config(checker, config.as_slice(), &target)?;
+                    Some(config)
+                }
+                _ => None,
+            };
+            match (cli.output, checked) {
+                (Some(path), Some(config)) => {
+                    let file = OutputFile {
+                        path: path.into(),
+                        content: String::from_utf8(config)?,
+                    };
+                    vec![StagedFile::of(&file, cli.dry_run)?]
+                }
+                (Some(path), None) => {
+                    let staged = StagedFile::create(path, header.as_deref(), cli.dry_run, |out| {
+                        write_config(out)
+                    })?;
+                    if let (Some(checker), Some(tmp)) = (checker, staged.tmp()) {
+                        check_config(checker, File::open(tmp)?, &target)?;
+                    }
+                    vec![staged]
                 }
-                None => {
-                    print!("{output}");
+                (None, config) => {
+                    let out: Box<dyn Write> = if cli.dry_run {
+                        Box::new(std::io::sink())
+                    } else {
+                        Box::new(BufWriter::new(std::io::stdout().lock()))
+                    };
+                    let mut out = CountingWriter::new(out);
+                    match config {
+                        Some(config) => out.write_all(&config)?,
+                        None => {
+                            out.write_all(header.unwrap_or_default().as_bytes())?;
+                            write_config(&mut out)?;
+                        }
+                    }
+                    out.flush()?;
+                    stats.bytes_changed = out.bytes;
+                    if cli.dry_run {
+                        println!("would print {} bytes to stdout", out.bytes);
+                    }
+                    vec![]
                 }
             }
         }
         #[cfg(feature = "nsd")]
         OutputFormat::Nsd => {
-            let output_dir = cli.output.unwrap_or("./nsd".to_string());
-            write_nsd(Path::new(&output_dir), &forward, &reverse)?;
+            let output_dir = PathBuf::from(cli.output.unwrap_or("./nsd".to_string()));
+            let column_width = cli.nsd_column_width.unwrap_or(NSD_COLUMN_WIDTH);
+            let layout = ZoneLayout {
+                dir: cli.nsd_zone_dir.as_deref().unwrap_or(NSD_ZONE_DIR),
+                file_template: cli
+                    .nsd_file_template
+                    .as_deref()
+                    .unwrap_or(NSD_FILE_TEMPLATE),
+            };
+            layout.check()?;
+            config_dir = output_dir.clone();
+            zone_files = zones
+                .forward
+                .iter()
+                .map(|zone| &zone.base.name)
+                .chain(zones.reverse.iter().map(|zone| &zone.base.name))
+                .map(|name| (name.as_str(), zone_path(&output_dir, layout, name)))
+                .collect();
+            let keys = match &tsig {
+                Some(tsig) => prepare_tsig(tsig, cli.dry_run)?,
+                None => Vec::new(),
+            };
+            let conf = output_dir.join("zones.conf");
+            // zones.conf, or the fragments that make it up
+            let mut conf_files = match &cli.nsd_conf_dir {
+                Some(conf_dir) => {
+                    let conf_dir = Path::new(conf_dir);
+                    let fragments = render_conf_fragments(
+                        conf_dir,
+                        &zones.forward,
+                        &zones.reverse,
+                        &zones.secondary,
+                        &keys,
+                        layout,
+                    )?;
+                    if cli.prune_fragments {
+                        // The fragments of zones left out by --only stay
+                        let mut keep: Vec<PathBuf> =
+                            fragments.iter().map(|f| f.path.clone()).collect();
+                        keep.extend(skipped.iter().map(|zone| fragment_path(conf_dir, zone)));
+                        stale = stale_fragments(conf_dir, &keep)?;
+                    }
+                    fragments
+                }
+                None if cli.prune_fragments => {
+                    bail!("--prune-fragments needs the fragments of --nsd-conf-dir");
+                }
+                None => vec![OutputFile {
+                    path: conf.clone(),
+                    content: render_zones_conf(
+                        &zones.forward,
+                        &zones.reverse,
+                        &zones.secondary,
+                        &keys,
+                        layout,
+                    )?,
+                }],
+            };
+            if let Some(checker) = cli.check_with {
+                if checker != ConfChecker::NsdCheckconf {
+                    bail!(
+                        "--check-with {} checks the Unbound output",
+                        checker.program()
+                    );
+                }
+                let config: String = conf_files
+                    .iter()
+                    .map(|file| file.content.as_str())
+                    .collect();
+                let conf_dir = cli.nsd_conf_dir.as_deref().map(Path::new);
+                let target = conf_dir.unwrap_or(&conf).display().to_string();
+                check_config(checker, config.as_bytes(), &target)?;
+            }
+            if let Some(header) = &header {
+                header.prepend(&mut conf_files, zones.serial, |_| None);
+            }
+
+            let writer = ZoneWriter {
+                output_dir: &output_dir,
+                layout,
+                column_width,
+                header: header.as_ref(),
+                #[cfg(feature = "dnssec")]
+                signing: &signing,
+                #[cfg(feature = "dnssec")]
+                imports: &imports,
+                #[cfg(feature = "dnssec")]
+                now: reference_time(cli.now)?.timestamp(),
+            };
+            let mut nsd_zones: Vec<NsdZone> = zones
+                .forward
+                .iter()
+                .map(NsdZone::Forward)
+                .chain(zones.reverse.iter().map(NsdZone::Reverse))
+                .collect();
+            nsd_zones.sort_by_cached_key(|zone| writer.path(*zone));
+            #[cfg(feature = "tar")]
+            if let Some(target) = &cli.tar {
+                if cli.nsd_conf_dir.is_some() {
+                    bail!("--tar packs zones.conf, not the fragments of --nsd-conf-dir");
+                }
+                if cli.verify.is_some() {
+                    bail!("--verify checks the zone files on disk, which --tar does not write");
+                }
+                // The archive is built in memory, its files are not written
+                let mut files = conf_files;
+                for zone in nsd_zones {
+                    files.extend(writer.files(zone, cli.nsd_split_size)?);
+                }
+                let mtime = reference_time(cli.now)?.timestamp();
+                let tar = render_tar(&files, &output_dir, mtime)?;
+                let compress = cli.gzip || target.ends_with(".gz") || target.ends_with(".tgz");
+                archive = Some(if compress { gzip(&tar) } else { tar });
+                nsd_zones = Vec::new();
+                conf_files = Vec::new();
+            }
+
+            // zones.conf goes first, the fragments of --nsd-conf-dir last
+            let (conf_files, fragments): (Vec<_>, Vec<_>) =
+                conf_files.iter().partition(|file| file.path == conf);
+            let mut staged = Vec::new();
+            for file in conf_files {
+                staged.push(StagedFile::of(file, cli.dry_run)?);
+            }
+            for zone in nsd_zones {
+                staged.extend(writer.stage(zone, cli.nsd_split_size, cli.dry_run)?);
+            }
+            for file in fragments {
+                staged.push(StagedFile::of(file, cli.dry_run)?);
+            }
+            staged
+        }
+    };
+
+    if cli.dry_run {
+        let mut changed = false;
+        for file in &staged {
+            changed |= file.change != FileChange::Unchanged;
+            println!("{:<9}  {}", file.change.as_str(), file.path.display());
+        }
+        #[cfg(feature = "tar")]
+        if let (Some(target), Some(archive)) = (&cli.tar, &archive) {
+            if target == "-" {
+                println!("would print {} bytes to stdout", archive.len());
+            } else {
+                let change = archive_change(Path::new(target), archive);
+                changed |= change != FileChange::Unchanged;
+                println!("{:<9}  {target}", change.as_str());
+            }
         }
+        #[cfg(feature = "nsd")]
+        for path in &stale {
+            changed = true;
+            println!("{:<9}  {}", "delete", path.display());
+        }
+        println!("serial {serial} would not be saved to {}", path.display());
+        print_warnings(&zones);
+        return Ok(if changed {
+            ExitCode::from(EXIT_CHANGES)
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
+
+    // A zone the checker rejects is neither deployed nor saved as generated
+    if let Some(checker) = cli.verify {
+        let checker = checker.unwrap_or_default();
+        verify_zones(
+            checker,
+            &config_dir,
+            staged
+                .iter()
+                .filter_map(|file| Some((file.path.as_path(), file.tmp()?))),
+            zone_files
+                .iter()
+                .map(|(zone, path)| (*zone, path.as_path())),
+        )?;
+    }
+    let mut changes = Vec::with_capacity(staged.len());
+    for file in staged {
+        let (path, bytes) = (file.path.clone(), file.bytes);
+        changes.push((path, bytes, file.commit(backup)?));
+    }
+    #[cfg(feature = "tar")]
+    if let (Some(target), Some(archive)) = (&cli.tar, &archive) {
+        if target == "-" {
+            let mut out = std::io::stdout().lock();
+            out.write_all(archive)?;
+            out.flush()?;
+            stats.bytes_changed = archive.len();
+        } else {
+            let change = write_archive(Path::new(target), archive, backup)?;
+            if change != FileChange::Unchanged {
+                eprintln!("{:<9}  {target}", change.as_str());
+                stats.bytes_changed = archive.len();
+            }
+        }
+    }
+    #[cfg(feature = "nsd")]
+    for path in &stale {
+        fs::remove_file(path)
+            .with_context(|| format!("Cannot delete stale fragment {}", path.display()))?;
+        eprintln!("{:<9}  {}", "delete", path.display());
+    }
+    if !cli.no_serial_save {
+        let mut new_state = SerialState::of(&zones, reference_time(cli.now)?)?;
+        new_state.keep_zones(&state, &skipped);
+        #[cfg(feature = "dnssec")]
+        for (zone, (keys, window)) in &signing {
+            let (tags, published) = key_tags(keys, reference_time(cli.now)?.timestamp());
+            let signed = SignedState {
+                inception: window.inception,
+                expiration: window.expiration,
+                keys: tags,
+                published,
+            };
+            new_state.set_signed(zone, signed);
+        }
+        #[cfg(feature = "dnssec")]
+        for (zone, keys) in &imports {
+            new_state.set_imported(zone, import_tags(keys));
+        }
+        new_state.save(path)?;
+    }
+
+    for (file, bytes, change) in &changes {
+        if *change != FileChange::Unchanged {
+            eprintln!("{:<9}  {}", change.as_str(), file.display());
+            stats.bytes_changed += bytes;
+        }
+    }
+    print_warnings(&zones);
+    if cli.stats {
+        eprint!("{stats}");
+    }
+    if cli.stats_json {
+        eprintln!("{}", stats.to_json());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Takes the lock of a run that writes output. A run with `--no-serial-save`
+/// only takes it with an explicit `--lock-file`, so it needs no write access
+/// to the directory of the serial file, e.g. in a read-only deployment.
+fn run_lock(cli: &Cli, serial_file: &str) -> Result<Option<RunLock>> {
+    if cli.command.is_some() || cli.dry_run {
+        return Ok(None);
+    }
+    let lock_file = match &cli.lock_file {
+        Some(lock_file) => lock_file.clone(),
+        None if cli.no_serial_save => return Ok(None),
+        None => format!("{serial_file}.lock"),
+    };
+    let wait = Duration::from_secs(cli.lock_timeout.unwrap_or(DEFAULT_LOCK_TIMEOUT));
+    Ok(Some(RunLock::acquire(Path::new(&lock_file), wait)?))
+}
+
+/// Maps an error to its documented exit code
+fn exit_code(err: &anyhow::Error) -> u8 {
+    if err.chain().any(|e| e.is::<ParseError>()) {
+        EXIT_PARSE
+    } else if err.chain().any(|e| e.is::<std::io::Error>()) {
+        EXIT_IO
+    } else {
+        EXIT_VALIDATION
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let code = if e.use_stderr() { EXIT_USAGE } else { 0 };
+            let _ = e.print();
+            return ExitCode::from(code);
+        }
+    };
+
+    match run(cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", render_error(&e, std::io::stderr().is_terminal()));
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_lock_read_only_serial_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let serial_file = dir.path().join(".serial");
+        let serial_file = serial_file.to_str().unwrap();
+        let mode = std::os::unix::fs::PermissionsExt::from_mode(0o555);
+        fs::set_permissions(dir.path(), mode).unwrap();
+
+        let cli = Cli::parse_from(["zonefile-rs", "-s", serial_file, "--no-serial-save"]);
+        assert!(run_lock(&cli, serial_file).unwrap().is_none());
+        assert!(!dir.path().join(".serial.lock").exists());
+
+        // An explicit lock file is taken all the same
+        let lock_file = tempfile::tempdir().unwrap();
+        let lock_file = lock_file.path().join("run.lock");
+        let lock_arg = format!("--lock-file={}", lock_file.display());
+        let args = [
+            "zonefile-rs",
+            "-s",
+            serial_file,
+            "--no-serial-save",
+            &lock_arg,
+        ];
+        let cli = Cli::parse_from(args);
+        assert!(run_lock(&cli, serial_file).unwrap().is_some());
+
+        let mode = std::os::unix::fs::PermissionsExt::from_mode(0o755);
+        fs::set_permissions(dir.path(), mode).unwrap();
+        let cli = Cli::parse_from(["zonefile-rs", "-s", serial_file]);
+        assert!(run_lock(&cli, serial_file).unwrap().is_some());
+        assert!(dir.path().join(".serial.lock").exists());
     }
-    save_serial(path, serial)
 }
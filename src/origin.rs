@@ -0,0 +1,362 @@
+//! Where the zones and entries of a config were written, to annotate the
+//! generated records with it. Deserializing loses the positions, so the
+//! config text is scanned once more for the lines of the keys.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::args::InputFormat;
+use crate::parser::{Content, ZoneSet};
+use crate::transform::{entry_names, parse_host_str, parse_srv_name, zone_fqdn};
+use crate::vars::expand_vars;
+
+/// File and line an entry of the config comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub file: String,
+    pub line: usize,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Lines of the keys of a config by their path, like
+/// `["zone", "example.com", "hosts", "www"]`. Zones given as a list are named
+/// by their `name`.
+#[derive(Debug, Clone, Default)]
+pub struct Origins(pub Vec<(Vec<String>, Origin)>);
+
+impl Origins {
+    pub fn scan(raw: &str, input_format: InputFormat, file: &str) -> Origins {
+        let lines = match input_format {
+            #[cfg(feature = "toml")]
+            InputFormat::Toml => toml_keys(raw),
+            #[cfg(feature = "yaml")]
+            InputFormat::Yaml => yaml_keys(raw),
+        };
+        let origin = |line| Origin {
+            file: file.to_string(),
+            line,
+        };
+        Origins(
+            lines
+                .into_iter()
+                .map(|(path, line)| (path, origin(line)))
+                .collect(),
+        )
+    }
+
+    /// Names the file all keys come from, e.g. once it is known for stdin
+    pub fn set_file(&mut self, file: &str) {
+        for (_, origin) in &mut self.0 {
+            origin.file = file.to_string();
+        }
+    }
+
+    pub fn extend(&mut self, other: Origins) {
+        self.0.extend(other.0);
+    }
+}
+
+/// Key of a YAML line and the rest after the colon, `None` for list items
+/// and flow collections
+#[cfg(feature = "yaml")]
+fn yaml_key(text: &str) -> Option<(String, &str)> {
+    let (key, rest) = match text.chars().next()? {
+        quote @ ('\'' | '"') => {
+            let end = text[1..].find(quote)? + 1;
+            (text[1..end].to_string(), text[end + 1..].strip_prefix(':')?)
+        }
+        '{' | '[' | '#' => return None,
+        _ => {
+            let end = text
+                .find(": ")
+                .or_else(|| text.strip_suffix(':').map(str::len))?;
+            (text[..end].trim_end().to_string(), &text[end + 1..])
+        }
+    };
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some((key, rest.trim()))
+}
+
+/// Plain or quoted scalar without a trailing comment
+fn unquote(value: &str) -> String {
+    let value = match value.find(" #") {
+        Some(end) if !value.starts_with(['\'', '"']) => &value[..end],
+        _ => value,
+    };
+    value.trim().trim_matches(['\'', '"']).to_string()
+}
+
+/// Keys of block style YAML by indentation. A list item counts as a level
+/// of its own, named by its `name` key.
+#[cfg(feature = "yaml")]
+fn yaml_keys(raw: &str) -> Vec<(Vec<String>, usize)> {
+    struct Level {
+        indent: usize,
+        key: String,
+        item: bool,
+    }
+    let mut levels: Vec<Level> = Vec::new();
+    let mut keys = Vec::new();
+    for (index, line) in raw.lines().enumerate() {
+        let mut text = line.trim_start();
+        if text.is_empty() || text.starts_with('#') || text.starts_with("---") {
+            continue;
+        }
+        let mut indent = line.len() - text.len();
+        while let Some(rest) = text.strip_prefix("- ").or((text == "-").then_some("")) {
+            levels.retain(|level| level.indent < indent || level.indent == indent && !level.item);
+            levels.push(Level {
+                indent,
+                key: "-".to_string(),
+                item: true,
+            });
+            let rest_trimmed = rest.trim_start();
+            indent += text.len() - rest_trimmed.len();
+            text = rest_trimmed;
+        }
+        let Some((key, value)) = yaml_key(text) else {
+            continue;
+        };
+        levels.retain(|level| level.indent < indent);
+        match levels.last_mut() {
+            Some(item) if item.item && key == "name" => item.key = unquote(value),
+            _ => {
+                let mut path: Vec<String> = levels.iter().map(|level| level.key.clone()).collect();
+                path.push(key.clone());
+                keys.push((path, index + 1));
+            }
+        }
+        levels.push(Level {
+            indent,
+            key,
+            item: false,
+        });
+    }
+    keys
+}
+
+/// Parts of a dotted TOML key, unquoted
+#[cfg(feature = "toml")]
+fn dotted_key(text: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '.') => parts.push(String::new()),
+            (None, c) if c.is_whitespace() => {}
+            _ => parts.last_mut().expect("never empty").push(c),
+        }
+    }
+    parts
+}
+
+/// Keys of TOML tables and their key/value pairs. Arrays of tables are named
+/// by their `name` key.
+#[cfg(feature = "toml")]
+fn toml_keys(raw: &str) -> Vec<(Vec<String>, usize)> {
+    let mut keys = Vec::new();
+    let mut table: Vec<String> = Vec::new();
+    // Array of tables, its current item and the line of the item's header
+    let mut array: Option<(Vec<String>, String, usize)> = None;
+    let in_array = |path: Vec<String>, array: &Option<(Vec<String>, String, usize)>| match array {
+        Some((prefix, name, _)) if path.starts_with(prefix) => {
+            let mut path = path;
+            path.insert(prefix.len(), name.clone());
+            path
+        }
+        _ => path,
+    };
+    for (index, line) in raw.lines().enumerate() {
+        let text = line.trim();
+        if let Some(header) = text.strip_prefix("[[") {
+            let path = dotted_key(header.split("]]").next().unwrap_or_default());
+            table = path.clone();
+            array = Some((path, "-".to_string(), index + 1));
+        } else if let Some(header) = text.strip_prefix('[') {
+            table = in_array(
+                dotted_key(header.split(']').next().unwrap_or_default()),
+                &array,
+            );
+            keys.push((table.clone(), index + 1));
+        } else if let Some((key, value)) = split_assignment(text) {
+            let key = dotted_key(key);
+            match &mut array {
+                Some((prefix, name, line)) if *prefix == table && key == ["name"] => {
+                    *name = unquote(value);
+                    let mut path = prefix.clone();
+                    path.push(name.clone());
+                    keys.push((path.clone(), *line));
+                    table = path;
+                }
+                _ => {
+                    let mut path = table.clone();
+                    path.extend(key);
+                    keys.push((path, index + 1));
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// Key and value of a `key = value` line, the `=` outside of quotes
+#[cfg(feature = "toml")]
+fn split_assignment(text: &str) -> Option<(&str, &str)> {
+    if text.is_empty() || text.starts_with('#') {
+        return None;
+    }
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '=') => return Some((&text[..i], text[i + 1..].trim())),
+            (None, ',' | '[' | '{') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Notes where every record of the zones comes from: records at the apex from
+/// their zone, hosts, pools, CNAMEs, SRV records and delegations from their
+/// entry and the PTR records from their host
+pub fn annotate(zones: &mut ZoneSet, content: &Content) -> Result<()> {
+    let mut content = content.clone();
+    expand_vars(&mut content)?;
+    for (path, origin) in &content.origins.0 {
+        let [section, zone, rest @ ..] = path.as_slice() else {
+            continue;
+        };
+        if section != "zone" {
+            continue;
+        }
+        let zone_name = zone_fqdn(zone);
+        let Some(forward) = zones.forward.iter_mut().find(|z| z.base.name == zone_name) else {
+            continue;
+        };
+        let names = match rest {
+            [] => vec![zone_name],
+            [kind, key] if matches!(kind.as_str(), "hosts" | "cname" | "pool") => {
+                entry_names(key, &zone_name).unwrap_or_default()
+            }
+            [kind, key] if kind == "srv" => parse_srv_name(key, &zone_name).into_iter().collect(),
+            [kind, key] if kind == "delegate" => {
+                parse_host_str(key, &zone_name).into_iter().collect()
+            }
+            _ => continue,
+        };
+        for name in names {
+            forward
+                .origins
+                .entry(name)
+                .or_insert_with(|| origin.clone());
+        }
+    }
+
+    let hosts: HashMap<String, Origin> = zones
+        .forward
+        .iter()
+        .flat_map(|zone| zone.origins.clone())
+        .collect();
+    for zone in &mut zones.reverse {
+        for ptr in &zone.ptr {
+            if let Some(origin) = hosts.get(&ptr.name) {
+                zone.origins.insert(ptr.name.clone(), origin.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Comment of a record and where it comes from, as written next to it
+pub fn note(comment: &Option<String>, origin: Option<&Origin>) -> Option<String> {
+    match (comment, origin) {
+        (Some(comment), Some(origin)) => Some(format!("{comment} ({origin})")),
+        (Some(comment), None) => Some(comment.clone()),
+        (None, Some(origin)) => Some(origin.to_string()),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_yaml_keys() {
+        let raw = "zone:\n  example.com:\n    hosts:\n      www: 10.0.0.1 # web\n      'node-[1-2]':\n        ip: 10.0.0.[2-3]\n\n    cname:\n      web: www\n  - name: other.org\n    hosts:\n      mail: 10.0.1.1\n";
+        let keys: Vec<(String, usize)> = yaml_keys(raw)
+            .into_iter()
+            .map(|(path, line)| (path.join("/"), line))
+            .collect();
+        assert_eq!(keys[3], ("zone/example.com/hosts/www".to_string(), 4));
+        assert_eq!(
+            keys[4],
+            ("zone/example.com/hosts/node-[1-2]".to_string(), 5)
+        );
+        assert_eq!(keys[7], ("zone/example.com/cname/web".to_string(), 9));
+        let zones = "zone:\n- name: 'other.org'\n  hosts:\n    mail: 10.0.1.1\nreverse: {}\n";
+        let keys: Vec<String> = yaml_keys(zones)
+            .into_iter()
+            .map(|(p, _)| p.join("/"))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "zone",
+                "zone/other.org/hosts",
+                "zone/other.org/hosts/mail",
+                "reverse"
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn test_toml_keys() {
+        let raw = "[zone.\"example.com\".hosts]\nwww = \"10.0.0.1\"\nmail = [\n  \"10.0.0.2\",\n]\n\n[[zone]]\nname = \"other.org\"\n[zone.hosts]\n\"db.lan\" = { ip = \"10.0.1.1\" }\n";
+        let keys: Vec<(String, usize)> = toml_keys(raw)
+            .into_iter()
+            .map(|(path, line)| (path.join("/"), line))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("zone/example.com/hosts".to_string(), 1),
+                ("zone/example.com/hosts/www".to_string(), 2),
+                ("zone/example.com/hosts/mail".to_string(), 3),
+                ("zone/other.org".to_string(), 7),
+                ("zone/other.org/hosts".to_string(), 9),
+                ("zone/other.org/hosts/db.lan".to_string(), 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_note() {
+        let origin = Origin {
+            file: "zones.yaml".to_string(),
+            line: 12,
+        };
+        assert_eq!(note(&None, Some(&origin)).as_deref(), Some("zones.yaml:12"));
+        let comment = Some("rack 4".to_string());
+        assert_eq!(
+            note(&comment, Some(&origin)).as_deref(),
+            Some("rack 4 (zones.yaml:12)")
+        );
+        assert_eq!(note(&comment, None).as_deref(), Some("rack 4"));
+    }
+}
@@ -1,13 +1,703 @@
 #[cfg(feature = "nsd")]
 pub mod nsd;
+#[cfg(feature = "tar")]
+pub mod tar;
 #[cfg(feature = "unbound")]
 pub mod unbound;
 
-/// Column width for name field in Unbound output
+/// Default column width for name field in Unbound output
 #[cfg(feature = "unbound")]
 pub const UNBOUND_COLUMN_WIDTH: usize = 46;
 
-/// Column width for name field in NSD output
+/// Default column width for name field in NSD output
 #[cfg(feature = "nsd")]
 pub const NSD_COLUMN_WIDTH: usize = 32;
 
+/// Default name of the NSD zone files, see [`nsd::zone_file`]
+#[cfg(feature = "nsd")]
+pub const NSD_FILE_TEMPLATE: &str = "{zone}zone";
+
+/// Default directory of the NSD zone files below the config directory
+#[cfg(feature = "nsd")]
+pub const NSD_ZONE_DIR: &str = "master";
+
+#[cfg(feature = "fs")]
+use std::fs::{self, File};
+use std::io::{self, Write};
+#[cfg(feature = "fs")]
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "fs")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "cli")]
+use chrono::Utc;
+#[cfg(feature = "fs")]
+use sha2::{Digest, Sha256};
+
+/// A generated file and its content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Where the files of a run come from, written as a comment block at their
+/// top so a deployed file can be traced back to its config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// Time of the run, like `2025-06-01T12:00:00Z`
+    pub generated: String,
+    /// Input file or directory, `stdin` without one
+    pub input: String,
+    /// Hex SHA-256 of the input
+    pub input_hash: String,
+}
+
+/// Start of the first header line, which tells a header from other comments
+const HEADER_START: &str = "Generated by zonefile-rs";
+
+impl Header {
+    /// Comment lines starting with `comment`, ended by an empty line
+    pub fn render(&self, comment: &str, serial: u32) -> String {
+        format!(
+            "{comment} {HEADER_START} {} at {}\n{comment} Input: {} (sha256 {})\n\
+             {comment} Serial: {serial}\n\n",
+            env!("CARGO_PKG_VERSION"),
+            self.generated,
+            self.input,
+            self.input_hash
+        )
+    }
+
+    /// Puts the header on top of every file: `;` comments and the serial of
+    /// the zone in zone files, which `zone_serial` knows the serial of, `#`
+    /// comments and `serial` in the others
+    pub fn prepend(
+        &self,
+        files: &mut [OutputFile],
+        serial: u32,
+        zone_serial: impl Fn(&Path) -> Option<u32>,
+    ) {
+        for file in files {
+            let header = match zone_serial(&file.path) {
+                Some(zone_serial) => self.render(";", zone_serial),
+                None => self.render("#", serial),
+            };
+            file.content.insert_str(0, &header);
+        }
+    }
+}
+
+/// Header and the rest of a generated file, `None` if it has no header
+#[cfg(feature = "fs")]
+fn split_header(text: &str) -> (Option<&str>, &str) {
+    match text.find("\n\n") {
+        Some(end) if is_header_start(text.as_bytes()) => (Some(&text[..end + 2]), &text[end + 2..]),
+        _ => (None, text),
+    }
+}
+
+/// Whether the first line of a file starts a header, after its comment sign
+#[cfg(feature = "fs")]
+fn is_header_start(line: &[u8]) -> bool {
+    line.iter()
+        .position(|&b| b == b' ')
+        .is_some_and(|space| line[space + 1..].starts_with(HEADER_START.as_bytes()))
+}
+
+/// SHA-256 of a file after its header and whether it has one. The file is
+/// read in pieces, so a large zone file is never in memory as a whole.
+#[cfg(feature = "fs")]
+fn body_hash(path: &Path) -> io::Result<(bool, [u8; 32])> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut head = Vec::new();
+    reader.read_until(b'\n', &mut head)?;
+    let mut has_header = false;
+    if is_header_start(&head) {
+        // The header ends at the first empty line, without one there is none
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            head.append(&mut line);
+            if head.ends_with(b"\n\n") {
+                has_header = true;
+                break;
+            }
+        }
+    }
+    if !has_header {
+        hasher.update(&head);
+    }
+    io::copy(&mut reader, &mut hasher)?;
+    Ok((has_header, hasher.finalize().into()))
+}
+
+/// Collects everything `write` produces into a string
+pub(crate) fn render(write: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> Result<String> {
+    let mut output = Vec::new();
+    write(&mut output)?;
+    Ok(String::from_utf8(output)?)
+}
+
+/// References to `records` ordered by `key`, so that the output does not depend
+/// on the order of the config. Records with equal keys keep their order.
+#[cfg(any(feature = "nsd", feature = "unbound"))]
+pub(crate) fn sorted<'a, T, K: Ord>(records: &'a [T], key: impl Fn(&'a T) -> K) -> Vec<&'a T> {
+    let mut sorted: Vec<&T> = records.iter().collect();
+    sorted.sort_by_key(|record| key(record));
+    sorted
+}
+
+/// Passes writes through to `inner` and counts the bytes written
+#[derive(Debug)]
+pub struct CountingWriter<W> {
+    inner: W,
+    pub bytes: usize,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        CountingWriter { inner, bytes: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Effect writing a file would have on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    Create,
+    Modify,
+    Unchanged,
+}
+
+impl FileChange {
+    /// A file differing only in its header is unchanged, it keeps the time
+    /// and input it was first generated from
+    #[cfg(feature = "fs")]
+    pub fn of(file: &OutputFile) -> Self {
+        let (header, body) = split_header(&file.content);
+        FileChange::of_hash(&file.path, header.is_some(), Sha256::digest(body).into())
+    }
+
+    /// Change to the file at `path` by new content with the SHA-256 `hash`
+    /// after its header, compared to the hash of the file on disk
+    #[cfg(feature = "fs")]
+    fn of_hash(path: &Path, has_header: bool, hash: [u8; 32]) -> Self {
+        match body_hash(path) {
+            Ok(existing) if existing == (has_header, hash) => FileChange::Unchanged,
+            Ok(_) => FileChange::Modify,
+            Err(_) => FileChange::Create,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileChange::Create => "create",
+            FileChange::Modify => "modify",
+            FileChange::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// How files that are about to be replaced are preserved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backup {
+    /// Copy to `<file>.bak`, replacing an older backup
+    Simple,
+    /// Copy to `<file>.<YYYYMMDDhhmmss>` and keep this many of the newest copies
+    #[cfg(feature = "cli")]
+    Timestamped(usize),
+}
+
+/// A generated file streamed into `<path>.tmp`, which replaces the file once
+/// complete. The content after the header is hashed on the way, so whether
+/// the file changes is known without holding it or the old file in memory.
+/// The temporary file is removed if the file is dropped without a commit.
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+pub struct StagedFile {
+    pub path: PathBuf,
+    /// Temporary file, `None` in a dry run
+    tmp: Option<PathBuf>,
+    /// Bytes of the file, header included
+    pub bytes: usize,
+    pub change: FileChange,
+}
+
+/// Writer of the body of a [`StagedFile`]
+#[cfg(feature = "fs")]
+pub struct StagedWriter {
+    file: Option<BufWriter<File>>,
+    hasher: Sha256,
+    bytes: usize,
+}
+
+#[cfg(feature = "fs")]
+impl Write for StagedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = match &mut self.file {
+            Some(file) => file.write(buf)?,
+            None => buf.len(),
+        };
+        self.hasher.update(&buf[..n]);
+        self.bytes += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl StagedFile {
+    /// Writes `header` and the body from `write` to the temporary file,
+    /// creating missing parent directories. A dry run only hashes the body.
+    pub fn create(
+        path: impl Into<PathBuf>,
+        header: Option<&str>,
+        dry_run: bool,
+        write: impl FnOnce(&mut StagedWriter) -> Result<()>,
+    ) -> Result<StagedFile> {
+        let path = path.into();
+        // Removes the temporary file if writing fails
+        let mut staged = StagedFile {
+            path,
+            tmp: None,
+            bytes: 0,
+            change: FileChange::Create,
+        };
+        if dry_run {
+            staged.write(None, header, write)?;
+            return Ok(staged);
+        }
+        if let Some(parent) = staged.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            create_dir(parent)?;
+        }
+        let mut tmp = staged.path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let file = File::create(&tmp).with_context(|| format!("Cannot write {}", tmp.display()))?;
+        staged.tmp = Some(tmp);
+        staged.write(Some(BufWriter::new(file)), header, write)?;
+        Ok(staged)
+    }
+
+    /// Stages a file that is already in memory, its header taken from the
+    /// content
+    pub fn of(file: &OutputFile, dry_run: bool) -> Result<StagedFile> {
+        let (header, body) = split_header(&file.content);
+        StagedFile::create(&file.path, header, dry_run, |out| {
+            Ok(out.write_all(body.as_bytes())?)
+        })
+    }
+
+    fn write(
+        &mut self,
+        mut file: Option<BufWriter<File>>,
+        header: Option<&str>,
+        write: impl FnOnce(&mut StagedWriter) -> Result<()>,
+    ) -> Result<()> {
+        if let (Some(file), Some(header)) = (&mut file, header) {
+            file.write_all(header.as_bytes())?;
+        }
+        let mut out = StagedWriter {
+            file,
+            hasher: Sha256::new(),
+            bytes: 0,
+        };
+        write(&mut out)?;
+        out.flush()?;
+        self.bytes = header.map_or(0, str::len) + out.bytes;
+        let hash = out.hasher.finalize().into();
+        self.change = FileChange::of_hash(&self.path, header.is_some(), hash);
+        Ok(())
+    }
+
+    /// Path of the temporary file, e.g. to check the content before the
+    /// commit. `None` in a dry run.
+    pub fn tmp(&self) -> Option<&Path> {
+        self.tmp.as_deref()
+    }
+
+    /// Replaces the file by the temporary one if it changes, backing the old
+    /// one up first if a backup mode is given. An unchanged file is left
+    /// untouched so its mtime is preserved.
+    pub fn commit(mut self, backup: Option<Backup>) -> Result<FileChange> {
+        let Some(tmp) = self.tmp.take() else {
+            return Ok(self.change);
+        };
+        if self.change == FileChange::Unchanged {
+            fs::remove_file(&tmp)?;
+            return Ok(self.change);
+        }
+        if let (Some(backup), FileChange::Modify) = (backup, self.change) {
+            backup_file(&self.path, backup)
+                .with_context(|| format!("Cannot back up {}", self.path.display()))?;
+        }
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Cannot write {}", self.path.display()))?;
+        Ok(self.change)
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        if let Some(tmp) = self.tmp.take() {
+            let _ = fs::remove_file(tmp);
+        }
+    }
+}
+
+/// Writes all files whose content differs from the file on disk, creating
+/// missing parent directories. Unchanged files are left untouched so their
+/// mtime is preserved. Changed files are backed up first if a backup mode is
+/// given. Returns the change applied to each file.
+#[cfg(feature = "fs")]
+pub fn write_files(files: &[OutputFile], backup: Option<Backup>) -> Result<Vec<FileChange>> {
+    let mut changes = Vec::with_capacity(files.len());
+    for file in files {
+        changes.push(StagedFile::of(file, false)?.commit(backup)?);
+    }
+    Ok(changes)
+}
+
+#[cfg(feature = "fs")]
+fn backup_file(path: &Path, backup: Backup) -> Result<()> {
+    let mut name = path.as_os_str().to_owned();
+    match backup {
+        Backup::Simple => {
+            name.push(".bak");
+            fs::copy(path, name)?;
+        }
+        #[cfg(feature = "cli")]
+        Backup::Timestamped(keep) => {
+            name.push(format!(".{}", Utc::now().format("%Y%m%d%H%M%S")));
+            copy_new(path, &PathBuf::from(name))?;
+            prune_backups(path, keep)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies a file to `backup`, or to `backup-N` with the lowest free N if a
+/// backup of the same second exists, so no backup is ever overwritten
+#[cfg(feature = "cli")]
+fn copy_new(path: &Path, backup: &Path) -> io::Result<()> {
+    let mut source = File::open(path)?;
+    for counter in 0.. {
+        let mut name = backup.as_os_str().to_owned();
+        if counter > 0 {
+            name.push(format!("-{counter}"));
+        }
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&name)
+        {
+            Ok(mut file) => {
+                io::copy(&mut source, &mut file)?;
+                return Ok(());
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("a free backup name is found before the counter overflows")
+}
+
+/// Time and counter of a timestamped backup suffix like `20250601120000`
+/// or `20250601120000-1`
+#[cfg(feature = "cli")]
+fn backup_stamp(suffix: &str) -> Option<(&str, u32)> {
+    let (stamp, counter) = match suffix.split_once('-') {
+        Some((stamp, counter)) => (stamp, counter.parse().ok()?),
+        None => (suffix, 0),
+    };
+    (stamp.len() == 14 && stamp.bytes().all(|b| b.is_ascii_digit())).then_some((stamp, counter))
+}
+
+/// Removes all but the newest `keep` timestamped backups of a file
+#[cfg(feature = "cli")]
+fn prune_backups(path: &Path, keep: usize) -> Result<()> {
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Ok(());
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let prefix = format!("{}.", file_name.to_string_lossy());
+
+    let mut backups: Vec<(String, u32, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let (stamp, counter) = backup_stamp(name.strip_prefix(&prefix)?)?;
+            Some((stamp.to_string(), counter, entry.path()))
+        })
+        .collect();
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep);
+    for (_, _, old) in &backups[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "fs")]
+fn create_dir(dir: &Path) -> Result<()> {
+    if !dir.is_dir() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counting_writer() {
+        let mut out = CountingWriter::new(Vec::new());
+        writeln!(out, "server:").unwrap();
+        writeln!(out, "local-zone: example.com. static").unwrap();
+        assert_eq!(out.bytes, 40);
+        assert_eq!(out.inner.len(), 40);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_file_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sub").join("zones.conf");
+        let file = OutputFile {
+            path: path.clone(),
+            content: "zone:\n".to_string(),
+        };
+        assert_eq!(FileChange::of(&file), FileChange::Create);
+
+        write_files(std::slice::from_ref(&file), None).unwrap();
+        assert_eq!(FileChange::of(&file), FileChange::Unchanged);
+
+        fs::write(&path, "old").unwrap();
+        assert_eq!(FileChange::of(&file), FileChange::Modify);
+    }
+
+    #[test]
+    fn test_header() {
+        let header = Header {
+            generated: "2025-06-01T12:00:00Z".to_string(),
+            input: "zones.yaml".to_string(),
+            input_hash: "ab12".to_string(),
+        };
+        let mut files = vec![
+            OutputFile {
+                path: PathBuf::from("nsd/master/example.com.zone"),
+                content: "$ORIGIN example.com.\n".to_string(),
+            },
+            OutputFile {
+                path: PathBuf::from("nsd/zones.conf"),
+                content: "zone:\n".to_string(),
+            },
+        ];
+        header.prepend(&mut files, 1, |path| {
+            path.ends_with("example.com.zone").then_some(2)
+        });
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(
+            files[0].content,
+            format!(
+                "; Generated by zonefile-rs {version} at 2025-06-01T12:00:00Z\n\
+                 ; Input: zones.yaml (sha256 ab12)\n; Serial: 2\n\n$ORIGIN example.com.\n"
+            )
+        );
+        assert!(files[1].content.starts_with("# Generated by zonefile-rs"));
+        assert!(files[1].content.ends_with("# Serial: 1\n\nzone:\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_file_change_ignores_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("example.com.zone");
+        let file = |content: &str| OutputFile {
+            path: path.clone(),
+            content: content.to_string(),
+        };
+        assert_eq!(
+            split_header("# other\n\nzone:\n"),
+            (None, "# other\n\nzone:\n")
+        );
+        let old = "; Generated by zonefile-rs 0.1.0 at 2025-01-01T00:00:00Z\n\n$TTL 3600\n";
+        fs::write(&path, old).unwrap();
+        let new = "; Generated by zonefile-rs 0.2.0 at 2025-06-01T00:00:00Z\n\n$TTL 3600\n";
+        assert_eq!(FileChange::of(&file(new)), FileChange::Unchanged);
+        assert_eq!(FileChange::of(&file("$TTL 3600\n")), FileChange::Modify);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_write_files_skips_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str, content: &str| OutputFile {
+            path: dir.path().join(name),
+            content: content.to_string(),
+        };
+        let files = vec![file("a.zone", "a"), file("b.zone", "b")];
+        assert_eq!(
+            write_files(&files, None).unwrap(),
+            vec![FileChange::Create, FileChange::Create]
+        );
+
+        let mtime = |name: &str| {
+            fs::metadata(dir.path().join(name))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+        let before = mtime("a.zone");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        let files = vec![file("a.zone", "a"), file("b.zone", "b2")];
+        assert_eq!(
+            write_files(&files, None).unwrap(),
+            vec![FileChange::Unchanged, FileChange::Modify]
+        );
+        assert_eq!(mtime("a.zone"), before);
+        assert_eq!(fs::read_to_string(dir.path().join("b.zone")).unwrap(), "b2");
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_staged_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("master/example.com.zone");
+        let tmp = dir.path().join("master/example.com.zone.tmp");
+        let body = |out: &mut StagedWriter| Ok(out.write_all(b"@ IN A 192.0.2.1\n")?);
+
+        let staged =
+            StagedFile::create(&path, Some("; Generated by zonefile-rs 1\n\n"), false, body)
+                .unwrap();
+        assert_eq!(staged.change, FileChange::Create);
+        assert_eq!(staged.bytes, 47);
+        assert!(tmp.exists() && !path.exists());
+        staged.commit(None).unwrap();
+        assert!(!tmp.exists());
+
+        // Only the body counts, and a dry run writes nothing
+        let header = Some("; Generated by zonefile-rs 2\n\n");
+        let staged = StagedFile::create(&path, header, true, body).unwrap();
+        assert_eq!(staged.change, FileChange::Unchanged);
+        assert!(!tmp.exists());
+        let staged = StagedFile::create(&path, None, false, body).unwrap();
+        assert_eq!(staged.change, FileChange::Modify);
+
+        // Dropped without a commit, the file stays as it was
+        drop(staged);
+        assert!(!tmp.exists());
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(
+            content.starts_with("; Generated by zonefile-rs 1\n"),
+            "{content}"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_backup_simple() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zones.conf");
+        let file = OutputFile {
+            path: path.clone(),
+            content: "new".to_string(),
+        };
+
+        write_files(std::slice::from_ref(&file), Some(Backup::Simple)).unwrap();
+        assert!(!dir.path().join("zones.conf.bak").exists());
+
+        fs::write(&path, "old").unwrap();
+        write_files(std::slice::from_ref(&file), Some(Backup::Simple)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("zones.conf.bak")).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_backup_same_second() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zones.conf");
+        let backup = dir.path().join("zones.conf.20250601120000");
+        for content in ["first", "second", "third"] {
+            fs::write(&path, content).unwrap();
+            copy_new(&path, &backup).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "first");
+        let second = dir.path().join("zones.conf.20250601120000-1");
+        assert_eq!(fs::read_to_string(second).unwrap(), "second");
+        let third = dir.path().join("zones.conf.20250601120000-2");
+        assert_eq!(fs::read_to_string(third).unwrap(), "third");
+
+        assert_eq!(
+            backup_stamp("20250601120000-2"),
+            Some(("20250601120000", 2))
+        );
+        assert_eq!(backup_stamp("20250601120000"), Some(("20250601120000", 0)));
+        assert_eq!(backup_stamp("20250601120000-x"), None);
+        assert_eq!(backup_stamp("bak"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_backup_timestamped_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zones.conf");
+        for stamp in ["20240101000000", "20240102000000", "20240103000000"] {
+            fs::write(dir.path().join(format!("zones.conf.{stamp}")), stamp).unwrap();
+        }
+        fs::write(dir.path().join("zones.conf.bak"), "unrelated").unwrap();
+        fs::write(&path, "old").unwrap();
+
+        let file = OutputFile {
+            path: path.clone(),
+            content: "new".to_string(),
+        };
+        write_files(std::slice::from_ref(&file), Some(Backup::Timestamped(2))).unwrap();
+
+        let mut names: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 4, "{names:?}");
+        assert_eq!(names[0], "zones.conf");
+        assert_eq!(names[1], "zones.conf.20240103000000");
+        assert_eq!(names[3], "zones.conf.bak");
+        assert_eq!(
+            fs::read_to_string(dir.path().join(&names[2])).unwrap(),
+            "old"
+        );
+    }
+}
@@ -1,21 +1,30 @@
 use std::cmp::{max, Ordering};
-use std::collections::HashMap;
-use std::fmt::Write;
-use std::fs;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
 
-use crate::output::NSD_COLUMN_WIDTH;
-use crate::parser::ZoneBase;
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "dnssec")]
+use crate::dnssec::SignedZone;
+use crate::origin::{note, Origin};
+#[cfg(feature = "fs")]
+use crate::output::StagedFile;
+use crate::output::{render, sorted, OutputFile, NSD_FILE_TEMPLATE, NSD_ZONE_DIR};
+use crate::parser::{ForwardZone, ReverseZone, SecondaryZone, ZoneBase};
 use crate::transform::ip_name;
+use crate::tsig::TsigKey;
 
-fn nsd_format(
+fn write_record(
+    output: &mut impl Write,
     value: &str,
     record_ttl: u32,
     zone_ttl: u32,
     record_type: &str,
     data: &str,
-) -> String {
-    let space = NSD_COLUMN_WIDTH as i32;
+    column_width: usize,
+) -> io::Result<()> {
+    let space = column_width as i32;
     let uspace = space as usize;
     let ttl = if record_ttl == zone_ttl {
         String::new()
@@ -23,26 +32,38 @@ fn nsd_format(
         record_ttl.to_string()
     };
     let value_ttl = if record_ttl == zone_ttl {
-        format!("{value:width$}", width = (uspace - 1))
+        format!("{value:width$}", width = uspace.saturating_sub(1))
     } else {
-        format!("{value:width$} {ttl}", width = (uspace - ttl.len() - 2))
+        format!(
+            "{value:width$} {ttl}",
+            width = uspace.saturating_sub(ttl.len() + 2)
+        )
     };
     let value_ttl_len = value_ttl.len() as i32;
     let type_len = max(0, 7 - (max(0, value_ttl_len - space - 1))) as usize;
-    format!(
-        "{value_ttl} {record_type:width$} {data}\n",
+    writeln!(
+        output,
+        "{value_ttl} {record_type:width$} {data}",
         width = type_len
     )
 }
 
-fn write_soa(base: &ZoneBase) -> String {
-    let mut output = String::new();
-    let indent = " ".repeat(NSD_COLUMN_WIDTH);
-    let ns = &base
-        .nameserver
-        .first()
-        .expect("Zone needs one nameserver")
-        .name;
+/// Data of a record followed by its comment
+fn with_comment(data: &str, comment: &Option<String>) -> String {
+    match comment {
+        Some(comment) => format!("{data} ; {comment}"),
+        None => data.to_string(),
+    }
+}
+
+fn write_soa(
+    output: &mut impl Write,
+    base: &ZoneBase,
+    origin: Option<&Origin>,
+    column_width: usize,
+) -> Result<()> {
+    let indent = " ".repeat(column_width);
+    let ns = base.primary_nameserver()?;
     let name = base.name.as_str();
     let email = base.email.as_str();
     let serial = base.serial;
@@ -52,27 +73,27 @@ fn write_soa(base: &ZoneBase) -> String {
     let ttl = base.ttl;
     let nrc_ttl = base.nrc_ttl;
 
-    writeln!(output, "$ORIGIN {name}").unwrap();
-    writeln!(output, "$TTL {ttl}").unwrap();
-    writeln!(output).unwrap();
+    writeln!(output, "$ORIGIN {name}")?;
+    writeln!(output, "$TTL {ttl}")?;
+    writeln!(output)?;
 
     writeln!(
         output,
         "@                            IN SOA     {ns} {email} (",
-    )
-    .unwrap();
-    writeln!(output, "{indent}           {serial:<12}; serial number").unwrap();
-    writeln!(output, "{indent}           {refresh:<12}; refresh").unwrap();
-    writeln!(output, "{indent}           {retry:<12}; retry").unwrap();
-    writeln!(output, "{indent}           {expire:<12}; expire").unwrap();
-    writeln!(output, "{indent}           {nrc_ttl:<12}; min ttl").unwrap();
-    writeln!(output, "{indent}        )").unwrap();
+    )?;
+    writeln!(output, "{indent}           {serial:<12}; serial number")?;
+    writeln!(output, "{indent}           {refresh:<12}; refresh")?;
+    writeln!(output, "{indent}           {retry:<12}; retry")?;
+    writeln!(output, "{indent}           {expire:<12}; expire")?;
+    writeln!(output, "{indent}           {nrc_ttl:<12}; min ttl")?;
+    writeln!(output, "{indent}        )")?;
 
     for ns in &base.nameserver {
-        output.push_str(&nsd_format("", ns.ttl, ttl, "NS", &ns.name));
+        let data = with_comment(&ns.name, &note(&None, origin));
+        write_record(output, "", ns.ttl, ttl, "NS", &data, column_width)?;
     }
 
-    output
+    Ok(())
 }
 
 fn strip_name(name: &str, zone_name: &str) -> String {
@@ -85,141 +106,599 @@ fn strip_name(name: &str, zone_name: &str) -> String {
     }
 }
 
-pub fn write_nsd(
-    output_dir: &Path,
-    forward: &[crate::parser::ForwardZone],
-    reverse: &[crate::parser::ReverseZone],
-) -> anyhow::Result<()> {
-    let master_dir = output_dir.join("master");
-    let master = master_dir.display();
-    fs::create_dir_all(output_dir).or_else(
-        |e| {
-            if output_dir.is_dir() {
-                Ok(())
-            } else {
-                Err(e)
-            }
-        },
-    )?;
-    fs::create_dir_all(&master_dir).or_else(
-        |e| {
-            if output_dir.is_dir() {
-                Ok(())
-            } else {
-                Err(e)
-            }
-        },
-    )?;
+/// Writes the `zone:` entries of zones.conf for all zones, with the NSD
+/// options of each and their files placed by `layout`. With TSIG keys,
+/// their `key:` entries come first and every zone notifies the secondaries of
+/// each key and allows them transfers signed with it. The secondary zones
+/// follow, transferred from their masters with the key they name.
+pub fn write_zones_conf(
+    output: &mut impl Write,
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+    secondary: &[SecondaryZone],
+    keys: &[TsigKey],
+    layout: ZoneLayout,
+) -> Result<()> {
+    write_keys(output, keys)?;
+    for base in zone_bases(forward, reverse) {
+        write_zone_entry(output, base, keys, layout)?;
+    }
+    for zone in secondary {
+        write_secondary_entry(output, zone, keys, layout)?;
+    }
+    Ok(())
+}
 
-    let mut conf = String::new();
-    let mut files: HashMap<String, String> = HashMap::new();
+fn zone_bases<'a>(
+    forward: &'a [ForwardZone],
+    reverse: &'a [ReverseZone],
+) -> impl Iterator<Item = &'a ZoneBase> {
+    forward
+        .iter()
+        .map(|zone| &zone.base)
+        .chain(reverse.iter().map(|zone| &zone.base))
+}
 
-    for zone in forward {
-        let zone_name = zone.base.name.as_str();
-        let zone_ttl = zone.base.ttl;
-        let mut output = String::new();
+/// Writes the `key:` entries of the TSIG keys
+fn write_keys(output: &mut impl Write, keys: &[TsigKey]) -> Result<()> {
+    for key in keys {
+        writeln!(output, "key:")?;
+        writeln!(output, "    name: \"{}\"", key.name)?;
+        writeln!(output, "    algorithm: {}", key.algorithm.name())?;
+        writeln!(output, "    secret: \"{}\"", key.secret)?;
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+/// Writes the `zone:` entry of a zone served from its generated zone file
+fn write_zone_entry(
+    output: &mut impl Write,
+    base: &ZoneBase,
+    keys: &[TsigKey],
+    layout: ZoneLayout,
+) -> Result<()> {
+    let zone_name = &base.name;
+    writeln!(output, "zone:")?;
+    writeln!(output, "    name: {zone_name}")?;
+    writeln!(output, "    zonefile: {}", layout.file(zone_name))?;
+    if let Some(pattern) = &base.nsd.pattern {
+        writeln!(output, "    include-pattern: {pattern}")?;
+    }
+    for notify in &base.nsd.notify {
+        writeln!(output, "    notify: {notify}")?;
+    }
+    for provide_xfr in &base.nsd.provide_xfr {
+        writeln!(output, "    provide-xfr: {provide_xfr}")?;
+    }
+    for interface in &base.nsd.outgoing_interface {
+        writeln!(output, "    outgoing-interface: {interface}")?;
+    }
+    for key in keys {
+        for secondary in &key.secondaries {
+            writeln!(output, "    notify: {secondary} {}", key.name)?;
+            writeln!(output, "    provide-xfr: {secondary} {}", key.name)?;
+        }
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
+/// Writes the `zone:` entry of a secondary zone
+fn write_secondary_entry(
+    output: &mut impl Write,
+    zone: &SecondaryZone,
+    keys: &[TsigKey],
+    layout: ZoneLayout,
+) -> Result<()> {
+    let key = match &zone.tsig_key {
+        Some(name) if !keys.iter().any(|key| &key.name == name) => bail!(
+            "Secondary zone {} uses the TSIG key {name}, which is not in the tsig section",
+            zone.name
+        ),
+        Some(name) => name.as_str(),
+        None => "NOKEY",
+    };
+    writeln!(output, "zone:")?;
+    writeln!(output, "    name: {}", zone.name)?;
+    let file = zone_file(layout.file_template, &zone.name);
+    writeln!(output, "    zonefile: secondary/{file}")?;
+    for master in &zone.masters {
+        writeln!(output, "    allow-notify: {master} {key}")?;
+        writeln!(output, "    request-xfr: {master} {key}")?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
 
-        writeln!(conf, "zone:").unwrap();
-        writeln!(conf, "    name: {zone_name}").unwrap();
-        writeln!(conf, "    zonefile: master/{zone_name}zone").unwrap();
-        writeln!(conf).unwrap();
+/// Renders zones.conf, e.g. to replace the one of [`generate_nsd`] by one
+/// with secondary zones or TSIG keys
+pub fn render_zones_conf(
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+    secondary: &[SecondaryZone],
+    keys: &[TsigKey],
+    layout: ZoneLayout,
+) -> Result<String> {
+    render(|out| write_zones_conf(out, forward, reverse, secondary, keys, layout))
+}
 
-        output.push_str(&write_soa(&zone.base));
+/// File of the TSIG keys among the conf fragments, sorting before those of
+/// the zones so a glob include reads the keys first
+pub const KEYS_FRAGMENT: &str = "00-tsig-keys.conf";
 
-        for mx in &zone.mx {
-            let record_type = format!("MX {:>4}", mx.prio);
-            output.push_str(&nsd_format("", mx.ttl, zone_ttl, &record_type, &mx.name));
-        }
+/// Path of the conf fragment of a zone in the fragment directory
+pub fn fragment_path(conf_dir: &Path, zone_name: &str) -> PathBuf {
+    conf_dir.join(format!("{}.conf", zone_name.trim_end_matches('.')))
+}
+
+/// Renders the entries of zones.conf as one fragment per zone in `conf_dir`,
+/// for an `include: "<conf_dir>/*.conf"` next to hand-managed NSD config.
+/// The TSIG keys get a fragment of their own, [`KEYS_FRAGMENT`].
+pub fn render_conf_fragments(
+    conf_dir: &Path,
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+    secondary: &[SecondaryZone],
+    keys: &[TsigKey],
+    layout: ZoneLayout,
+) -> Result<Vec<OutputFile>> {
+    let mut fragments = Vec::with_capacity(forward.len() + reverse.len() + secondary.len() + 1);
+    if !keys.is_empty() {
+        fragments.push(OutputFile {
+            path: conf_dir.join(KEYS_FRAGMENT),
+            content: render(|out| write_keys(out, keys))?,
+        });
+    }
+    for base in zone_bases(forward, reverse) {
+        fragments.push(OutputFile {
+            path: fragment_path(conf_dir, &base.name),
+            content: render(|out| write_zone_entry(out, base, keys, layout))?,
+        });
+    }
+    for zone in secondary {
+        fragments.push(OutputFile {
+            path: fragment_path(conf_dir, &zone.name),
+            content: render(|out| write_secondary_entry(out, zone, keys, layout))?,
+        });
+    }
+    Ok(fragments)
+}
+
+/// The `*.conf` files in `conf_dir` that are not in `keep`, i.e. fragments of
+/// zones no longer in the config. A missing directory has none.
+#[cfg(feature = "fs")]
+pub fn stale_fragments(conf_dir: &Path, keep: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if !conf_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut stale: Vec<PathBuf> = std::fs::read_dir(conf_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "conf"))
+        .filter(|path| !keep.contains(path))
+        .collect();
+    stale.sort();
+    Ok(stale)
+}
 
-        let mut a_records: Vec<_> = zone.hosts.iter().collect();
-        a_records.sort_unstable_by(|a, b| {
-            // Special order for zone apex "@"
-            let a_is_apex = a.name == zone_name;
-            let b_is_apex = b.name == zone_name;
-
-            match (a_is_apex, b_is_apex) {
-                (true, true) => Ordering::Equal,
-                (true, false) => Ordering::Less,
-                (false, true) => Ordering::Greater,
-                (false, false) => {
-                    let ncmp = a.name.cmp(&b.name);
-                    if ncmp == Ordering::Equal {
-                        a.ip.cmp(&b.ip)
-                    } else {
-                        ncmp
-                    }
+/// Writes the zone file of a forward zone record by record
+pub fn write_forward_zone(
+    output: &mut impl Write,
+    zone: &ForwardZone,
+    column_width: usize,
+) -> Result<()> {
+    let zone_name = zone.base.name.as_str();
+    let zone_ttl = zone.base.ttl;
+    let apex = zone.origins.get(zone_name);
+
+    write_soa(output, &zone.base, apex, column_width)?;
+
+    for mx in sorted(&zone.mx, |mx| (mx.prio, &mx.name)) {
+        let record_type = format!("MX {:>4}", mx.prio);
+        write_record(
+            output,
+            "",
+            mx.ttl,
+            zone_ttl,
+            &record_type,
+            &with_comment(&mx.name, &note(&mx.comment, apex)),
+            column_width,
+        )?;
+    }
+
+    for ns in sorted(&zone.delegation, |ns| (&ns.name, &ns.target)) {
+        let name = strip_name(&ns.name, zone_name);
+        let data = with_comment(&ns.target, &note(&None, zone.origins.get(&ns.name)));
+        write_record(output, &name, ns.ttl, zone_ttl, "NS", &data, column_width)?;
+    }
+
+    let mut a_records: Vec<_> = zone.hosts.iter().collect();
+    a_records.sort_unstable_by(|a, b| {
+        // Special order for zone apex "@"
+        let a_is_apex = a.name == zone_name;
+        let b_is_apex = b.name == zone_name;
+
+        match (a_is_apex, b_is_apex) {
+            (true, true) => a.ip.cmp(&b.ip),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => {
+                let ncmp = a.name.cmp(&b.name);
+                if ncmp == Ordering::Equal {
+                    a.ip.cmp(&b.ip)
+                } else {
+                    ncmp
                 }
             }
-        });
+        }
+    });
 
-        let mut hostname = "".to_string();
-        for record in a_records {
-            let name = strip_name(&record.name, zone_name);
-            let record_name = if hostname == name {
-                ""
-            } else {
-                hostname = name.clone();
-                &hostname
-            };
-            let record_type = if record.ip.is_ipv4() { "A" } else { "AAAA" };
+    let mut hostname = "".to_string();
+    for record in a_records {
+        let name = strip_name(&record.name, zone_name);
+        let record_name = if hostname == name {
+            ""
+        } else {
+            hostname = name.clone();
+            &hostname
+        };
+        let record_type = if record.ip.is_ipv4() { "A" } else { "AAAA" };
 
-            output.push_str(&nsd_format(
-                record_name,
-                record.ttl,
-                zone_ttl,
-                record_type,
+        write_record(
+            output,
+            record_name,
+            record.ttl,
+            zone_ttl,
+            record_type,
+            &with_comment(
                 &record.ip.to_string(),
-            ));
-        }
+                &note(&record.comment, zone.origins.get(&record.name)),
+            ),
+            column_width,
+        )?;
+    }
 
-        for srv in &zone.srv {
-            let data = format!("{} {} {} {}", srv.prio, srv.weight, srv.port, &srv.target);
-            let name = strip_name(&srv.name, zone_name);
-            output.push_str(&nsd_format(&name, srv.ttl, zone_ttl, "SRV", &data));
-        }
+    for srv in sorted(&zone.srv, |srv| {
+        (&srv.name, srv.prio, srv.weight, srv.port, &srv.target)
+    }) {
+        let data = format!("{} {} {} {}", srv.prio, srv.weight, srv.port, &srv.target);
+        let data = with_comment(&data, &note(&srv.comment, zone.origins.get(&srv.name)));
+        let name = strip_name(&srv.name, zone_name);
+        write_record(output, &name, srv.ttl, zone_ttl, "SRV", &data, column_width)?;
+    }
 
-        for cname in &zone.cname {
-            let name = strip_name(&cname.name, zone_name);
-            output.push_str(&nsd_format(
-                &name,
-                cname.ttl,
-                zone_ttl,
-                "CNAME",
+    for txt in sorted(&zone.txt, |txt| (&txt.name, &txt.text)) {
+        let name = strip_name(&txt.name, zone_name);
+        let data = with_comment(&txt.data(), &note(&None, zone.origins.get(&txt.name)));
+        write_record(output, &name, txt.ttl, zone_ttl, "TXT", &data, column_width)?;
+    }
+
+    for cname in sorted(&zone.cname, |cname| &cname.name) {
+        let name = strip_name(&cname.name, zone_name);
+        write_record(
+            output,
+            &name,
+            cname.ttl,
+            zone_ttl,
+            "CNAME",
+            &with_comment(
                 &cname.target,
-            ));
+                &note(&cname.comment, zone.origins.get(&cname.name)),
+            ),
+            column_width,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes the zone file of a reverse zone record by record
+pub fn write_reverse_zone(
+    output: &mut impl Write,
+    zone: &ReverseZone,
+    column_width: usize,
+) -> Result<()> {
+    let zone_ttl = zone.base.ttl;
+
+    write_soa(output, &zone.base, None, column_width)?;
+
+    for ns in sorted(&zone.delegation, |ns| (&ns.name, &ns.target)) {
+        let child = strip_name(&ns.name, &zone.base.name);
+        write_record(
+            output,
+            &child,
+            ns.ttl,
+            zone_ttl,
+            "NS",
+            &ns.target,
+            column_width,
+        )?;
+    }
+
+    let mut ptrs: Vec<_> = zone.ptr.iter().collect();
+    ptrs.sort_by(|a, b| (a.ip, &a.name).cmp(&(b.ip, &b.name)));
+    for ptr in ptrs {
+        let ip_entry = ip_name(&ptr.ip, zone.split);
+        write_record(
+            output,
+            &ip_entry,
+            ptr.ttl,
+            zone_ttl,
+            "PTR",
+            &with_comment(&ptr.name, &note(&None, zone.origins.get(&ptr.name))),
+            column_width,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes the DNSKEY, NSEC and RRSIG records of a signed zone, to follow its
+/// other records, with the DS records for the parent as comments
+#[cfg(feature = "dnssec")]
+pub fn write_signatures(
+    output: &mut impl Write,
+    base: &ZoneBase,
+    signed: &SignedZone,
+    column_width: usize,
+) -> Result<()> {
+    writeln!(output)?;
+    writeln!(output, "; DNSSEC")?;
+    if !signed.ds.is_empty() {
+        writeln!(output, "; DS records for the parent zone:")?;
+        for ds in &signed.ds {
+            writeln!(output, ";   {ds}")?;
         }
+    }
+    for record in &signed.records {
+        let name = strip_name(&record.owner, &base.name);
+        write_record(
+            output,
+            &name,
+            record.ttl,
+            base.ttl,
+            record.record_type,
+            &record.data,
+            column_width,
+        )?;
+    }
+    Ok(())
+}
+
+/// Renders the records added by signing a zone
+#[cfg(feature = "dnssec")]
+pub fn render_signatures(
+    base: &ZoneBase,
+    signed: &SignedZone,
+    column_width: usize,
+) -> Result<String> {
+    render(|out| write_signatures(out, base, signed, column_width))
+}
 
-        files.insert(format!("{master}/{zone_name}zone"), output);
+/// Fails unless `template` names the zone files with `{zone}`, the zone name
+/// with its trailing dot, or `{name}`, the zone name without it
+pub fn check_file_template(template: &str) -> Result<()> {
+    if !template.contains("{zone}") && !template.contains("{name}") {
+        bail!("File name template '{template}' has neither {{zone}} nor {{name}}")
     }
+    let rest = template.replace("{zone}", "").replace("{name}", "");
+    if rest.contains(['{', '}', '/', '\\']) {
+        bail!(
+            "File name template '{template}' has braces other than {{zone}} and {{name}} or a '/'"
+        )
+    }
+    Ok(())
+}
 
-    for zone in reverse {
-        let zone_name = zone.base.name.as_str();
-        let zone_ttl = zone.base.ttl;
-        let mut output = String::new();
-
-        writeln!(conf, "zone:").unwrap();
-        writeln!(conf, "    name: {zone_name}").unwrap();
-        writeln!(conf, "    zonefile: master/{zone_name}zone").unwrap();
-        writeln!(conf).unwrap();
-
-        let soa = write_soa(&zone.base);
-        output.push_str(&soa);
-
-        let mut ptrs: Vec<_> = zone.ptr.iter().collect();
-        ptrs.sort_by(|a, b| a.ip.cmp(&b.ip));
-        for ptr in ptrs {
-            let ip_entry = ip_name(&ptr.ip, zone.split);
-            output.push_str(&nsd_format(&ip_entry, ptr.ttl, zone_ttl, "PTR", &ptr.name));
+/// File name of the zone file of a zone from a template like `db.{name}`, see
+/// [`check_file_template`]. A trailing dot of the name is dropped.
+pub fn zone_file(template: &str, zone_name: &str) -> String {
+    let name = zone_name.trim_end_matches('.');
+    let file = template
+        .replace("{zone}", &format!("{name}."))
+        .replace("{name}", name);
+    file.trim_end_matches('.').to_string()
+}
+
+/// Where the zone files of the NSD output go: `dir` below the NSD config
+/// directory, named by `file_template`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZoneLayout<'a> {
+    pub dir: &'a str,
+    pub file_template: &'a str,
+}
+
+impl Default for ZoneLayout<'_> {
+    fn default() -> Self {
+        ZoneLayout {
+            dir: NSD_ZONE_DIR,
+            file_template: NSD_FILE_TEMPLATE,
+        }
+    }
+}
+
+impl ZoneLayout<'_> {
+    /// Fails unless the directory is relative to the NSD config directory and
+    /// the file names are valid, see [`check_file_template`]
+    pub fn check(&self) -> Result<()> {
+        let dir = Path::new(self.dir);
+        if self.dir.is_empty() || dir.is_absolute() {
+            bail!(
+                "Zone directory '{}' must be relative to the NSD config directory",
+                self.dir
+            );
         }
+        check_file_template(self.file_template)
+    }
 
-        files.insert(format!("{master}/{zone_name}zone"), output);
+    /// Zone file of a zone as zones.conf and `$INCLUDE` name it, relative to
+    /// the NSD config directory
+    pub fn file(&self, zone_name: &str) -> String {
+        let dir = self.dir.trim_end_matches('/');
+        format!("{dir}/{}", zone_file(self.file_template, zone_name))
     }
+}
 
-    fs::write(output_dir.join("zones.conf"), conf)?;
+/// Path of the zone file of a zone below the output directory
+pub fn zone_path(output_dir: &Path, layout: ZoneLayout, zone_name: &str) -> PathBuf {
+    output_dir.join(layout.file(zone_name))
+}
+
+/// Renders the zone file of one forward zone, e.g. to preview it
+pub fn render_forward_zone(zone: &ForwardZone, column_width: usize) -> Result<String> {
+    render(|out| write_forward_zone(out, zone, column_width))
+}
 
-    for (path, content) in files {
-        fs::write(path, content)?;
+/// Renders the zone file of one reverse zone, e.g. to preview it
+pub fn render_reverse_zone(zone: &ReverseZone, column_width: usize) -> Result<String> {
+    render(|out| write_reverse_zone(out, zone, column_width))
+}
+
+/// Key of the part file of a split zone that holds the records of a name
+/// relative to the zone: its first character in forward zones, the label
+/// right below the zone in reverse zones, e.g. the /24 of a /16 zone. The
+/// apex, and the names directly below a reverse zone, stay in the zone file.
+pub fn part_key(name: &str, reverse: bool) -> Option<String> {
+    if name == "@" {
+        return None;
+    }
+    if reverse {
+        let (_, label) = name.rsplit_once('.')?;
+        return Some(label.to_string());
+    }
+    let first = name.chars().next()?.to_ascii_lowercase();
+    if first.is_ascii_alphanumeric() {
+        Some(first.to_string())
+    } else {
+        Some("_".to_string())
+    }
+}
+
+/// Path of a part file of a split zone, next to its zone file
+pub fn part_path(zone_path: &Path, key: &str) -> PathBuf {
+    let mut path = zone_path.as_os_str().to_owned();
+    path.push(format!(".{key}"));
+    PathBuf::from(path)
+}
+
+/// Splits a rendered zone file into the zone file and its part files by the
+/// owner names of the records, see [`part_key`]. Lines with a blank owner
+/// belong to the record before them; directives, comments and blank lines
+/// stay in the zone file. The zone file pulls in the parts with `$INCLUDE`,
+/// naming them like zones.conf names the zone file, relative to the NSD
+/// config directory.
+pub fn split_zone(
+    file: OutputFile,
+    base: &ZoneBase,
+    reverse: bool,
+    layout: ZoneLayout,
+) -> Vec<OutputFile> {
+    let mut head = String::new();
+    let mut parts: BTreeMap<String, String> = BTreeMap::new();
+    let mut key = None;
+    for line in file.content.split_inclusive('\n') {
+        if !line.starts_with(char::is_whitespace) {
+            key = match line.as_bytes()[0] {
+                b'$' | b';' => None,
+                _ => {
+                    let owner = line.split_whitespace().next().unwrap_or("@");
+                    let zone_name = base.name.trim_end_matches('.');
+                    part_key(&strip_name(owner.trim_end_matches('.'), zone_name), reverse)
+                }
+            };
+        } else if line.trim().is_empty() {
+            key = None;
+        }
+        match &key {
+            Some(key) => parts.entry(key.clone()).or_default().push_str(line),
+            None => head.push_str(line),
+        }
+    }
+    if parts.is_empty() {
+        return vec![file];
     }
 
+    head.push('\n');
+    let include = layout.file(&base.name);
+    let mut files = Vec::with_capacity(parts.len() + 1);
+    for (key, records) in parts {
+        head += &format!("$INCLUDE {include}.{key}\n");
+        files.push(OutputFile {
+            path: part_path(&file.path, &key),
+            content: format!("$ORIGIN {}\n$TTL {}\n\n{records}", base.name, base.ttl),
+        });
+    }
+    files.insert(
+        0,
+        OutputFile {
+            path: file.path,
+            content: head,
+        },
+    );
+    files
+}
+
+/// Renders zones.conf and one zone file per zone below `output_dir`, placed
+/// by `layout`
+pub fn generate_nsd(
+    output_dir: &Path,
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+    column_width: usize,
+    layout: ZoneLayout,
+) -> Result<Vec<OutputFile>> {
+    let mut zone_files = Vec::with_capacity(forward.len() + reverse.len());
+    for zone in forward {
+        zone_files.push(OutputFile {
+            path: zone_path(output_dir, layout, &zone.base.name),
+            content: render_forward_zone(zone, column_width)?,
+        });
+    }
+    for zone in reverse {
+        zone_files.push(OutputFile {
+            path: zone_path(output_dir, layout, &zone.base.name),
+            content: render_reverse_zone(zone, column_width)?,
+        });
+    }
+    zone_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut output_files = vec![OutputFile {
+        path: output_dir.join("zones.conf"),
+        content: render_zones_conf(forward, reverse, &[], &[], layout)?,
+    }];
+    output_files.extend(zone_files);
+    Ok(output_files)
+}
+
+/// Writes zones.conf and streams each zone straight into its zone file, so
+/// no zone is rendered in memory
+#[cfg(feature = "fs")]
+pub fn write_nsd(
+    output_dir: &Path,
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+    column_width: usize,
+    layout: ZoneLayout,
+) -> Result<()> {
+    StagedFile::create(output_dir.join("zones.conf"), None, false, |out| {
+        write_zones_conf(out, forward, reverse, &[], &[], layout)
+    })?
+    .commit(None)?;
+    for zone in forward {
+        let path = zone_path(output_dir, layout, &zone.base.name);
+        StagedFile::create(path, None, false, |out| {
+            write_forward_zone(out, zone, column_width)
+        })?
+        .commit(None)?;
+    }
+    for zone in reverse {
+        let path = zone_path(output_dir, layout, &zone.base.name);
+        StagedFile::create(path, None, false, |out| {
+            write_reverse_zone(out, zone, column_width)
+        })?
+        .commit(None)?;
+    }
     Ok(())
 }
@@ -0,0 +1,215 @@
+//! Tar archives (POSIX ustar) of the generated files, optionally compressed
+//! with gzip, so a whole NSD output can be copied to a server in one piece
+
+#[cfg(feature = "fs")]
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use anyhow::Context;
+use anyhow::{bail, Result};
+
+use super::OutputFile;
+#[cfg(feature = "fs")]
+use super::{backup_file, create_dir, Backup, FileChange};
+
+const BLOCK: usize = 512;
+
+/// Packs the files into a tar archive, naming each entry by its path below
+/// `root`. All entries are owned by root with mode 0644 and the given mtime,
+/// so the same files always give the same archive.
+pub fn render_tar(files: &[OutputFile], root: &Path, mtime: i64) -> Result<Vec<u8>> {
+    let mut archive = Vec::new();
+    for file in files {
+        let path = file.path.strip_prefix(root).unwrap_or(&file.path);
+        let Some(name) = path.to_str() else {
+            bail!("Path {} is not valid UTF-8", path.display());
+        };
+        archive.extend_from_slice(&header(name, file.content.len(), mtime)?);
+        archive.extend_from_slice(file.content.as_bytes());
+        archive.resize(archive.len().next_multiple_of(BLOCK), 0);
+    }
+    // End of archive: two zero blocks
+    archive.resize(archive.len() + 2 * BLOCK, 0);
+    Ok(archive)
+}
+
+/// Header block of a regular file
+fn header(name: &str, size: usize, mtime: i64) -> Result<[u8; BLOCK]> {
+    let mut block = [0u8; BLOCK];
+    let (prefix, name) = split_name(name)?;
+    block[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut block[100..108], 0o644);
+    octal(&mut block[108..116], 0);
+    octal(&mut block[116..124], 0);
+    octal(&mut block[124..136], size as u64);
+    octal(&mut block[136..148], mtime.max(0) as u64);
+    block[156] = b'0';
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+    block[265..269].copy_from_slice(b"root");
+    block[297..301].copy_from_slice(b"root");
+    block[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    // The checksum is taken with its own field filled with spaces
+    block[148..156].fill(b' ');
+    let checksum: u32 = block.iter().map(|&b| u32::from(b)).sum();
+    octal(&mut block[148..155], u64::from(checksum));
+    Ok(block)
+}
+
+/// Splits a name too long for the 100 byte name field at a '/' into the
+/// prefix and name fields of ustar
+fn split_name(name: &str) -> Result<(&str, &str)> {
+    if name.len() <= 100 {
+        return Ok(("", name));
+    }
+    name.match_indices('/')
+        .map(|(i, _)| (&name[..i], &name[i + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100 && !name.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Path {name} is too long for a tar archive"))
+}
+
+/// Writes a number as zero padded octal digits, leaving the last byte of the
+/// field as its terminating NUL
+fn octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let text = format!("{value:0digits$o}");
+    field[..digits].copy_from_slice(&text.as_bytes()[text.len() - digits..]);
+    field[digits] = 0;
+}
+
+/// Compresses data into a gzip file (RFC 1952) without a name or mtime
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    // Magic, deflate, no flags, no mtime, default compression, unknown OS
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+    out.extend(miniz_oxide::deflate::compress_to_vec(data, 6));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+/// CRC-32 as used by gzip (IEEE 802.3, reflected)
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Whether an archive file already has exactly this content
+#[cfg(feature = "fs")]
+pub fn archive_change(path: &Path, archive: &[u8]) -> FileChange {
+    match fs::read(path) {
+        Ok(existing) if existing == archive => FileChange::Unchanged,
+        Ok(_) => FileChange::Modify,
+        Err(_) => FileChange::Create,
+    }
+}
+
+/// Writes the archive to a temporary file that replaces the target, so a
+/// reader or a copy never sees a partial archive. An unchanged archive is
+/// left untouched.
+#[cfg(feature = "fs")]
+pub fn write_archive(path: &Path, archive: &[u8], backup: Option<Backup>) -> Result<FileChange> {
+    let change = archive_change(path, archive);
+    if change == FileChange::Unchanged {
+        return Ok(change);
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        create_dir(parent)?;
+    }
+    if let (Some(backup), FileChange::Modify) = (backup, change) {
+        backup_file(path, backup).with_context(|| format!("Cannot back up {}", path.display()))?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, archive)
+        .and_then(|()| fs::rename(&tmp, path))
+        .with_context(|| format!("Cannot write archive {}", path.display()))?;
+    Ok(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn file(path: &str, content: &str) -> OutputFile {
+        OutputFile {
+            path: PathBuf::from(path),
+            content: content.to_string(),
+        }
+    }
+
+    fn field(block: &[u8], range: std::ops::Range<usize>) -> &str {
+        let field = &block[range];
+        let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+        std::str::from_utf8(&field[..end]).unwrap()
+    }
+
+    #[test]
+    fn test_render_tar() {
+        let files = vec![
+            file("nsd/zones.conf", "zone:\n"),
+            file("nsd/example.com.zone", &"x".repeat(600)),
+        ];
+        let archive = render_tar(&files, Path::new("nsd"), 1_700_000_000).unwrap();
+        // Header and one data block, header and two data blocks, end of archive
+        assert_eq!(archive.len(), 7 * BLOCK);
+
+        let first = &archive[..BLOCK];
+        assert_eq!(field(first, 0..100), "zones.conf");
+        assert_eq!(field(first, 100..108), "0000644");
+        assert_eq!(field(first, 124..136), "00000000006");
+        assert_eq!(field(first, 136..148), format!("{:011o}", 1_700_000_000));
+        assert_eq!(field(first, 257..263), "ustar");
+        assert_eq!(&archive[BLOCK..BLOCK + 6], b"zone:\n");
+
+        let checksum = u32::from_str_radix(field(first, 148..155), 8).unwrap();
+        let mut blank = first.to_vec();
+        blank[148..156].fill(b' ');
+        assert_eq!(checksum, blank.iter().map(|&b| u32::from(b)).sum::<u32>());
+
+        let second = &archive[2 * BLOCK..3 * BLOCK];
+        assert_eq!(field(second, 0..100), "example.com.zone");
+        assert_eq!(field(second, 124..136), "00000001130");
+        assert!(archive[5 * BLOCK..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_long_names() {
+        let long = format!("{}/{}", "d".repeat(120), "f".repeat(90));
+        let archive = render_tar(&[file(&long, "")], Path::new(""), 0).unwrap();
+        assert_eq!(field(&archive, 0..100), "f".repeat(90));
+        assert_eq!(field(&archive, 345..500), "d".repeat(120));
+
+        let err = render_tar(&[file(&"f".repeat(101), "")], Path::new(""), 0).unwrap_err();
+        assert!(err.to_string().contains("too long for a tar archive"));
+    }
+
+    #[test]
+    fn test_gzip() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let data = "zone:\n\tname: example.com.\n".repeat(100);
+        let compressed = gzip(data.as_bytes());
+        assert_eq!(&compressed[..3], &[0x1f, 0x8b, 8]);
+        assert!(compressed.len() < data.len());
+
+        let body = &compressed[10..compressed.len() - 8];
+        let inflated = miniz_oxide::inflate::decompress_to_vec(body).unwrap();
+        assert_eq!(inflated, data.as_bytes());
+        let trailer = &compressed[compressed.len() - 8..];
+        assert_eq!(trailer[..4], crc32(data.as_bytes()).to_le_bytes());
+        assert_eq!(trailer[4..], (data.len() as u32).to_le_bytes());
+    }
+}
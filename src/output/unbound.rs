@@ -1,6 +1,20 @@
-use std::fmt::Write;
+use std::io::Write;
 
-use crate::output::UNBOUND_COLUMN_WIDTH;
+use anyhow::Result;
+
+use crate::check::special_use_domain;
+use crate::origin::note;
+use crate::output::{render, sorted};
+use crate::parser::{ForwardZone, ReverseZone, UnboundOption};
+use crate::record::DelegationRecord;
+
+/// Comment of a record trailing its `local-data` line
+fn format_comment(comment: &Option<String>) -> String {
+    match comment {
+        Some(comment) => format!(" # {comment}"),
+        None => String::new(),
+    }
+}
 
 fn format_ttl(record_ttl: u32, zone_ttl: u32) -> String {
     if record_ttl == zone_ttl {
@@ -10,158 +24,292 @@ fn format_ttl(record_ttl: u32, zone_ttl: u32) -> String {
     }
 }
 
+/// Renders the Unbound config into a string
 pub fn generate_unbound(
-    forward: &[crate::parser::ForwardZone],
-    reverse: &[crate::parser::ReverseZone],
-) -> String {
-    let mut output = String::new();
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+    options: &[UnboundOption],
+    column_width: usize,
+) -> Result<String> {
+    render(|out| write_unbound(out, forward, reverse, options, column_width))
+}
 
-    writeln!(output, "server:").unwrap();
+/// Writes the Unbound config record by record, so large zones are never held
+/// in memory as a whole. The options of the config come first in the
+/// `server:` clause.
+pub fn write_unbound(
+    output: &mut impl Write,
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+    options: &[UnboundOption],
+    column_width: usize,
+) -> Result<()> {
+    writeln!(output, "server:")?;
+    for option in options {
+        writeln!(output, "{}: {}", option.name, option.value)?;
+    }
+    if !options.is_empty() {
+        writeln!(output)?;
+    }
 
     for zone in forward {
-        let zone_name = zone.base.name.as_str();
-        let zone_ttl = zone.base.ttl;
-        writeln!(output, "local-zone:  {} static", zone_name).unwrap();
-        let ttl = zone.base.ttl.to_string();
-        let nameserver = &zone
-            .base
-            .nameserver
-            .first()
-            .expect("Zone needs one nameserver")
-            .name;
-        let email = &zone.base.email;
-        let retry = zone.base.retry;
-        let refresh = zone.base.refresh;
-        let serial = zone.base.serial;
-        let expire = zone.base.expire;
-        let nrc_ttl = zone.base.nrc_ttl;
-        writeln!(output, r#"local-data: "{zone_name:width$} {ttl} IN SOA  {nameserver} {email} {serial} {refresh} {retry} {expire} {nrc_ttl}""#, width=UNBOUND_COLUMN_WIDTH-ttl.len()).unwrap();
-
-        for ns in &zone.base.nameserver {
-            let ttl = format_ttl(ns.ttl, zone_ttl);
-            let name = &ns.name;
-            writeln!(
-                output,
-                r#"local-data: "{zone_name:width$} {ttl} IN NS   {name}""#,
-                width = UNBOUND_COLUMN_WIDTH - ttl.len()
-            )
-            .unwrap();
-        }
+        write_forward_zone(output, zone, column_width)?;
+    }
+    for zone in reverse {
+        write_reverse_zone(output, zone, column_width)?;
+    }
+    write_stub_zones(output, forward, reverse)?;
+    Ok(())
+}
 
-        for mx in &zone.mx {
-            let ttl = format_ttl(mx.ttl, zone_ttl);
-            let name = &mx.name;
-            let prio = &mx.prio;
-            writeln!(
-                output,
-                r#"local-data: "{zone_name:width$} {ttl} IN MX   {prio} {name}""#,
-                width = UNBOUND_COLUMN_WIDTH - ttl.len()
-            )
-            .unwrap();
+/// Delegations of all zones
+fn delegations<'a>(
+    forward: &'a [ForwardZone],
+    reverse: &'a [ReverseZone],
+) -> impl Iterator<Item = &'a DelegationRecord> {
+    let forward = forward.iter().flat_map(|zone| &zone.delegation);
+    forward.chain(reverse.iter().flat_map(|zone| &zone.delegation))
+}
+
+/// Writes a `stub-zone:` clause for every name delegated from a zone, which
+/// ends the `server:` clause
+pub fn write_stub_zones(
+    output: &mut impl Write,
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+) -> Result<()> {
+    let mut children: Vec<&str> = Vec::new();
+    for ns in delegations(forward, reverse) {
+        if !children.contains(&ns.name.as_str()) {
+            children.push(&ns.name);
+        }
+    }
+    children.sort();
+    for child in children {
+        writeln!(output, "stub-zone:")?;
+        writeln!(output, r#"    name: "{child}""#)?;
+        let mut hosts: Vec<&str> = delegations(forward, reverse)
+            .filter(|ns| ns.name == child)
+            .map(|ns| ns.target.as_str())
+            .collect();
+        hosts.sort();
+        for host in hosts {
+            writeln!(output, r#"    stub-host: "{host}""#)?;
         }
+        writeln!(output)?;
+    }
+    Ok(())
+}
 
-        let mut hosts: Vec<_> = zone.hosts.iter().collect();
+/// Writes the `local-zone` and `local-data` lines of one forward zone, without
+/// the `server:` header
+pub fn write_forward_zone(
+    output: &mut impl Write,
+    zone: &ForwardZone,
+    column_width: usize,
+) -> Result<()> {
+    let zone_name = zone.base.name.as_str();
+    let zone_ttl = zone.base.ttl;
+    let apex = zone.origins.get(zone_name);
+    writeln!(output, "local-zone:  {} static", zone_name)?;
+    let ttl = zone.base.ttl.to_string();
+    let nameserver = zone.base.primary_nameserver()?;
+    let email = &zone.base.email;
+    let retry = zone.base.retry;
+    let refresh = zone.base.refresh;
+    let serial = zone.base.serial;
+    let expire = zone.base.expire;
+    let nrc_ttl = zone.base.nrc_ttl;
+    writeln!(
+        output,
+        r#"local-data: "{zone_name:width$} {ttl} IN SOA  {nameserver} {email} {serial} {refresh} {retry} {expire} {nrc_ttl}""#,
+        width = column_width.saturating_sub(ttl.len())
+    )?;
 
-        hosts.sort_by(|a, b| a.name.cmp(&b.name));
-        for host in hosts {
-            let ttl = format_ttl(host.ttl, zone_ttl);
-            let name = &host.name;
-            let ip = &host.ip;
-            match ip {
-                std::net::IpAddr::V4(ipv4) => {
-                    writeln!(
-                        output,
-                        r#"local-data: "{name:width$} {ttl} IN A    {ipv4}""#,
-                        width = UNBOUND_COLUMN_WIDTH - ttl.len()
-                    )
-                    .unwrap();
-                }
-                std::net::IpAddr::V6(ipv6) => {
-                    writeln!(
-                        output,
-                        r#"local-data: "{name:width$} {ttl} IN AAAA {ipv6}""#,
-                        width = UNBOUND_COLUMN_WIDTH - ttl.len()
-                    )
-                    .unwrap();
-                }
+    for ns in &zone.base.nameserver {
+        let ttl = format_ttl(ns.ttl, zone_ttl);
+        let name = &ns.name;
+        let comment = format_comment(&note(&None, apex));
+        writeln!(
+            output,
+            r#"local-data: "{zone_name:width$} {ttl} IN NS   {name}"{comment}"#,
+            width = column_width.saturating_sub(ttl.len())
+        )?;
+    }
+
+    for mx in sorted(&zone.mx, |mx| (mx.prio, &mx.name)) {
+        let ttl = format_ttl(mx.ttl, zone_ttl);
+        let name = &mx.name;
+        let prio = &mx.prio;
+        let comment = format_comment(&note(&mx.comment, apex));
+        writeln!(
+            output,
+            r#"local-data: "{zone_name:width$} {ttl} IN MX   {prio} {name}"{comment}"#,
+            width = column_width.saturating_sub(ttl.len())
+        )?;
+    }
+
+    for host in sorted(&zone.hosts, |host| (&host.name, host.ip)) {
+        let ttl = format_ttl(host.ttl, zone_ttl);
+        let name = &host.name;
+        let ip = &host.ip;
+        let comment = format_comment(&note(&host.comment, zone.origins.get(name)));
+        match ip {
+            std::net::IpAddr::V4(ipv4) => {
+                writeln!(
+                    output,
+                    r#"local-data: "{name:width$} {ttl} IN A    {ipv4}"{comment}"#,
+                    width = column_width.saturating_sub(ttl.len())
+                )?;
+            }
+            std::net::IpAddr::V6(ipv6) => {
+                writeln!(
+                    output,
+                    r#"local-data: "{name:width$} {ttl} IN AAAA {ipv6}"{comment}"#,
+                    width = column_width.saturating_sub(ttl.len())
+                )?;
             }
         }
+    }
 
-        for srv in &zone.srv {
-            let ttl = format_ttl(srv.ttl, zone_ttl);
-            let name = &srv.name;
-            let prio = &srv.prio;
-            let weight = &srv.weight;
-            let port = &srv.port;
-            let target = &srv.target;
-            writeln!(
-                output,
-                r#"local-data: "{name:width$} {ttl} IN SRV  {prio} {weight} {port} {target}""#,
-                width = UNBOUND_COLUMN_WIDTH - ttl.len()
-            )
-            .unwrap();
-        }
+    for srv in sorted(&zone.srv, |srv| {
+        (&srv.name, srv.prio, srv.weight, srv.port, &srv.target)
+    }) {
+        let ttl = format_ttl(srv.ttl, zone_ttl);
+        let name = &srv.name;
+        let prio = &srv.prio;
+        let weight = &srv.weight;
+        let port = &srv.port;
+        let target = &srv.target;
+        let comment = format_comment(&note(&srv.comment, zone.origins.get(name)));
+        writeln!(
+            output,
+            r#"local-data: "{name:width$} {ttl} IN SRV  {prio} {weight} {port} {target}"{comment}"#,
+            width = column_width.saturating_sub(ttl.len())
+        )?;
+    }
 
-        for cname in &zone.cname {
-            let ttl = format_ttl(cname.ttl, zone_ttl);
-            let name = &cname.name;
-            let target = &cname.target;
-            writeln!(
-                output,
-                r#"local-data: "{name:width$} {ttl} CNAME   {target}""#,
-                width = UNBOUND_COLUMN_WIDTH - ttl.len()
-            )
-            .unwrap();
-        }
+    // Single quotes, as the text is quoted itself, and none within it
+    for txt in sorted(&zone.txt, |txt| (&txt.name, &txt.text)) {
+        let ttl = format_ttl(txt.ttl, zone_ttl);
+        let name = &txt.name;
+        let data = txt.data().replace('\'', "\\039");
+        let comment = format_comment(&note(&None, zone.origins.get(name)));
+        writeln!(
+            output,
+            r#"local-data: '{name:width$} {ttl} IN TXT  {data}'{comment}"#,
+            width = column_width.saturating_sub(ttl.len())
+        )?;
+    }
 
-        output.push('\n');
+    for cname in sorted(&zone.cname, |cname| &cname.name) {
+        let ttl = format_ttl(cname.ttl, zone_ttl);
+        let name = &cname.name;
+        let target = &cname.target;
+        let comment = format_comment(&note(&cname.comment, zone.origins.get(name)));
+        writeln!(
+            output,
+            r#"local-data: "{name:width$} {ttl} CNAME   {target}"{comment}"#,
+            width = column_width.saturating_sub(ttl.len())
+        )?;
+    }
+    // Answered by the stub zones instead of this static zone
+    let mut children: Vec<&str> = zone.delegation.iter().map(|ns| ns.name.as_str()).collect();
+    children.sort();
+    children.dedup();
+    for child in children {
+        writeln!(output, "local-zone:      {child} transparent")?;
     }
 
-    for zone in reverse {
-        let zone_name = zone.base.name.as_str();
-        writeln!(output, "local-zone:      {} static", zone_name).unwrap();
-        let zone_ttl = zone.base.ttl;
-        let ttl = zone_ttl.to_string();
-        let nameserver = &zone
-            .base
-            .nameserver
-            .first()
-            .expect("Zone needs one nameserver")
-            .name;
-        let email = &zone.base.email;
-        let retry = zone.base.retry;
-        let refresh = zone.base.refresh;
-        let serial = zone.base.serial;
-        let expire = zone.base.expire;
-        let nrc_ttl = zone.base.nrc_ttl;
-        writeln!(output, r#"local-data:     "{zone_name:width$} {ttl} IN SOA  {nameserver} {email} {serial} {refresh} {retry} {expire} {nrc_ttl}""#, width=UNBOUND_COLUMN_WIDTH-ttl.len()).unwrap();
-
-        for ns in &zone.base.nameserver {
-            let ttl = format_ttl(ns.ttl, zone_ttl);
-            let name = &ns.name;
-            writeln!(
-                output,
-                r#"local-data:     "{zone_name:width$} {ttl} IN NS   {name}""#,
-                width = UNBOUND_COLUMN_WIDTH - ttl.len()
-            )
-            .unwrap();
-        }
+    writeln!(output)?;
+    Ok(())
+}
 
-        let mut ptrs: Vec<_> = zone.ptr.iter().collect();
-        ptrs.sort_by(|a, b| a.ip.cmp(&b.ip));
-        for ptr in ptrs {
-            let ttl = format_ttl(ptr.ttl, zone_ttl);
-            let name = &ptr.name;
-            let ip = ptr.ip;
-            writeln!(
-                output,
-                r#"local-data-ptr: "{ip:width$} {ttl} {name}""#,
-                width = UNBOUND_COLUMN_WIDTH - ttl.len()
-            )
-            .unwrap();
-        }
+/// Writes the `local-zone` and `local-data` lines of one reverse zone, without
+/// the `server:` header
+pub fn write_reverse_zone(
+    output: &mut impl Write,
+    zone: &ReverseZone,
+    column_width: usize,
+) -> Result<()> {
+    let zone_name = zone.base.name.as_str();
+    writeln!(output, "local-zone:      {} static", zone_name)?;
+    let zone_ttl = zone.base.ttl;
+    let ttl = zone_ttl.to_string();
+    let nameserver = zone.base.primary_nameserver()?;
+    let email = &zone.base.email;
+    let retry = zone.base.retry;
+    let refresh = zone.base.refresh;
+    let serial = zone.base.serial;
+    let expire = zone.base.expire;
+    let nrc_ttl = zone.base.nrc_ttl;
+    writeln!(
+        output,
+        r#"local-data:     "{zone_name:width$} {ttl} IN SOA  {nameserver} {email} {serial} {refresh} {retry} {expire} {nrc_ttl}""#,
+        width = column_width.saturating_sub(ttl.len())
+    )?;
 
-        output.push('\n');
+    for ns in &zone.base.nameserver {
+        let ttl = format_ttl(ns.ttl, zone_ttl);
+        let name = &ns.name;
+        writeln!(
+            output,
+            r#"local-data:     "{zone_name:width$} {ttl} IN NS   {name}""#,
+            width = column_width.saturating_sub(ttl.len())
+        )?;
+    }
+    // Answered by the stub zones instead of this static zone
+    let mut children: Vec<&str> = zone.delegation.iter().map(|ns| ns.name.as_str()).collect();
+    children.sort();
+    children.dedup();
+    for child in children {
+        writeln!(output, "local-zone:      {child} transparent")?;
     }
-    output
+
+    let mut ptrs: Vec<_> = zone.ptr.iter().collect();
+    ptrs.sort_by(|a, b| (a.ip, &a.name).cmp(&(b.ip, &b.name)));
+    for ptr in ptrs {
+        let ttl = format_ttl(ptr.ttl, zone_ttl);
+        let name = &ptr.name;
+        let ip = ptr.ip;
+        let comment = format_comment(&note(&None, zone.origins.get(name)));
+        writeln!(
+            output,
+            r#"local-data-ptr: "{ip:width$} {ttl} {name}"{comment}"#,
+            width = column_width.saturating_sub(ttl.len())
+        )?;
+    }
+
+    writeln!(output)?;
+    Ok(())
+}
+
+/// Writes `private-domain` and `domain-insecure` lines for the forward zones
+/// below special-use domains like `home.arpa.`, so Unbound neither removes
+/// their private addresses from answers nor expects them to be DNSSEC signed
+pub fn write_private_domains(output: &mut impl Write, forward: &[ForwardZone]) -> Result<()> {
+    let zones: Vec<&str> = forward
+        .iter()
+        .map(|zone| zone.base.name.as_str())
+        .filter(|name| special_use_domain(name).is_some())
+        .collect();
+    if zones.is_empty() {
+        return Ok(());
+    }
+    for name in zones {
+        writeln!(output, r#"private-domain:  "{name}""#)?;
+        writeln!(output, r#"domain-insecure: "{name}""#)?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
+/// Renders one forward zone, e.g. to preview it
+pub fn render_forward_zone(zone: &ForwardZone, column_width: usize) -> Result<String> {
+    render(|out| write_forward_zone(out, zone, column_width))
+}
+
+/// Renders one reverse zone, e.g. to preview it
+pub fn render_reverse_zone(zone: &ReverseZone, column_width: usize) -> Result<String> {
+    render(|out| write_reverse_zone(out, zone, column_width))
 }
@@ -1,23 +1,59 @@
-use anyhow::anyhow;
-use ipnetwork::IpNetwork;
+use indexmap::IndexMap;
+use ipnetwork::{IpNetwork, Ipv6Network};
 use serde_path_to_error;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "fs")]
+use std::fs;
 use std::net::IpAddr;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::path::PathBuf;
 
-use anyhow::bail;
 use anyhow::Result;
-use serde::Deserialize;
-
-use crate::args::InputFormat;
+use anyhow::{anyhow, bail};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::args::{InputFormat, OutputFormat};
+use crate::check::{Finding, Severity};
+#[cfg(feature = "toml")]
+use crate::error::line_col;
+#[cfg(feature = "yaml")]
+use crate::error::offset_of;
+#[cfg(feature = "fs")]
+use crate::error::reword;
+use crate::error::{token_span, Errors, ParseError};
+use crate::lint::LintConfig;
+use crate::origin::{Origin, Origins};
 use crate::record::CnameRecord;
+use crate::record::DelegationRecord;
 use crate::record::MxRecord;
 use crate::record::NsRecord;
 use crate::record::PtrRecord;
+use crate::record::Record;
 use crate::record::SrvRecord;
+use crate::record::TxtRecord;
+use crate::report::name_matches;
+use crate::transform::apply_groups;
+use crate::transform::check_soa_timers;
+use crate::transform::clamp_ttls;
+use crate::transform::inherit_zones;
+use crate::transform::ip_name;
+use crate::transform::mixed_case_defaults;
+use crate::transform::mixed_case_names;
 use crate::transform::parse_email;
 use crate::transform::parse_forward;
+use crate::transform::parse_nsd;
 use crate::transform::parse_reverse;
+use crate::transform::parse_secondaries;
+use crate::transform::parse_unbound_options;
+use crate::transform::zone_fqdn;
+use crate::transform::{resolve_ptr_claims, PtrClaim};
+#[cfg(feature = "nsd")]
+use crate::tsig::TsigConfig;
 use crate::validation::{validate_dns_name, validate_email};
+use crate::vars::{expand_vars, has_vars};
+use crate::window::Date;
 use crate::{
     constants::{
         DEFAULT_EXPIRE, DEFAULT_MX_PRIO, DEFAULT_NRC_TTL, DEFAULT_REFRESH, DEFAULT_RETRY,
@@ -26,8 +62,38 @@ use crate::{
     record::ARecord,
 };
 
-#[derive(Debug, Default)]
-pub struct TTL(pub u32);
+/// TTL in seconds, and the duration it was written as (e.g. `1h`), so
+/// `fmt` and `convert` write it back unchanged
+#[derive(Debug, Clone, Default)]
+pub struct TTL(pub u32, pub Option<String>);
+
+/// Seconds of a duration like `30m`, `2d` or `1h30m`: numbers followed by
+/// `s`, `m`, `h`, `d` or `w`. A plain number is seconds.
+pub fn parse_duration(text: &str) -> Option<u64> {
+    if let Ok(seconds) = text.parse() {
+        return Some(seconds);
+    }
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        let value: u64 = number.parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+    (!text.is_empty() && number.is_empty()).then_some(total)
+}
 
 impl<'de> Deserialize<'de> for TTL {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -42,7 +108,7 @@ impl<'de> Deserialize<'de> for TTL {
             type Value = TTL;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("a positive TTL value (1-2147483647)")
+                formatter.write_str("a positive TTL value (1-2147483647) or a duration like 1h")
             }
 
             fn visit_u32<E>(self, value: u32) -> Result<TTL, E>
@@ -55,7 +121,7 @@ impl<'de> Deserialize<'de> for TTL {
                 if value > 2147483647 {
                     return Err(E::custom("TTL too large (max 2147483647)"));
                 }
-                Ok(TTL(value))
+                Ok(TTL(value, None))
             }
 
             fn visit_u64<E>(self, value: u64) -> Result<TTL, E>
@@ -77,40 +143,413 @@ impl<'de> Deserialize<'de> for TTL {
                 }
                 self.visit_u32(value as u32)
             }
+
+            fn visit_str<E>(self, value: &str) -> Result<TTL, E>
+            where
+                E: de::Error,
+            {
+                let Some(seconds) = parse_duration(value) else {
+                    return Err(E::custom(format!(
+                        "invalid TTL '{value}', expected seconds or a duration like 1h, 30m or 2d"
+                    )));
+                };
+                let ttl = self.visit_u64(seconds)?;
+                Ok(TTL(ttl.0, Some(value.to_string())))
+            }
         }
 
-        deserializer.deserialize_u32(TTLVisitor)
+        deserializer.deserialize_any(TTLVisitor)
+    }
+}
+
+impl Serialize for TTL {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.1 {
+            Some(duration) => serializer.serialize_str(duration),
+            None => serializer.serialize_u32(self.0),
+        }
     }
 }
-#[derive(Debug, Deserialize)]
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct NameserverEntry {
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<TTL>,
+}
+
+/// Nameserver of a child zone delegated from a forward zone, with the glue
+/// addresses it needs when its name lies within the child
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DelegateEntry {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<SingleOrVecValue<IpAddr>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<TTL>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Zone this server only transfers from its primaries, without a zone file of
+/// its own
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecondaryEntry {
+    pub name: String,
+    /// Primaries the zone is transferred from and notifications come from
+    pub masters: SingleOrVecValue<IpAddr>,
+    /// Key of the `tsig` section signing the transfers
+    #[serde(rename = "tsig-key", skip_serializing_if = "Option::is_none")]
+    pub tsig_key: Option<String>,
+}
+
+/// Value of an option passed through to a server config
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum OptionValue {
+    Flag(bool),
+    Number(i64),
+    Text(String),
+}
+
+/// Options of a zone in the zones.conf of NSD, passed through as written
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NsdEntry {
+    /// Pattern of `nsd.conf` the zone includes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Secondaries told about changes, like `192.0.2.2 NOKEY`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify: Option<SingleOrVecValue<String>>,
+    /// Secondaries allowed to transfer the zone, `allow-transfer` of BIND
+    #[serde(
+        rename = "provide-xfr",
+        alias = "allow-transfer",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub provide_xfr: Option<SingleOrVecValue<String>>,
+    #[serde(rename = "outgoing-interface", skip_serializing_if = "Option::is_none")]
+    pub outgoing_interface: Option<SingleOrVecValue<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct MxEntry {
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub prio: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<TTL>,
+    /// Written next to the records in the output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// First day the records are generated on
+    #[serde(rename = "not-before", skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<Date>,
+    /// Last day the records are generated on
+    #[serde(rename = "not-after", skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<Date>,
+}
+
+/// Most numbers a range of hosts may have
+const MAX_RANGE: u32 = 65536;
+
+/// A range of numbers like `[01-24]` in a host name or address, standing for
+/// one host per number as with `$GENERATE`. A leading zero pads all numbers
+/// to the width of the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberRange {
+    prefix: String,
+    start: u32,
+    end: u32,
+    width: usize,
+    suffix: String,
+}
+
+impl NumberRange {
+    /// The range in `text`, `None` if it has none
+    pub fn find(text: &str) -> Result<Option<NumberRange>, String> {
+        let Some(open) = text.find('[') else {
+            if text.contains(']') {
+                return Err(format!("']' without '[' in '{text}'"));
+            }
+            return Ok(None);
+        };
+        let invalid = || format!("Invalid range in '{text}', expected e.g. [1-24] or [01-24]");
+        let close = text[open..]
+            .find(']')
+            .map(|i| open + i)
+            .ok_or_else(invalid)?;
+        let suffix = &text[close + 1..];
+        if suffix.contains(['[', ']']) {
+            return Err(format!("'{text}' has more than one range"));
+        }
+        let (first, last) = text[open + 1..close].split_once('-').ok_or_else(invalid)?;
+        let number = |s: &str| {
+            if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            s.parse::<u32>().ok()
+        };
+        let (Some(start), Some(end)) = (number(first), number(last)) else {
+            return Err(invalid());
+        };
+        if start > end {
+            return Err(format!("Range [{first}-{last}] in '{text}' runs backwards"));
+        }
+        if end - start >= MAX_RANGE {
+            return Err(format!(
+                "Range [{first}-{last}] in '{text}' has more than {MAX_RANGE} numbers"
+            ));
+        }
+        let width = if first.len() > 1 && first.starts_with('0') {
+            first.len()
+        } else {
+            1
+        };
+        Ok(Some(NumberRange {
+            prefix: text[..open].to_string(),
+            start,
+            end,
+            width,
+            suffix: suffix.to_string(),
+        }))
+    }
+
+    /// Number of hosts the range stands for
+    pub fn count(&self) -> usize {
+        (self.end - self.start) as usize + 1
+    }
+
+    /// Text of the host at `index` of the range
+    pub fn nth(&self, index: usize) -> String {
+        let number = self.start as usize + index;
+        format!(
+            "{}{:0width$}{}",
+            self.prefix,
+            number,
+            self.suffix,
+            width = self.width
+        )
+    }
+}
+
+impl std::fmt::Display for NumberRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}[{:0width$}-{:0width$}]{}",
+            self.prefix,
+            self.start,
+            self.end,
+            self.suffix,
+            width = self.width
+        )
+    }
+}
+
+/// Address of a host, or a range of addresses like `10.0.2.[10-33]` for a
+/// range of hosts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostAddress {
+    Ip(IpAddr),
+    Range(NumberRange),
+    /// Refers to variables, checked once `resolve` has expanded them
+    Vars(String),
+}
+
+impl std::str::FromStr for HostAddress {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        if let Ok(ip) = text.parse() {
+            return Ok(HostAddress::Ip(ip));
+        }
+        if has_vars(text) {
+            return Ok(HostAddress::Vars(text.to_string()));
+        }
+        let Some(range) = NumberRange::find(text)? else {
+            return Err(format!("'{}' is not a valid IP address", text));
+        };
+        if let Some(bad) = (0..range.count())
+            .map(|index| range.nth(index))
+            .find(|ip| ip.parse::<IpAddr>().is_err())
+        {
+            return Err(format!(
+                "'{bad}' of the range '{text}' is not a valid IP address"
+            ));
+        }
+        Ok(HostAddress::Range(range))
+    }
 }
 
-#[derive(Debug, Deserialize)]
+impl std::fmt::Display for HostAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HostAddress::Ip(ip) => ip.fmt(f),
+            HostAddress::Range(range) => range.fmt(f),
+            HostAddress::Vars(text) => text.fmt(f),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HostAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for HostAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct HostEntry {
-    pub ip: SingleOrVecValue<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<SingleOrVecValue<HostAddress>>,
+    /// Interface identifier of the addresses in the zone's `ip6-prefix`
+    /// networks, like `::1:5`
+    #[serde(rename = "ip6-suffix", skip_serializing_if = "Option::is_none")]
+    pub ip6_suffix: Option<HostAddress>,
+    /// MAC address the interface identifier is derived from, as by SLAAC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eui64: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub alias: Option<SingleOrVecValue<String>>,
+    /// Host of the zone whose addresses this host has too, instead of its own
+    #[serde(rename = "same-as", skip_serializing_if = "Option::is_none")]
+    pub same_as: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<TTL>,
-    #[serde(rename = "with-ptr")]
+    #[serde(rename = "with-ptr", skip_serializing_if = "Option::is_none")]
     pub with_ptr: Option<bool>,
+    #[serde(rename = "ptr-policy", skip_serializing_if = "Option::is_none")]
+    pub ptr_policy: Option<PtrPolicy>,
+    #[serde(rename = "alias-mode", skip_serializing_if = "Option::is_none")]
+    pub alias_mode: Option<AliasMode>,
+    /// This host owns the PTR record of its addresses, whatever other hosts
+    /// share them
+    #[serde(rename = "ptr-primary", skip_serializing_if = "Option::is_none")]
+    pub ptr_primary: Option<bool>,
+    /// Challenge zone or nameserver the DNS-01 validation is handed to
+    #[serde(rename = "acme-delegate", skip_serializing_if = "Option::is_none")]
+    pub acme_delegate: Option<String>,
+    /// Written next to the records in the output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// First day the records are generated on
+    #[serde(rename = "not-before", skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<Date>,
+    /// Last day the records are generated on
+    #[serde(rename = "not-after", skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<Date>,
+    /// Parked in the config without generating any records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    /// Labels for `--include-tag` and `--exclude-tag`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<SingleOrVecValue<String>>,
+    /// Outputs generated from this, all of them without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<SingleOrVecValue<Backend>>,
 }
 
-#[derive(Debug)]
+/// Output a zone or entry is limited to with `outputs`, named like the
+/// output formats whether they are compiled in or not
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Unbound,
+    Nsd,
+}
+
+impl From<OutputFormat> for Backend {
+    fn from(format: OutputFormat) -> Backend {
+        match format {
+            #[cfg(feature = "unbound")]
+            OutputFormat::Unbound => Backend::Unbound,
+            #[cfg(feature = "nsd")]
+            OutputFormat::Nsd => Backend::Nsd,
+        }
+    }
+}
+
+/// What happens when several hosts ask for the PTR record of one address
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PtrPolicy {
+    /// Fail the run
+    #[default]
+    Error,
+    /// Keep the PTR of the host defined first
+    FirstWins,
+    /// Emit a PTR for every host
+    All,
+}
+
+impl std::fmt::Display for PtrPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            PtrPolicy::Error => "error",
+            PtrPolicy::FirstWins => "first-wins",
+            PtrPolicy::All => "all",
+        })
+    }
+}
+
+fn is_default_ptr_policy(policy: &PtrPolicy) -> bool {
+    *policy == PtrPolicy::default()
+}
+
+/// Records the aliases of a host get
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AliasMode {
+    /// The A/AAAA records of the host
+    #[default]
+    Address,
+    /// A CNAME pointing at the host
+    Cname,
+}
+
+impl std::fmt::Display for AliasMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            AliasMode::Address => "address",
+            AliasMode::Cname => "cname",
+        })
+    }
+}
+
+fn is_default_alias_mode(mode: &AliasMode) -> bool {
+    *mode == AliasMode::default()
+}
+
+/// Records handing the DNS-01 validation of a name to a challenge zone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AcmeMode {
+    /// A CNAME from `_acme-challenge` to the name below the challenge zone
+    #[default]
+    Cname,
+    /// `_acme-challenge` delegated to the challenge nameserver
+    Ns,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum HostValue {
-    Ip(SingleOrVecValue<IpAddr>),
-    Entry(HostEntry),
+    Ip(SingleOrVecValue<HostAddress>),
+    Entry(Box<HostEntry>),
 }
 
 impl<'de> Deserialize<'de> for HostValue {
@@ -134,22 +573,23 @@ impl<'de> Deserialize<'de> for HostValue {
             where
                 E: de::Error,
             {
-                v.parse::<IpAddr>()
+                v.parse::<HostAddress>()
                     .map(|ip| HostValue::Ip(SingleOrVecValue::Single(ip)))
-                    .map_err(|_| E::custom(format!("'{}' is not a valid IP address", v)))
+                    .map_err(E::custom)
             }
 
             fn visit_seq<V>(self, seq: V) -> Result<HostValue, V::Error>
             where
                 V: SeqAccess<'de>,
             {
-                let ips = Vec::<IpAddr>::deserialize(de::value::SeqAccessDeserializer::new(seq))
-                    .map_err(|e| {
-                        de::Error::custom(format!(
-                            "Expected array of IP addresses, but got invalid values: {}",
-                            e
-                        ))
-                    })?;
+                let ips =
+                    Vec::<HostAddress>::deserialize(de::value::SeqAccessDeserializer::new(seq))
+                        .map_err(|e| {
+                            de::Error::custom(format!(
+                                "Expected array of IP addresses, but got invalid values: {}",
+                                e
+                            ))
+                        })?;
                 Ok(HostValue::Ip(SingleOrVecValue::Multiple(ips)))
             }
 
@@ -158,7 +598,7 @@ impl<'de> Deserialize<'de> for HostValue {
                 M: MapAccess<'de>,
             {
                 HostEntry::deserialize(de::value::MapAccessDeserializer::new(map))
-                    .map(HostValue::Entry)
+                    .map(|entry| HostValue::Entry(Box::new(entry)))
                     .map_err(|e| de::Error::custom(format!("Invalid host entry object: {}", e)))
             }
         }
@@ -167,21 +607,77 @@ impl<'de> Deserialize<'de> for HostValue {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct CnameEntry {
     pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<TTL>,
+    /// Written next to the records in the output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// First day the records are generated on
+    #[serde(rename = "not-before", skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<Date>,
+    /// Last day the records are generated on
+    #[serde(rename = "not-after", skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<Date>,
+    /// Parked in the config without generating any records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    /// Outputs generated from this, all of them without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<SingleOrVecValue<Backend>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct SrvEntry {
     pub target: String,
     pub port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<TTL>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub prio: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub weight: Option<u16>,
+    /// Written next to the records in the output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// First day the records are generated on
+    #[serde(rename = "not-before", skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<Date>,
+    /// Last day the records are generated on
+    #[serde(rename = "not-after", skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<Date>,
+    /// Parked in the config without generating any records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    /// Outputs generated from this, all of them without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<SingleOrVecValue<Backend>>,
+}
+
+/// Mail setup of a zone around one mail host: MX and SPF at the apex, the
+/// `autoconfig` and `autodiscover` CNAMEs of mail clients and optionally the
+/// SRV records of submission and IMAP
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MailEntry {
+    pub host: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prio: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<TTL>,
+    /// SPF policy, `v=spf1 mx -all` if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spf: Option<String>,
+    /// `autoconfig` and `autodiscover` CNAMEs, on by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoconfig: Option<bool>,
+    /// SRV records of submission and IMAP, off by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srv: Option<bool>,
 }
 
 // Wrapper für Email-Validierung mit besseren Fehlermeldungen
@@ -208,6 +704,9 @@ impl<'de> Deserialize<'de> for Email {
             where
                 E: de::Error,
             {
+                if has_vars(value) {
+                    return Ok(Email(value.to_string()));
+                }
                 validate_email(value)
                     .map(|_| Email(value.to_string()))
                     .map_err(|e| E::custom(format!("Invalid email: {}", e)))
@@ -218,9 +717,16 @@ impl<'de> Deserialize<'de> for Email {
     }
 }
 
+impl Serialize for Email {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
 // Wrapper für bessere Fehlermeldungen bei SRV-Einträgen
-#[derive(Debug)]
-pub struct SrvMap(pub HashMap<String, SrvEntry>);
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct SrvMap(pub IndexMap<String, SrvEntry>);
 
 impl<'de> Deserialize<'de> for SrvMap {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -242,7 +748,7 @@ impl<'de> Deserialize<'de> for SrvMap {
             where
                 M: MapAccess<'de>,
             {
-                let mut entries = HashMap::new();
+                let mut entries = IndexMap::new();
                 let mut index = 0;
                 while let Some(key) = map.next_key::<String>()? {
                     index += 1;
@@ -290,7 +796,8 @@ impl<'de> Deserialize<'de> for SrvMap {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum StringOrTableValue<T> {
     Entry(String),
     Table(T),
@@ -303,12 +810,38 @@ impl StringOrTableValue<MxEntry> {
                 name: val,
                 prio: None,
                 ttl: None,
+                comment: None,
+                not_before: None,
+                not_after: None,
             },
             Self::Table(val) => val,
         }
     }
 }
 
+impl StringOrTableValue<NameserverEntry> {
+    /// A nameserver written as a string may follow its name by a TTL, like
+    /// `ns1.example.net. 1d`
+    pub fn to_entry(self) -> Result<NameserverEntry> {
+        let text = match self {
+            StringOrTableValue::Entry(text) => text,
+            Self::Table(val) => return Ok(val),
+        };
+        let mut parts = text.split_whitespace();
+        let name = parts.next().unwrap_or_default().to_string();
+        let ttl = match (parts.next(), parts.next()) {
+            (None, _) => None,
+            (Some(ttl), None) => {
+                let ttl = TTL::deserialize(ttl.into_deserializer())
+                    .map_err(|e: serde::de::value::Error| anyhow!("Nameserver {name}: {e}"))?;
+                Some(ttl)
+            }
+            _ => bail!("Nameserver '{text}' is more than a name and a TTL"),
+        };
+        Ok(NameserverEntry { name, ttl })
+    }
+}
+
 impl<'de, T> Deserialize<'de> for StringOrTableValue<T>
 where
     T: Deserialize<'de>,
@@ -352,7 +885,8 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum SingleOrVecValue<T> {
     Single(T),
     Multiple(Vec<T>),
@@ -365,6 +899,20 @@ impl<T> SingleOrVecValue<T> {
             SingleOrVecValue::Multiple(vec) => vec,
         }
     }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        match self {
+            SingleOrVecValue::Single(val) => std::slice::from_ref(val).iter(),
+            SingleOrVecValue::Multiple(vec) => vec.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        match self {
+            SingleOrVecValue::Single(val) => std::slice::from_mut(val).iter_mut(),
+            SingleOrVecValue::Multiple(vec) => vec.iter_mut(),
+        }
+    }
 }
 
 impl<'de, T> Deserialize<'de> for SingleOrVecValue<T>
@@ -409,6 +957,16 @@ where
                 }
             }
 
+            fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match T::deserialize(de::value::BoolDeserializer::<E>::new(v)) {
+                    Ok(val) => Ok(SingleOrVecValue::Single(val)),
+                    Err(e) => Err(E::custom(format!("Invalid value {}: {}", v, e))),
+                }
+            }
+
             fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
             where
                 E: de::Error,
@@ -444,31 +1002,52 @@ where
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct ZoneBaseEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub serial: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub expire: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub nameserver: Option<SingleOrVecValue<StringOrTableValue<NameserverEntry>>>,
-    #[serde(rename = "nrc-ttl")]
+    #[serde(rename = "nrc-ttl", skip_serializing_if = "Option::is_none")]
     pub nrc_ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub refresh: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub retry: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<TTL>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nsd: Option<NsdEntry>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct ReverseEntry {
     #[serde(flatten)]
     pub base: ZoneBaseEntry,
+    /// Parts of the network served by other nameservers, with their NS records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegate:
+        Option<IndexMap<IpNetwork, SingleOrVecValue<StringOrTableValue<NameserverEntry>>>>,
+    /// Pattern of the PTR names of addresses no host has, e.g.
+    /// `dhcp-{ip}.dyn.example.com.`
+    #[serde(rename = "fill-ptr", skip_serializing_if = "Option::is_none")]
+    pub fill_ptr: Option<String>,
+    /// Parts of the network served elsewhere, their addresses get no PTR here
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<SingleOrVecValue<IpNetwork>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum ReverseValue {
     Net(SingleOrVecValue<IpNetwork>),
-    Entry(HashMap<IpNetwork, ReverseEntry>),
+    Entry(IndexMap<IpNetwork, ReverseEntry>),
 }
 
 impl<'de> Deserialize<'de> for ReverseValue {
@@ -517,7 +1096,7 @@ impl<'de> Deserialize<'de> for ReverseValue {
             where
                 V: MapAccess<'de>,
             {
-                match HashMap::<IpNetwork, ReverseEntry>::deserialize(
+                match IndexMap::<IpNetwork, ReverseEntry>::deserialize(
                     de::value::MapAccessDeserializer::new(map),
                 ) {
                     Ok(entries) => Ok(ReverseValue::Entry(entries)),
@@ -533,9 +1112,42 @@ impl<'de> Deserialize<'de> for ReverseValue {
     }
 }
 
-#[derive(Debug)]
+impl ReverseValue {
+    /// Converts plain network lists into entries without overrides
+    pub fn into_entries(self) -> IndexMap<IpNetwork, ReverseEntry> {
+        match self {
+            ReverseValue::Entry(entries) => entries,
+            ReverseValue::Net(nets) => nets
+                .to_vec()
+                .into_iter()
+                .map(|net| {
+                    let entry = ReverseEntry {
+                        base: ZoneBaseEntry {
+                            serial: None,
+                            email: None,
+                            expire: None,
+                            nameserver: None,
+                            nrc_ttl: None,
+                            refresh: None,
+                            retry: None,
+                            ttl: None,
+                            nsd: None,
+                        },
+                        exclude: None,
+                        delegate: None,
+                        fill_ptr: None,
+                    };
+                    (net, entry)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum Zones {
-    Map(HashMap<String, ZoneWithoutName>),
+    Map(IndexMap<String, ZoneWithoutName>),
     Array(Vec<Zone>),
 }
 
@@ -573,7 +1185,7 @@ impl<'de> Deserialize<'de> for Zones {
             where
                 V: MapAccess<'de>,
             {
-                match HashMap::<String, ZoneWithoutName>::deserialize(
+                match IndexMap::<String, ZoneWithoutName>::deserialize(
                     de::value::MapAccessDeserializer::new(map),
                 ) {
                     Ok(zones) => Ok(Zones::Map(zones)),
@@ -586,37 +1198,251 @@ impl<'de> Deserialize<'de> for Zones {
     }
 }
 
-#[derive(Debug, Deserialize)]
+impl Zones {
+    pub fn into_vec(self) -> Vec<Zone> {
+        match self {
+            Zones::Array(a) => a,
+            Zones::Map(m) => m
+                .into_iter()
+                .map(|(name, zwn)| zwn.with_name(name))
+                .collect(),
+        }
+    }
+}
+
+/// Settings a group of zones shares, between the `defaults` and the settings
+/// of each zone
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZoneGroup {
+    #[serde(flatten)]
+    pub base: ZoneBaseEntry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mx: Option<SingleOrVecValue<StringOrTableValue<MxEntry>>>,
+    #[serde(rename = "mx-prio", skip_serializing_if = "Option::is_none")]
+    pub mx_prio: Option<u16>,
+    #[serde(rename = "srv-prio", skip_serializing_if = "Option::is_none")]
+    pub srv_prio: Option<u16>,
+    #[serde(rename = "srv-weight", skip_serializing_if = "Option::is_none")]
+    pub srv_weight: Option<u16>,
+    #[serde(rename = "with-ptr", skip_serializing_if = "Option::is_none")]
+    pub with_ptr: Option<bool>,
+    #[serde(rename = "ptr-policy", skip_serializing_if = "Option::is_none")]
+    pub ptr_policy: Option<PtrPolicy>,
+    #[serde(rename = "alias-mode", skip_serializing_if = "Option::is_none")]
+    pub alias_mode: Option<AliasMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public: Option<bool>,
+    #[serde(rename = "ttl-min", skip_serializing_if = "Option::is_none")]
+    pub ttl_min: Option<TTL>,
+    #[serde(rename = "ttl-max", skip_serializing_if = "Option::is_none")]
+    pub ttl_max: Option<TTL>,
+}
+
+/// The raw configuration as written, before defaults are applied.
+/// Obtained from `parse_config` and turned into zones by `resolve`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Content {
-    #[serde(default = "RawDefaults::default")]
-    pub defaults: RawDefaults,
+    /// Values referenced as `{{vars.name}}`, expanded by `resolve`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vars: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<RawDefaults>,
+    /// Settings shared by the zones of a `group`, by group name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<IndexMap<String, ZoneGroup>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reverse: Option<ReverseValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub zone: Option<Zones>,
+    /// Zones transferred from other primaries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secondary: Option<SingleOrVecValue<SecondaryEntry>>,
+    /// Options written under `server:` of the Unbound output, by name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unbound: Option<IndexMap<String, SingleOrVecValue<OptionValue>>>,
+    /// TSIG keys of the secondaries, wired into the NSD zones.conf
+    #[cfg(feature = "nsd")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tsig: Option<TsigConfig>,
+    /// Rule settings of the `lint` subcommand
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lint: Option<LintConfig>,
+    /// Lines of the entries in the config files
+    #[serde(skip)]
+    pub origins: Origins,
+    /// Zones left out by [`crate::tags::filter_tags`] or failed in
+    /// [`crate::vars::expand_vars`], resolved only for the zones inheriting
+    /// from them
+    #[serde(skip)]
+    pub dropped_zones: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Default)]
+impl Content {
+    /// Mutable access to all forward zones, e.g. to add hosts from other sources
+    /// before resolving. Zones given as a map are converted to a list.
+    pub fn zones_mut(&mut self) -> &mut Vec<Zone> {
+        let zones = self.zone.take().map(Zones::into_vec).unwrap_or_default();
+        match self.zone.insert(Zones::Array(zones)) {
+            Zones::Array(zones) => zones,
+            Zones::Map(_) => unreachable!(),
+        }
+    }
+
+    /// Merges the variables, zones, secondary zones, Unbound options and
+    /// reverse networks of another config into this one. Only one of both may
+    /// carry a `defaults`, `tsig` or `lint` section.
+    pub fn merge(&mut self, other: Content) -> Result<()> {
+        match (&self.defaults, other.defaults) {
+            (Some(_), Some(_)) => bail!("defaults section is defined more than once"),
+            (None, Some(defaults)) => self.defaults = Some(defaults),
+            _ => (),
+        }
+        #[cfg(feature = "nsd")]
+        match (&self.tsig, other.tsig) {
+            (Some(_), Some(_)) => bail!("tsig section is defined more than once"),
+            (None, Some(tsig)) => self.tsig = Some(tsig),
+            _ => (),
+        }
+        match (&self.lint, other.lint) {
+            (Some(_), Some(_)) => bail!("lint section is defined more than once"),
+            (None, Some(lint)) => self.lint = Some(lint),
+            _ => (),
+        }
+
+        for (name, group) in other.groups.unwrap_or_default() {
+            let groups = self.groups.get_or_insert_with(IndexMap::new);
+            if groups.contains_key(&name) {
+                bail!("Group {name} is defined more than once")
+            }
+            groups.insert(name, group);
+        }
+
+        for (name, value) in other.vars.unwrap_or_default() {
+            let vars = self.vars.get_or_insert_with(IndexMap::new);
+            if vars.contains_key(&name) {
+                bail!("Variable {name} is defined more than once")
+            }
+            vars.insert(name, value);
+        }
+
+        self.origins.extend(other.origins);
+        self.dropped_zones.extend(other.dropped_zones);
+        let mut zones = self.zone.take().map(Zones::into_vec).unwrap_or_default();
+        for zone in other.zone.map(Zones::into_vec).unwrap_or_default() {
+            let name = zone.name.trim_end_matches('.');
+            if zones.iter().any(|z| z.name.trim_end_matches('.') == name) {
+                bail!("Zone {name} is defined more than once")
+            }
+            zones.push(zone);
+        }
+        self.zone = Some(Zones::Array(zones));
+
+        if let Some(other) = other.secondary {
+            let own = self.secondary.take().map(SingleOrVecValue::to_vec);
+            let mut secondary = own.unwrap_or_default();
+            for entry in other.to_vec() {
+                let name = entry.name.trim_end_matches('.');
+                if secondary
+                    .iter()
+                    .any(|s| s.name.trim_end_matches('.') == name)
+                {
+                    bail!("Secondary zone {name} is defined more than once")
+                }
+                secondary.push(entry);
+            }
+            self.secondary = Some(SingleOrVecValue::Multiple(secondary));
+        }
+
+        for (name, value) in other.unbound.unwrap_or_default() {
+            let unbound = self.unbound.get_or_insert_with(IndexMap::new);
+            if unbound.contains_key(&name) {
+                bail!("Unbound option {name} is defined more than once")
+            }
+            unbound.insert(name, value);
+        }
+
+        self.reverse = match (self.reverse.take(), other.reverse) {
+            (None, None) => None,
+            (Some(reverse), None) | (None, Some(reverse)) => Some(reverse),
+            (Some(own), Some(other)) => {
+                let mut entries = own.into_entries();
+                for (net, entry) in other.into_entries() {
+                    if entries.contains_key(&net) {
+                        bail!("Reverse network {net} is defined more than once")
+                    }
+                    entries.insert(net, entry);
+                }
+                Some(ReverseValue::Entry(entries))
+            }
+        };
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 #[serde(default = "RawDefaults::default")]
 #[serde(deny_unknown_fields)]
 pub struct RawDefaults {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub serial: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<Email>,
+    #[serde(skip_serializing_if = "is_u32::<DEFAULT_EXPIRE>")]
     pub expire: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mx: Option<SingleOrVecValue<StringOrTableValue<MxEntry>>>,
-    #[serde(rename = "mx-prio")]
+    #[serde(rename = "mx-prio", skip_serializing_if = "is_u16::<DEFAULT_MX_PRIO>")]
     pub mx_prio: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub nameserver: Option<SingleOrVecValue<String>>,
-    #[serde(rename = "nrc-ttl")]
+    #[serde(rename = "nrc-ttl", skip_serializing_if = "is_u32::<DEFAULT_NRC_TTL>")]
     pub nrc_ttl: u32,
+    #[serde(skip_serializing_if = "is_u32::<DEFAULT_REFRESH>")]
     pub refresh: u32,
+    #[serde(skip_serializing_if = "is_u32::<DEFAULT_RETRY>")]
     pub retry: u32,
-    #[serde(rename = "srv-prio")]
+    #[serde(
+        rename = "srv-prio",
+        skip_serializing_if = "is_u16::<DEFAULT_SRV_PRIO>"
+    )]
     pub srv_prio: u16,
-    #[serde(rename = "srv-weight")]
+    #[serde(
+        rename = "srv-weight",
+        skip_serializing_if = "is_u16::<DEFAULT_SRV_WEIGHT>"
+    )]
     pub srv_weight: u16,
+    #[serde(skip_serializing_if = "is_default_ttl")]
     pub ttl: TTL,
-    #[serde(rename = "with-ptr")]
+    #[serde(
+        rename = "with-ptr",
+        skip_serializing_if = "is_bool::<DEFAULT_WITH_PTR>"
+    )]
     pub with_ptr: bool,
+    #[serde(rename = "ptr-policy", skip_serializing_if = "is_default_ptr_policy")]
+    pub ptr_policy: PtrPolicy,
+    #[serde(rename = "alias-mode", skip_serializing_if = "is_default_alias_mode")]
+    pub alias_mode: AliasMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nsd: Option<NsdEntry>,
+}
+
+fn is_u32<const V: u32>(value: &u32) -> bool {
+    *value == V
+}
+
+fn is_u16<const V: u16>(value: &u16) -> bool {
+    *value == V
+}
+
+fn is_bool<const V: bool>(value: &bool) -> bool {
+    *value == V
+}
+
+fn is_default_ttl(ttl: &TTL) -> bool {
+    ttl.0 == DEFAULT_TTL
 }
 
 impl RawDefaults {
@@ -633,8 +1459,11 @@ impl RawDefaults {
             retry: DEFAULT_RETRY,
             srv_prio: DEFAULT_SRV_PRIO,
             srv_weight: DEFAULT_SRV_WEIGHT,
-            ttl: TTL(DEFAULT_TTL),
+            ttl: TTL(DEFAULT_TTL, None),
             with_ptr: DEFAULT_WITH_PTR,
+            ptr_policy: PtrPolicy::default(),
+            alias_mode: AliasMode::default(),
+            nsd: None,
         }
     }
 }
@@ -646,7 +1475,7 @@ pub struct SessionDefaults {
     pub expire: u32,
     pub mx: Vec<MxEntry>,
     pub mx_prio: u16,
-    pub nameserver: Vec<String>,
+    pub nameserver: Vec<NameserverEntry>,
     pub nrc_ttl: u32,
     pub refresh: u32,
     pub retry: u32,
@@ -654,6 +1483,9 @@ pub struct SessionDefaults {
     pub srv_weight: u16,
     pub ttl: u32,
     pub with_ptr: bool,
+    pub ptr_policy: PtrPolicy,
+    pub alias_mode: AliasMode,
+    pub nsd: NsdOptions,
 }
 
 impl SessionDefaults {
@@ -662,11 +1494,7 @@ impl SessionDefaults {
             Some(s) => s,
             None => gen_serial,
         };
-        if raw.retry >= raw.refresh {
-            let retry = raw.retry;
-            let refresh = raw.refresh;
-            bail!("retry ({retry}) must be less than refresh {refresh}");
-        }
+        check_soa_timers("defaults", raw.refresh, raw.retry, raw.expire)?;
         let email = match raw.email {
             Some(validated_email) => Some(parse_email(&validated_email.0)?),
             None => None,
@@ -674,11 +1502,15 @@ impl SessionDefaults {
         let nameserver = raw
             .nameserver
             .map(SingleOrVecValue::to_vec)
-            .unwrap_or_default();
-
-        for ns_entry in &nameserver {
-            validate_dns_name(ns_entry)?
-        }
+            .unwrap_or_default()
+            .into_iter()
+            .map(|ns| {
+                let ns = StringOrTableValue::<NameserverEntry>::Entry(ns).to_entry()?;
+                let name = ns.name.to_ascii_lowercase();
+                validate_dns_name(&name)?;
+                Ok(NameserverEntry { name, ..ns })
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         let mx = raw
             .mx
@@ -686,6 +1518,10 @@ impl SessionDefaults {
             .unwrap_or_default()
             .into_iter()
             .map(StringOrTableValue::<MxEntry>::to_entry)
+            .map(|mx| MxEntry {
+                name: mx.name.to_ascii_lowercase(),
+                ..mx
+            })
             .collect();
 
         Ok(Self {
@@ -702,49 +1538,159 @@ impl SessionDefaults {
             srv_weight: raw.srv_weight,
             ttl: raw.ttl.0,
             with_ptr: raw.with_ptr,
+            ptr_policy: raw.ptr_policy,
+            alias_mode: raw.alias_mode,
+            nsd: parse_nsd(raw.nsd, &NsdOptions::default())?,
         })
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// Zone another zone inherits from
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Inherit {
+    /// `true` for the closest parent zone in the config
+    Parent(bool),
+    Zone(String),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Zone {
     #[serde(flatten)]
     pub base: ZoneBaseEntry,
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mx: Option<SingleOrVecValue<StringOrTableValue<MxEntry>>>,
-    #[serde(rename = "mx-prio")]
+    #[serde(rename = "mx-prio", skip_serializing_if = "Option::is_none")]
     pub mx_prio: Option<u16>,
-    #[serde(rename = "srv-prio")]
+    #[serde(rename = "srv-prio", skip_serializing_if = "Option::is_none")]
     pub srv_prio: Option<u16>,
-    #[serde(rename = "srv-weight")]
+    #[serde(rename = "srv-weight", skip_serializing_if = "Option::is_none")]
     pub srv_weight: Option<u16>,
-    #[serde(rename = "with-ptr")]
+    #[serde(rename = "with-ptr", skip_serializing_if = "Option::is_none")]
     pub with_ptr: Option<bool>,
-
-    pub hosts: Option<std::collections::HashMap<String, HostValue>>,
-    pub cname: Option<std::collections::HashMap<String, StringOrTableValue<CnameEntry>>>,
+    #[serde(rename = "ptr-policy", skip_serializing_if = "Option::is_none")]
+    pub ptr_policy: Option<PtrPolicy>,
+    #[serde(rename = "alias-mode", skip_serializing_if = "Option::is_none")]
+    pub alias_mode: Option<AliasMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public: Option<bool>,
+    /// Bounds the TTLs of the records are clamped into, with a warning
+    #[serde(rename = "ttl-min", skip_serializing_if = "Option::is_none")]
+    pub ttl_min: Option<TTL>,
+    #[serde(rename = "ttl-max", skip_serializing_if = "Option::is_none")]
+    pub ttl_max: Option<TTL>,
+    /// Zone whose nameservers, email and SOA timers this zone uses where it
+    /// has none of its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherit: Option<Inherit>,
+    /// Group whose settings this zone uses where it has none of its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// IPv6 networks the `ip6-suffix` and `eui64` of the hosts are placed in
+    #[serde(rename = "ip6-prefix", skip_serializing_if = "Option::is_none")]
+    pub ip6_prefix: Option<SingleOrVecValue<Ipv6Network>>,
+    /// Challenge zone or nameserver the DNS-01 validation of the apex is
+    /// handed to
+    #[serde(rename = "acme-delegate", skip_serializing_if = "Option::is_none")]
+    pub acme_delegate: Option<String>,
+    #[serde(rename = "acme-mode", skip_serializing_if = "Option::is_none")]
+    pub acme_mode: Option<AcmeMode>,
+    /// Labels for `--include-tag` and `--exclude-tag`, shared by the hosts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<SingleOrVecValue<String>>,
+    /// Outputs generated from this, all of them without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<SingleOrVecValue<Backend>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hosts: Option<IndexMap<String, HostValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cname: Option<IndexMap<String, StringOrTableValue<CnameEntry>>>,
+    /// Names with the A/AAAA records of all their member hosts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool: Option<IndexMap<String, SingleOrVecValue<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub srv: Option<SrvMap>,
+    /// Hosts of common services like `ldap`, which get the SRV records the
+    /// service is looked up with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub services: Option<IndexMap<String, SingleOrVecValue<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mail: Option<MailEntry>,
+    /// Child zones served by other nameservers, with their NS and glue records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegate: Option<IndexMap<String, SingleOrVecValue<StringOrTableValue<DelegateEntry>>>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct ZoneWithoutName {
     #[serde(flatten)]
     pub base: ZoneBaseEntry,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mx: Option<SingleOrVecValue<StringOrTableValue<MxEntry>>>,
-    #[serde(rename = "mx-prio")]
+    #[serde(rename = "mx-prio", skip_serializing_if = "Option::is_none")]
     pub mx_prio: Option<u16>,
-    #[serde(rename = "srv-prio")]
+    #[serde(rename = "srv-prio", skip_serializing_if = "Option::is_none")]
     pub srv_prio: Option<u16>,
-    #[serde(rename = "srv-weight")]
+    #[serde(rename = "srv-weight", skip_serializing_if = "Option::is_none")]
     pub srv_weight: Option<u16>,
-    #[serde(rename = "with-ptr")]
+    #[serde(rename = "with-ptr", skip_serializing_if = "Option::is_none")]
     pub with_ptr: Option<bool>,
-
-    pub hosts: Option<std::collections::HashMap<String, HostValue>>,
-    pub cname: Option<std::collections::HashMap<String, StringOrTableValue<CnameEntry>>>,
+    #[serde(rename = "ptr-policy", skip_serializing_if = "Option::is_none")]
+    pub ptr_policy: Option<PtrPolicy>,
+    #[serde(rename = "alias-mode", skip_serializing_if = "Option::is_none")]
+    pub alias_mode: Option<AliasMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public: Option<bool>,
+    /// Bounds the TTLs of the records are clamped into, with a warning
+    #[serde(rename = "ttl-min", skip_serializing_if = "Option::is_none")]
+    pub ttl_min: Option<TTL>,
+    #[serde(rename = "ttl-max", skip_serializing_if = "Option::is_none")]
+    pub ttl_max: Option<TTL>,
+    /// Zone whose nameservers, email and SOA timers this zone uses where it
+    /// has none of its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherit: Option<Inherit>,
+    /// Group whose settings this zone uses where it has none of its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// IPv6 networks the `ip6-suffix` and `eui64` of the hosts are placed in
+    #[serde(rename = "ip6-prefix", skip_serializing_if = "Option::is_none")]
+    pub ip6_prefix: Option<SingleOrVecValue<Ipv6Network>>,
+    /// Challenge zone or nameserver the DNS-01 validation of the apex is
+    /// handed to
+    #[serde(rename = "acme-delegate", skip_serializing_if = "Option::is_none")]
+    pub acme_delegate: Option<String>,
+    #[serde(rename = "acme-mode", skip_serializing_if = "Option::is_none")]
+    pub acme_mode: Option<AcmeMode>,
+    /// Labels for `--include-tag` and `--exclude-tag`, shared by the hosts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<SingleOrVecValue<String>>,
+    /// Outputs generated from this, all of them without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<SingleOrVecValue<Backend>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hosts: Option<IndexMap<String, HostValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cname: Option<IndexMap<String, StringOrTableValue<CnameEntry>>>,
+    /// Names with the A/AAAA records of all their member hosts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool: Option<IndexMap<String, SingleOrVecValue<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub srv: Option<SrvMap>,
+    /// Hosts of common services like `ldap`, which get the SRV records the
+    /// service is looked up with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub services: Option<IndexMap<String, SingleOrVecValue<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mail: Option<MailEntry>,
+    /// Child zones served by other nameservers, with their NS and glue records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegate: Option<IndexMap<String, SingleOrVecValue<StringOrTableValue<DelegateEntry>>>>,
 }
 impl ZoneWithoutName {
     pub fn with_name(self, name: String) -> Zone {
@@ -756,13 +1702,62 @@ impl ZoneWithoutName {
             srv_prio: self.srv_prio,
             srv_weight: self.srv_weight,
             with_ptr: self.with_ptr,
+            ptr_policy: self.ptr_policy,
+            alias_mode: self.alias_mode,
+            public: self.public,
+            ttl_min: self.ttl_min,
+            ttl_max: self.ttl_max,
+            inherit: self.inherit,
+            group: self.group,
+            ip6_prefix: self.ip6_prefix,
+            acme_delegate: self.acme_delegate,
+            acme_mode: self.acme_mode,
+            tags: self.tags,
+            outputs: self.outputs,
             hosts: self.hosts,
             cname: self.cname,
+            pool: self.pool,
             srv: self.srv, // Beide nutzen jetzt SrvMap
+            services: self.services,
+            mail: self.mail,
+            delegate: self.delegate,
         }
     }
 }
-#[derive(Debug)]
+
+impl Zone {
+    pub fn split_name(self) -> (String, ZoneWithoutName) {
+        let zone = ZoneWithoutName {
+            base: self.base,
+            mx: self.mx,
+            mx_prio: self.mx_prio,
+            srv_prio: self.srv_prio,
+            srv_weight: self.srv_weight,
+            with_ptr: self.with_ptr,
+            ptr_policy: self.ptr_policy,
+            alias_mode: self.alias_mode,
+            public: self.public,
+            ttl_min: self.ttl_min,
+            ttl_max: self.ttl_max,
+            inherit: self.inherit,
+            group: self.group,
+            ip6_prefix: self.ip6_prefix,
+            acme_delegate: self.acme_delegate,
+            acme_mode: self.acme_mode,
+            tags: self.tags,
+            outputs: self.outputs,
+            hosts: self.hosts,
+            cname: self.cname,
+            pool: self.pool,
+            srv: self.srv,
+            services: self.services,
+            mail: self.mail,
+            delegate: self.delegate,
+        };
+        (self.name, zone)
+    }
+}
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ZoneBase {
     pub serial: u32,
     pub name: String,
@@ -773,64 +1768,238 @@ pub struct ZoneBase {
     pub refresh: u32,
     pub retry: u32,
     pub ttl: u32,
+    /// Options of the zone in zones.conf, which leave the zone file and its
+    /// serial alone
+    #[serde(skip)]
+    pub nsd: NsdOptions,
 }
 
-#[derive(Debug)]
+/// Option of the `server:` clause of Unbound, like `prefetch: yes`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UnboundOption {
+    pub name: String,
+    pub value: String,
+}
+
+/// Zone transferred from other primaries, see [`SecondaryEntry`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SecondaryZone {
+    pub name: String,
+    pub masters: Vec<IpAddr>,
+    pub tsig_key: Option<String>,
+}
+
+/// Options of a zone in the zones.conf of NSD, see [`NsdEntry`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NsdOptions {
+    pub pattern: Option<String>,
+    pub notify: Vec<String>,
+    pub provide_xfr: Vec<String>,
+    pub outgoing_interface: Vec<String>,
+}
+
+impl ZoneBase {
+    /// Nameserver named in the SOA record
+    pub fn primary_nameserver(&self) -> Result<&str> {
+        match self.nameserver.first() {
+            Some(ns) => Ok(&ns.name),
+            None => bail!("Zone {} needs a nameserver", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ForwardZone {
     pub base: ZoneBase,
+    /// NS records of the names delegated to other nameservers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delegation: Vec<DelegationRecord>,
     pub mx: Vec<MxRecord>,
     pub hosts: Vec<ARecord>,
     pub cname: Vec<CnameRecord>,
     pub srv: Vec<SrvRecord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub txt: Vec<TxtRecord>,
+    /// PTR records generated from the hosts of this zone
+    pub ptr: Vec<PtrRecord>,
+    /// Zone is served on the internet and should not expose private addresses
+    pub public: bool,
+    /// Config lines of the records by owner, see [`crate::origin::annotate`]
+    #[serde(skip)]
+    pub origins: HashMap<String, Origin>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ReverseZone {
     pub base: ZoneBase,
     pub ptr: Vec<PtrRecord>,
     pub split: usize,
+    /// NS records of the child zones delegated to other nameservers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delegation: Vec<DelegationRecord>,
+    /// Config lines of the hosts of the PTR records by host name
+    #[serde(skip)]
+    pub origins: HashMap<String, Origin>,
 }
 
-fn extract_location(error_msg: &str) -> String {
-    // Extrahiere das ERSTE "at line X column Y" aus der Fehlermeldung
-    // Das ist die spezifischste Position
-    if let Some(pos) = error_msg.find("at line ") {
-        let rest = &error_msg[pos..];
-        // Finde das Ende dieser Location (entweder " at line " oder Ende des Strings)
-        if let Some(next_at) = rest[8..].find(" at line ") {
-            // Es gibt noch ein weiteres "at line", nimm nur bis dahin
-            return format!(" ({})", rest[..8 + next_at].trim());
-        } else if let Some(newline) = rest.find('\n') {
-            // Nimm bis zum Newline
-            return format!(" ({})", rest[..newline].trim());
-        } else {
-            // Nimm den Rest, aber maximal ~50 Zeichen
-            let end = rest.len().min(50);
-            return format!(" ({})", rest[..end].trim());
+impl ForwardZone {
+    /// All records of the zone: NS, the NS of delegated names, MX, A/AAAA, CNAME,
+    /// SRV, TXT, then the generated PTRs
+    pub fn records(&self) -> impl Iterator<Item = Record<'_>> {
+        self.base
+            .nameserver
+            .iter()
+            .map(Record::Ns)
+            .chain(self.delegation.iter().map(Record::Delegation))
+            .chain(self.mx.iter().map(Record::Mx))
+            .chain(self.hosts.iter().map(Record::Address))
+            .chain(self.cname.iter().map(Record::Cname))
+            .chain(self.srv.iter().map(Record::Srv))
+            .chain(self.txt.iter().map(Record::Txt))
+            .chain(self.ptr.iter().map(Record::Ptr))
+    }
+
+    /// Records of one type such as `"AAAA"`, ignoring case
+    pub fn filter_by_type<'a>(&'a self, record_type: &'a str) -> impl Iterator<Item = Record<'a>> {
+        self.records()
+            .filter(move |r| r.record_type().eq_ignore_ascii_case(record_type))
+    }
+
+    /// Records owned by a name, ignoring case and a missing trailing dot. NS and
+    /// MX records are owned by the zone apex. The generated PTRs are owned by
+    /// their reverse zone and never match.
+    pub fn find_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Record<'a>> {
+        let apex = name_matches(&self.base.name, name);
+        self.records().filter(move |r| match r {
+            Record::Ns(_) | Record::Mx(_) => apex,
+            Record::Delegation(d) => name_matches(&d.name, name),
+            Record::Address(a) => name_matches(&a.name, name),
+            Record::Cname(c) => name_matches(&c.name, name),
+            Record::Srv(s) => name_matches(&s.name, name),
+            Record::Txt(t) => name_matches(&t.name, name),
+            Record::Ptr(_) => false,
+        })
+    }
+}
+
+impl ReverseZone {
+    /// All records of the zone: NS, the NS of delegated child zones, then PTR
+    pub fn records(&self) -> impl Iterator<Item = Record<'_>> {
+        self.base
+            .nameserver
+            .iter()
+            .map(Record::Ns)
+            .chain(self.delegation.iter().map(Record::Delegation))
+            .chain(self.ptr.iter().map(Record::Ptr))
+    }
+
+    /// Records of one type such as `"PTR"`, ignoring case
+    pub fn filter_by_type<'a>(&'a self, record_type: &'a str) -> impl Iterator<Item = Record<'a>> {
+        self.records()
+            .filter(move |r| r.record_type().eq_ignore_ascii_case(record_type))
+    }
+
+    /// Records owned by a name like `5.1.168.192.in-addr.arpa.`, ignoring case
+    /// and a missing trailing dot. NS records are owned by the zone apex.
+    pub fn find_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Record<'a>> {
+        let apex = name_matches(&self.base.name, name);
+        self.records().filter(move |r| match r {
+            Record::Ns(_) => apex,
+            Record::Delegation(d) => name_matches(&d.name, name),
+            Record::Ptr(p) => {
+                let owner = format!("{}.{}", ip_name(&p.ip, self.split), self.base.name);
+                name_matches(&owner, name)
+            }
+            _ => false,
+        })
+    }
+}
+
+/// All zones resolved from one configuration
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ZoneSet {
+    pub forward: Vec<ForwardZone>,
+    pub reverse: Vec<ReverseZone>,
+    /// Zones this server transfers from other primaries
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secondary: Vec<SecondaryZone>,
+    /// Options of the `server:` clause of the Unbound output
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unbound: Vec<UnboundOption>,
+    /// Generated serial used by every zone without its own
+    pub serial: u32,
+    /// File or directory the configuration was read from
+    pub source: Option<PathBuf>,
+    /// Problems that did not prevent generating the zones
+    #[serde(skip_deserializing)]
+    pub warnings: Vec<Finding>,
+}
+
+/// Position of the innermost error. Nested deserialization errors repeat the
+/// location of every enclosing map, the first one is the most specific.
+#[cfg(feature = "yaml")]
+fn yaml_position(msg: &str) -> Option<(usize, usize)> {
+    let rest = &msg[msg.find(" at line ")? + 9..];
+    let (line, rest) = rest.split_once(" column ")?;
+    let column: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    Some((line.parse().ok()?, column.parse().ok()?))
+}
+
+/// Removes the " at line X column Y" suffixes the YAML deserializer adds to nested errors
+#[cfg(feature = "yaml")]
+fn strip_yaml_positions(msg: &str) -> String {
+    let mut result = String::new();
+    let mut rest = msg;
+    while let Some(pos) = rest.find(" at line ") {
+        result.push_str(&rest[..pos]);
+        let tail = &rest[pos + 9..];
+        let skip = tail
+            .find(" column ")
+            .filter(|&c| tail[..c].chars().all(|ch| ch.is_ascii_digit()))
+            .map(|c| {
+                c + 8
+                    + tail[c + 8..]
+                        .chars()
+                        .take_while(|ch| ch.is_ascii_digit())
+                        .count()
+            });
+        match skip {
+            Some(skip) => rest = &tail[skip..],
+            None => {
+                result.push_str(" at line ");
+                rest = tail;
+            }
         }
     }
-    String::new()
+    result.push_str(rest);
+    result
 }
 
-pub fn parse(
-    raw: &str,
-    serial: u32,
-    input_format: InputFormat,
-) -> Result<(Vec<ForwardZone>, Vec<ReverseZone>)> {
-    let content: Content = match input_format {
+pub(crate) fn parse_content(raw: &str, input_format: InputFormat, name: &str) -> Result<Content> {
+    let origins = Origins::scan(raw, input_format.clone(), name);
+    let mut content: Content = match input_format {
         #[cfg(feature = "toml")]
         InputFormat::Toml => {
             let deserializer = toml::Deserializer::new(raw);
             serde_path_to_error::deserialize(deserializer).map_err(|e| {
-                let inner_err = e.inner().to_string();
-                // Versuche Zeile/Spalte aus der Fehlermeldung zu extrahieren
-                let location = extract_location(&inner_err);
-                anyhow!(
+                let message = e.inner().message().trim().to_string();
+                let offset = e.inner().span().map(|s| s.start);
+                let location = offset
+                    .map(|o| line_col(raw, o))
+                    .map(|(line, column)| format!("line {line}, column {column}"))
+                    .unwrap_or_default();
+                let err = ParseError::new(format!(
                     "TOML parse error:\n  Path:  '{}'\n. Location: {}\n. Error: {}",
                     e.path(),
-                    location.trim_start_matches(" (").trim_end_matches(")"),
-                    inner_err
-                )
+                    location,
+                    message
+                ));
+                match offset {
+                    Some(o) => err
+                        .with_source(name, raw, token_span(raw, o))
+                        .with_label(message.lines().next().unwrap_or_default()),
+                    None => err,
+                }
             })?
         }
         #[cfg(feature = "yaml")]
@@ -838,42 +2007,291 @@ pub fn parse(
             let deserializer = serde_yml::Deserializer::from_str(raw);
             serde_path_to_error::deserialize(deserializer).map_err(|e| {
                 let inner_err = e.inner().to_string();
-                let location = extract_location(&inner_err);
-                anyhow!(
+                let message = strip_yaml_positions(&inner_err);
+                let position = yaml_position(&inner_err)
+                    .or_else(|| e.inner().location().map(|l| (l.line(), l.column())));
+                let location = position
+                    .map(|(line, column)| format!("line {line}, column {column}"))
+                    .unwrap_or_default();
+                let err = ParseError::new(format!(
                     "YAML parse error:\n  Path:  '{}'\n. Location: {}\n. Error: {}",
                     e.path(),
-                    location.trim_start_matches(" (").trim_end_matches(")"),
-                    inner_err
-                )
+                    location,
+                    message
+                ));
+                match position.and_then(|(line, column)| offset_of(raw, line, column)) {
+                    Some(o) => {
+                        let path = format!("{}: ", e.path());
+                        let label = message
+                            .rfind(&path)
+                            .map_or(message.as_str(), |i| &message[i + path.len()..]);
+                        err.with_source(name, raw, token_span(raw, o))
+                            .with_label(label)
+                    }
+                    None => err,
+                }
             })?
         }
     };
+    content.origins = origins;
+    Ok(content)
+}
 
-    let defaults: SessionDefaults = SessionDefaults::from_raw(content.defaults, serial)?;
+fn mixed_case_warning(zone: &str, names: &[&str]) -> Finding {
+    Finding {
+        rule: "mixed-case",
+        zone: zone.to_string(),
+        severity: Severity::Warning,
+        message: format!("Lowercased {}", names.join(", ")),
+    }
+}
 
-    let mut ips: HashMap<IpAddr, PtrRecord> = HashMap::new();
-    let zones = match content.zone {
-        Some(Zones::Array(a)) => a,
-        Some(Zones::Map(m)) => m
-            .into_iter()
-            .map(|(name, zwn)| zwn.with_name(name))
-            .collect(),
-        None => Vec::new(),
+fn ttl_clamp_warning(zone: &str, clamped: &[String]) -> Finding {
+    Finding {
+        rule: "ttl-clamp",
+        zone: zone.to_string(),
+        severity: Severity::Warning,
+        message: format!("Clamped the TTL of {}", clamped.join(", ")),
+    }
+}
+
+/// PTR records outside all reverse networks, listed in the zone of the host
+fn orphan_ptr_warnings(
+    forward: &[ForwardZone],
+    orphans: HashMap<IpAddr, Vec<PtrRecord>>,
+) -> Vec<Finding> {
+    let mut orphans: Vec<PtrRecord> = orphans.into_values().flatten().collect();
+    orphans.sort_by_key(|ptr| ptr.ip);
+    forward
+        .iter()
+        .filter_map(|zone| {
+            let hosts: Vec<String> = orphans
+                .iter()
+                .filter(|ptr| zone.ptr.contains(ptr))
+                .map(|ptr| format!("{} ({})", ptr.name, ptr.ip))
+                .collect();
+            (!hosts.is_empty()).then(|| Finding {
+                rule: "orphan-ptr",
+                zone: zone.base.name.clone(),
+                severity: Severity::Warning,
+                message: format!(
+                    "No reverse network for the PTR records of {}",
+                    hosts.join(", ")
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Resolves a raw config into forward and reverse zones, applying the defaults
+pub fn resolve(mut content: Content, serial: u32) -> Result<ZoneSet> {
+    // Zones are resolved independently, so the errors of all of them are reported
+    let mut errors = Errors::default();
+    errors.check(expand_vars(&mut content));
+    let raw_defaults = content.defaults.unwrap_or_else(RawDefaults::default);
+    let mut warnings = Vec::new();
+    let mixed = mixed_case_defaults(&raw_defaults);
+    if !mixed.is_empty() {
+        warnings.push(mixed_case_warning("defaults", &mixed));
+    }
+    let defaults = match SessionDefaults::from_raw(raw_defaults, serial) {
+        Ok(defaults) => Some(defaults),
+        Err(err) => {
+            errors.push(Some("defaults"), err);
+            None
+        }
+    };
+
+    // Ordered, so that conflicting claims are reported in address order
+    let mut claims: BTreeMap<IpAddr, Vec<PtrClaim>> = BTreeMap::new();
+    let mut zones = content.zone.map(Zones::into_vec).unwrap_or_default();
+    errors.check(apply_groups(
+        &mut zones,
+        &content.groups.unwrap_or_default(),
+    ));
+    errors.check(inherit_zones(&mut zones));
+    // Without valid defaults every zone would fail on them as well
+    let Some(defaults) = defaults else {
+        errors.check(parse_unbound_options(content.unbound));
+        errors.finish()?;
+        unreachable!("the defaults are invalid");
     };
     let mut forward: Vec<ForwardZone> = vec![];
     for zone in zones {
-        let (z, ptrs) = parse_forward(zone, &defaults)?;
-        forward.push(z);
-        for ptr in ptrs {
-            if ips.contains_key(&ptr.ip) {
-                bail!("Duplicate Ptr Record: {:?}", ptr)
+        let mixed = mixed_case_names(&zone);
+        if !mixed.is_empty() {
+            warnings.push(mixed_case_warning(&zone.name, &mixed));
+        }
+        let name = zone.name.clone();
+        let fqdn = zone_fqdn(&name);
+        if content
+            .dropped_zones
+            .iter()
+            .any(|dropped| zone_fqdn(dropped) == fqdn)
+        {
+            continue;
+        }
+        let ttl_min = zone.ttl_min.as_ref().map(|ttl| ttl.0);
+        let ttl_max = zone.ttl_max.as_ref().map(|ttl| ttl.0);
+        let (mut z, mut ptrs) = match parse_forward(zone, &defaults) {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                errors.push(Some(&name), err);
+                continue;
             }
-            ips.insert(ptr.ip, ptr);
+        };
+        match clamp_ttls(&mut z, &mut ptrs, ttl_min, ttl_max) {
+            Ok(clamped) if !clamped.is_empty() => warnings.push(ttl_clamp_warning(&name, &clamped)),
+            Ok(_) => {}
+            Err(err) => {
+                errors.push(Some(&name), err);
+                continue;
+            }
+        }
+        forward.push(z);
+        for claim in ptrs {
+            claims.entry(claim.record.ip).or_default().push(claim);
         }
     }
+    // Decided once all hosts are known, a later host may be the primary one
+    let mut ips: HashMap<IpAddr, Vec<PtrRecord>> = HashMap::new();
+    for (ip, claims) in claims {
+        if let Some(records) = errors.check(resolve_ptr_claims(claims)) {
+            ips.insert(ip, records);
+        }
+    }
+
+    // Without any reverse network no PTR records are wanted at all
+    let with_reverse = content.reverse.is_some();
+    let reverse = errors
+        .check(parse_reverse(content.reverse, &defaults, &mut ips))
+        .unwrap_or_default();
+    let secondary = errors
+        .check(parse_secondaries(content.secondary, &forward, &reverse))
+        .unwrap_or_default();
+    let unbound = errors
+        .check(parse_unbound_options(content.unbound))
+        .unwrap_or_default();
+    errors.finish()?;
+    if with_reverse {
+        warnings.extend(orphan_ptr_warnings(&forward, ips));
+    }
+    Ok(ZoneSet {
+        forward,
+        reverse,
+        secondary,
+        unbound,
+        serial,
+        source: None,
+        warnings,
+    })
+}
+
+pub fn parse(raw: &str, serial: u32, input_format: InputFormat) -> Result<ZoneSet> {
+    let content = parse_content(raw, input_format, "input")?;
+    resolve(content, serial)
+}
+
+/// Parses a config into the raw model without resolving it into zones
+pub fn parse_config(raw: &str, input_format: InputFormat) -> Result<Content> {
+    parse_content(raw, input_format, "input")
+}
+
+/// Serializes a raw config model back to YAML or TOML
+pub fn format_config(content: &Content, output_format: InputFormat) -> Result<String> {
+    let output = match output_format {
+        #[cfg(feature = "toml")]
+        InputFormat::Toml => toml::to_string(content)?,
+        #[cfg(feature = "yaml")]
+        InputFormat::Yaml => unquote_addresses(&serde_yml::to_string(content)?),
+    };
+    Ok(output)
+}
+
+/// Removes the quotes serde_yml puts around IP addresses and networks, which
+/// read back as the same strings without them. Only whole values and map
+/// keys are touched, never quotes inside a longer string.
+#[cfg(feature = "yaml")]
+fn unquote_addresses(yaml: &str) -> String {
+    fn quoted(s: &str) -> Option<&str> {
+        let inner = s.strip_prefix('\'')?.strip_suffix('\'')?;
+        let plain = !inner.contains('\'')
+            && inner.parse::<IpNetwork>().is_ok()
+            && serde_yml::from_str::<serde_yml::Value>(inner).ok()
+                == Some(serde_yml::Value::String(inner.to_string()));
+        plain.then_some(inner)
+    }
+    let mut output = String::with_capacity(yaml.len());
+    for line in yaml.lines() {
+        let indent = line.len() - line.trim_start_matches([' ', '-']).len();
+        let (head, rest) = line.split_at(indent);
+        let unquoted = if let Some(value) = quoted(rest) {
+            // A list item or a whole scalar document
+            format!("{head}{value}")
+        } else if let Some(key) = rest.strip_suffix(':').and_then(quoted) {
+            // A key before a nested block
+            format!("{head}{key}:")
+        } else if let Some((key, value)) =
+            rest.split_once(": ").filter(|(key, _)| !key.contains('\''))
+        {
+            match quoted(value) {
+                Some(value) => format!("{head}{key}: {value}"),
+                None => line.to_string(),
+            }
+        } else {
+            line.to_string()
+        };
+        output.push_str(&unquoted);
+        output.push('\n');
+    }
+    output
+}
+
+/// Parses every `*.yaml`, `*.yml` and `*.toml` file of a directory in sorted
+/// order and merges them into one configuration.
+#[cfg(feature = "fs")]
+pub fn parse_dir(dir: &Path, serial: u32) -> Result<ZoneSet> {
+    let mut zones = resolve(parse_dir_config(dir)?, serial)?;
+    zones.source = Some(dir.to_path_buf());
+    Ok(zones)
+}
 
-    let reverse = parse_reverse(content.reverse, &defaults, ips)?;
-    Ok((forward, reverse))
+/// YAML and TOML files of a config directory, in name order
+#[cfg(feature = "fs")]
+pub fn config_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<_>>()?;
+    paths.sort();
+    paths.retain(|path| {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        path.is_file() && matches!(extension, "yaml" | "yml" | "toml")
+    });
+    Ok(paths)
+}
+
+/// Merges the config files of a directory without resolving them into zones
+#[cfg(feature = "fs")]
+pub fn parse_dir_config(dir: &Path) -> Result<Content> {
+    let mut content = Content::default();
+    for path in config_files(dir)? {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let input_format = InputFormat::from_extension(extension).ok_or_else(|| {
+            ParseError::new(format!(
+                "{}: support for '{extension}' files is not enabled",
+                path.display()
+            ))
+        })?;
+        let raw = fs::read_to_string(&path)?;
+        parse_content(&raw, input_format, &path.display().to_string())
+            .and_then(|c| content.merge(c))
+            .map_err(|e| {
+                let msg = format!("{}: {e}", path.display());
+                reword(e, msg)
+            })?;
+    }
+
+    Ok(content)
 }
 
 #[cfg(test)]
@@ -940,6 +2358,45 @@ mod tests {
         let toml = "ttl = 10800";
         let config: Config = toml::from_str(toml).unwrap();
         assert_eq!(config.ttl.0, 10800);
+
+        let toml = "ttl = \"3h\"";
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.ttl.0, 10800);
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_ttl_deserialize_duration() {
+        for (yaml, seconds) in [
+            ("1h", 3600),
+            ("30m", 1800),
+            ("2d", 172800),
+            ("1h30m", 5400),
+            ("1W", 604800),
+            ("90s", 90),
+            ("'300'", 300),
+        ] {
+            let ttl: TTL = serde_yml::from_str(yaml).unwrap();
+            assert_eq!(ttl.0, seconds, "{yaml}");
+        }
+        // Written back as it was
+        let ttl: TTL = serde_yml::from_str("1h").unwrap();
+        let yaml = serde_yml::to_string(&ttl).unwrap();
+        assert_eq!(
+            serde_yml::from_str::<TTL>(&yaml).unwrap().1.as_deref(),
+            Some("1h")
+        );
+        assert_eq!(
+            serde_yml::to_string(&TTL(3600, None)).unwrap().trim(),
+            "3600"
+        );
+
+        for yaml in ["1x", "h", "1h30", "''", "0m", "100000w"] {
+            let result: Result<TTL, _> = serde_yml::from_str(yaml);
+            assert!(result.is_err(), "{yaml}");
+        }
+        let err = serde_yml::from_str::<TTL>("1y").unwrap_err().to_string();
+        assert!(err.contains("invalid TTL '1y'"), "{err}");
     }
 
     #[test]
@@ -1104,4 +2561,1151 @@ ttl: 0
         let result: Result<Defaults, _> = serde_yml::from_str(yaml);
         assert!(result.is_err());
     }
+
+    // ==================== Error Location Tests ====================
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_yaml_position_innermost() {
+        let msg = "zone: Invalid zone map: hosts: bad at line 4 column 12 at line 2 column 3";
+        assert_eq!(yaml_position(msg), Some((4, 12)));
+        assert_eq!(
+            strip_yaml_positions(msg),
+            "zone: Invalid zone map: hosts: bad"
+        );
+        assert_eq!(yaml_position("no position"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_parse_error_has_span() {
+        let yaml = "zone:\n  example.com:\n    hosts:\n      www: 999.1.1.1\n";
+        let err = parse(yaml, 1, InputFormat::Yaml).unwrap_err();
+        let parse_err = err.downcast_ref::<ParseError>().unwrap();
+        assert!(parse_err.message.contains("Location: line 4, column 12"));
+        assert_eq!(parse_err.span, Some((43, 9).into()));
+        assert_eq!(parse_err.position(), Some((4, 12)));
+        assert_eq!(
+            parse_err.label.as_deref(),
+            Some("'999.1.1.1' is not a valid IP address")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_serialize_resolved_zones() {
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      www: 192.168.1.2\n";
+        let forward = parse(yaml, 2025060100, InputFormat::Yaml).unwrap().forward;
+
+        let json = serde_json::to_value(&forward[0]).unwrap();
+        assert_eq!(json["base"]["name"], "example.com.");
+        assert_eq!(json["base"]["serial"], 2025060100);
+        assert_eq!(json["hosts"][0]["ip"], "192.168.1.2");
+
+        let back: ForwardZone = serde_json::from_value(json).unwrap();
+        assert_eq!(back.hosts, forward[0].hosts);
+        assert_eq!(back.base.nameserver, forward[0].base.nameserver);
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_empty_nameserver_list() {
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    nameserver: []\n";
+        let err = parse(yaml, 2025060100, InputFormat::Yaml).unwrap_err();
+        assert_eq!(err.to_string(), "Zone example.com. needs a nameserver");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_nameserver_ttl() {
+        let config = |ns: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: [{ns}]\nzone:\n  example.com:\n    ttl: 300\n")
+        };
+        let nameservers = "'ns1.example.net. 2d', ns2.example.net.";
+        let zones = parse(&config(nameservers), 2025060100, InputFormat::Yaml).unwrap();
+        let ns: Vec<String> = zones.forward[0]
+            .base
+            .nameserver
+            .iter()
+            .map(|ns| format!("{} {}", ns.name, ns.ttl))
+            .collect();
+        assert_eq!(ns, ["ns1.example.net. 172800", "ns2.example.net. 300"]);
+
+        let err = |ns: &str| {
+            parse(&config(ns), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("'ns1.example.net. soon'"),
+            "Nameserver ns1.example.net.: invalid TTL 'soon', expected seconds or a duration like 1h, 30m or 2d"
+        );
+        assert_eq!(
+            err("'ns1.example.net. 1h IN'"),
+            "Nameserver 'ns1.example.net. 1h IN' is more than a name and a TTL"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_errors_of_all_zones() {
+        let yaml = "defaults:\n  nameserver: ns1.example.com.\nzone:\n  a.example:\n    email: admin@example.com\n    mx: bad..name\n  b.example:\n    refresh: 100\n    hosts:\n      www: 192.168.1.2\nreverse:\n  - 192.168.1.0/24\n";
+        let err = parse(yaml, 2025060100, InputFormat::Yaml).unwrap_err();
+        let message = err.to_string();
+        let lines: Vec<&str> = message.lines().collect();
+        assert_eq!(lines.len(), 5, "{message}");
+        assert_eq!(lines[0], "4 errors:");
+        assert!(lines[1].starts_with("- a.example: "), "{message}");
+        assert!(
+            lines[2].starts_with("- b.example: SOA timers of b.example."),
+            "{message}"
+        );
+        assert_eq!(lines[3], "- b.example: Email is required");
+        assert_eq!(lines[4], "- 1.168.192.in-addr.arpa.: Email is required");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_mixed_case_names() {
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: NS1.example.com.\nzone:\n  Example.COM:\n    mx: Mail\n    hosts:\n      Mail: 192.168.1.3\n      www: 192.168.1.2\n    cname:\n      web: WWW\n";
+        let zones = parse(yaml, 2025060100, InputFormat::Yaml).unwrap();
+        let zone = &zones.forward[0];
+        assert_eq!(zone.base.name, "example.com.");
+        assert_eq!(zone.base.nameserver[0].name, "ns1.example.com.");
+        assert_eq!(zone.mx[0].name, "mail.example.com.");
+        assert_eq!(zone.hosts[0].name, "mail.example.com.");
+        assert_eq!(zone.cname[0].target, "www.example.com.");
+        assert_eq!(
+            zones
+                .warnings
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "defaults: Lowercased NS1.example.com.",
+                "Example.COM: Lowercased Example.COM, Mail, WWW",
+            ]
+        );
+
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      host: 192.168.1.2\n      Host: 192.168.1.3\n";
+        let err = parse(yaml, 2025060100, InputFormat::Yaml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Hosts 'host' and 'Host' both define host.example.com."
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_shared_ptr() {
+        let config = |hosts: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n{hosts}reverse:\n  - 192.168.1.0/24\n")
+        };
+        let ptrs = |hosts: &str| -> Vec<String> {
+            let zones = parse(&config(hosts), 2025060100, InputFormat::Yaml).unwrap();
+            zones.reverse[0]
+                .ptr
+                .iter()
+                .map(|p| p.name.clone())
+                .collect()
+        };
+
+        let shared = "      www: 192.168.1.2\n      web: 192.168.1.2\n";
+        let err = parse(&config(shared), 2025060100, InputFormat::Yaml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Duplicate PTR for 192.168.1.2: www.example.com. and web.example.com. (set ptr-policy or ptr-primary)"
+        );
+        assert_eq!(
+            ptrs(
+                "      www: 192.168.1.2\n      web: { ip: 192.168.1.2, ptr-policy: first-wins }\n"
+            ),
+            ["www.example.com."]
+        );
+        assert_eq!(
+            ptrs("      www: 192.168.1.2\n      web: { ip: 192.168.1.2, ptr-policy: all }\n"),
+            ["www.example.com.", "web.example.com."]
+        );
+        assert_eq!(
+            ptrs("      www: 192.168.1.2\n      web: { ip: 192.168.1.2, ptr-primary: true }\n"),
+            ["web.example.com."]
+        );
+        let both = "      www: { ip: 192.168.1.2, ptr-primary: true }\n      web: { ip: 192.168.1.2, ptr-primary: true }\n";
+        let err = parse(&config(both), 2025060100, InputFormat::Yaml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Hosts www.example.com. and web.example.com. both have ptr-primary for 192.168.1.2"
+        );
+
+        let zone_policy =
+            config(shared).replace("    hosts:", "    ptr-policy: first-wins\n    hosts:");
+        let zones = parse(&zone_policy, 2025060100, InputFormat::Yaml).unwrap();
+        assert_eq!(zones.reverse[0].ptr.len(), 1);
+    }
+
+    #[test]
+    fn test_number_range() {
+        let range = NumberRange::find("node-[08-10]").unwrap().unwrap();
+        assert_eq!(range.count(), 3);
+        assert_eq!(range.nth(0), "node-08");
+        assert_eq!(range.nth(2), "node-10");
+        assert_eq!(range.to_string(), "node-[08-10]");
+        let range = NumberRange::find("10.0.2.[9-10]").unwrap().unwrap();
+        assert_eq!(range.nth(0), "10.0.2.9");
+        assert_eq!(range.to_string(), "10.0.2.[9-10]");
+        assert_eq!(NumberRange::find("node").unwrap(), None);
+
+        for text in [
+            "n[1-]",
+            "n[1-2",
+            "n]",
+            "n[2-1]",
+            "n[1-2][1-2]",
+            "n[0-65536]",
+            "n[-1-2]",
+        ] {
+            assert!(NumberRange::find(text).is_err(), "{text}");
+        }
+        assert!("10.0.2.[250-256]".parse::<HostAddress>().is_err());
+        assert!("10.0.2.[250-255]".parse::<HostAddress>().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_host_ranges() {
+        let config = |hosts: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n{hosts}reverse:\n  - 10.0.2.0/24\n")
+        };
+        let hosts = "      node-[01-24]:\n        ip: 10.0.2.[10-33]\n        alias: n[1-24]\n";
+        let zones = parse(&config(hosts), 2025060100, InputFormat::Yaml).unwrap();
+        let records: Vec<String> = zones.forward[0]
+            .hosts
+            .iter()
+            .map(|host| format!("{} {}", host.name, host.ip))
+            .collect();
+        assert_eq!(records.len(), 48);
+        assert_eq!(
+            records[..2],
+            [
+                "node-01.example.com. 10.0.2.10",
+                "n1.example.com. 10.0.2.10"
+            ]
+        );
+        assert_eq!(
+            records[46..],
+            [
+                "node-24.example.com. 10.0.2.33",
+                "n24.example.com. 10.0.2.33"
+            ]
+        );
+        assert_eq!(zones.reverse[0].ptr.len(), 24);
+
+        let err = |hosts: &str| {
+            parse(&config(hosts), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("      node-[1-3]: 10.0.2.[10-11]\n"),
+            "Host 'node-[1-3]' stands for 3 hosts, but its address range 10.0.2.[10-11] for 2"
+        );
+        assert_eq!(
+            err("      login:\n        ip: 10.0.2.1\n        alias: all-[1-2]\n"),
+            "Host 'login' has the alias all-[1-2], but no range in its name"
+        );
+        assert_eq!(
+            err("      node-[1-3]: 10.0.2.[10-12]\n      node-2: 10.0.2.99\n"),
+            "Hosts 'node-[1-3]' and 'node-2' both define node-2.example.com."
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_reverse_exclude() {
+        let config = |reverse: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      www: 10.0.1.2\n      vpn: 10.0.1.130\n      db: 10.0.2.3\nreverse:\n{reverse}")
+        };
+        let zones = parse(
+            &config("  10.0.1.0/24:\n    exclude: 10.0.1.128/25\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        let names: Vec<&str> = zones.reverse[0]
+            .ptr
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["www.example.com."]);
+
+        // A network may lie in the excluded part of another one
+        let zones = parse(
+            &config("  10.0.0.0/16:\n    exclude: [10.0.2.0/24]\n  10.0.2.0/24: {}\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(zones.reverse[0].ptr.len(), 2);
+        assert_eq!(zones.reverse[1].ptr[0].name, "db.example.com.");
+
+        let err = parse(
+            &config("  10.0.0.0/16:\n    exclude: 10.0.2.0/24\n  10.0.0.0/23: {}\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Reverse zone networks overlap: 10.0.0.0/23 and 10.0.0.0/16"),
+            "{err}"
+        );
+        let err = parse(
+            &config("  10.0.1.0/24:\n    exclude: 10.0.0.0/23\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Excluded network 10.0.0.0/23 is not part of 10.0.1.0/24"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_orphan_ptr() {
+        let config = |reverse: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      www: [10.0.1.2, \"2001:db8::2\"]\n      vpn: 10.0.1.130\n      ext: {{ ip: 192.0.2.1, with-ptr: false }}\n{reverse}")
+        };
+        let warnings = |reverse: &str| -> Vec<String> {
+            let zones = parse(&config(reverse), 2025060100, InputFormat::Yaml).unwrap();
+            zones.warnings.iter().map(ToString::to_string).collect()
+        };
+
+        assert_eq!(
+            warnings("reverse:\n  - 10.0.1.0/25\n"),
+            ["example.com.: No reverse network for the PTR records of vpn.example.com. (10.0.1.130), www.example.com. (2001:db8::2)"]
+        );
+        // Excluded parts are served elsewhere
+        assert_eq!(
+            warnings("reverse:\n  10.0.1.0/24:\n    exclude: 10.0.1.128/25\n  2001:db8::/64: {}\n"),
+            Vec::<String>::new()
+        );
+        assert!(warnings("").is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_fill_ptr() {
+        let config = |reverse: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      www: 10.0.1.2\nreverse:\n{reverse}")
+        };
+        let zones = parse(
+            &config("  10.0.1.0/29:\n    fill-ptr: dhcp-{ip}.dyn.example.com.\n    exclude: 10.0.1.4/31\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        let ptrs: Vec<String> = zones.reverse[0]
+            .ptr
+            .iter()
+            .map(|p| format!("{} {}", p.ip, p.name))
+            .collect();
+        assert_eq!(
+            ptrs,
+            [
+                "10.0.1.1 dhcp-10-0-1-1.dyn.example.com.",
+                "10.0.1.2 www.example.com.",
+                "10.0.1.3 dhcp-10-0-1-3.dyn.example.com.",
+                "10.0.1.6 dhcp-10-0-1-6.dyn.example.com.",
+            ]
+        );
+
+        let zones = parse(
+            &config("  \"2001:db8::/126\":\n    fill-ptr: host{n}.example.com.\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(zones.reverse[0].ptr[3].name, "host3.example.com.");
+
+        let err = parse(
+            &config("  10.0.0.0/8:\n    fill-ptr: dhcp-{ip}.example.com.\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("would create more than 65536 PTR records"),
+            "{err}"
+        );
+        let err = parse(
+            &config("  10.0.1.0/24:\n    fill-ptr: dhcp-{ip}\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Invalid fill-ptr pattern dhcp-{ip}"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_alias_mode() {
+        let config = |zone: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n{zone}")
+        };
+        let records = |zone: &str| {
+            let zones = parse(&config(zone), 2025060100, InputFormat::Yaml).unwrap();
+            let zone = &zones.forward[0];
+            let a = zone.hosts.iter().map(|a| format!("{} A {}", a.name, a.ip));
+            let cname = zone
+                .cname
+                .iter()
+                .map(|c| format!("{} CNAME {}", c.name, c.target));
+            a.chain(cname).collect::<Vec<_>>()
+        };
+        let hosts = "    hosts:\n      www:\n        ip: [10.0.0.5, 2001:db8::5]\n        alias: [web, shop]\n";
+        assert_eq!(
+            records(hosts),
+            [
+                "www.example.com. A 10.0.0.5",
+                "web.example.com. A 10.0.0.5",
+                "shop.example.com. A 10.0.0.5",
+                "www.example.com. A 2001:db8::5",
+                "web.example.com. A 2001:db8::5",
+                "shop.example.com. A 2001:db8::5"
+            ]
+        );
+        let cnames = [
+            "www.example.com. A 10.0.0.5",
+            "www.example.com. A 2001:db8::5",
+            "web.example.com. CNAME www.example.com.",
+            "shop.example.com. CNAME www.example.com.",
+        ];
+        assert_eq!(records(&format!("    alias-mode: cname\n{hosts}")), cnames);
+        let host_mode = hosts.replace(
+            "alias: [web, shop]",
+            "alias: [web, shop]\n        alias-mode: cname",
+        );
+        assert_eq!(records(&host_mode), cnames);
+        let ranges = "    alias-mode: cname\n    hosts:\n      node-[1-2]:\n        ip: 10.0.1.[1-2]\n        alias: n[1-2]\n";
+        assert_eq!(
+            records(ranges)[2..],
+            [
+                "n1.example.com. CNAME node-1.example.com.",
+                "n2.example.com. CNAME node-2.example.com."
+            ]
+        );
+
+        let err = |zone: &str| {
+            parse(&config(zone), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("    alias-mode: cname\n    hosts:\n      www:\n        ip: 10.0.0.5\n        alias: mail\n      mail: 10.0.0.6\n"),
+            "Alias mail.example.com. of host 'www' is also host 'mail'"
+        );
+        assert_eq!(
+            err("    alias-mode: cname\n    hosts:\n      www:\n        ip: 10.0.0.5\n        alias: web\n      db:\n        ip: 10.0.0.6\n        alias: web\n"),
+            "Hosts 'www' and 'db' both have the alias web.example.com."
+        );
+        assert_eq!(
+            err("    alias-mode: cname\n    hosts:\n      www:\n        ip: 10.0.0.5\n        alias: web\n    cname:\n      web: www\n"),
+            "web.example.com. is both an alias of a host and a CNAME"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_ip6_prefix() {
+        let config = |zone: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n{zone}reverse:\n  - 2001:db8:1::/64\n")
+        };
+        let prefixes = "    ip6-prefix:\n      - 2001:db8:1::/64\n      - fd00:1::/48\n";
+        let hosts = "    hosts:\n      www:\n        ip: 10.0.0.5\n        ip6-suffix: ::1:5\n      nas:\n        eui64: 00:1a:2b:3c:4d:5e\n      node-[1-2]:\n        ip6-suffix: ::[10-11]\n";
+        let zones = parse(
+            &config(&format!("{prefixes}{hosts}")),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        let records: Vec<String> = zones.forward[0]
+            .hosts
+            .iter()
+            .map(|host| format!("{} {}", host.name, host.ip))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                "www.example.com. 10.0.0.5",
+                "www.example.com. 2001:db8:1::1:5",
+                "www.example.com. fd00:1::1:5",
+                "nas.example.com. 2001:db8:1:0:21a:2bff:fe3c:4d5e",
+                "nas.example.com. fd00:1::21a:2bff:fe3c:4d5e",
+                "node-1.example.com. 2001:db8:1::10",
+                "node-1.example.com. fd00:1::10",
+                "node-2.example.com. 2001:db8:1::11",
+                "node-2.example.com. fd00:1::11"
+            ]
+        );
+        // Only the addresses in the reverse network get a PTR
+        assert_eq!(zones.reverse[0].ptr.len(), 4);
+
+        let err = |zone: &str| {
+            parse(&config(zone), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("    hosts:\n      www:\n        ip6-suffix: ::5\n"),
+            "Host 'www' has an interface identifier, but the zone no ip6-prefix"
+        );
+        assert_eq!(
+            err(&format!(
+                "{prefixes}    hosts:\n      www:\n        ttl: 300\n"
+            )),
+            "Host 'www' has no address"
+        );
+        assert_eq!(
+            err(&format!(
+                "{prefixes}    hosts:\n      www:\n        ip6-suffix: 10.0.0.5\n"
+            )),
+            "ip6-suffix 10.0.0.5 of host 'www' is not an IPv6 address"
+        );
+        assert_eq!(
+            err("    ip6-prefix: 2001:db8::/96\n    hosts:\n      nas:\n        eui64: 00:1a:2b:3c:4d:5e\n"),
+            "Host 'nas' has an eui64, which needs prefixes of /64 or shorter, not 2001:db8::/96"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_pools() {
+        let config = |zone: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      web1: [10.0.0.1, 2001:db8::1]\n      web-[2-3]: 10.0.0.[2-3]\n      db: 10.0.0.9\n{zone}reverse:\n  - 10.0.0.0/24\n")
+        };
+        let zones = parse(
+            &config("    pool:\n      www:\n        - web1\n        - web-[2-3]\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        let www: Vec<String> = zones.forward[0]
+            .find_name("www.example.com.")
+            .map(|record| match record {
+                Record::Address(a) => a.ip.to_string(),
+                record => record.record_type().to_string(),
+            })
+            .collect();
+        assert_eq!(www, ["10.0.0.1", "2001:db8::1", "10.0.0.2", "10.0.0.3"]);
+        // No PTR records for the pool name
+        assert_eq!(zones.reverse[0].ptr.len(), 4);
+
+        let err = |zone: &str| {
+            parse(&config(zone), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("    pool:\n      www: web4\n"),
+            "Pool 'www' has the member web4.example.com., which is no host of the zone"
+        );
+        assert_eq!(
+            err("    pool:\n      db: web1\n"),
+            "Pool 'db' is also a host"
+        );
+        assert_eq!(
+            err("    cname:\n      www: db\n    pool:\n      www: web1\n"),
+            "Pool 'www' is also a CNAME"
+        );
+        assert_eq!(
+            err("    pool:\n      www: []\n"),
+            "Pool 'www' has no members"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_services() {
+        let config = |zone: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      dc1: 10.0.0.1\n      dc2: 10.0.0.2\n{zone}")
+        };
+        let zone = "    srv-prio: 10\n    services:\n      kerberos: dc1\n      ldap:\n        - dc1\n        - dc2\n    srv:\n      _http._tcp:\n        target: dc1\n        port: 80\n";
+        let zones = parse(&config(zone), 2025060100, InputFormat::Yaml).unwrap();
+        let srv: Vec<String> = zones.forward[0]
+            .srv
+            .iter()
+            .map(|srv| format!("{} {} {} {}", srv.name, srv.prio, srv.port, srv.target))
+            .collect();
+        assert_eq!(
+            srv,
+            [
+                "_http._tcp.example.com. 10 80 dc1.example.com.",
+                "_kerberos._udp.example.com. 10 88 dc1.example.com.",
+                "_kerberos._tcp.example.com. 10 88 dc1.example.com.",
+                "_kerberos-master._udp.example.com. 10 88 dc1.example.com.",
+                "_kerberos-master._tcp.example.com. 10 88 dc1.example.com.",
+                "_kpasswd._udp.example.com. 10 464 dc1.example.com.",
+                "_kpasswd._tcp.example.com. 10 464 dc1.example.com.",
+                "_ldap._tcp.example.com. 10 389 dc1.example.com.",
+                "_ldap._tcp.example.com. 10 389 dc2.example.com."
+            ]
+        );
+
+        let err = parse(
+            &config("    services:\n      ntp: dc1\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown service 'ntp', known are gc, imap, kerberos, ldap, ldaps, sip, submission, xmpp"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_disabled_entries() {
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.0.1\n      old:\n        ip: 10.0.0.2\n        alias: legacy\n        disabled: true\n    cname:\n      www: ns1\n      web:\n        target: old\n        disabled: true\n    srv:\n      _ldap._tcp:\n        target: old\n        port: 389\n        disabled: true\nreverse:\n  - 10.0.0.0/24\n";
+        let zones = parse(yaml, 2025060100, InputFormat::Yaml).unwrap();
+        let zone = &zones.forward[0];
+        let hosts: Vec<&str> = zone.hosts.iter().map(|host| host.name.as_str()).collect();
+        assert_eq!(hosts, ["ns1.example.com."]);
+        let cnames: Vec<&str> = zone.cname.iter().map(|cname| cname.name.as_str()).collect();
+        assert_eq!(cnames, ["www.example.com."]);
+        assert!(zone.srv.is_empty());
+        assert_eq!(zones.reverse[0].ptr.len(), 1);
+
+        // A parked entry still has to be a valid one
+        let typo = yaml.replace(
+            "disabled: true\n    cname",
+            "disabled: true\n        tll: 60\n    cname",
+        );
+        assert!(parse(&typo, 2025060100, InputFormat::Yaml).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_mail() {
+        let config = |zone: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\n  mx: mx.example.net.\nzone:\n  example.com:\n    hosts:\n      mail: 10.0.0.25\n{zone}")
+        };
+        let zone =
+            "    mail:\n      host: mail\n      spf: a:mail.example.com -all\n      srv: true\n";
+        let zones = parse(&config(zone), 2025060100, InputFormat::Yaml).unwrap();
+        let zone = &zones.forward[0];
+        let records: Vec<String> = zone
+            .records()
+            .filter(|r| !matches!(r, Record::Ns(_) | Record::Address(_) | Record::Ptr(_)))
+            .map(|r| match r {
+                Record::Mx(r) => format!("MX {} {}", r.prio, r.name),
+                Record::Cname(r) => format!("CNAME {} {}", r.name, r.target),
+                Record::Srv(r) => format!("SRV {} {} {}", r.name, r.port, r.target),
+                Record::Txt(r) => format!("TXT {} {}", r.name, r.data()),
+                r => unreachable!("{r:?}"),
+            })
+            .collect();
+        assert_eq!(
+            records,
+            [
+                "MX 0 mail.example.com.",
+                "CNAME autoconfig.example.com. mail.example.com.",
+                "CNAME autodiscover.example.com. mail.example.com.",
+                "SRV _submission._tcp.example.com. 587 mail.example.com.",
+                "SRV _imap._tcp.example.com. 143 mail.example.com.",
+                "SRV _imaps._tcp.example.com. 993 mail.example.com.",
+                "TXT example.com. \"v=spf1 a:mail.example.com -all\""
+            ]
+        );
+
+        // Defaults: SPF of the MX hosts, no SRV records
+        let zones = parse(
+            &config("    mail:\n      host: mail\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(zones.forward[0].txt[0].text, "v=spf1 mx -all");
+        assert!(zones.forward[0].srv.is_empty());
+
+        let zone = "    mail:\n      host: mail\n    cname:\n      autoconfig: www\n";
+        let err = parse(&config(zone), 2025060100, InputFormat::Yaml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "autoconfig.example.com. is both a CNAME and set by the mail block"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_acme_delegate() {
+        let config = |zone: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    acme-delegate: acme.example.net.\n    hosts:\n      www:\n        ip: 10.0.0.1\n        acme-delegate: acme.example.net.\n      '*':\n        ip: 10.0.0.2\n        acme-delegate: acme.example.net.\n{zone}")
+        };
+        let zones = parse(&config(""), 2025060100, InputFormat::Yaml).unwrap();
+        let cname: Vec<String> = zones.forward[0]
+            .cname
+            .iter()
+            .map(|c| format!("{} {}", c.name, c.target))
+            .collect();
+        // The wildcard is validated at the apex
+        assert_eq!(
+            cname,
+            [
+                "_acme-challenge.example.com. example.com.acme.example.net.",
+                "_acme-challenge.www.example.com. www.example.com.acme.example.net."
+            ]
+        );
+
+        let zones = parse(
+            &config("    acme-mode: ns\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        let zone = &zones.forward[0];
+        assert!(zone.cname.is_empty());
+        let ns: Vec<String> = zone
+            .delegation
+            .iter()
+            .map(|ns| format!("{} {}", ns.name, ns.target))
+            .collect();
+        assert_eq!(
+            ns,
+            [
+                "_acme-challenge.example.com. acme.example.net.",
+                "_acme-challenge.www.example.com. acme.example.net."
+            ]
+        );
+
+        let zone = "    cname:\n      _acme-challenge.www: www\n";
+        let err = parse(&config(zone), 2025060100, InputFormat::Yaml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "_acme-challenge.www.example.com. is both a CNAME and set by acme-delegate"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_child_delegations() {
+        let config = |zone: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.0.1\n{zone}reverse:\n  - 10.0.0.0/24\n")
+        };
+        let zone = "    delegate:\n      lab:\n        - name: ns1\n          ip: [10.0.1.1, 2001:db8::1]\n        - ns1.example.net.\n      dev.example.com.: ns1.example.com.\n";
+        let zones = parse(&config(zone), 2025060100, InputFormat::Yaml).unwrap();
+        let zone = &zones.forward[0];
+        let ns: Vec<String> = zone
+            .delegation
+            .iter()
+            .map(|ns| format!("{} {}", ns.name, ns.target))
+            .collect();
+        assert_eq!(
+            ns,
+            [
+                "lab.example.com. ns1.lab.example.com.",
+                "lab.example.com. ns1.example.net.",
+                "dev.example.com. ns1.example.com."
+            ]
+        );
+        let hosts: Vec<String> = zone
+            .hosts
+            .iter()
+            .map(|a| format!("{} {}", a.name, a.ip))
+            .collect();
+        assert_eq!(
+            hosts,
+            [
+                "ns1.example.com. 10.0.0.1",
+                "ns1.lab.example.com. 10.0.1.1",
+                "ns1.lab.example.com. 2001:db8::1"
+            ]
+        );
+        // Glue gets no PTR records
+        assert_eq!(zones.reverse[0].ptr.len(), 1);
+
+        let err = |zone: &str| {
+            parse(&config(zone), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("    delegate:\n      lab: ns1\n"),
+            "Nameserver ns1.lab.example.com. lies within lab.example.com. and needs a glue ip"
+        );
+        assert_eq!(
+            err(
+                "    delegate:\n      lab:\n        name: ns1.example.net.\n        ip: 10.0.1.1\n"
+            ),
+            "Nameserver ns1.example.net. lies outside of lab.example.com. and takes no glue ip"
+        );
+        assert_eq!(
+            err("    delegate:\n      example.org.: ns1.example.net.\n"),
+            "Delegated zone example.org. is not below example.com."
+        );
+        assert_eq!(
+            err("    cname:\n      www.lab: ns1\n    delegate:\n      lab: ns1.example.net.\n"),
+            "www.lab.example.com. lies within the delegated zone lab.example.com."
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_same_as() {
+        let config = |hosts: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n{hosts}reverse:\n  - 10.0.0.0/24\n")
+        };
+        let hosts = "      gw:\n        same-as: router\n        alias: gateway\n      router: [10.0.0.1, 10.0.0.254]\n      vpn:\n        same-as: gw\n        ttl: 60\n";
+        let zones = parse(&config(hosts), 2025060100, InputFormat::Yaml).unwrap();
+        let records: Vec<String> = zones.forward[0]
+            .hosts
+            .iter()
+            .map(|a| format!("{} {} {}", a.name, a.ttl, a.ip))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                "router.example.com. 10800 10.0.0.1",
+                "router.example.com. 10800 10.0.0.254",
+                "gw.example.com. 10800 10.0.0.1",
+                "gw.example.com. 10800 10.0.0.254",
+                "gateway.example.com. 10800 10.0.0.1",
+                "gateway.example.com. 10800 10.0.0.254",
+                "vpn.example.com. 60 10.0.0.1",
+                "vpn.example.com. 60 10.0.0.254"
+            ]
+        );
+        // The addresses keep the PTR records of their own host
+        let ptr: Vec<&str> = zones.reverse[0]
+            .ptr
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(ptr, ["router.example.com.", "router.example.com."]);
+
+        let err = |hosts: &str| {
+            parse(&config(hosts), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("      gw:\n        same-as: router\n"),
+            "Host 'gw' is the same as router.example.com., which is no host of the zone with addresses"
+        );
+        assert_eq!(
+            err("      a:\n        same-as: b\n      b:\n        same-as: a\n"),
+            "Host 'a' is the same as b.example.com., which is no host of the zone with addresses"
+        );
+        assert_eq!(
+            err("      gw:\n        same-as: router\n        ip: 10.0.0.2\n      router: 10.0.0.1\n"),
+            "Host 'gw' has both same-as and addresses of its own"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_ttl_clamp() {
+        let config = |limits: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n{limits}    hosts:\n      ns1: 10.0.0.1\n      www:\n        ip: 10.0.0.2\n        ttl: 60\n      cdn:\n        ip: 2001:db8::1\n        ttl: 2w\n        with-ptr: false\n    cname:\n      web: www\nreverse:\n  - 10.0.0.0/24\n")
+        };
+        let limits = "    ttl-min: 300\n    ttl-max: 1d\n";
+        let zones = parse(&config(limits), 2025060100, InputFormat::Yaml).unwrap();
+        let records: Vec<String> = zones.forward[0]
+            .hosts
+            .iter()
+            .map(|a| format!("{} {}", a.name, a.ttl))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                "ns1.example.com. 10800",
+                "www.example.com. 300",
+                "cdn.example.com. 86400"
+            ]
+        );
+        let ptr: Vec<u32> = zones.reverse[0].ptr.iter().map(|p| p.ttl).collect();
+        assert_eq!(ptr, [10800, 300]);
+        let warnings: Vec<String> = zones.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            ["example.com: Clamped the TTL of www.example.com. A 60 -> 300, cdn.example.com. AAAA 1209600 -> 86400, 10.0.0.2 PTR 60 -> 300"]
+        );
+
+        let zones = parse(
+            &config("    ttl-max: 3600\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(zones.forward[0].base.ttl, 3600);
+        assert_eq!(zones.forward[0].cname[0].ttl, 3600);
+
+        let limits = "    ttl-min: 1h\n    ttl-max: 300\n";
+        let err = parse(&config(limits), 2025060100, InputFormat::Yaml).unwrap_err();
+        assert_eq!(err.to_string(), "ttl-min 3600 is above ttl-max 300");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_wildcard_hosts() {
+        let config = |hosts: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n{hosts}reverse:\n  - 10.0.0.0/24\n")
+        };
+        let hosts = "      web:\n        ip: 10.0.0.1\n        alias: ['*', '*.apps']\n      '*.dev': 10.0.0.2\n";
+        let zones = parse(&config(hosts), 2025060100, InputFormat::Yaml).unwrap();
+        let names: Vec<&str> = zones.forward[0]
+            .hosts
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "web.example.com.",
+                "*.example.com.",
+                "*.apps.example.com.",
+                "*.dev.example.com."
+            ]
+        );
+        // Only the host itself gets a PTR
+        let ptr: Vec<&str> = zones.reverse[0]
+            .ptr
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(ptr, ["web.example.com."]);
+
+        let err = |hosts: &str| {
+            parse(&config(hosts), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("      '*':\n        ip: 10.0.0.2\n        with-ptr: true\n"),
+            "Wildcard host *.example.com. cannot have PTR records"
+        );
+        assert_eq!(
+            err("      '*':\n        ip: 10.0.0.2\n        ptr-primary: true\n"),
+            "Wildcard host *.example.com. cannot have PTR records"
+        );
+        assert_eq!(
+            err("      web:\n        ip: 10.0.0.1\n        alias: 'a.*'\n"),
+            "Wildcard '*' must be leftmost label, got: a.*.example.com."
+        );
+        assert_eq!(
+            err("      'dev*': 10.0.0.2\n"),
+            "Wildcard '*' must be entire label, got: dev*"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_zone_groups() {
+        let config = |zones: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\ngroups:\n  lab:\n    nameserver: ns1.lab.example.com.\n    ttl: 300\n    refresh: 9000\n    with-ptr: false\nzone:\n{zones}")
+        };
+        let zones = "  a.lab.example.com:\n    group: lab\n    hosts:\n      pc: 10.0.0.1\n  b.lab.example.com:\n    group: lab\n    refresh: 8000\n  example.com: {}\nreverse:\n  - 10.0.0.0/24\n";
+        let zones = parse(&config(zones), 2025060100, InputFormat::Yaml).unwrap();
+        let base = |name: &str| {
+            &zones
+                .forward
+                .iter()
+                .find(|zone| zone.base.name == name)
+                .unwrap()
+                .base
+        };
+
+        assert_eq!(
+            base("a.lab.example.com.").nameserver[0].name,
+            "ns1.lab.example.com."
+        );
+        assert_eq!(base("a.lab.example.com.").ttl, 300);
+        assert_eq!(base("a.lab.example.com.").refresh, 9000);
+        assert_eq!(base("b.lab.example.com.").refresh, 8000);
+        assert_eq!(base("example.com.").nameserver[0].name, "ns1.example.com.");
+        assert!(zones.reverse[0].ptr.is_empty());
+
+        let err = parse(
+            &config("  example.org:\n    group: prod\n"),
+            2025060100,
+            InputFormat::Yaml,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Zone example.org. is in the group prod, which is not in the config"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_inherit_zones() {
+        let config = |zones: &str| {
+            format!("defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    email: hostmaster@example.com\n    nameserver:\n      - ns1.example.com.\n      - ns2.example.com.\n    refresh: 9000\n    expire: 1209600\n{zones}")
+        };
+        let zones = "  lab.example.com:\n    inherit: true\n    refresh: 7200\n  a.lab.example.com:\n    inherit: true\n  other.org:\n    inherit: example.com\n  plain.example.com: {}\n";
+        let zones = parse(&config(zones), 2025060100, InputFormat::Yaml).unwrap();
+        let base = |name: &str| {
+            &zones
+                .forward
+                .iter()
+                .find(|zone| zone.base.name == name)
+                .unwrap()
+                .base
+        };
+        let ns = |name| {
+            base(name)
+                .nameserver
+                .iter()
+                .map(|ns| ns.name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(base("lab.example.com.").email, "hostmaster.example.com.");
+        assert_eq!(base("lab.example.com.").refresh, 7200);
+        assert_eq!(base("lab.example.com.").expire, 1209600);
+        assert_eq!(
+            ns("lab.example.com."),
+            ["ns1.example.com.", "ns2.example.com."]
+        );
+        // The chain passes the values on, the child's own ones included
+        assert_eq!(base("a.lab.example.com.").refresh, 7200);
+        assert_eq!(base("a.lab.example.com.").email, "hostmaster.example.com.");
+        assert_eq!(base("other.org.").refresh, 9000);
+        // Without inherit only the defaults apply
+        assert_eq!(base("plain.example.com.").email, "admin.example.com.");
+        assert_eq!(ns("plain.example.com."), ["ns1.example.com."]);
+
+        let err = |zones: &str| {
+            parse(&config(zones), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("  example.org:\n    inherit: true\n"),
+            "Zone example.org. has no parent zone in the config to inherit from"
+        );
+        assert_eq!(
+            err("  example.org:\n    inherit: example.net\n"),
+            "Zone example.org. inherits from example.net., which is not in the config"
+        );
+        assert_eq!(
+            err("  example.org:\n    inherit: example.org\n"),
+            "Zone example.org. cannot inherit from itself"
+        );
+        assert_eq!(
+            err("  a.org:\n    inherit: b.org\n  b.org:\n    inherit: a.org\n"),
+            "Zones inherit from each other: a.org. -> b.org. -> a.org."
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_resolve_reports_all_errors() {
+        let yaml = "vars:\n  lan: 10.0.2\n  wan: '{{vars.lan}}'\ndefaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      www: '{{vars.lan}}.5'\n  example.org:\n    hosts:\n      www: 10.0.2.6\n    pool:\n      www: [web]\n  example.net:\n    group: lab\n";
+        let err = parse(yaml, 2025060100, InputFormat::Yaml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "3 errors:\n- Variable wan refers to another variable\n\
+             - Zone example.net. is in the group lab, which is not in the config\n\
+             - example.org: Pool 'www' is also a host"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_reverse_split_prefix() {
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      www: 10.0.5.2\n      db: 10.0.7.3\nreverse:\n  - 10.0.4.0/22\n";
+        let zones = parse(yaml, 2025060100, InputFormat::Yaml).unwrap();
+        let names: Vec<&str> = zones.reverse.iter().map(|z| z.base.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "4.0.10.in-addr.arpa.",
+                "5.0.10.in-addr.arpa.",
+                "6.0.10.in-addr.arpa.",
+                "7.0.10.in-addr.arpa."
+            ]
+        );
+        assert_eq!(zones.reverse[1].ptr[0].name, "www.example.com.");
+        assert_eq!(zones.reverse[3].ptr[0].name, "db.example.com.");
+        assert_eq!(zones.reverse[3].split, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_record_accessors() {
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    mx: mail\n    hosts:\n      www: [192.168.1.2, \"2001:db8::2\"]\n      mail: 192.168.1.3\n    cname:\n      web: www\nreverse:\n  - 192.168.1.0/24\n";
+        let zones = parse(yaml, 2025060100, InputFormat::Yaml).unwrap();
+        let zone = &zones.forward[0];
+
+        let types: Vec<&str> = zone.records().map(|r| r.record_type()).collect();
+        assert_eq!(
+            types,
+            ["NS", "MX", "A", "AAAA", "A", "CNAME", "PTR", "PTR", "PTR"]
+        );
+        assert_eq!(zone.filter_by_type("aaaa").count(), 1);
+
+        let www: Vec<&str> = zone
+            .find_name("WWW.example.com")
+            .map(|r| r.record_type())
+            .collect();
+        assert_eq!(www, ["A", "AAAA"]);
+        assert_eq!(zone.find_name("example.com.").count(), 2);
+
+        let reverse = &zones.reverse[0];
+        let ptr: Vec<_> = reverse.find_name("2.1.168.192.in-addr.arpa").collect();
+        assert!(matches!(ptr[..], [Record::Ptr(p)] if p.name == "www.example.com."));
+        assert_eq!(reverse.filter_by_type("NS").count(), 1);
+    }
+
+    #[test]
+    #[cfg(all(feature = "yaml", feature = "nsd"))]
+    fn test_tsig_section() {
+        let yaml =
+            "tsig:\n  key-dir: /etc/nsd/tsig\n  keys:\n    ns2: [192.0.2.53, \"2001:db8::53\"]\n";
+        let content = parse_config(yaml, InputFormat::Yaml).unwrap();
+        let tsig = content.tsig.as_ref().unwrap();
+        assert_eq!(tsig.key_dir, PathBuf::from("/etc/nsd/tsig"));
+        assert_eq!(tsig.algorithm, crate::tsig::TsigAlgorithm::HmacSha256);
+        assert_eq!(tsig.keys["ns2"].len(), 2);
+        // fmt keeps the section
+        let formatted = format_config(&content, InputFormat::Yaml).unwrap();
+        let again = parse_config(&formatted, InputFormat::Yaml).unwrap();
+        assert_eq!(again.tsig, content.tsig);
+
+        let mut merged = Content::default();
+        merged.merge(content.clone()).unwrap();
+        let err = merged.merge(content).unwrap_err();
+        assert_eq!(err.to_string(), "tsig section is defined more than once");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_lint_section() {
+        let yaml = "lint:\n  rules:\n    mx-target: warning\n    min-ttl:\n      value: 300\n";
+        let content = parse_config(yaml, InputFormat::Yaml).unwrap();
+        let lint = content.lint.as_ref().unwrap();
+        assert_eq!(
+            lint.rules["mx-target"],
+            crate::lint::RuleConfig::Level(crate::lint::Level::Warning)
+        );
+        // fmt keeps the section
+        let formatted = format_config(&content, InputFormat::Yaml).unwrap();
+        let again = parse_config(&formatted, InputFormat::Yaml).unwrap();
+        assert_eq!(again.lint, content.lint);
+
+        let mut merged = Content::default();
+        merged.merge(content.clone()).unwrap();
+        let err = merged.merge(content).unwrap_err();
+        assert_eq!(err.to_string(), "lint section is defined more than once");
+    }
 }
@@ -0,0 +1,25 @@
+//! Commonly used types and functions in one place:
+//!
+//! ```
+//! use zonefile_rs::prelude::*;
+//! ```
+
+pub use crate::args::{InputFormat, OutputFormat};
+pub use crate::diff::{diff, ZoneDiff};
+pub use crate::error::ParseError;
+#[cfg(feature = "nsd")]
+pub use crate::output::nsd::generate_nsd;
+#[cfg(feature = "unbound")]
+pub use crate::output::unbound::{generate_unbound, write_unbound};
+pub use crate::output::OutputFile;
+#[cfg(feature = "fs")]
+pub use crate::parser::parse_dir;
+pub use crate::parser::{
+    format_config, parse, parse_config, resolve, Content, ForwardZone, ReverseZone, ZoneBase,
+    ZoneSet,
+};
+pub use crate::record::{
+    ARecord, CnameRecord, MxRecord, NsRecord, PtrRecord, Record, SrvRecord, TxtRecord,
+};
+#[cfg(feature = "source")]
+pub use crate::source::{fetch_all, Source};
@@ -1,42 +1,59 @@
 use std::net::IpAddr;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ARecord {
     pub name: String,
     pub ip: IpAddr,
     pub ttl: u32,
+    /// Written next to the record in the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct PtrRecord {
     pub name: String,
     pub ip: IpAddr,
     pub ttl: u32,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct NsRecord {
     pub name: String,
     pub ttl: u32,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// NS record handing the child zone `name` over to the nameserver `target`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DelegationRecord {
+    pub name: String,
+    pub target: String,
+    pub ttl: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct MxRecord {
     pub name: String,
     pub ttl: u32,
     pub prio: u16,
+    /// Written next to the record in the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct CnameRecord {
     pub name: String,
     pub target: String,
     pub ttl: u32,
+    /// Written next to the record in the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct SrvRecord {
     pub name: String,
     pub target: String,
@@ -44,4 +61,141 @@ pub struct SrvRecord {
     pub prio: u16,
     pub weight: u16,
     pub port: u16,
+    /// Written next to the record in the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TxtRecord {
+    pub name: String,
+    /// Text as written in the config, without quotes or escapes
+    pub text: String,
+    pub ttl: u32,
+}
+
+/// Longest character string of a TXT record, in octets
+const MAX_STRING_LEN: usize = 255;
+
+impl TxtRecord {
+    /// Character strings of at most 255 octets the text is split into, like
+    /// a DKIM key, without splitting a character
+    pub fn strings(&self) -> Vec<&str> {
+        let mut strings = Vec::new();
+        let mut rest = self.text.as_str();
+        while rest.len() > MAX_STRING_LEN {
+            let mut end = MAX_STRING_LEN;
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (string, tail) = rest.split_at(end);
+            strings.push(string);
+            rest = tail;
+        }
+        strings.push(rest);
+        strings
+    }
+
+    /// Text in zone file syntax: quoted character strings with `"` and `\`
+    /// escaped and control characters as `\DDD`
+    pub fn data(&self) -> String {
+        let strings: Vec<String> = self
+            .strings()
+            .into_iter()
+            .map(|string| {
+                let mut data = String::from('"');
+                for c in string.chars() {
+                    match c {
+                        '"' | '\\' => {
+                            data.push('\\');
+                            data.push(c);
+                        }
+                        c if c.is_ascii_control() => data.push_str(&format!("\\{:03}", c as u8)),
+                        c => data.push(c),
+                    }
+                }
+                data.push('"');
+                data
+            })
+            .collect();
+        strings.join(" ")
+    }
+}
+
+/// Any resolved record, borrowed from its zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Record<'a> {
+    Ns(&'a NsRecord),
+    /// NS record of a child zone
+    Delegation(&'a DelegationRecord),
+    Mx(&'a MxRecord),
+    /// A or AAAA record, depending on the address
+    Address(&'a ARecord),
+    Cname(&'a CnameRecord),
+    Srv(&'a SrvRecord),
+    Txt(&'a TxtRecord),
+    Ptr(&'a PtrRecord),
+}
+
+impl Record<'_> {
+    pub fn record_type(&self) -> &'static str {
+        match self {
+            Record::Ns(_) | Record::Delegation(_) => "NS",
+            Record::Mx(_) => "MX",
+            Record::Address(a) if a.ip.is_ipv4() => "A",
+            Record::Address(_) => "AAAA",
+            Record::Cname(_) => "CNAME",
+            Record::Srv(_) => "SRV",
+            Record::Txt(_) => "TXT",
+            Record::Ptr(_) => "PTR",
+        }
+    }
+
+    pub fn ttl(&self) -> u32 {
+        match self {
+            Record::Ns(r) => r.ttl,
+            Record::Delegation(r) => r.ttl,
+            Record::Mx(r) => r.ttl,
+            Record::Address(r) => r.ttl,
+            Record::Cname(r) => r.ttl,
+            Record::Srv(r) => r.ttl,
+            Record::Txt(r) => r.ttl,
+            Record::Ptr(r) => r.ttl,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn txt(text: &str) -> TxtRecord {
+        TxtRecord {
+            name: "example.com.".to_string(),
+            text: text.to_string(),
+            ttl: 3600,
+        }
+    }
+
+    #[test]
+    fn test_txt_data() {
+        assert_eq!(txt("v=spf1 mx -all").data(), r#""v=spf1 mx -all""#);
+        assert_eq!(txt(r#"say "hi" \ bye"#).data(), r#""say \"hi\" \\ bye""#);
+        assert_eq!(txt("tab\there").data(), r#""tab\009here""#);
+        assert_eq!(txt("").data(), r#""""#);
+
+        let key = "k".repeat(600);
+        assert_eq!(
+            txt(&key).data(),
+            format!(
+                r#""{}" "{}" "{}""#,
+                "k".repeat(255),
+                "k".repeat(255),
+                "k".repeat(90)
+            )
+        );
+        // Characters stay whole
+        let record = txt(&format!("{}ü", "a".repeat(254)));
+        assert_eq!(record.strings(), ["a".repeat(254), "ü".to_string()]);
+    }
 }
@@ -0,0 +1,898 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::constants::{
+    DEFAULT_EXPIRE, DEFAULT_MX_PRIO, DEFAULT_NRC_TTL, DEFAULT_REFRESH, DEFAULT_RETRY,
+    DEFAULT_SRV_PRIO, DEFAULT_SRV_WEIGHT, DEFAULT_TTL, DEFAULT_WITH_PTR,
+};
+use crate::parser::{
+    AliasMode, Content, ForwardZone, PtrPolicy, RawDefaults, ReverseZone, ZoneBase, ZoneBaseEntry,
+};
+use crate::transform::{
+    apply_groups, create_reverse_zone_name, inherit_zones, ip_name, reverse_zone_networks,
+};
+use crate::vars::expand_vars;
+
+/// A resolved record flattened into printable columns
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordRow {
+    pub zone: String,
+    pub name: String,
+    pub record_type: &'static str,
+    pub ttl: u32,
+    pub data: String,
+}
+
+fn base_rows(base: &ZoneBase) -> Vec<RecordRow> {
+    let row = |record_type, ttl, data| RecordRow {
+        zone: base.name.clone(),
+        name: base.name.clone(),
+        record_type,
+        ttl,
+        data,
+    };
+    let soa = format!(
+        "{} {} {} {} {} {} {}",
+        base.nameserver
+            .first()
+            .map(|ns| ns.name.as_str())
+            .unwrap_or("-"),
+        base.email,
+        base.serial,
+        base.refresh,
+        base.retry,
+        base.expire,
+        base.nrc_ttl
+    );
+
+    let mut rows = vec![row("SOA", base.ttl, soa)];
+    for ns in &base.nameserver {
+        rows.push(row("NS", ns.ttl, ns.name.clone()));
+    }
+    rows
+}
+
+/// Flattens all resolved records of all zones, SOA and NS first
+pub fn record_rows(forward: &[ForwardZone], reverse: &[ReverseZone]) -> Vec<RecordRow> {
+    let mut rows = Vec::new();
+
+    for zone in forward {
+        let zone_name = &zone.base.name;
+        let row = |name: &str, record_type, ttl, data| RecordRow {
+            zone: zone_name.clone(),
+            name: name.to_string(),
+            record_type,
+            ttl,
+            data,
+        };
+        rows.extend(base_rows(&zone.base));
+        for ns in &zone.delegation {
+            rows.push(row(&ns.name, "NS", ns.ttl, ns.target.clone()));
+        }
+        for mx in &zone.mx {
+            rows.push(row(
+                zone_name,
+                "MX",
+                mx.ttl,
+                format!("{} {}", mx.prio, mx.name),
+            ));
+        }
+        for host in &zone.hosts {
+            let record_type = if host.ip.is_ipv4() { "A" } else { "AAAA" };
+            rows.push(row(&host.name, record_type, host.ttl, host.ip.to_string()));
+        }
+        for cname in &zone.cname {
+            rows.push(row(&cname.name, "CNAME", cname.ttl, cname.target.clone()));
+        }
+        for srv in &zone.srv {
+            let data = format!("{} {} {} {}", srv.prio, srv.weight, srv.port, srv.target);
+            rows.push(row(&srv.name, "SRV", srv.ttl, data));
+        }
+        for txt in &zone.txt {
+            rows.push(row(&txt.name, "TXT", txt.ttl, txt.data()));
+        }
+    }
+
+    for zone in reverse {
+        rows.extend(base_rows(&zone.base));
+        for ns in &zone.delegation {
+            rows.push(RecordRow {
+                zone: zone.base.name.clone(),
+                name: ns.name.clone(),
+                record_type: "NS",
+                ttl: ns.ttl,
+                data: ns.target.clone(),
+            });
+        }
+        for ptr in &zone.ptr {
+            rows.push(RecordRow {
+                zone: zone.base.name.clone(),
+                name: format!("{}.{}", ip_name(&ptr.ip, zone.split), zone.base.name),
+                record_type: "PTR",
+                ttl: ptr.ttl,
+                data: ptr.name.clone(),
+            });
+        }
+    }
+
+    rows
+}
+
+/// Formats records as a table with name, type, TTL and data columns
+pub fn dump_records(rows: &[RecordRow]) -> String {
+    let width = rows.iter().map(|r| r.name.len()).max().unwrap_or(0).max(4);
+
+    let mut output = String::new();
+    writeln!(output, "{:width$}  TYPE   TTL         DATA", "NAME").unwrap();
+    for row in rows {
+        writeln!(
+            output,
+            "{:width$}  {:<5}  {:<10}  {}",
+            row.name, row.record_type, row.ttl, row.data
+        )
+        .unwrap();
+    }
+    output
+}
+
+/// Selects all records owned by a name plus the PTR records pointing to it
+pub fn query_records(rows: Vec<RecordRow>, name: &str) -> Vec<RecordRow> {
+    rows.into_iter()
+        .filter(|r| {
+            name_matches(&r.name, name) || (r.record_type == "PTR" && name_matches(&r.data, name))
+        })
+        .collect()
+}
+
+/// Checks whether a name matches, ignoring case and a missing trailing dot
+pub fn name_matches(name: &str, pattern: &str) -> bool {
+    name.trim_end_matches('.')
+        .eq_ignore_ascii_case(pattern.trim_end_matches('.'))
+}
+
+/// Formats an overview of all zones with serial, TTL and record counts per type,
+/// with DELEG counting the NS records delegating child zones.
+/// Types a zone kind cannot hold are shown as "-": the PTR records generated
+/// from the hosts of a forward zone are counted in their reverse zones.
+pub fn list_zones(forward: &[ForwardZone], reverse: &[ReverseZone]) -> String {
+    let width = forward
+        .iter()
+        .map(|z| z.base.name.len())
+        .chain(reverse.iter().map(|z| z.base.name.len()))
+        .max()
+        .unwrap_or(0)
+        .max(4);
+
+    let mut output = String::new();
+    writeln!(
+        output,
+        "{:width$}  KIND     SERIAL      TTL         NS DELEG    MX     A  AAAA CNAME   SRV   TXT   PTR",
+        "ZONE"
+    )
+    .unwrap();
+
+    for zone in forward {
+        let a = zone.hosts.iter().filter(|h| h.ip.is_ipv4()).count();
+        let aaaa = zone.hosts.len() - a;
+        writeln!(
+            output,
+            "{:width$}  forward  {:<10}  {:<10} {:>3} {:>5} {:>5} {:>5} {:>5} {:>5} {:>5} {:>5} {:>5}",
+            zone.base.name,
+            zone.base.serial,
+            zone.base.ttl,
+            zone.base.nameserver.len(),
+            zone.delegation.len(),
+            zone.mx.len(),
+            a,
+            aaaa,
+            zone.cname.len(),
+            zone.srv.len(),
+            zone.txt.len(),
+            "-",
+        )
+        .unwrap();
+    }
+
+    for zone in reverse {
+        writeln!(
+            output,
+            "{:width$}  reverse  {:<10}  {:<10} {:>3} {:>5} {:>5} {:>5} {:>5} {:>5} {:>5} {:>5} {:>5}",
+            zone.base.name,
+            zone.base.serial,
+            zone.base.ttl,
+            zone.base.nameserver.len(),
+            zone.delegation.len(),
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            zone.ptr.len(),
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+/// Where an effective zone setting comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Zone,
+    Defaults,
+    BuiltIn,
+    Generated,
+    /// The zone it inherits from
+    Inherited,
+    /// The group of the zone
+    Group,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Zone => "zone",
+            Source::Defaults => "defaults",
+            Source::BuiltIn => "built-in",
+            Source::Generated => "generated",
+            Source::Inherited => "inherited",
+            Source::Group => "group",
+        }
+    }
+}
+
+/// An effective zone setting with its origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explained {
+    pub key: &'static str,
+    pub value: String,
+    pub source: Source,
+}
+
+/// Source of a setting the zone lacks but its group has
+fn from_group(grouped: bool, source: Source) -> Source {
+    match source {
+        Source::Zone => Source::Zone,
+        _ if grouped => Source::Group,
+        source => source,
+    }
+}
+
+fn source_of<T: PartialEq>(zone: bool, defaults: Option<&T>, built_in: T) -> Source {
+    match (zone, defaults) {
+        (true, _) => Source::Zone,
+        (false, Some(value)) if *value != built_in => Source::Defaults,
+        _ => Source::BuiltIn,
+    }
+}
+
+/// Settings of a zone base. `grouped` is the raw entry with the settings of
+/// its group, `effective` the grouped one after inheritance: values one has
+/// and the one before lacks come from the group or the inherited zone.
+fn explain_base(
+    base: &ZoneBase,
+    raw: &ZoneBaseEntry,
+    grouped: &ZoneBaseEntry,
+    effective: &ZoneBaseEntry,
+    defaults: Option<&RawDefaults>,
+) -> Vec<Explained> {
+    let item = |key, value: String, source| Explained { key, value, source };
+    let inherited = |grouped: bool, inherited: bool, source| match (source, grouped, inherited) {
+        (Source::Zone, _, _) => Source::Zone,
+        (_, true, _) => Source::Group,
+        (_, false, true) => Source::Inherited,
+        (source, false, false) => source,
+    };
+    let optional = |zone: bool, in_defaults: bool, fallback| match (zone, in_defaults) {
+        (true, _) => Source::Zone,
+        (false, true) => Source::Defaults,
+        _ => fallback,
+    };
+    let nameserver = base
+        .nameserver
+        .iter()
+        .map(|ns| format!("{} (ttl {})", ns.name, ns.ttl))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    vec![
+        item(
+            "serial",
+            base.serial.to_string(),
+            from_group(
+                grouped.serial.is_some(),
+                optional(
+                    raw.serial.is_some(),
+                    defaults.is_some_and(|d| d.serial.is_some()),
+                    Source::Generated,
+                ),
+            ),
+        ),
+        item(
+            "ttl",
+            base.ttl.to_string(),
+            from_group(
+                grouped.ttl.is_some(),
+                source_of(raw.ttl.is_some(), defaults.map(|d| &d.ttl.0), DEFAULT_TTL),
+            ),
+        ),
+        item(
+            "refresh",
+            base.refresh.to_string(),
+            inherited(
+                grouped.refresh.is_some(),
+                effective.refresh.is_some(),
+                source_of(
+                    raw.refresh.is_some(),
+                    defaults.map(|d| &d.refresh),
+                    DEFAULT_REFRESH,
+                ),
+            ),
+        ),
+        item(
+            "retry",
+            base.retry.to_string(),
+            inherited(
+                grouped.retry.is_some(),
+                effective.retry.is_some(),
+                source_of(
+                    raw.retry.is_some(),
+                    defaults.map(|d| &d.retry),
+                    DEFAULT_RETRY,
+                ),
+            ),
+        ),
+        item(
+            "expire",
+            base.expire.to_string(),
+            inherited(
+                grouped.expire.is_some(),
+                effective.expire.is_some(),
+                source_of(
+                    raw.expire.is_some(),
+                    defaults.map(|d| &d.expire),
+                    DEFAULT_EXPIRE,
+                ),
+            ),
+        ),
+        item(
+            "nrc-ttl",
+            base.nrc_ttl.to_string(),
+            inherited(
+                grouped.nrc_ttl.is_some(),
+                effective.nrc_ttl.is_some(),
+                source_of(
+                    raw.nrc_ttl.is_some(),
+                    defaults.map(|d| &d.nrc_ttl),
+                    DEFAULT_NRC_TTL,
+                ),
+            ),
+        ),
+        item(
+            "email",
+            base.email.clone(),
+            inherited(
+                grouped.email.is_some(),
+                effective.email.is_some(),
+                optional(raw.email.is_some(), true, Source::Defaults),
+            ),
+        ),
+        item(
+            "nameserver",
+            nameserver,
+            inherited(
+                grouped.nameserver.is_some(),
+                effective.nameserver.is_some(),
+                optional(raw.nameserver.is_some(), true, Source::Defaults),
+            ),
+        ),
+    ]
+}
+
+/// Lists the SOA, TTL, NS and MX values a zone uses and where each comes from:
+/// the zone itself, its group, the zone it inherits from, the `defaults`
+/// section, a built-in constant or the generated serial. Returns `None` if no
+/// zone of that name exists.
+pub fn explain_zone(
+    mut config: Content,
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+    name: &str,
+) -> Option<Vec<Explained>> {
+    // The config resolved already, so expanding and inheriting do not fail
+    expand_vars(&mut config).ok()?;
+    let defaults = config.defaults.as_ref();
+
+    if let Some(zone) = forward.iter().find(|z| name_matches(&z.base.name, name)) {
+        let mut zones = config
+            .zone
+            .map(|zones| zones.into_vec())
+            .unwrap_or_default();
+        let raw = zones.iter().find(|z| name_matches(&z.name, name))?.clone();
+        apply_groups(&mut zones, &config.groups.clone().unwrap_or_default()).ok()?;
+        let grouped = zones.iter().find(|z| name_matches(&z.name, name))?.clone();
+        inherit_zones(&mut zones).ok()?;
+        let effective = zones.into_iter().find(|z| name_matches(&z.name, name))?;
+
+        let mut items = explain_base(
+            &zone.base,
+            &raw.base,
+            &grouped.base,
+            &effective.base,
+            defaults,
+        );
+        let mx = zone
+            .mx
+            .iter()
+            .map(|mx| format!("{} {} (ttl {})", mx.prio, mx.name, mx.ttl))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mx_source = match (raw.mx.is_some(), defaults.is_some_and(|d| d.mx.is_some())) {
+            (true, _) => Source::Zone,
+            (false, _) if grouped.mx.is_some() => Source::Group,
+            (false, true) => Source::Defaults,
+            _ => Source::BuiltIn,
+        };
+        items.push(Explained {
+            key: "mx",
+            value: mx,
+            source: mx_source,
+        });
+        items.push(Explained {
+            key: "mx-prio",
+            value: grouped
+                .mx_prio
+                .or(defaults.map(|d| d.mx_prio))
+                .unwrap_or(DEFAULT_MX_PRIO)
+                .to_string(),
+            source: from_group(
+                grouped.mx_prio.is_some(),
+                source_of(
+                    raw.mx_prio.is_some(),
+                    defaults.map(|d| &d.mx_prio),
+                    DEFAULT_MX_PRIO,
+                ),
+            ),
+        });
+        items.push(Explained {
+            key: "srv-prio",
+            value: grouped
+                .srv_prio
+                .or(defaults.map(|d| d.srv_prio))
+                .unwrap_or(DEFAULT_SRV_PRIO)
+                .to_string(),
+            source: from_group(
+                grouped.srv_prio.is_some(),
+                source_of(
+                    raw.srv_prio.is_some(),
+                    defaults.map(|d| &d.srv_prio),
+                    DEFAULT_SRV_PRIO,
+                ),
+            ),
+        });
+        items.push(Explained {
+            key: "srv-weight",
+            value: grouped
+                .srv_weight
+                .or(defaults.map(|d| d.srv_weight))
+                .unwrap_or(DEFAULT_SRV_WEIGHT)
+                .to_string(),
+            source: from_group(
+                grouped.srv_weight.is_some(),
+                source_of(
+                    raw.srv_weight.is_some(),
+                    defaults.map(|d| &d.srv_weight),
+                    DEFAULT_SRV_WEIGHT,
+                ),
+            ),
+        });
+        items.push(Explained {
+            key: "with-ptr",
+            value: grouped
+                .with_ptr
+                .or(defaults.map(|d| d.with_ptr))
+                .unwrap_or(DEFAULT_WITH_PTR)
+                .to_string(),
+            source: from_group(
+                grouped.with_ptr.is_some(),
+                source_of(
+                    raw.with_ptr.is_some(),
+                    defaults.map(|d| &d.with_ptr),
+                    DEFAULT_WITH_PTR,
+                ),
+            ),
+        });
+        items.push(Explained {
+            key: "ptr-policy",
+            value: grouped
+                .ptr_policy
+                .or(defaults.map(|d| d.ptr_policy))
+                .unwrap_or_default()
+                .to_string(),
+            source: from_group(
+                grouped.ptr_policy.is_some(),
+                source_of(
+                    raw.ptr_policy.is_some(),
+                    defaults.map(|d| &d.ptr_policy),
+                    PtrPolicy::default(),
+                ),
+            ),
+        });
+        items.push(Explained {
+            key: "alias-mode",
+            value: grouped
+                .alias_mode
+                .or(defaults.map(|d| d.alias_mode))
+                .unwrap_or_default()
+                .to_string(),
+            source: from_group(
+                grouped.alias_mode.is_some(),
+                source_of(
+                    raw.alias_mode.is_some(),
+                    defaults.map(|d| &d.alias_mode),
+                    AliasMode::default(),
+                ),
+            ),
+        });
+        let bounds = [
+            ("ttl-min", &raw.ttl_min, &grouped.ttl_min),
+            ("ttl-max", &raw.ttl_max, &grouped.ttl_max),
+        ];
+        for (key, own, ttl) in bounds {
+            if let Some(ttl) = ttl {
+                items.push(Explained {
+                    key,
+                    value: ttl.0.to_string(),
+                    source: if own.is_some() {
+                        Source::Zone
+                    } else {
+                        Source::Group
+                    },
+                });
+            }
+        }
+        return Some(items);
+    }
+
+    let zone = reverse.iter().find(|z| name_matches(&z.base.name, name))?;
+    let entry = config
+        .reverse
+        .map(|r| r.into_entries())
+        .unwrap_or_default()
+        .into_iter()
+        .find(|(net, _)| {
+            reverse_zone_networks(net)
+                .iter()
+                .any(|net| name_matches(&create_reverse_zone_name(net).0, name))
+        })?
+        .1;
+    Some(explain_base(
+        &zone.base,
+        &entry.base,
+        &entry.base,
+        &entry.base,
+        defaults,
+    ))
+}
+
+/// Formats explained settings as a table with key, source and value columns
+pub fn format_explained(items: &[Explained]) -> String {
+    let mut output = String::new();
+    writeln!(output, "{:<10}  {:<9}  VALUE", "KEY", "SOURCE").unwrap();
+    for item in items {
+        writeln!(
+            output,
+            "{:<10}  {:<9}  {}",
+            item.key,
+            item.source.as_str(),
+            item.value
+        )
+        .unwrap();
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parser::{NsdOptions, ZoneBase};
+    use crate::record::{
+        ARecord, CnameRecord, DelegationRecord, MxRecord, NsRecord, PtrRecord, TxtRecord,
+    };
+
+    fn base(name: &str) -> ZoneBase {
+        ZoneBase {
+            serial: 2025012500,
+            name: name.to_string(),
+            email: "admin.example.com.".to_string(),
+            expire: 1209600,
+            nameserver: vec![NsRecord {
+                name: "ns1.example.com.".to_string(),
+                ttl: 10800,
+            }],
+            nrc_ttl: 3600,
+            refresh: 7200,
+            retry: 3600,
+            ttl: 10800,
+            nsd: NsdOptions::default(),
+        }
+    }
+
+    fn zones() -> (Vec<ForwardZone>, Vec<ReverseZone>) {
+        let forward = vec![ForwardZone {
+            base: base("example.com."),
+            delegation: vec![],
+            mx: vec![MxRecord {
+                name: "mail.example.com.".to_string(),
+                ttl: 10800,
+                prio: 10,
+                comment: None,
+            }],
+            hosts: vec![ARecord {
+                name: "www.example.com.".to_string(),
+                ip: "192.168.1.2".parse().unwrap(),
+                ttl: 3600,
+                comment: None,
+            }],
+            cname: vec![CnameRecord {
+                name: "web.example.com.".to_string(),
+                target: "www.example.com.".to_string(),
+                ttl: 10800,
+                comment: None,
+            }],
+            srv: vec![],
+            txt: vec![],
+            ptr: vec![],
+            public: false,
+            origins: HashMap::new(),
+        }];
+        let reverse = vec![ReverseZone {
+            base: base("1.168.192.in-addr.arpa."),
+            ptr: vec![PtrRecord {
+                name: "www.example.com.".to_string(),
+                ip: "192.168.1.2".parse().unwrap(),
+                ttl: 3600,
+            }],
+            split: 1,
+            delegation: vec![],
+            origins: HashMap::new(),
+        }];
+        (forward, reverse)
+    }
+
+    #[test]
+    fn test_record_rows() {
+        let (forward, reverse) = zones();
+        let rows = record_rows(&forward, &reverse);
+        let summary: Vec<String> = rows
+            .iter()
+            .map(|r| format!("{} {} {} {}", r.name, r.record_type, r.ttl, r.data))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "example.com. SOA 10800 ns1.example.com. admin.example.com. 2025012500 7200 3600 1209600 3600",
+                "example.com. NS 10800 ns1.example.com.",
+                "example.com. MX 10800 10 mail.example.com.",
+                "www.example.com. A 3600 192.168.1.2",
+                "web.example.com. CNAME 10800 www.example.com.",
+                "1.168.192.in-addr.arpa. SOA 10800 ns1.example.com. admin.example.com. 2025012500 7200 3600 1209600 3600",
+                "1.168.192.in-addr.arpa. NS 10800 ns1.example.com.",
+                "2.1.168.192.in-addr.arpa. PTR 3600 www.example.com.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dump_records() {
+        let (forward, reverse) = zones();
+        let rows: Vec<RecordRow> = record_rows(&forward, &reverse)
+            .into_iter()
+            .filter(|r| r.record_type == "A")
+            .collect();
+        assert_eq!(
+            dump_records(&rows),
+            "NAME              TYPE   TTL         DATA\nwww.example.com.  A      3600        192.168.1.2\n"
+        );
+    }
+
+    #[test]
+    fn test_query_records() {
+        let (forward, reverse) = zones();
+        let rows = query_records(record_rows(&forward, &reverse), "www.example.com");
+        let types: Vec<&str> = rows.iter().map(|r| r.record_type).collect();
+        assert_eq!(types, vec!["A", "PTR"]);
+
+        let rows = query_records(record_rows(&forward, &reverse), "2.1.168.192.in-addr.arpa.");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].data, "www.example.com.");
+    }
+
+    #[test]
+    fn test_name_matches() {
+        assert!(name_matches("example.com.", "example.com"));
+        assert!(name_matches("example.com.", "Example.COM."));
+        assert!(!name_matches("www.example.com.", "example.com"));
+    }
+
+    #[test]
+    fn test_list_zones() {
+        let forward = vec![ForwardZone {
+            base: base("example.com."),
+            delegation: vec![
+                DelegationRecord {
+                    name: "sub.example.com.".to_string(),
+                    target: "ns1.sub.example.com.".to_string(),
+                    ttl: 10800,
+                },
+                DelegationRecord {
+                    name: "sub.example.com.".to_string(),
+                    target: "ns2.sub.example.com.".to_string(),
+                    ttl: 10800,
+                },
+            ],
+            mx: vec![],
+            hosts: vec![
+                ARecord {
+                    name: "www.example.com.".to_string(),
+                    ip: "192.168.1.2".parse().unwrap(),
+                    ttl: 10800,
+                    comment: None,
+                },
+                ARecord {
+                    name: "www.example.com.".to_string(),
+                    ip: "2001:db8::2".parse().unwrap(),
+                    ttl: 10800,
+                    comment: None,
+                },
+            ],
+            cname: vec![],
+            srv: vec![],
+            txt: vec![TxtRecord {
+                name: "example.com.".to_string(),
+                text: "v=spf1 -all".to_string(),
+                ttl: 10800,
+            }],
+            ptr: vec![],
+            public: false,
+            origins: HashMap::new(),
+        }];
+        let reverse = vec![ReverseZone {
+            base: base("1.168.192.in-addr.arpa."),
+            ptr: vec![PtrRecord {
+                name: "www.example.com.".to_string(),
+                ip: "192.168.1.2".parse().unwrap(),
+                ttl: 10800,
+            }],
+            split: 1,
+            delegation: vec![],
+            origins: HashMap::new(),
+        }];
+
+        let output = list_zones(&forward, &reverse);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let columns = |line: &str| line.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert_eq!(
+            columns(lines[1]),
+            "example.com. forward 2025012500 10800 1 2 0 1 1 0 0 1 -"
+        );
+        assert_eq!(
+            columns(lines[2]),
+            "1.168.192.in-addr.arpa. reverse 2025012500 10800 1 0 - - - - - - 1"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_explain_zone() {
+        use crate::args::InputFormat;
+        use crate::parser::{parse_config, resolve};
+
+        let yaml = r#"
+defaults:
+  email: admin@example.com
+  nameserver: ns1.example.com.
+  ttl: 600
+zone:
+  example.com:
+    refresh: 9000
+    mx: mail
+"#;
+        let config = parse_config(yaml, InputFormat::Yaml).unwrap();
+        let zones = resolve(config.clone(), 2025060100).unwrap();
+        let items = explain_zone(config, &zones.forward, &zones.reverse, "example.com").unwrap();
+        let find = |key| items.iter().find(|i| i.key == key).unwrap();
+
+        assert_eq!(find("serial").source, Source::Generated);
+        assert_eq!(find("ttl").value, "600");
+        assert_eq!(find("ttl").source, Source::Defaults);
+        assert_eq!(find("refresh").source, Source::Zone);
+        assert_eq!(find("retry").source, Source::BuiltIn);
+        assert_eq!(find("email").source, Source::Defaults);
+        assert_eq!(find("mx").value, "0 mail.example.com. (ttl 600)");
+        assert_eq!(find("mx").source, Source::Zone);
+        assert_eq!(find("with-ptr").source, Source::BuiltIn);
+        assert_eq!(find("ptr-policy").value, "error");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_explain_inherited() {
+        use crate::args::InputFormat;
+        use crate::parser::{parse_config, resolve};
+
+        let yaml = r#"
+defaults:
+  email: admin@example.com
+  nameserver: ns1.example.com.
+zone:
+  example.com:
+    nameserver: [ns1.example.com., ns2.example.com.]
+    refresh: 9000
+  lab.example.com:
+    inherit: true
+    retry: 900
+"#;
+        let config = parse_config(yaml, InputFormat::Yaml).unwrap();
+        let zones = resolve(config.clone(), 2025060100).unwrap();
+        let items =
+            explain_zone(config, &zones.forward, &zones.reverse, "lab.example.com").unwrap();
+        let find = |key| items.iter().find(|i| i.key == key).unwrap();
+
+        assert_eq!(find("refresh").value, "9000");
+        assert_eq!(find("refresh").source, Source::Inherited);
+        assert_eq!(find("retry").source, Source::Zone);
+        assert_eq!(find("expire").source, Source::BuiltIn);
+        assert_eq!(find("email").source, Source::Defaults);
+        assert_eq!(find("nameserver").source, Source::Inherited);
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_explain_group() {
+        use crate::args::InputFormat;
+        use crate::parser::{parse_config, resolve};
+
+        let yaml = r#"
+defaults:
+  email: admin@example.com
+  nameserver: ns1.example.com.
+groups:
+  lab:
+    refresh: 9000
+    ttl: 300
+    ttl-min: 30
+    mx: mail.lab.example.com.
+zone:
+  example.com:
+    expire: 604800
+  lab.example.com:
+    group: lab
+    inherit: true
+    ttl: 60
+    ttl-max: 86400
+"#;
+        let config = parse_config(yaml, InputFormat::Yaml).unwrap();
+        let zones = resolve(config.clone(), 2025060100).unwrap();
+        let items =
+            explain_zone(config, &zones.forward, &zones.reverse, "lab.example.com").unwrap();
+        let find = |key| items.iter().find(|i| i.key == key).unwrap();
+
+        assert_eq!(find("refresh").value, "9000");
+        assert_eq!(find("refresh").source, Source::Group);
+        assert_eq!(find("ttl").value, "60");
+        assert_eq!(find("ttl").source, Source::Zone);
+        assert_eq!(find("expire").source, Source::Inherited);
+        assert_eq!(find("mx").value, "0 mail.lab.example.com. (ttl 60)");
+        assert_eq!(find("mx").source, Source::Group);
+        assert_eq!(find("retry").source, Source::BuiltIn);
+        assert_eq!(find("ttl-min").source, Source::Group);
+        assert_eq!(find("ttl-max").value, "86400");
+        assert_eq!(find("ttl-max").source, Source::Zone);
+    }
+}
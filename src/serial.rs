@@ -1,32 +1,520 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use chrono::Datelike;
-use chrono::Utc;
-use std::cmp::max;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fs;
+use std::io;
 use std::path::Path;
+use std::process::Command;
 
+use crate::check::serial_cmp;
+use crate::error::ParseError;
+use crate::parser::{ZoneBase, ZoneSet};
+
+/// How the next serial is derived from the previous one
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialStrategy {
+    /// YYYYMMDDnn or another `SerialFormat`
+    #[default]
+    Date,
+    /// Seconds since the epoch
+    Unixtime,
+    /// The previous serial plus one
+    Counter,
+}
+
+/// Layout of date serials, e.g. `YYYYMMDDnn`: `YYYY`/`YY` year, `MM` month,
+/// `DD` day, `DDD` day of the year and a trailing run of `n` sequence digits
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct SerialFormat {
+    layout: String,
+    fields: Vec<DateField>,
+    sequence_digits: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DateField {
+    Year4,
+    Year2,
+    Month,
+    Day,
+    DayOfYear,
+}
+
+impl DateField {
+    fn digits(self) -> u32 {
+        match self {
+            DateField::Year4 => 4,
+            DateField::Year2 | DateField::Month | DateField::Day => 2,
+            DateField::DayOfYear => 3,
+        }
+    }
+
+    fn value(self, date: NaiveDate) -> u32 {
+        match self {
+            DateField::Year4 => date.year() as u32,
+            DateField::Year2 => date.year() as u32 % 100,
+            DateField::Month => date.month(),
+            DateField::Day => date.day(),
+            DateField::DayOfYear => date.ordinal(),
+        }
+    }
+}
+
+impl Default for SerialFormat {
+    fn default() -> Self {
+        "YYYYMMDDnn"
+            .parse()
+            .expect("default serial format is valid")
+    }
+}
+
+impl std::str::FromStr for SerialFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(layout: &str) -> Result<Self> {
+        let mut fields = Vec::new();
+        let mut rest = layout;
+        while !rest.is_empty() && !rest.starts_with('n') {
+            let (field, len) = [
+                ("YYYY", DateField::Year4),
+                ("YY", DateField::Year2),
+                ("MM", DateField::Month),
+                ("DDD", DateField::DayOfYear),
+                ("DD", DateField::Day),
+            ]
+            .into_iter()
+            .find(|(token, _)| rest.starts_with(token))
+            .map(|(token, field)| (field, token.len()))
+            .with_context(|| format!("Unknown field in serial format '{layout}' at '{rest}'"))?;
+            fields.push(field);
+            rest = &rest[len..];
+        }
+        if rest.is_empty() || rest.chars().any(|c| c != 'n') {
+            bail!("Serial format '{layout}' must end with the sequence digits, e.g. 'YYYYMMDDnn'");
+        }
+        let digits = fields.iter().map(|f| f.digits()).sum::<u32>() + rest.len() as u32;
+        if digits > 10 {
+            bail!("Serial format '{layout}' has {digits} digits, a serial has at most 10");
+        }
+        Ok(SerialFormat {
+            layout: layout.to_string(),
+            fields,
+            sequence_digits: rest.len() as u32,
+        })
+    }
+}
+
+impl TryFrom<String> for SerialFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(layout: String) -> Result<Self> {
+        layout.parse()
+    }
+}
+
+impl std::fmt::Display for SerialFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.layout)
+    }
+}
+
+impl SerialFormat {
+    /// Serial of the first generation on `date`
+    fn base(&self, date: NaiveDate) -> u64 {
+        let date = self.fields.iter().fold(0u64, |serial, field| {
+            serial * 10u64.pow(field.digits()) + field.value(date) as u64
+        });
+        date * 10u64.pow(self.sequence_digits)
+    }
+
+    /// Calculates the next serial as if it were generated on `date`. Fails if
+    /// the date does not fit into 32 bits in this layout, e.g. `YYMMDDnnnn`
+    /// after 2042.
+    pub fn next(&self, old_serial: u32, date: NaiveDate) -> Result<u32> {
+        let base = self.base(date);
+        let base = u32::try_from(base).with_context(|| {
+            format!(
+                "Serial {base} of format '{}' does not fit into 32 bits",
+                self.layout
+            )
+        })?;
+        Ok(newer(old_serial, base))
+    }
+}
+
+/// How a serial is derived from the git history of the input
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GitSerial {
+    /// Date serial of the last commit, the sequence counting the commits of that day
+    Date,
+    /// Number of commits
+    Count,
+}
+
+/// State kept in the serial file between runs
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct SerialState {
+    /// Serial generated by the last run
+    pub serial: u32,
+    /// Time of the last run (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<String>,
+    /// Serial and content hash of every zone of the last run
+    #[serde(default)]
+    pub zones: BTreeMap<String, ZoneState>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ZoneState {
+    pub serial: u32,
+    /// Hash of the zone content without the serial
+    pub hash: String,
+    /// Signatures the zone was written with, if it was signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed: Option<SignedState>,
+    /// Tags of the DNSKEY records published for an external signer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imported: Vec<u16>,
+}
+
+/// Validity and keys of the signatures of a zone, so unchanged zones keep
+/// identical signatures until they are due for renewal
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SignedState {
+    /// Start of the validity in seconds since the epoch
+    pub inception: i64,
+    /// End of the validity in seconds since the epoch
+    pub expiration: i64,
+    /// Tags of the signing keys
+    pub keys: Vec<u16>,
+    /// Tags of the keys only published, before or after their signing time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub published: Vec<u16>,
+}
+
+impl SerialState {
+    /// Reads the state file. A missing file is an empty state, a legacy file
+    /// holding only the serial number is migrated.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Cannot read serial file {}", path.display()))
+            }
+        };
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        if let Ok(serial) = content.trim().parse() {
+            return Ok(SerialState {
+                serial,
+                ..Self::default()
+            });
+        }
+        serde_json::from_str(&content).map_err(|e| {
+            ParseError::new(format!("Invalid serial file {}: {e}", path.display())).into()
+        })
+    }
+
+    /// Writes the state to a temporary file that replaces the serial file, so
+    /// readers never see a partially written state
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)? + "\n")
+            .and_then(|()| fs::rename(&tmp, path))
+            .with_context(|| format!("Cannot write serial file {}", path.display()))
+    }
+
+    /// State after generating `zones` at `time`
+    pub fn of(zones: &ZoneSet, time: DateTime<Utc>) -> Result<Self> {
+        let mut state = SerialState {
+            serial: zones.serial,
+            last_run: Some(time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            zones: BTreeMap::new(),
+        };
+        for zone in &zones.forward {
+            state.add(&zone.base, zone)?;
+        }
+        for zone in &zones.reverse {
+            state.add(&zone.base, zone)?;
+        }
+        Ok(state)
+    }
+
+    fn add(&mut self, base: &ZoneBase, zone: &impl Serialize) -> Result<()> {
+        let hash = content_hash(zone)?;
+        let serial = base.serial;
+        self.zones.insert(
+            base.name.clone(),
+            ZoneState {
+                serial,
+                hash,
+                signed: None,
+                imported: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Records the signatures a zone was written with
+    pub fn set_signed(&mut self, zone: &str, signed: SignedState) {
+        if let Some(state) = self.zones.get_mut(zone) {
+            state.signed = Some(signed);
+        }
+    }
+
+    /// Records the tags of the DNSKEY records imported for a zone
+    pub fn set_imported(&mut self, zone: &str, tags: Vec<u16>) {
+        if let Some(state) = self.zones.get_mut(zone) {
+            state.imported = tags;
+        }
+    }
+
+    /// Tags of the DNSKEY records imported for a zone in the last run
+    pub fn imported_keys(&self, zone: &str) -> &[u16] {
+        self.zones
+            .get(zone)
+            .map_or(&[], |state| state.imported.as_slice())
+    }
+
+    /// Signatures of the last run that can be reused: the zone kept its
+    /// serial, was signed by the same `keys` with the same keys `published`
+    /// next to them and the signatures stay valid for at least `min_remaining`
+    /// seconds after `now`
+    pub fn reusable_signatures(
+        &self,
+        zone: &str,
+        serial: u32,
+        keys: &[u16],
+        published: &[u16],
+        now: i64,
+        min_remaining: i64,
+    ) -> Option<&SignedState> {
+        let state = self
+            .zones
+            .get(zone)
+            .filter(|state| state.serial == serial)?;
+        state.signed.as_ref().filter(|signed| {
+            signed.keys == keys
+                && signed.published == published
+                && signed.expiration - now >= min_remaining
+        })
+    }
+
+    /// Takes over the state of `zones` from `previous`, for zones that were
+    /// not generated this time
+    pub fn keep_zones(&mut self, previous: &SerialState, zones: &[String]) {
+        for zone in zones {
+            if let Some(state) = previous.zones.get(zone) {
+                self.zones.insert(zone.clone(), state.clone());
+            }
+        }
+    }
+
+    /// Moves the state ahead to the `live` serial of a zone, e.g. queried from
+    /// the primary after an old serial file was restored, so the next serial
+    /// is newer than the live one. Returns whether the state was behind.
+    pub fn fast_forward(&mut self, zone: &str, live: u32) -> bool {
+        let ahead = |serial| serial_cmp(live, serial) == Some(Ordering::Greater);
+        let mut behind = false;
+        if ahead(self.serial) {
+            self.serial = live;
+            behind = true;
+        }
+        // The zone must not keep a serial the secondaries already consider old
+        if self
+            .zones
+            .get(zone)
+            .is_some_and(|state| ahead(state.serial))
+        {
+            self.zones.remove(zone);
+            behind = true;
+        }
+        behind
+    }
+
+    /// Gives zones with the generated serial their previous serial back if
+    /// their content did not change, so only changed zones get a new serial
+    pub fn keep_unchanged(&self, zones: &mut ZoneSet) -> Result<()> {
+        let generated = zones.serial;
+        let unchanged = |base: &ZoneBase, hash: String| {
+            self.zones
+                .get(&base.name)
+                .filter(|zone| base.serial == generated && zone.hash == hash)
+                .map(|zone| zone.serial)
+        };
+        for zone in &mut zones.forward {
+            if let Some(serial) = unchanged(&zone.base, content_hash(&*zone)?) {
+                zone.base.serial = serial;
+            }
+        }
+        for zone in &mut zones.reverse {
+            if let Some(serial) = unchanged(&zone.base, content_hash(&*zone)?) {
+                zone.base.serial = serial;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a hash of the zone as JSON with the serial left out. It must stay
+/// stable across versions, so std's hashers are no option.
+fn content_hash(zone: &impl Serialize) -> Result<String> {
+    let mut value = serde_json::to_value(zone)?;
+    value["base"]["serial"] = 0.into();
+    let hash = value
+        .to_string()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    Ok(format!("{hash:016x}"))
+}
+
+/// Serial of the last run, 0 if the serial file is missing or unreadable
 pub fn load_serial(path: &Path) -> u32 {
-    fs::read_to_string(path)
-        .ok()
-        .and_then(|s| s.trim().parse().ok())
+    SerialState::load(path)
+        .map(|state| state.serial)
         .unwrap_or(0)
 }
 
-pub fn calc_serial(old_serial: u32) -> u32 {
-    let now = Utc::now();
-    let year = now.year() as u32;
-    let month = now.month();
-    let day = now.day();
+pub fn calc_serial(old_serial: u32, strategy: SerialStrategy) -> u32 {
+    calc_serial_with(old_serial, strategy, &SerialFormat::default(), Utc::now())
+        .expect("YYYYMMDDnn serials fit into 32 bits until 4294")
+}
+
+/// Calculates the next serial with `strategy` as if it were generated at `time`.
+/// Date serials use `format`. The serial always increases, also after
+/// switching strategies.
+pub fn calc_serial_with(
+    old_serial: u32,
+    strategy: SerialStrategy,
+    format: &SerialFormat,
+    time: DateTime<Utc>,
+) -> Result<u32> {
+    Ok(match strategy {
+        SerialStrategy::Date => format.next(old_serial, time.date_naive())?,
+        SerialStrategy::Unixtime => newer(old_serial, time.timestamp() as u32),
+        SerialStrategy::Counter => old_serial.wrapping_add(1),
+    })
+}
+
+/// `candidate` if it is newer than `old_serial` in RFC 1982 serial arithmetic,
+/// else `old_serial + 1`, which wraps past zero after 2^32 - 1. A serial of 0
+/// means there is no previous serial.
+fn newer(old_serial: u32, candidate: u32) -> u32 {
+    if old_serial == 0 || serial_cmp(candidate, old_serial) == Some(Ordering::Greater) {
+        candidate
+    } else {
+        old_serial.wrapping_add(1)
+    }
+}
+
+/// Calculates the next YYYYMMDDnn serial as if it were generated on `date`
+pub fn calc_serial_at(old_serial: u32, date: NaiveDate) -> u32 {
+    SerialFormat::default()
+        .next(old_serial, date)
+        .expect("YYYYMMDDnn serials fit into 32 bits until 4294")
+}
+
+/// Time the serial is based on: midnight of `now` if given, else `SOURCE_DATE_EPOCH`,
+/// else the current time (UTC)
+pub fn reference_time(now: Option<NaiveDate>) -> Result<DateTime<Utc>> {
+    if let Some(now) = now {
+        return Ok(now.and_time(chrono::NaiveTime::MIN).and_utc());
+    }
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch_time(&epoch),
+        Err(_) => Ok(Utc::now()),
+    }
+}
 
-    max(
-        old_serial + 1,
-        year * 1_000_000 + month * 10_000 + day * 100,
-    )
+/// Serial derived from the commits touching `path` (a file or directory), so
+/// identical checkouts produce identical serials without a serial file.
+/// Uncommitted changes are not taken into account.
+pub fn git_serial(path: &Path, mode: GitSerial, format: &SerialFormat) -> Result<u32> {
+    let (dir, pathspec) = if path.is_dir() {
+        (path, Path::new("."))
+    } else {
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+        (
+            dir.unwrap_or(Path::new(".")),
+            path.file_name().map(Path::new).unwrap_or(path),
+        )
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["log", "--format=%ct", "--"])
+        .arg(pathspec)
+        .output()
+        .context("Cannot run git")?;
+    if !output.status.success() {
+        bail!(
+            "git log of {} failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let times: Vec<i64> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect();
+    commit_serial(&times, mode, format)?
+        .with_context(|| format!("{} has no git history", path.display()))
 }
 
-pub fn save_serial(path: &Path, serial: u32) -> Result<()> {
-    fs::write(path, serial.to_string())?;
-    Ok(())
+/// Serial of the newest of the commit times, which are ordered newest first
+fn commit_serial(times: &[i64], mode: GitSerial, format: &SerialFormat) -> Result<Option<u32>> {
+    let Some(latest) = times
+        .first()
+        .and_then(|time| DateTime::from_timestamp(*time, 0))
+    else {
+        return Ok(None);
+    };
+    let latest = latest.date_naive();
+    Ok(match mode {
+        GitSerial::Count => Some(times.len() as u32),
+        GitSerial::Date => {
+            let same_day = times
+                .iter()
+                .filter_map(|time| DateTime::from_timestamp(*time, 0))
+                .filter(|time| time.date_naive() == latest)
+                .count() as u32;
+            // The first commit of the day has sequence 0
+            let sequence = same_day - 1;
+            if u64::from(sequence) >= 10u64.pow(format.sequence_digits) {
+                bail!(
+                    "{same_day} commits on {latest} do not fit into the {} sequence digits of \
+                     serial format '{format}'",
+                    format.sequence_digits
+                );
+            }
+            let serial = format.next(0, latest)?.checked_add(sequence);
+            Some(serial.with_context(|| {
+                format!("Serial of {same_day} commits on {latest} does not fit into 32 bits")
+            })?)
+        }
+    })
+}
+
+fn epoch_time(epoch: &str) -> Result<DateTime<Utc>> {
+    epoch
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .with_context(|| format!("Invalid SOURCE_DATE_EPOCH: {epoch}"))
 }
 
 #[cfg(test)]
@@ -70,17 +558,18 @@ mod tests {
 
     #[test]
     fn test_calc_serial_first_time() {
-        let serial = calc_serial(0);
-        // Serial should be at least YYYYMMDD00
-        assert!(serial >= 2025000000);
-        assert!(serial < 2026000000);
+        let serial = calc_serial(0, SerialStrategy::Date);
+        // Serial should be at least YYYYMMDD00 of the current year
+        let year = Utc::now().year() as u32;
+        assert!(serial >= year * 1_000_000);
+        assert!(serial < (year + 1) * 1_000_000);
     }
 
     #[test]
     fn test_calc_serial_increment() {
         // Test that serial is always incremented when old is less than today
         let old = 2020012301; // Old date
-        let new = calc_serial(old);
+        let new = calc_serial(old, SerialStrategy::Date);
         // Should be at least old + 1, and at least today's date
         assert!(new > old);
         assert!(new >= 2025000000);
@@ -90,7 +579,7 @@ mod tests {
     fn test_calc_serial_date_based() {
         // When old serial is from yesterday, new should be today's date
         let old = 2020010199; // Old date with high sequence
-        let new = calc_serial(old);
+        let new = calc_serial(old, SerialStrategy::Date);
         // New serial should be current date based (YYYYMMDD00)
         assert!(new >= 2025000000);
         assert!(new.is_multiple_of(100)); // Sequence should start at 00
@@ -105,30 +594,269 @@ mod tests {
         let day = now.day();
         let today_base = year * 1_000_000 + month * 10_000 + day * 100;
 
-        let serial1 = calc_serial(today_base + 5);
+        let serial1 = calc_serial(today_base + 5, SerialStrategy::Date);
         assert_eq!(serial1, today_base + 6);
 
-        let serial2 = calc_serial(serial1);
+        let serial2 = calc_serial(serial1, SerialStrategy::Date);
         assert_eq!(serial2, today_base + 7);
     }
 
     #[test]
-    fn test_save_serial() {
-        let file = NamedTempFile::new().unwrap();
+    fn test_calc_serial_at() {
+        let date = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        assert_eq!(calc_serial_at(0, date), 2025060100);
+        assert_eq!(calc_serial_at(2025060100, date), 2025060101);
+        assert_eq!(calc_serial_at(2025070300, date), 2025070301);
+    }
+
+    #[test]
+    fn test_calc_serial_with() {
+        let time = DateTime::from_timestamp(1748736000, 0).unwrap();
+        let next = |old, strategy| {
+            calc_serial_with(old, strategy, &SerialFormat::default(), time).unwrap()
+        };
+        assert_eq!(next(0, SerialStrategy::Date), 2025060100);
+        assert_eq!(next(0, SerialStrategy::Unixtime), 1748736000);
+        assert_eq!(next(1748736000, SerialStrategy::Unixtime), 1748736001);
+        // Switching from date serials keeps counting up
+        assert_eq!(next(2025060100, SerialStrategy::Unixtime), 2025060101);
+        assert_eq!(next(0, SerialStrategy::Counter), 1);
+        assert_eq!(next(2025060100, SerialStrategy::Counter), 2025060101);
+    }
+
+    #[test]
+    fn test_calc_serial_wraps() {
+        let date = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let time = DateTime::from_timestamp(1748736000, 0).unwrap();
+        let format = SerialFormat::default();
+        let next = |old, strategy| calc_serial_with(old, strategy, &format, time).unwrap();
+
+        // Counters wrap past zero instead of overflowing
+        assert_eq!(next(u32::MAX, SerialStrategy::Counter), 0);
+        assert_eq!(next(0, SerialStrategy::Counter), 1);
+        // Far behind a high serial in plain numbers, but newer in serial arithmetic
+        assert_eq!(calc_serial_at(4294967290, date), 2025060100);
+        assert_eq!(next(4000000000, SerialStrategy::Unixtime), 1748736000);
+        // A serial less than 2^31 ahead of the date serial keeps counting
+        assert_eq!(calc_serial_at(4000000000, date), 4000000001);
+        assert_eq!(calc_serial_at(u32::MAX, date), 2025060100);
+        let wrapped = calc_serial_at(2025060099 + (1 << 31), date);
+        assert_eq!(wrapped, 2025060100 + (1 << 31));
+        assert_eq!(
+            serial_cmp(wrapped, 2025060099 + (1 << 31)),
+            Some(Ordering::Greater)
+        );
+    }
+
+    #[test]
+    fn test_serial_format() {
+        let date = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let format: SerialFormat = "YYMMDDnnnn".parse().unwrap();
+        assert_eq!(format.next(0, date).unwrap(), 2506010000);
+        assert_eq!(format.next(2506010000, date).unwrap(), 2506010001);
+        let later = NaiveDate::from_ymd_opt(2043, 1, 1).unwrap();
+        let err = format.next(0, later).unwrap_err();
+        assert!(err.to_string().contains("32 bits"), "{err}");
+        let format: SerialFormat = "YYYYDDDnnn".parse().unwrap();
+        assert_eq!(format.next(0, date).unwrap(), 2025152000);
+        assert_eq!(format.to_string(), "YYYYDDDnnn");
+        assert_eq!(calc_serial_at(0, date), 2025060100);
+
+        let err = "YYYYMMDDnnnn".parse::<SerialFormat>().unwrap_err();
+        assert!(err.to_string().contains("12 digits"), "{err}");
+        let err = "YYYYMMDD".parse::<SerialFormat>().unwrap_err();
+        assert!(err.to_string().contains("sequence digits"), "{err}");
+        let err = "YYYY-MMnn".parse::<SerialFormat>().unwrap_err();
+        assert!(err.to_string().contains("at '-MMnn'"), "{err}");
+    }
+
+    #[test]
+    fn test_commit_serial() {
+        // 2025-06-01 12:00, 2025-06-01 08:00 and 2025-05-31 08:00
+        let times = [1748779200, 1748764800, 1748678400];
+        let serial =
+            |times: &[i64], mode| commit_serial(times, mode, &SerialFormat::default()).unwrap();
+        assert_eq!(serial(&times, GitSerial::Date), Some(2025060101));
+        assert_eq!(serial(&times[1..], GitSerial::Date), Some(2025060100));
+        assert_eq!(serial(&times, GitSerial::Count), Some(3));
+        assert_eq!(serial(&[], GitSerial::Count), None);
+
+        // 101 commits on one day overflow a two digit sequence
+        let busy: Vec<i64> = (0..101).map(|i| 1748779200 - i * 60).collect();
+        assert_eq!(serial(&busy[1..], GitSerial::Date), Some(2025060199));
+        let err = commit_serial(&busy, GitSerial::Date, &SerialFormat::default()).unwrap_err();
+        assert!(err.to_string().contains("2 sequence digits"), "{err}");
+        // In 4294 the sequence of YYYYnnnnnn fits its digits but not 32 bits
+        let format = "YYYYnnnnnn".parse().unwrap();
+        let noon = NaiveDate::from_ymd_opt(4294, 6, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp();
+        let busy = vec![noon; 967_297];
+        let serial = commit_serial(&busy[1..], GitSerial::Date, &format).unwrap();
+        assert_eq!(serial, Some(u32::MAX));
+        let err = commit_serial(&busy, GitSerial::Date, &format).unwrap_err();
+        assert!(err.to_string().contains("32 bits"), "{err}");
+    }
+
+    #[test]
+    fn test_epoch_time() {
+        assert_eq!(
+            epoch_time("1748736000").unwrap().date_naive(),
+            NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+        );
+        assert!(epoch_time("yesterday").is_err());
+    }
 
-        save_serial(file.path(), 2025012301).unwrap();
+    #[test]
+    fn test_reference_time_now() {
+        let now = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        assert_eq!(reference_time(Some(now)).unwrap().timestamp(), 1748736000);
+    }
+
+    #[test]
+    fn test_load_state_legacy() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "2025012301").unwrap();
 
-        let content = fs::read_to_string(file.path()).unwrap();
-        assert_eq!(content, "2025012301");
+        let state = SerialState::load(file.path()).unwrap();
+        assert_eq!(state.serial, 2025012301);
+        assert!(state.zones.is_empty());
+        assert_eq!(
+            SerialState::load(Path::new("/nonexistent/file")).unwrap(),
+            SerialState::default()
+        );
+    }
+
+    #[test]
+    fn test_load_state_invalid() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "not a number").unwrap();
+
+        let err = SerialState::load(file.path()).unwrap_err();
+        assert!(err.is::<ParseError>());
     }
 
     #[test]
     fn test_round_trip() {
         let file = NamedTempFile::new().unwrap();
+        let mut state = SerialState {
+            serial: 2025012301,
+            last_run: Some("2025-01-23T10:00:00Z".to_string()),
+            ..SerialState::default()
+        };
+        state.zones.insert(
+            "example.com.".to_string(),
+            ZoneState {
+                serial: 2025012300,
+                hash: "0123456789abcdef".to_string(),
+                signed: Some(SignedState {
+                    inception: 1737586800,
+                    expiration: 1740182400,
+                    keys: vec![3613],
+                    published: vec![40000],
+                }),
+                imported: vec![20326],
+            },
+        );
 
-        save_serial(file.path(), 2025012301).unwrap();
-        let loaded = load_serial(file.path());
+        state.save(file.path()).unwrap();
+        assert_eq!(SerialState::load(file.path()).unwrap(), state);
+        assert_eq!(load_serial(file.path()), 2025012301);
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        let mut state = SerialState {
+            serial: 2025060100,
+            ..SerialState::default()
+        };
+        for (zone, serial) in [("example.com.", 2025060100), ("example.org.", 2025050100)] {
+            let hash = String::new();
+            state.zones.insert(
+                zone.to_string(),
+                ZoneState {
+                    serial,
+                    hash,
+                    signed: None,
+                    imported: Vec::new(),
+                },
+            );
+        }
+        assert!(!state.fast_forward("example.com.", 2025060100));
+        assert!(state.fast_forward("example.org.", 2025050200));
+        assert_eq!(state.serial, 2025060100);
+        assert!(!state.zones.contains_key("example.org."));
+        assert!(state.fast_forward("example.com.", 2025070105));
+        assert_eq!(state.serial, 2025070105);
+        assert!(state.zones.is_empty());
+    }
 
-        assert_eq!(loaded, 2025012301);
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_keep_unchanged() {
+        use crate::args::InputFormat;
+        use crate::parser::parse;
+
+        let config = |ip: &str| {
+            format!(
+                "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      www: {ip}\n  example.org:\n    hosts:\n      www: 192.0.2.9\n"
+            )
+        };
+        let time = DateTime::from_timestamp(1748736000, 0).unwrap();
+        let zones = parse(&config("192.0.2.1"), 2025060100, InputFormat::Yaml).unwrap();
+        let state = SerialState::of(&zones, time).unwrap();
+        assert_eq!(state.last_run.as_deref(), Some("2025-06-01T00:00:00Z"));
+        assert_eq!(state.zones["example.com."].serial, 2025060100);
+
+        let mut zones = parse(&config("192.0.2.2"), 2025060101, InputFormat::Yaml).unwrap();
+        state.keep_unchanged(&mut zones).unwrap();
+        let serials: Vec<_> = zones.forward.iter().map(|z| z.base.serial).collect();
+        assert_eq!(serials, [2025060101, 2025060100]);
+
+        // Only example.org is generated, example.com keeps its state
+        zones.forward.retain(|z| z.base.name == "example.org.");
+        let mut next = SerialState::of(&zones, time).unwrap();
+        next.keep_zones(&state, &["example.com.".to_string()]);
+        assert_eq!(next.zones, state.zones);
+    }
+
+    #[test]
+    fn test_reusable_signatures() {
+        let mut state = SerialState::default();
+        let hash = String::new();
+        state.zones.insert(
+            "example.com.".to_string(),
+            ZoneState {
+                serial: 2025060100,
+                hash,
+                signed: None,
+                imported: Vec::new(),
+            },
+        );
+        let signed = SignedState {
+            inception: 1748736000,
+            expiration: 1751328000,
+            keys: vec![3613, 40000],
+            published: Vec::new(),
+        };
+        state.set_signed("example.com.", signed.clone());
+        let now = 1749000000;
+        let reuse = |serial, keys: &[u16], published: &[u16], min| {
+            state
+                .reusable_signatures("example.com.", serial, keys, published, now, min)
+                .cloned()
+        };
+        assert_eq!(reuse(2025060100, &[3613, 40000], &[], 86400), Some(signed));
+        // New serial, other keys, another published key, expiring too soon
+        assert_eq!(reuse(2025060101, &[3613, 40000], &[], 86400), None);
+        assert_eq!(reuse(2025060100, &[3613], &[], 86400), None);
+        assert_eq!(reuse(2025060100, &[3613], &[40000], 86400), None);
+        assert_eq!(reuse(2025060100, &[3613, 40000], &[], 30 * 86400), None);
+        assert!(state
+            .reusable_signatures("example.org.", 2025060100, &[3613], &[], now, 0)
+            .is_none());
     }
 }
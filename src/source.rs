@@ -0,0 +1,239 @@
+use std::future::Future;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::pin::Pin;
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::task::JoinSet;
+
+use crate::args::InputFormat;
+use crate::error::reword;
+use crate::parser::{parse_content, Content};
+
+/// Future returned by [`Source::fetch`]
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output = Result<Content>> + Send + 'a>>;
+
+/// An input the raw configuration is fetched from, e.g. a file, an HTTP
+/// endpoint or an inventory API. Sources are fetched concurrently on the
+/// tokio runtime by [`fetch_all`] and merged before the synchronous resolve
+/// step.
+pub trait Source: Send + Sync {
+    /// Name used in error messages
+    fn name(&self) -> String;
+
+    fn fetch(&self) -> FetchFuture<'_>;
+}
+
+/// Config text that is already in memory
+#[derive(Debug, Clone)]
+pub struct TextSource {
+    pub name: String,
+    pub text: String,
+    pub format: InputFormat,
+}
+
+impl Source for TextSource {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn fetch(&self) -> FetchFuture<'_> {
+        Box::pin(async move { parse_content(&self.text, self.format.clone(), &self.name) })
+    }
+}
+
+/// A config file, read without blocking the runtime
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    pub path: PathBuf,
+    pub format: InputFormat,
+}
+
+#[cfg(feature = "fs")]
+impl Source for FileSource {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn fetch(&self) -> FetchFuture<'_> {
+        Box::pin(async move {
+            let raw = tokio::fs::read_to_string(&self.path).await?;
+            parse_content(&raw, self.format.clone(), &self.name())
+        })
+    }
+}
+
+/// Time to connect to the server of an [`HttpSource`]
+#[cfg(feature = "http")]
+pub const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time for the whole request of an [`HttpSource`], body included
+#[cfg(feature = "http")]
+pub const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A config served over HTTP or HTTPS, e.g. by NetBox or a config repository.
+/// A response with an error status fails the fetch, as does a server that
+/// does not answer within the timeouts.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    pub url: String,
+    pub format: InputFormat,
+    pub connect_timeout: Duration,
+    pub timeout: Duration,
+}
+
+#[cfg(feature = "http")]
+impl HttpSource {
+    /// Source with the default timeouts
+    pub fn new(url: impl Into<String>, format: InputFormat) -> Self {
+        HttpSource {
+            url: url.into(),
+            format,
+            connect_timeout: HTTP_CONNECT_TIMEOUT,
+            timeout: HTTP_TIMEOUT,
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl Source for HttpSource {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    fn fetch(&self) -> FetchFuture<'_> {
+        Box::pin(async move {
+            let client = reqwest::Client::builder()
+                .connect_timeout(self.connect_timeout)
+                .timeout(self.timeout)
+                .build()?;
+            let response = client.get(&self.url).send().await?.error_for_status()?;
+            let raw = response.text().await?;
+            parse_content(&raw, self.format.clone(), &self.url)
+        })
+    }
+}
+
+/// Fetches all sources concurrently as tasks on the current tokio runtime
+/// and merges them in the given order, like the files of an input directory.
+/// A zone defined by two sources is an error.
+pub async fn fetch_all(sources: Vec<Box<dyn Source>>) -> Result<Content> {
+    let mut tasks = JoinSet::new();
+    for (index, source) in sources.into_iter().enumerate() {
+        tasks.spawn(async move {
+            let result = source.fetch().await;
+            (index, source.name(), result)
+        });
+    }
+    let mut results = tasks.join_all().await;
+    results.sort_by_key(|(index, _, _)| *index);
+
+    let mut content = Content::default();
+    for (_, name, result) in results {
+        result.and_then(|c| content.merge(c)).map_err(|e| {
+            let msg = format!("{name}: {e}");
+            reword(e, msg)
+        })?;
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+#[cfg(feature = "yaml")]
+mod tests {
+    use super::*;
+    use crate::parser::resolve;
+    #[cfg(feature = "http")]
+    use std::io::{Read, Write};
+    #[cfg(feature = "http")]
+    use std::net::TcpListener;
+
+    fn text(name: &str, text: &str) -> Box<dyn Source> {
+        Box::new(TextSource {
+            name: name.to_string(),
+            text: text.to_string(),
+            format: InputFormat::Yaml,
+        })
+    }
+
+    /// Serves one response on a local port, returns the URL
+    #[cfg(feature = "http")]
+    fn serve_once(status: &str, body: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/zones.yaml", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_merges_in_order() {
+        let defaults = text(
+            "defaults.yaml",
+            "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\n",
+        );
+        let com = text("com.yaml", "zone:\n  example.com: {}\n");
+        let org = text("org.yaml", "zone:\n  example.org: {}\n");
+
+        let content = fetch_all(vec![defaults, com, org]).await.unwrap();
+        let zones = resolve(content, 2025060100).unwrap();
+        let names: Vec<&str> = zones.forward.iter().map(|z| z.base.name.as_str()).collect();
+        assert_eq!(names, ["example.com.", "example.org."]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_names_failing_source() {
+        let com = text("com.yaml", "zone:\n  example.com: {}\n");
+        let again = text("https://example.net/zones", "zone:\n  example.com: {}\n");
+
+        let err = fetch_all(vec![com, again]).await.unwrap_err().to_string();
+        assert!(err.starts_with("https://example.net/zones: "), "{err}");
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "http")]
+    async fn test_http_source() {
+        let defaults = text(
+            "defaults.yaml",
+            "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\n",
+        );
+        let url = serve_once("200 OK", "zone:\n  example.com: {}\n");
+        let remote = Box::new(HttpSource::new(url, InputFormat::Yaml));
+        let content = fetch_all(vec![defaults, remote]).await.unwrap();
+        let zones = resolve(content, 2025060100).unwrap();
+        assert_eq!(zones.forward[0].base.name, "example.com.");
+
+        let url = serve_once("404 Not Found", "");
+        let missing = Box::new(HttpSource::new(url.clone(), InputFormat::Yaml));
+        let err = fetch_all(vec![missing]).await.unwrap_err().to_string();
+        assert!(err.starts_with(&format!("{url}: ")), "{err}");
+        assert!(err.contains("404"), "{err}");
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "http")]
+    async fn test_http_source_times_out() {
+        // Accepts the connection but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/zones.yaml", listener.local_addr().unwrap());
+        let source = HttpSource {
+            timeout: Duration::from_millis(200),
+            ..HttpSource::new(url.clone(), InputFormat::Yaml)
+        };
+        let err = fetch_all(vec![Box::new(source)]).await.unwrap_err();
+        assert!(err.to_string().starts_with(&format!("{url}: ")), "{err}");
+        drop(listener);
+    }
+}
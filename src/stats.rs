@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::net::IpAddr;
+
+use serde::Serialize;
+
+use crate::parser::{ForwardZone, ReverseZone};
+use crate::report::record_rows;
+
+/// Summary of a generation run
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    pub forward_zones: usize,
+    pub reverse_zones: usize,
+    /// Record counts per record type
+    pub records: BTreeMap<String, usize>,
+    /// PTR records placed into a reverse zone
+    pub ptr_matched: usize,
+    /// PTR records outside of every reverse network
+    pub ptr_unmatched: usize,
+    /// Size of the files created or modified and of the output printed to
+    /// stdout; unchanged files are not rewritten and not counted
+    pub bytes_changed: usize,
+}
+
+/// Record count and estimated zone file size of one zone
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ZoneSize {
+    pub zone: String,
+    pub records: usize,
+    /// Size of the records in presentation format, one `name ttl IN type data`
+    /// line each, without alignment
+    pub bytes: usize,
+}
+
+/// Sizes of all zones, forward zones first
+pub fn zone_sizes(forward: &[ForwardZone], reverse: &[ReverseZone]) -> Vec<ZoneSize> {
+    let mut sizes: Vec<ZoneSize> = Vec::new();
+    for row in record_rows(forward, reverse) {
+        let line = format!(
+            "{} {} IN {} {}\n",
+            row.name, row.ttl, row.record_type, row.data
+        );
+        let bytes = line.len();
+        match sizes.last_mut() {
+            Some(size) if size.zone == row.zone => {
+                size.records += 1;
+                size.bytes += bytes;
+            }
+            _ => sizes.push(ZoneSize {
+                zone: row.zone,
+                records: 1,
+                bytes,
+            }),
+        }
+    }
+    sizes
+}
+
+impl Stats {
+    pub fn collect(forward: &[ForwardZone], reverse: &[ReverseZone]) -> Self {
+        let mut records = BTreeMap::new();
+        for row in record_rows(forward, reverse) {
+            *records.entry(row.record_type.to_string()).or_insert(0) += 1;
+        }
+
+        let matched: HashSet<IpAddr> = reverse
+            .iter()
+            .flat_map(|z| z.ptr.iter().map(|p| p.ip))
+            .collect();
+        let ptr_unmatched = forward
+            .iter()
+            .flat_map(|z| &z.ptr)
+            .filter(|p| !matched.contains(&p.ip))
+            .count();
+
+        Stats {
+            forward_zones: forward.len(),
+            reverse_zones: reverse.len(),
+            records,
+            ptr_matched: reverse.iter().map(|z| z.ptr.len()).sum(),
+            ptr_unmatched,
+            bytes_changed: 0,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Stats are always serializable")
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "zones:   {} forward, {} reverse",
+            self.forward_zones, self.reverse_zones
+        )?;
+        let records: Vec<String> = self
+            .records
+            .iter()
+            .map(|(record_type, count)| format!("{record_type} {count}"))
+            .collect();
+        writeln!(f, "records: {}", records.join(", "))?;
+        writeln!(
+            f,
+            "ptr:     {} matched, {} unmatched",
+            self.ptr_matched, self.ptr_unmatched
+        )?;
+        writeln!(f, "bytes:   {} changed", self.bytes_changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parser::{NsdOptions, ZoneBase};
+    use crate::record::{ARecord, NsRecord, PtrRecord};
+
+    fn base(name: &str) -> ZoneBase {
+        ZoneBase {
+            serial: 2025012500,
+            name: name.to_string(),
+            email: "admin.example.com.".to_string(),
+            expire: 1209600,
+            nameserver: vec![NsRecord {
+                name: "ns1.example.com.".to_string(),
+                ttl: 10800,
+            }],
+            nrc_ttl: 3600,
+            refresh: 7200,
+            retry: 3600,
+            ttl: 10800,
+            nsd: NsdOptions::default(),
+        }
+    }
+
+    fn ptr(ip: &str) -> PtrRecord {
+        PtrRecord {
+            name: "www.example.com.".to_string(),
+            ip: ip.parse().unwrap(),
+            ttl: 10800,
+        }
+    }
+
+    #[test]
+    fn test_collect() {
+        let forward = vec![ForwardZone {
+            base: base("example.com."),
+            delegation: vec![],
+            mx: vec![],
+            hosts: vec![ARecord {
+                name: "www.example.com.".to_string(),
+                ip: "192.168.1.2".parse().unwrap(),
+                ttl: 10800,
+                comment: None,
+            }],
+            cname: vec![],
+            srv: vec![],
+            txt: vec![],
+            ptr: vec![ptr("192.168.1.2"), ptr("10.0.0.2")],
+            public: false,
+            origins: HashMap::new(),
+        }];
+        // With `ptr-policy: all` an address shared by two hosts gets two PTRs
+        let mut shared = ptr("192.168.1.2");
+        shared.name = "mail.example.com.".to_string();
+        let reverse = vec![ReverseZone {
+            base: base("1.168.192.in-addr.arpa."),
+            ptr: vec![ptr("192.168.1.2"), shared],
+            split: 1,
+            delegation: vec![],
+            origins: HashMap::new(),
+        }];
+
+        let stats = Stats::collect(&forward, &reverse);
+        assert_eq!(stats.forward_zones, 1);
+        assert_eq!(stats.reverse_zones, 1);
+        assert_eq!(stats.records["SOA"], 2);
+        assert_eq!(stats.records["A"], 1);
+        assert_eq!(stats.records["PTR"], 2);
+        assert_eq!(stats.ptr_matched, 2);
+        assert_eq!(stats.ptr_unmatched, 1);
+        assert_eq!(
+            zone_sizes(&forward, &reverse),
+            [
+                ZoneSize {
+                    zone: "example.com.".to_string(),
+                    records: 3,
+                    bytes: 178,
+                },
+                ZoneSize {
+                    zone: "1.168.192.in-addr.arpa.".to_string(),
+                    records: 4,
+                    bytes: 273,
+                },
+            ]
+        );
+        assert_eq!(
+            stats.to_json(),
+            r#"{"forward_zones":1,"reverse_zones":1,"records":{"A":1,"NS":2,"PTR":2,"SOA":2},"ptr_matched":2,"ptr_unmatched":1,"bytes_changed":0}"#
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Tags of zones and hosts, like `tags: [prod, dmz]`, and the `outputs` of
+//! zones and entries, which select the part of a config one output is
+//! generated from. Entries left out are parked as if they were `disabled`,
+//! zones left out are dropped after resolving, so the zones inheriting from
+//! them still can.
+
+use crate::parser::{Backend, Content, HostEntry, HostValue, SingleOrVecValue, StringOrTableValue};
+
+/// Tags an output is limited to and tags it leaves out
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl TagFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Without tags to include, everything not excluded is kept
+    fn keeps(&self, tags: &[&String]) -> bool {
+        let included = self.include.is_empty() || tags.iter().any(|t| self.include.contains(t));
+        included && !tags.iter().any(|t| self.exclude.contains(t))
+    }
+}
+
+fn tags(tags: &Option<SingleOrVecValue<String>>) -> impl Iterator<Item = &String> {
+    tags.iter().flat_map(SingleOrVecValue::iter)
+}
+
+/// Leaves the zones and hosts out that the filter does not keep. Hosts carry
+/// the tags of their zone too. Untagged zones stay, with the hosts the filter
+/// keeps.
+pub fn filter_tags(content: &mut Content, filter: &TagFilter) {
+    if filter.is_empty() || content.zone.is_none() {
+        return;
+    }
+    let mut dropped = Vec::new();
+    for zone in content.zones_mut() {
+        let zone_tags: Vec<&String> = tags(&zone.tags).collect();
+        let zone_kept = zone_tags.is_empty() || filter.keeps(&zone_tags);
+        if !zone_kept {
+            dropped.push(zone.name.clone());
+        }
+        for host in zone.hosts.iter_mut().flat_map(|hosts| hosts.values_mut()) {
+            let kept = zone_kept && {
+                let mut host_tags = zone_tags.clone();
+                if let HostValue::Entry(entry) = host {
+                    host_tags.extend(tags(&entry.tags));
+                }
+                filter.keeps(&host_tags)
+            };
+            if kept {
+                continue;
+            }
+            match host {
+                HostValue::Ip(ip) => {
+                    *host = HostValue::Entry(Box::new(HostEntry {
+                        ip: Some(ip.clone()),
+                        disabled: Some(true),
+                        ..HostEntry::default()
+                    }))
+                }
+                HostValue::Entry(entry) => entry.disabled = Some(true),
+            }
+        }
+    }
+    content.dropped_zones.extend(dropped);
+}
+
+/// Whether `outputs` lets `backend` have the records, all do without them
+fn generated(outputs: &Option<SingleOrVecValue<Backend>>, backend: Backend) -> bool {
+    outputs
+        .as_ref()
+        .is_none_or(|outputs| outputs.iter().any(|b| *b == backend))
+}
+
+/// Leaves the zones, hosts, CNAMEs and SRV entries out whose `outputs` do not
+/// name `backend`, e.g. overrides only the resolver should answer
+pub fn filter_outputs(content: &mut Content, backend: Backend) {
+    if content.zone.is_none() {
+        return;
+    }
+    let mut dropped = Vec::new();
+    for zone in content.zones_mut() {
+        if !generated(&zone.outputs, backend) {
+            dropped.push(zone.name.clone());
+        }
+        for host in zone.hosts.iter_mut().flat_map(|hosts| hosts.values_mut()) {
+            if let HostValue::Entry(entry) = host {
+                if !generated(&entry.outputs, backend) {
+                    entry.disabled = Some(true);
+                }
+            }
+        }
+        for cname in zone.cname.iter_mut().flat_map(|cname| cname.values_mut()) {
+            if let StringOrTableValue::Table(entry) = cname {
+                if !generated(&entry.outputs, backend) {
+                    entry.disabled = Some(true);
+                }
+            }
+        }
+        for entry in zone.srv.iter_mut().flat_map(|srv| srv.0.values_mut()) {
+            if !generated(&entry.outputs, backend) {
+                entry.disabled = Some(true);
+            }
+        }
+    }
+    content.dropped_zones.extend(dropped);
+}
+
+#[cfg(all(test, feature = "yaml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_tags() {
+        use crate::args::InputFormat;
+        use crate::parser::{parse_config, resolve};
+
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.0.1\n      db:\n        ip: 10.0.0.5\n        tags: internal\n      web:\n        ip: 10.0.0.10\n        tags: [prod, dmz]\n    pool:\n      app: [web, db]\n  corp.example.com:\n    tags: internal\n    hosts:\n      git: 10.0.0.20\n  lab.corp.example.com:\n    inherit: corp.example.com\n    hosts:\n      pc: 10.0.0.30\nreverse:\n  - 10.0.0.0/24\n";
+        let generate = |include: &[&str], exclude: &[&str]| {
+            let mut content = parse_config(yaml, InputFormat::Yaml).unwrap();
+            let filter = TagFilter {
+                include: include.iter().map(ToString::to_string).collect(),
+                exclude: exclude.iter().map(ToString::to_string).collect(),
+            };
+            filter_tags(&mut content, &filter);
+            let zones = resolve(content, 2025060100).unwrap();
+            let mut hosts: Vec<String> = zones
+                .forward
+                .iter()
+                .flat_map(|zone| &zone.hosts)
+                .map(|host| format!("{} {}", host.name, host.ip))
+                .collect();
+            hosts.extend(
+                zones.reverse[0]
+                    .ptr
+                    .iter()
+                    .map(|ptr| format!("PTR {}", ptr.ip)),
+            );
+            hosts
+        };
+
+        // Zones inheriting from a zone left out keep what they inherit
+        assert_eq!(
+            generate(&[], &["internal"]),
+            [
+                "ns1.example.com. 10.0.0.1",
+                "web.example.com. 10.0.0.10",
+                "app.example.com. 10.0.0.10",
+                "pc.lab.corp.example.com. 10.0.0.30",
+                "PTR 10.0.0.1",
+                "PTR 10.0.0.10",
+                "PTR 10.0.0.30"
+            ]
+        );
+        assert_eq!(
+            generate(&["prod"], &[]),
+            [
+                "web.example.com. 10.0.0.10",
+                "app.example.com. 10.0.0.10",
+                "PTR 10.0.0.10"
+            ]
+        );
+        assert_eq!(
+            generate(&["internal"], &["dmz"]),
+            [
+                "db.example.com. 10.0.0.5",
+                "app.example.com. 10.0.0.5",
+                "git.corp.example.com. 10.0.0.20",
+                "PTR 10.0.0.5",
+                "PTR 10.0.0.20"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_outputs() {
+        use crate::args::InputFormat;
+        use crate::parser::{parse_config, resolve};
+
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.0.1\n      www:\n        ip: 192.168.1.10\n        outputs: unbound\n    cname:\n      intranet:\n        target: ns1\n        outputs: [unbound]\n    srv:\n      _ldap._tcp:\n        target: ns1\n        port: 389\n        outputs: nsd\n  lan.example.com:\n    outputs: unbound\n    hosts:\n      pc: 10.0.1.1\n";
+        let generate = |backend| {
+            let mut content = parse_config(yaml, InputFormat::Yaml).unwrap();
+            filter_outputs(&mut content, backend);
+            let zones = resolve(content, 2025060100).unwrap();
+            let mut names = Vec::new();
+            for zone in &zones.forward {
+                names.extend(zone.hosts.iter().map(|a| format!("A {}", a.name)));
+                names.extend(zone.cname.iter().map(|c| format!("CNAME {}", c.name)));
+                names.extend(zone.srv.iter().map(|s| format!("SRV {}", s.name)));
+            }
+            names.sort();
+            names
+        };
+        assert_eq!(
+            generate(Backend::Unbound),
+            [
+                "A ns1.example.com.",
+                "A pc.lan.example.com.",
+                "A www.example.com.",
+                "CNAME intranet.example.com."
+            ]
+        );
+        assert_eq!(
+            generate(Backend::Nsd),
+            ["A ns1.example.com.", "SRV _ldap._tcp.example.com."]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use anyhow::{Error, Result};
+
+use crate::args::InputFormat;
+use crate::error::{offset_of, token_span, ParseError};
+use crate::parser::{parse_config, Content};
+use minijinja::syntax::SyntaxConfig;
+use minijinja::Environment;
+
+/// Renders a Jinja-style config template into plain YAML/TOML
+pub fn render(template: &str) -> Result<String> {
+    let mut env = Environment::new();
+    let syntax = SyntaxConfig::builder()
+        .trim_blocks(true)
+        .lstrip_blocks(true)
+        .build()?;
+    env.set_syntax(syntax);
+
+    let rendered = env
+        .add_template("config", template)
+        .and_then(|_| env.get_template("config"))
+        .and_then(|t| t.render(()))
+        .map_err(|e| {
+            let line = e.line().map(|l| l.to_string()).unwrap_or("?".to_string());
+            let detail = e.detail().map(|d| format!(": {d}")).unwrap_or_default();
+            ParseError::new(format!(
+                "Template error:\n. Location: template line {line}\n. Error: {}{detail}",
+                e.kind()
+            ))
+        })?;
+
+    Ok(rendered)
+}
+
+/// Renders a config template and parses the result, naming the template line
+/// of a parse error where it can be found
+pub fn parse_template(template: &str, input_format: InputFormat) -> Result<Content> {
+    let rendered = render(template)?;
+    parse_config(&rendered, input_format).map_err(|e| locate_error(e, template, &rendered))
+}
+
+/// Adds the originating template line to a parse error of the rendered config
+/// and points its snippet at that line. Without a line map, the rendered line
+/// is shown instead.
+pub fn locate_error(err: Error, template: &str, rendered: &str) -> Error {
+    let mut err = match err.downcast::<ParseError>() {
+        Ok(err) => err,
+        Err(err) => return err,
+    };
+    let Some((line, _)) = err.position() else {
+        return err.into();
+    };
+    let template_line = line_map(template, rendered).and_then(|map| map.get(line - 1).copied());
+    match template_line.and_then(|l| Some((l, offset_of(template, l, 1)?))) {
+        Some((template_line, start)) => {
+            let indent = template[start..].len() - template[start..].trim_start().len();
+            err.message = format!(
+                "{}\n. Template line: {template_line} (rendered line {line})",
+                err.message
+            );
+            err.with_source("template", template, token_span(template, start + indent))
+                .into()
+        }
+        None => {
+            let text = rendered.lines().nth(line - 1).unwrap_or("").trim();
+            err.message = format!("{}\n. Rendered line {line}: {text}", err.message);
+            err.into()
+        }
+    }
+}
+
+/// Marks the end of a template line in the output, around its line number.
+/// Both are private use characters that no config contains.
+const MARK_START: char = '\u{e000}';
+const MARK_END: char = '\u{e001}';
+
+/// Template line of every rendered line. The template is rendered again with
+/// a marker at the end of each of its lines, and a rendered line belongs to
+/// the first marker at or after its start, so every line of a loop maps to the
+/// loop body. None if the markers change the output, e.g. when a filter works
+/// on a string they ended up in.
+fn line_map(template: &str, rendered: &str) -> Option<Vec<usize>> {
+    if template.contains([MARK_START, MARK_END]) {
+        return None;
+    }
+    let marked = render(&mark_lines(template)).ok()?;
+
+    let mut text = String::with_capacity(rendered.len());
+    let mut first_marks = Vec::new();
+    for (i, line) in marked.split('\n').enumerate() {
+        if i > 0 {
+            text.push('\n');
+        }
+        let mut first = None;
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            if c == MARK_START {
+                let digits: String = chars.by_ref().take_while(|&c| c != MARK_END).collect();
+                first = first.or(digits.parse::<usize>().ok());
+            } else {
+                text.push(c);
+            }
+        }
+        first_marks.push(first);
+    }
+    if text != rendered {
+        return None;
+    }
+
+    // Output after the last marker comes from the last template line
+    let mut next = template.lines().count();
+    let mut map: Vec<usize> = first_marks
+        .into_iter()
+        .rev()
+        .map(|first| {
+            next = first.unwrap_or(next);
+            next
+        })
+        .collect();
+    map.reverse();
+    Some(map)
+}
+
+/// Inserts a marker before the line break of every template line that does
+/// not end inside a tag. Lines ending with a block or comment tag, or with
+/// whitespace control, get none, as the marker would keep their line break
+/// from being removed.
+fn mark_lines(template: &str) -> String {
+    let mut marked = String::with_capacity(template.len());
+    let mut close = None;
+    for (i, line) in template.split_inclusive('\n').enumerate() {
+        let mut rest = line;
+        while !rest.is_empty() {
+            match close {
+                Some(end) if rest.starts_with(end) => {
+                    close = None;
+                    rest = &rest[2..];
+                }
+                None if rest.starts_with("{{") => {
+                    close = Some("}}");
+                    rest = &rest[2..];
+                }
+                None if rest.starts_with("{%") => {
+                    close = Some("%}");
+                    rest = &rest[2..];
+                }
+                None if rest.starts_with("{#") => {
+                    close = Some("#}");
+                    rest = &rest[2..];
+                }
+                _ => {
+                    let len = rest.chars().next().map_or(1, char::len_utf8);
+                    rest = &rest[len..];
+                }
+            }
+        }
+        let Some(content) = line.strip_suffix('\n') else {
+            marked.push_str(line);
+            continue;
+        };
+        let content_end = content.trim_end();
+        let keeps_break = !["%}", "#}", "-}}"].iter().any(|e| content_end.ends_with(e));
+        marked.push_str(content);
+        if close.is_none() && keeps_break {
+            marked.push(MARK_START);
+            marked.push_str(&(i + 1).to_string());
+            marked.push(MARK_END);
+        }
+        marked.push('\n');
+    }
+    marked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_loop() {
+        let template =
+            "hosts:\n{% for i in range(1, 3) %}\n  node{{ i }}: 10.0.0.{{ i }}\n{% endfor %}\n";
+        let rendered = render(template).unwrap();
+        assert_eq!(rendered, "hosts:\n  node1: 10.0.0.1\n  node2: 10.0.0.2\n");
+    }
+
+    #[test]
+    fn test_render_syntax_error_line() {
+        let template = "a: 1\nb: 2\n{% if %}\n";
+        let err = render(template).unwrap_err().to_string();
+        assert!(err.contains("template line 3"), "{err}");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_locate_error_static_line() {
+        let template =
+            "{% set net = \"10.0.0\" %}\nzone:\n  example.com:\n    hosts:\n      www: 999.1.1.1\n";
+        let err = parse_template(template, InputFormat::Yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("Location: line 4, column 12"),
+            "{err}"
+        );
+        assert!(err.to_string().contains("Template line: 5"), "{err}");
+        // The snippet shows the template line
+        let parse_err = err.downcast_ref::<ParseError>().unwrap();
+        assert_eq!(parse_err.position(), Some((5, 7)));
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_locate_error_generated_line() {
+        let template = "{% set net = \"10.0.0\" %}\nzone:\n  example.com:\n    hosts:\n      www: {{ net }}.999\n";
+        let err = parse_template(template, InputFormat::Yaml).unwrap_err();
+        assert!(err.to_string().contains("Template line: 5"), "{err}");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_locate_error_loop() {
+        let template = "zone:\n  example.com:\n    hosts:\n{% for i in range(1, 4) %}\n      node{{ i }}: 10.0.{{ i * 100 }}.1\n{% endfor %}\n      www: 10.0.0.1\n";
+        let rendered = render(template).unwrap();
+        assert_eq!(
+            line_map(template, &rendered),
+            Some(vec![1, 2, 3, 5, 5, 5, 7])
+        );
+
+        // node3 gets 10.0.300.1 on rendered line 6
+        let err = parse_template(template, InputFormat::Yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("Location: line 6, column 14"),
+            "{err}"
+        );
+        assert!(err.to_string().contains("Template line: 5"), "{err}");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_locate_error_without_line_map() {
+        // The markers would end up in the captured block and change its length
+        let template = "{% set body %}\na\n{% endset %}\nzone:\n  example.com:\n    ttl: {{ body | length }}x\n";
+        let rendered = render(template).unwrap();
+        assert_eq!(line_map(template, &rendered), None);
+
+        let err = parse_template(template, InputFormat::Yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("Rendered line 3: ttl: 2x"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_mark_lines() {
+        let template = "a: {{ x }}\n{% if y %}\nb: {{ z -}}\n{{ w\n}}\n";
+        let mark = |line: usize| format!("{MARK_START}{line}{MARK_END}");
+        assert_eq!(
+            mark_lines(template),
+            format!(
+                "a: {{{{ x }}}}{}\n{{% if y %}}\nb: {{{{ z -}}}}\n{{{{ w\n}}}}{}\n",
+                mark(1),
+                mark(5)
+            )
+        );
+    }
+}
@@ -1,18 +1,22 @@
+use crate::error::Errors;
 use crate::parser::{
-    CnameEntry, ForwardZone, HostValue, MxEntry, NameserverEntry, ReverseValue, ReverseZone,
-    SessionDefaults, TTL, Zone, ZoneBase,
+    AcmeMode, AliasMode, CnameEntry, DelegateEntry, ForwardZone, HostAddress, HostEntry, HostValue,
+    Inherit, MailEntry, MxEntry, NameserverEntry, NsdEntry, NsdOptions, NumberRange, OptionValue,
+    PtrPolicy, RawDefaults, ReverseValue, ReverseZone, SecondaryEntry, SecondaryZone,
+    SessionDefaults, UnboundOption, Zone, ZoneBase, ZoneGroup, TTL,
 };
-use crate::record::{CnameRecord, NsRecord, PtrRecord, SrvRecord};
-use crate::validation::validate_dns_name;
+use crate::record::{CnameRecord, DelegationRecord, NsRecord, PtrRecord, SrvRecord, TxtRecord};
+use crate::validation::{is_wildcard, validate_dns_name};
+use indexmap::IndexMap;
 use ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
-use std::collections::HashMap;
-use std::net::IpAddr;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 use crate::{
     parser::{SingleOrVecValue, StringOrTableValue},
     record::{ARecord, MxRecord},
 };
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 
 pub fn parse_ttl(raw: &Option<TTL>, default: u32) -> u32 {
     match raw {
@@ -21,9 +25,10 @@ pub fn parse_ttl(raw: &Option<TTL>, default: u32) -> u32 {
     }
 }
 
-/// Converts a hostname to FQDN (Fully Qualified Domain Name)
+/// Converts a hostname to a lowercase FQDN (Fully Qualified Domain Name)
 pub fn parse_host_str(name: &str, zone_name: &str) -> Result<String> {
-    let host = name.trim();
+    let host = name.trim().to_ascii_lowercase();
+    let host = host.as_str();
 
     if host.ends_with(".") {
         return Ok(host.to_string());
@@ -60,6 +65,111 @@ pub fn parse_srv_name(name: &str, zone_name: &str) -> Result<String> {
     parse_host_str(srv_name, zone_name)
 }
 
+/// Checks the relations between SOA timers that secondaries rely on and
+/// reports every violation of a zone at once
+pub fn check_soa_timers(zone_name: &str, refresh: u32, retry: u32, expire: u32) -> Result<()> {
+    let mut problems = Vec::new();
+    if retry >= refresh {
+        problems.push(format!(
+            "retry ({retry}) must be less than refresh ({refresh})"
+        ));
+    }
+    if u64::from(expire) < u64::from(refresh) + u64::from(retry) {
+        problems.push(format!(
+            "expire ({expire}) must be at least refresh + retry ({})",
+            u64::from(refresh) + u64::from(retry)
+        ));
+    }
+    if !problems.is_empty() {
+        bail!("SOA timers of {zone_name}: {}", problems.join(", "))
+    }
+    Ok(())
+}
+
+/// Distinct names with uppercase letters, in their original order
+fn mixed_case(mut names: Vec<&str>) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    names.retain(|name| name.chars().any(|c| c.is_ascii_uppercase()) && seen.insert(*name));
+    names
+}
+
+fn entry_name<T>(value: &StringOrTableValue<T>, name: impl Fn(&T) -> &str) -> &str {
+    match value {
+        StringOrTableValue::Entry(entry) => entry,
+        StringOrTableValue::Table(table) => name(table),
+    }
+}
+
+/// Names of a raw zone written with uppercase letters, in config order.
+/// Resolving the zone lowercases them.
+pub fn mixed_case_names(zone: &Zone) -> Vec<&str> {
+    let mut names = vec![zone.name.as_str()];
+    let nameservers = zone.base.nameserver.iter().flat_map(|ns| ns.iter());
+    names.extend(nameservers.map(|ns| entry_name(ns, |t| &t.name)));
+    let mx = zone.mx.iter().flat_map(|mx| mx.iter());
+    names.extend(mx.map(|mx| entry_name(mx, |t| &t.name)));
+    for (host, value) in zone.hosts.iter().flatten() {
+        names.push(host);
+        if let HostValue::Entry(entry) = value {
+            names.extend(
+                entry
+                    .alias
+                    .iter()
+                    .flat_map(|a| a.iter())
+                    .map(String::as_str),
+            );
+            names.extend(entry.acme_delegate.as_deref());
+            names.extend(entry.same_as.as_deref());
+        }
+    }
+    for (name, entry) in zone.cname.iter().flatten() {
+        names.push(name);
+        names.push(entry_name(entry, |t| &t.target));
+    }
+    for (name, members) in zone.pool.iter().flatten() {
+        names.push(name);
+        names.extend(members.iter().map(String::as_str));
+    }
+    for (name, entry) in zone.srv.iter().flat_map(|srv| &srv.0) {
+        names.push(name);
+        names.push(&entry.target);
+    }
+    for hosts in zone.services.iter().flat_map(|services| services.values()) {
+        names.extend(hosts.iter().map(String::as_str));
+    }
+    names.extend(zone.acme_delegate.as_deref());
+    names.extend(zone.mail.iter().map(|mail| mail.host.as_str()));
+    for (child, nameservers) in zone.delegate.iter().flatten() {
+        names.push(child);
+        names.extend(nameservers.iter().map(|ns| entry_name(ns, |t| &t.name)));
+    }
+    mixed_case(names)
+}
+
+/// Nameserver and MX names of the defaults written with uppercase letters
+pub fn mixed_case_defaults(defaults: &RawDefaults) -> Vec<&str> {
+    let mut names: Vec<&str> = defaults
+        .nameserver
+        .iter()
+        .flat_map(|ns| ns.iter())
+        .map(String::as_str)
+        .collect();
+    let mx = defaults.mx.iter().flat_map(|mx| mx.iter());
+    names.extend(mx.map(|mx| entry_name(mx, |t| &t.name)));
+    mixed_case(names)
+}
+
+/// Email of a zone, falling back to the one of the defaults
+fn parse_zone_email(raw: Option<String>, defaults: &SessionDefaults) -> Result<String> {
+    match raw {
+        Some(mail) => parse_email(&mail),
+        None => match defaults.email.clone() {
+            Some(default_mail) => Ok(default_mail),
+            None => bail!("Email is required"),
+        },
+    }
+}
+
 pub fn parse_email(raw: &str) -> Result<String> {
     let (local, domain) = raw
         .split_once('@')
@@ -90,12 +200,13 @@ pub fn parse_mx(
             .to_vec()
             .into_iter()
             .map(|entry| {
-                let (name, ttl, prio) = match entry {
-                    StringOrTableValue::Entry(e) => (e, default_ttl, default_mx_prio),
+                let (name, ttl, prio, comment) = match entry {
+                    StringOrTableValue::Entry(e) => (e, default_ttl, default_mx_prio, None),
                     StringOrTableValue::Table(t) => (
                         t.name,
                         parse_ttl(&t.ttl, default_ttl),
                         t.prio.unwrap_or(default_mx_prio),
+                        t.comment,
                     ),
                 };
                 let fqdn = parse_host_str(&name, zone_name)?;
@@ -104,6 +215,7 @@ pub fn parse_mx(
                     name: fqdn,
                     ttl,
                     prio,
+                    comment: parse_comment(comment)?,
                 })
             })
             .collect(),
@@ -114,67 +226,209 @@ pub fn parse_mx(
                     name: entry.name.clone(),
                     ttl: parse_ttl(&entry.ttl, default_ttl),
                     prio: entry.prio.unwrap_or(default_mx_prio),
+                    comment: parse_comment(entry.comment.clone())?,
                 })
             })
             .collect(),
     }
 }
 
+/// Comment of an entry, which has to fit on the line of its record
+fn parse_comment(comment: Option<String>) -> Result<Option<String>> {
+    match comment.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(text) if text.contains(['\n', '\r']) => bail!("Comment '{text}' spans several lines"),
+        Some(text) => Ok(Some(text.to_string())),
+    }
+}
+
+/// NSD options of a zone, those of the defaults without any. The values are
+/// written into zones.conf as they are, so each has to fit on one line.
+pub fn parse_nsd(raw: Option<NsdEntry>, default: &NsdOptions) -> Result<NsdOptions> {
+    let Some(raw) = raw else {
+        return Ok(default.clone());
+    };
+    let values = |key: &str, values: Option<SingleOrVecValue<String>>| {
+        values
+            .map(SingleOrVecValue::to_vec)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|value| option_value("NSD", key, &value))
+            .collect::<Result<Vec<_>>>()
+    };
+    let pattern = match raw.pattern {
+        Some(pattern) if pattern.trim().contains(char::is_whitespace) => {
+            bail!("NSD pattern '{pattern}' is more than one name")
+        }
+        Some(pattern) => Some(option_value("NSD", "pattern", &pattern)?),
+        None => None,
+    };
+    Ok(NsdOptions {
+        pattern,
+        notify: values("notify", raw.notify)?,
+        provide_xfr: values("provide-xfr", raw.provide_xfr)?,
+        outgoing_interface: values("outgoing-interface", raw.outgoing_interface)?,
+    })
+}
+
+/// Value of an option of `server`, written on one line of its config
+fn option_value(server: &str, key: &str, value: &str) -> Result<String> {
+    match value.trim() {
+        "" => bail!("{server} option {key} is empty"),
+        text if text.contains(['\n', '\r', '#', '"']) => {
+            bail!("{server} option {key} '{text}' has a line break, '#' or '\"'")
+        }
+        text => Ok(text.to_string()),
+    }
+}
+
+/// Options of the `server:` clause of Unbound in config order, a list repeats
+/// its option. Flags are written as `yes` or `no`.
+pub fn parse_unbound_options(
+    raw: Option<IndexMap<String, SingleOrVecValue<OptionValue>>>,
+) -> Result<Vec<UnboundOption>> {
+    let mut options = Vec::new();
+    for (name, values) in raw.unwrap_or_default() {
+        let valid = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-';
+        if name.is_empty() || !name.chars().all(valid) {
+            bail!("Unbound option '{name}' is not a name like cache-min-ttl")
+        }
+        for value in values.to_vec() {
+            let value = match value {
+                OptionValue::Flag(true) => "yes".to_string(),
+                OptionValue::Flag(false) => "no".to_string(),
+                OptionValue::Number(number) => number.to_string(),
+                OptionValue::Text(text) => option_value("Unbound", &name, &text)?,
+            };
+            options.push(UnboundOption {
+                name: name.clone(),
+                value,
+            });
+        }
+    }
+    Ok(options)
+}
+
 pub fn parse_ns(
     raw: Option<SingleOrVecValue<StringOrTableValue<NameserverEntry>>>,
     zone_name: &str,
     default_ttl: u32,
-    default_ns: &[String],
+    default_ns: &[NameserverEntry],
 ) -> Result<Vec<NsRecord>> {
-    match raw {
+    let records = match raw {
         Some(zone_ns) => zone_ns
             .to_vec()
             .into_iter()
             .map(|entry| {
-                let (name, ttl) = match entry {
-                    StringOrTableValue::Entry(e) => (e, default_ttl),
-                    StringOrTableValue::Table(t) => (t.name, parse_ttl(&t.ttl, default_ttl)),
-                };
-                let fqdn = parse_host_str(&name, zone_name)?;
+                let entry = entry.to_entry()?;
+                let fqdn = parse_host_str(&entry.name, zone_name)?;
                 validate_dns_name(&fqdn)?;
-                Ok(NsRecord { name: fqdn, ttl })
+                Ok(NsRecord {
+                    name: fqdn,
+                    ttl: parse_ttl(&entry.ttl, default_ttl),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => default_ns
+            .iter()
+            .map(|ns| NsRecord {
+                name: ns.name.clone(),
+                ttl: parse_ttl(&ns.ttl, default_ttl),
             })
             .collect(),
-        None => {
-            if default_ns.is_empty() {
-                bail!("Forward zone {zone_name} needs a nameserver")
-            }
-            let records: Vec<NsRecord> = default_ns
-                .iter()
-                .map(|name| NsRecord {
-                    name: name.clone(),
-                    ttl: default_ttl,
-                })
-                .collect();
-            Ok(records)
-        }
+    };
+    if records.is_empty() {
+        bail!("Zone {zone_name} needs a nameserver")
     }
+    Ok(records)
 }
 
 pub fn parse_cname(
-    raw: Option<HashMap<String, StringOrTableValue<CnameEntry>>>,
+    raw: Option<IndexMap<String, StringOrTableValue<CnameEntry>>>,
     zone_name: &str,
     default_ttl: u32,
 ) -> Result<Vec<CnameRecord>> {
+    let mut seen: HashMap<String, String> = HashMap::new();
     raw.unwrap_or_default()
         .into_iter()
+        .filter(|(_, entry)| {
+            !matches!(entry, StringOrTableValue::Table(t) if t.disabled.unwrap_or(false))
+        })
         .map(|(cname, entry)| {
             let name = parse_host_str(&cname, zone_name)?;
-            let (host, ttl) = match entry {
-                StringOrTableValue::Entry(e) => (e, default_ttl),
-                StringOrTableValue::Table(t) => (t.target, parse_ttl(&t.ttl, default_ttl)),
+            if let Some(first) = seen.insert(name.clone(), cname.clone()) {
+                bail!("CNAMEs '{first}' and '{cname}' both define {name}")
+            }
+            let (host, ttl, comment) = match entry {
+                StringOrTableValue::Entry(e) => (e, default_ttl, None),
+                StringOrTableValue::Table(t) => {
+                    (t.target, parse_ttl(&t.ttl, default_ttl), t.comment)
+                }
             };
             let target = parse_host_str(&host, zone_name)?;
-            Ok(CnameRecord { name, target, ttl })
+            Ok(CnameRecord {
+                name,
+                target,
+                ttl,
+                comment: parse_comment(comment)?,
+            })
         })
         .collect()
 }
 
+/// A/AAAA records of the pools: those of all their members under the pool's
+/// name. Members are hosts of the zone, a range like `web-[1-3]` stands for
+/// all hosts of the range. Disabled members are left out.
+pub fn parse_pools(
+    raw: Option<IndexMap<String, SingleOrVecValue<String>>>,
+    zone_name: &str,
+    ttl: u32,
+    hosts: &[ARecord],
+    cnames: &[CnameRecord],
+    parked: &HashSet<String>,
+) -> Result<Vec<ARecord>> {
+    let mut records = Vec::new();
+    for (pool, members) in raw.unwrap_or_default() {
+        let name = parse_host_str(&pool, zone_name)?;
+        if hosts.iter().any(|host| host.name == name) {
+            bail!("Pool '{pool}' is also a host")
+        }
+        if cnames.iter().any(|cname| cname.name == name) {
+            bail!("Pool '{pool}' is also a CNAME")
+        }
+        if records.iter().any(|record: &ARecord| record.name == name) {
+            bail!("Pool {name} is defined more than once")
+        }
+        let members = members.to_vec();
+        if members.is_empty() {
+            bail!("Pool '{pool}' has no members")
+        }
+        for member in members {
+            let range = HostRange::of(&member)?;
+            for index in 0..range.count() {
+                let host = parse_host_str(&range.nth(index), zone_name)?;
+                let ips: Vec<IpAddr> = hosts
+                    .iter()
+                    .filter(|record| record.name == host)
+                    .map(|record| record.ip)
+                    .collect();
+                if ips.is_empty() && !parked.contains(&host) {
+                    bail!("Pool '{pool}' has the member {host}, which is no host of the zone")
+                }
+                for ip in ips {
+                    records.push(ARecord {
+                        name: name.clone(),
+                        ip,
+                        ttl,
+                        comment: None,
+                    });
+                }
+            }
+        }
+    }
+    Ok(records)
+}
+
 pub fn parse_srv(
     raw: Option<crate::parser::SrvMap>,
     zone_name: &str,
@@ -185,9 +439,14 @@ pub fn parse_srv(
     raw.map(|m| m.0)
         .unwrap_or_default()
         .into_iter()
+        .filter(|(_, entry)| !entry.disabled.unwrap_or(false))
         .map(|(srv_name, entry)| {
             let name = parse_srv_name(&srv_name, zone_name)?;
             let target = parse_host_str(&entry.target, zone_name)?;
+            // "." states that the service is not provided (RFC 2782)
+            if target != "." {
+                validate_dns_name(&target)?;
+            }
             let ttl = parse_ttl(&entry.ttl, default_ttl);
             let prio = entry.prio.unwrap_or(default_srv_prio);
             let weight = entry.weight.unwrap_or(default_srv_weight);
@@ -198,66 +457,726 @@ pub fn parse_srv(
                 ttl,
                 prio,
                 weight,
+                comment: parse_comment(entry.comment)?,
             })
         })
         .collect()
 }
 
+/// SRV records of the services of `services:`, as service and protocol with
+/// the port. Kerberos covers what MIT, Heimdal and Active Directory look up.
+const SERVICES: &[(&str, &[(&str, u16)])] = &[
+    ("gc", &[("_gc._tcp", 3268)]),
+    ("imap", &[("_imap._tcp", 143), ("_imaps._tcp", 993)]),
+    (
+        "kerberos",
+        &[
+            ("_kerberos._udp", 88),
+            ("_kerberos._tcp", 88),
+            ("_kerberos-master._udp", 88),
+            ("_kerberos-master._tcp", 88),
+            ("_kpasswd._udp", 464),
+            ("_kpasswd._tcp", 464),
+        ],
+    ),
+    ("ldap", &[("_ldap._tcp", 389)]),
+    ("ldaps", &[("_ldaps._tcp", 636)]),
+    (
+        "sip",
+        &[
+            ("_sip._udp", 5060),
+            ("_sip._tcp", 5060),
+            ("_sips._tcp", 5061),
+        ],
+    ),
+    ("submission", &[("_submission._tcp", 587)]),
+    (
+        "xmpp",
+        &[("_xmpp-client._tcp", 5222), ("_xmpp-server._tcp", 5269)],
+    ),
+];
+
+/// SRV records of the well-known services, one set per host of a service
+pub fn parse_services(
+    raw: Option<IndexMap<String, SingleOrVecValue<String>>>,
+    zone_name: &str,
+    ttl: u32,
+    prio: u16,
+    weight: u16,
+) -> Result<Vec<SrvRecord>> {
+    let mut records = Vec::new();
+    for (service, hosts) in raw.unwrap_or_default() {
+        let Some((_, srv)) = SERVICES.iter().find(|(name, _)| *name == service) else {
+            let known: Vec<&str> = SERVICES.iter().map(|(name, _)| *name).collect();
+            bail!(
+                "Unknown service '{service}', known are {}",
+                known.join(", ")
+            )
+        };
+        let targets = hosts
+            .iter()
+            .map(|host| {
+                let target = parse_host_str(host, zone_name)?;
+                validate_dns_name(&target)?;
+                Ok(target)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (srv_name, port) in srv.iter() {
+            for target in &targets {
+                records.push(SrvRecord {
+                    name: format!("{srv_name}.{zone_name}"),
+                    target: target.clone(),
+                    ttl,
+                    prio,
+                    weight,
+                    port: *port,
+                    comment: None,
+                });
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Records of the `mail` block of a zone
+#[derive(Debug, Default)]
+pub struct MailRecords {
+    pub mx: Vec<MxRecord>,
+    pub txt: Vec<TxtRecord>,
+    pub cname: Vec<CnameRecord>,
+    pub srv: Vec<SrvRecord>,
+}
+
+/// MX and SPF of the mail host at the apex, the CNAMEs mail clients look up
+/// their settings with and, if asked for, the SRV records of submission and
+/// IMAP, all pointing to the same host
+pub fn parse_mail(
+    raw: Option<MailEntry>,
+    zone_name: &str,
+    default_ttl: u32,
+    mx_prio: u16,
+    srv_prio: u16,
+    srv_weight: u16,
+) -> Result<MailRecords> {
+    let Some(mail) = raw else {
+        return Ok(MailRecords::default());
+    };
+    let host = parse_host_str(&mail.host, zone_name)?;
+    validate_dns_name(&host)?;
+    let ttl = parse_ttl(&mail.ttl, default_ttl);
+    let spf = match mail.spf {
+        None => "v=spf1 mx -all".to_string(),
+        Some(spf) if spf == "v=spf1" || spf.starts_with("v=spf1 ") => spf,
+        Some(spf) if spf.starts_with("v=") => bail!("Invalid SPF policy '{spf}'"),
+        Some(spf) => format!("v=spf1 {spf}"),
+    };
+    let cname = if mail.autoconfig.unwrap_or(true) {
+        ["autoconfig", "autodiscover"]
+            .into_iter()
+            .map(|name| CnameRecord {
+                name: format!("{name}.{zone_name}"),
+                target: host.clone(),
+                ttl,
+                comment: None,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let srv = if mail.srv.unwrap_or(false) {
+        let services = ["submission", "imap"]
+            .into_iter()
+            .map(|service| (service.to_string(), SingleOrVecValue::Single(host.clone())))
+            .collect();
+        parse_services(Some(services), zone_name, ttl, srv_prio, srv_weight)?
+    } else {
+        Vec::new()
+    };
+    Ok(MailRecords {
+        mx: vec![MxRecord {
+            name: host,
+            ttl,
+            prio: mail.prio.unwrap_or(mx_prio),
+            comment: None,
+        }],
+        txt: vec![TxtRecord {
+            name: zone_name.to_string(),
+            text: spf,
+            ttl,
+        }],
+        cname,
+        srv,
+    })
+}
+
+/// `_acme-challenge` records handing the DNS-01 validation of names to a
+/// challenge zone, as CNAMEs to the name below the challenge zone or as
+/// delegations to the challenge nameserver. A wildcard is validated at its
+/// parent, like the certificate of `*.example.com` at `example.com`.
+pub fn parse_acme(
+    challenges: Vec<(String, String)>,
+    mode: AcmeMode,
+    ttl: u32,
+) -> Result<(Vec<CnameRecord>, Vec<DelegationRecord>)> {
+    let mut delegates: IndexMap<String, String> = IndexMap::new();
+    for (name, delegate) in challenges {
+        validate_dns_name(&delegate)?;
+        let owner = name.strip_prefix("*.").unwrap_or(&name);
+        let target = match mode {
+            AcmeMode::Cname => format!("{owner}{delegate}"),
+            AcmeMode::Ns => delegate,
+        };
+        let name = format!("_acme-challenge.{owner}");
+        validate_dns_name(&name)?;
+        validate_dns_name(&target)?;
+        match delegates.get(&name) {
+            Some(first) if *first != target => {
+                bail!("{name} is delegated to both {first} and {target}")
+            }
+            Some(_) => {}
+            None => {
+                delegates.insert(name, target);
+            }
+        }
+    }
+    let delegates = delegates.into_iter();
+    Ok(match mode {
+        AcmeMode::Cname => (
+            delegates
+                .map(|(name, target)| CnameRecord {
+                    name,
+                    target,
+                    ttl,
+                    comment: None,
+                })
+                .collect(),
+            Vec::new(),
+        ),
+        AcmeMode::Ns => (
+            Vec::new(),
+            delegates
+                .map(|(name, target)| DelegationRecord { name, target, ttl })
+                .collect(),
+        ),
+    })
+}
+
+/// NS records of the child zones delegated from the forward zone `zone_name`
+/// and the glue addresses of their nameservers. Nameserver names are relative
+/// to the child, those within it need glue, those outside take none.
+pub fn parse_child_delegations(
+    raw: Option<IndexMap<String, SingleOrVecValue<StringOrTableValue<DelegateEntry>>>>,
+    zone_name: &str,
+    default_ttl: u32,
+) -> Result<(Vec<DelegationRecord>, Vec<ARecord>)> {
+    let mut delegation: Vec<DelegationRecord> = Vec::new();
+    let mut glue: Vec<ARecord> = Vec::new();
+    for (child, nameservers) in raw.unwrap_or_default() {
+        let child_name = parse_host_str(&child, zone_name)?;
+        validate_dns_name(&child_name)?;
+        if !child_name.ends_with(&format!(".{zone_name}")) {
+            bail!("Delegated zone {child_name} is not below {zone_name}")
+        }
+        if delegation.iter().any(|ns| ns.name == child_name) {
+            bail!("{child_name} is delegated twice")
+        }
+        for entry in nameservers.to_vec() {
+            let entry = match entry {
+                StringOrTableValue::Entry(name) => DelegateEntry {
+                    name,
+                    ip: None,
+                    ttl: None,
+                },
+                StringOrTableValue::Table(entry) => entry,
+            };
+            let ttl = parse_ttl(&entry.ttl, default_ttl);
+            let target = parse_host_str(&entry.name, &child_name)?;
+            validate_dns_name(&target)?;
+            let within = target == child_name || target.ends_with(&format!(".{child_name}"));
+            let ips = entry.ip.map(SingleOrVecValue::to_vec).unwrap_or_default();
+            match (within, ips.is_empty()) {
+                (true, true) => {
+                    bail!("Nameserver {target} lies within {child_name} and needs a glue ip")
+                }
+                (false, false) => {
+                    bail!("Nameserver {target} lies outside of {child_name} and takes no glue ip")
+                }
+                _ => {}
+            }
+            for ip in ips {
+                if !glue.iter().any(|a| a.name == target && a.ip == ip) {
+                    glue.push(ARecord {
+                        name: target.clone(),
+                        ip,
+                        ttl,
+                        comment: None,
+                    });
+                }
+            }
+            delegation.push(DelegationRecord {
+                name: child_name.clone(),
+                target,
+                ttl,
+            });
+        }
+    }
+    Ok((delegation, glue))
+}
+
+/// PTR record a host asks for. Hosts sharing an address are settled by
+/// `resolve_ptr_claims` once all zones are parsed.
+#[derive(Debug, Clone)]
+pub struct PtrClaim {
+    pub record: PtrRecord,
+    pub policy: PtrPolicy,
+    pub primary: bool,
+}
+
+/// PTR records of one address claimed by several hosts, in config order: the
+/// primary host's alone, else as the policy of each later host decides
+pub fn resolve_ptr_claims(claims: Vec<PtrClaim>) -> Result<Vec<PtrRecord>> {
+    let primaries: Vec<&PtrClaim> = claims.iter().filter(|c| c.primary).collect();
+    match primaries.as_slice() {
+        [] => (),
+        [primary] => return Ok(vec![primary.record.clone()]),
+        [first, second, ..] => bail!(
+            "Hosts {} and {} both have ptr-primary for {}",
+            first.record.name,
+            second.record.name,
+            first.record.ip
+        ),
+    }
+
+    let mut records: Vec<PtrRecord> = Vec::new();
+    for claim in claims {
+        let Some(first) = records.first() else {
+            records.push(claim.record);
+            continue;
+        };
+        match claim.policy {
+            PtrPolicy::Error => bail!(
+                "Duplicate PTR for {}: {} and {} (set ptr-policy or ptr-primary)",
+                claim.record.ip,
+                first.name,
+                claim.record.name
+            ),
+            PtrPolicy::FirstWins => (),
+            PtrPolicy::All => records.push(claim.record),
+        }
+    }
+    Ok(records)
+}
+
+/// Interface identifier of the MAC address `mac` (RFC 4291, appendix A)
+fn eui64(mac: &str) -> Result<Ipv6Addr> {
+    let bytes: Vec<u8> = mac
+        .split([':', '-'])
+        .map(|byte| {
+            u8::from_str_radix(byte, 16)
+                .ok()
+                .filter(|_| byte.len() == 2)
+        })
+        .collect::<Option<_>>()
+        .filter(|bytes: &Vec<u8>| bytes.len() == 6)
+        .ok_or_else(|| anyhow!("'{mac}' is not a MAC address"))?;
+    let id = [
+        bytes[0] ^ 0x02,
+        bytes[1],
+        bytes[2],
+        0xff,
+        0xfe,
+        bytes[3],
+        bytes[4],
+        bytes[5],
+    ];
+    Ok(Ipv6Addr::from(u64::from_be_bytes(id) as u128))
+}
+
+/// Address of the network `prefix` with the interface identifier `suffix`
+fn with_prefix(prefix: &Ipv6Network, suffix: Ipv6Addr) -> Result<Ipv6Addr> {
+    let mask = u128::from(prefix.mask());
+    if u128::from(suffix) & mask != 0 {
+        bail!("Suffix {suffix} does not fit into the host part of {prefix}")
+    }
+    Ok(Ipv6Addr::from(
+        u128::from(prefix.network()) | u128::from(suffix),
+    ))
+}
+
+/// A/AAAA records of the hosts and their aliases, or the CNAMEs of the aliases
+/// with `alias-mode: cname`, and the PTR records the hosts ask for. Hosts with
+/// an `ip6-suffix` or `eui64` get an address in each of `ip6_prefixes`.
+/// Records of the `hosts` section of a zone
+#[derive(Debug, Default)]
+pub struct Hosts {
+    pub addresses: Vec<ARecord>,
+    /// CNAMEs of the aliases of hosts with `alias-mode: cname`
+    pub aliases: Vec<CnameRecord>,
+    pub ptr: Vec<PtrClaim>,
+    /// Hosts with an `acme-delegate` and the delegate, both FQDNs
+    pub acme: Vec<(String, String)>,
+    /// FQDNs of the disabled hosts
+    pub parked: HashSet<String>,
+}
+
+/// Names of a host with `same-as`, which get the addresses of the host
+/// `target` once all hosts are known
+struct SameAs {
+    hostname: String,
+    names: Vec<String>,
+    target: String,
+    ttl: u32,
+    comment: Option<String>,
+}
+
 pub fn parse_hosts(
-    raw: Option<std::collections::HashMap<String, HostValue>>,
+    raw: Option<IndexMap<String, HostValue>>,
     zone_name: &str,
+    ip6_prefixes: &[Ipv6Network],
     default_ttl: u32,
     default_with_ptr: bool,
-) -> Result<(Vec<ARecord>, Vec<PtrRecord>)> {
+    default_ptr_policy: PtrPolicy,
+    default_alias_mode: AliasMode,
+) -> Result<Hosts> {
     let mut a_records: Vec<ARecord> = Vec::new();
-    let mut ptr_records: Vec<PtrRecord> = Vec::new();
+    let mut cnames: Vec<CnameRecord> = Vec::new();
+    let mut ptr_records: Vec<PtrClaim> = Vec::new();
+    let mut acme: Vec<(String, String)> = Vec::new();
+    let mut parked: HashSet<String> = HashSet::new();
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut alias_of: HashMap<String, String> = HashMap::new();
+    let mut copies: Vec<SameAs> = Vec::new();
 
     for (hostname, value) in raw.unwrap_or_default() {
-        let fqdn = parse_host_str(&hostname, zone_name)?;
-
-        let (ips, aliases, ttl, with_ptr) = match value {
-            HostValue::Ip(ip) => (ip.to_vec(), vec![], default_ttl, default_with_ptr),
-            HostValue::Entry(entry) => (
-                entry.ip.to_vec(),
-                entry.alias.map(|a| a.to_vec()).unwrap_or_default(),
-                parse_ttl(&entry.ttl, default_ttl),
-                entry.with_ptr.unwrap_or(default_with_ptr),
-            ),
+        let entry = match value {
+            HostValue::Ip(ip) => HostEntry {
+                ip: Some(ip),
+                ..HostEntry::default()
+            },
+            HostValue::Entry(entry) => *entry,
         };
-        for ip in ips {
-            a_records.push(ARecord {
-                name: fqdn.clone(),
-                ip,
-                ttl,
-            });
-            for alias in &aliases {
-                let name = parse_host_str(alias, zone_name)?;
-                a_records.push(ARecord { name, ip, ttl });
+        if entry.disabled.unwrap_or(false) {
+            let names = HostRange::of(&hostname)?;
+            for index in 0..names.count() {
+                parked.insert(parse_host_str(&names.nth(index), zone_name)?);
+            }
+            continue;
+        }
+        let ttl = parse_ttl(&entry.ttl, default_ttl);
+        let with_ptr = entry.with_ptr.unwrap_or(default_with_ptr);
+        let policy = entry.ptr_policy.unwrap_or(default_ptr_policy);
+        let primary = entry.ptr_primary.unwrap_or(false);
+        let alias_mode = entry.alias_mode.unwrap_or(default_alias_mode);
+        let comment = parse_comment(entry.comment)?;
+        let acme_delegate = entry
+            .acme_delegate
+            .map(|delegate| parse_host_str(&delegate, zone_name))
+            .transpose()?;
+
+        let names = HostRange::of(&hostname)?;
+        let aliases = entry
+            .alias
+            .map(SingleOrVecValue::to_vec)
+            .unwrap_or_default()
+            .iter()
+            .map(|alias| names.matching(alias, "alias"))
+            .collect::<Result<Vec<_>>>()?;
+        let address = |ip: &HostAddress, kind| match ip {
+            HostAddress::Ip(ip) => Ok(HostRange::Single(ip.to_string())),
+            HostAddress::Range(range) => names.matching(&range.to_string(), kind),
+            HostAddress::Vars(text) => {
+                bail!("Host '{hostname}' has unexpanded variables in {text}")
+            }
+        };
+        let ips = entry
+            .ip
+            .iter()
+            .flat_map(SingleOrVecValue::iter)
+            .map(|ip| address(ip, "address range"))
+            .collect::<Result<Vec<_>>>()?;
+        let suffix = match (&entry.ip6_suffix, &entry.eui64) {
+            (Some(_), Some(_)) => bail!("Host '{hostname}' has both an ip6-suffix and an eui64"),
+            (Some(suffix), None) => Some(address(suffix, "ip6-suffix range")?),
+            (None, Some(mac)) => {
+                if let Some(prefix) = ip6_prefixes.iter().find(|prefix| prefix.prefix() > 64) {
+                    bail!(
+                        "Host '{hostname}' has an eui64, which needs prefixes of /64 or shorter, \
+                         not {prefix}"
+                    )
+                }
+                Some(HostRange::Single(eui64(mac)?.to_string()))
+            }
+            (None, None) => None,
+        };
+        if suffix.is_some() && ip6_prefixes.is_empty() {
+            bail!("Host '{hostname}' has an interface identifier, but the zone no ip6-prefix")
+        }
+        let same_as = entry
+            .same_as
+            .as_ref()
+            .map(|target| names.matching(target, "same-as"))
+            .transpose()?;
+        match (&same_as, ips.is_empty() && suffix.is_none()) {
+            (Some(_), false) => {
+                bail!("Host '{hostname}' has both same-as and addresses of its own")
+            }
+            (Some(_), true) if entry.with_ptr == Some(true) || primary => {
+                bail!("Host '{hostname}' has the addresses of same-as and cannot have PTR records")
+            }
+            (None, true) => bail!("Host '{hostname}' has no address"),
+            _ => {}
+        }
+
+        for index in 0..names.count() {
+            let fqdn = parse_host_str(&names.nth(index), zone_name)?;
+            validate_dns_name(&fqdn)?;
+            // A PTR record names one host, which a wildcard is not
+            let wildcard = is_wildcard(&fqdn);
+            if wildcard && (entry.with_ptr == Some(true) || primary) {
+                bail!("Wildcard host {fqdn} cannot have PTR records")
+            }
+            if let Some(first) = seen.insert(fqdn.clone(), hostname.clone()) {
+                bail!("Hosts '{first}' and '{hostname}' both define {fqdn}")
+            }
+            if let Some(delegate) = &acme_delegate {
+                acme.push((fqdn.clone(), delegate.clone()));
+            }
+            let alias_names = aliases
+                .iter()
+                .map(|alias| {
+                    let name = parse_host_str(&alias.nth(index), zone_name)?;
+                    validate_dns_name(&name)?;
+                    Ok(name)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if alias_mode == AliasMode::Cname {
+                for name in &alias_names {
+                    if let Some(first) = alias_of.insert(name.clone(), hostname.clone()) {
+                        bail!("Hosts '{first}' and '{hostname}' both have the alias {name}")
+                    }
+                    cnames.push(CnameRecord {
+                        name: name.clone(),
+                        target: fqdn.clone(),
+                        ttl,
+                        comment: comment.clone(),
+                    });
+                }
+            }
+            if let Some(same_as) = &same_as {
+                let mut names = vec![fqdn];
+                if alias_mode == AliasMode::Address {
+                    names.extend(alias_names);
+                }
+                copies.push(SameAs {
+                    hostname: hostname.clone(),
+                    names,
+                    target: parse_host_str(&same_as.nth(index), zone_name)?,
+                    ttl,
+                    comment: comment.clone(),
+                });
+                continue;
+            }
+            let mut addresses = ips
+                .iter()
+                .map(|ip| ip.nth(index).parse())
+                .collect::<Result<Vec<IpAddr>, _>>()?;
+            if let Some(suffix) = &suffix {
+                let suffix = suffix.nth(index);
+                let Ok(suffix) = suffix.parse::<Ipv6Addr>() else {
+                    bail!("ip6-suffix {suffix} of host '{hostname}' is not an IPv6 address")
+                };
+                for prefix in ip6_prefixes {
+                    addresses.push(IpAddr::V6(with_prefix(prefix, suffix)?));
+                }
             }
-            if with_ptr && !fqdn.starts_with('*') {
-                ptr_records.push(PtrRecord {
+            for ip in addresses {
+                a_records.push(ARecord {
                     name: fqdn.clone(),
                     ip,
                     ttl,
+                    comment: comment.clone(),
                 });
+                if alias_mode == AliasMode::Address {
+                    for name in &alias_names {
+                        a_records.push(ARecord {
+                            name: name.clone(),
+                            ip,
+                            ttl,
+                            comment: comment.clone(),
+                        });
+                    }
+                }
+                if with_ptr && !wildcard {
+                    ptr_records.push(PtrClaim {
+                        record: PtrRecord {
+                            name: fqdn.clone(),
+                            ip,
+                            ttl,
+                        },
+                        policy,
+                        primary,
+                    });
+                }
             }
         }
     }
 
-    Ok((a_records, ptr_records))
+    // Hosts with same-as may refer to hosts defined later or to each other
+    while !copies.is_empty() {
+        let pending = copies.len();
+        let mut waiting = Vec::new();
+        for copy in copies {
+            let ips: Vec<IpAddr> = a_records
+                .iter()
+                .filter(|a| a.name == copy.target)
+                .map(|a| a.ip)
+                .collect();
+            if ips.is_empty() {
+                waiting.push(copy);
+                continue;
+            }
+            for name in &copy.names {
+                for &ip in &ips {
+                    a_records.push(ARecord {
+                        name: name.clone(),
+                        ip,
+                        ttl: copy.ttl,
+                        comment: copy.comment.clone(),
+                    });
+                }
+            }
+        }
+        if let Some(copy) = waiting.first().filter(|_| waiting.len() == pending) {
+            bail!(
+                "Host '{}' is the same as {}, which is no host of the zone with addresses",
+                copy.hostname,
+                copy.target
+            )
+        }
+        copies = waiting;
+    }
+
+    // A CNAME owner has no other records (RFC 1034, section 3.6.2)
+    for cname in &cnames {
+        if let Some(host) = seen.get(&cname.name) {
+            let alias_host = &alias_of[&cname.name];
+            bail!(
+                "Alias {} of host '{alias_host}' is also host '{host}'",
+                cname.name
+            )
+        }
+    }
+
+    Ok(Hosts {
+        addresses: a_records,
+        aliases: cnames,
+        ptr: ptr_records,
+        acme,
+        parked,
+    })
+}
+
+/// FQDNs of the hosts a key of `hosts`, `pool` or `cname` stands for
+pub(crate) fn entry_names(key: &str, zone_name: &str) -> Result<Vec<String>> {
+    let range = HostRange::of(key)?;
+    (0..range.count())
+        .map(|index| parse_host_str(&range.nth(index), zone_name))
+        .collect()
+}
+
+/// Name, alias or address of a host, or of a range of hosts
+enum HostRange {
+    Single(String),
+    Range(NumberRange),
+}
+
+impl HostRange {
+    fn of(text: &str) -> Result<HostRange> {
+        match NumberRange::find(text).map_err(anyhow::Error::msg)? {
+            Some(range) => Ok(HostRange::Range(range)),
+            None => Ok(HostRange::Single(text.to_string())),
+        }
+    }
+
+    /// The `kind` of value `text` of the hosts of this name. A range in it
+    /// must have as many numbers as the name, a single value is the same for
+    /// all hosts.
+    fn matching(&self, text: &str, kind: &str) -> Result<HostRange> {
+        let value = HostRange::of(text)?;
+        match (self, &value) {
+            (_, HostRange::Single(_)) => (),
+            (HostRange::Single(name), HostRange::Range(_)) => {
+                bail!("Host '{name}' has the {kind} {text}, but no range in its name")
+            }
+            (HostRange::Range(name), HostRange::Range(range)) => {
+                if range.count() != name.count() {
+                    bail!(
+                        "Host '{name}' stands for {} hosts, but its {kind} {text} for {}",
+                        name.count(),
+                        range.count()
+                    )
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            HostRange::Single(_) => 1,
+            HostRange::Range(range) => range.count(),
+        }
+    }
+
+    fn nth(&self, index: usize) -> String {
+        match self {
+            HostRange::Single(text) => text.clone(),
+            HostRange::Range(range) => range.nth(index),
+        }
+    }
+}
+
+/// Networks of the reverse zones that serve `network`. A reverse zone can
+/// only be cut at an octet, or a nibble for IPv6, so a network of a shorter
+/// prefix is split into the aligned networks it contains, a /22 into four
+/// /24. Longer prefixes stay in the zone of their /24 or /124.
+pub fn reverse_zone_networks(network: &IpNetwork) -> Vec<IpNetwork> {
+    match network {
+        IpNetwork::V4(net) if net.prefix() < 24 && net.prefix() % 8 != 0 => {
+            let prefix = net.prefix().next_multiple_of(8);
+            let step = 1u32 << (32 - prefix);
+            (0..1u32 << (prefix - net.prefix()))
+                .map(|i| {
+                    let ip = Ipv4Addr::from(u32::from(net.network()) + i * step);
+                    IpNetwork::V4(Ipv4Network::new(ip, prefix).expect("valid prefix"))
+                })
+                .collect()
+        }
+        IpNetwork::V6(net) if net.prefix() < 124 && net.prefix() % 4 != 0 => {
+            let prefix = net.prefix().next_multiple_of(4);
+            let step = 1u128 << (128 - prefix);
+            (0..1u128 << (prefix - net.prefix()))
+                .map(|i| {
+                    let ip = Ipv6Addr::from(u128::from(net.network()) + i * step);
+                    IpNetwork::V6(Ipv6Network::new(ip, prefix).expect("valid prefix"))
+                })
+                .collect()
+        }
+        _ => vec![*network],
+    }
 }
 
 pub fn create_reverse_zone_name(network: &IpNetwork) -> (String, usize) {
     match network {
         IpNetwork::V4(net) => {
             let prefix_len = net.prefix();
-            let split = ((32 - prefix_len) / 8) as usize;
+            // A network within a /24 is served by the zone of the /24
+            let zone_octets = (prefix_len / 8).min(3) as usize;
+            let split = 4 - zone_octets;
 
             let ip = net.network();
             let octets = ip.octets();
 
-            let zone_octets = (prefix_len / 8) as usize;
-
             let mut parts = Vec::new();
             for i in (0..zone_octets).rev() {
                 parts.push(octets[i].to_string());
@@ -268,12 +1187,13 @@ pub fn create_reverse_zone_name(network: &IpNetwork) -> (String, usize) {
         }
         IpNetwork::V6(net) => {
             let prefix_len = net.prefix();
-            let split = ((128 - prefix_len) / 4) as usize;
+            // A network within a /124 is served by the zone of the /124
+            let zone_nibbles = (prefix_len / 4).min(31) as usize;
+            let split = 32 - zone_nibbles;
 
             let ip = net.network();
             let hex_str = format!("{:032x}", u128::from(ip));
             let nibbles: Vec<char> = hex_str.chars().collect();
-            let zone_nibbles = (prefix_len / 4) as usize;
 
             let parts: Vec<String> = nibbles
                 .iter()
@@ -313,14 +1233,155 @@ pub fn ip_name(address: &IpAddr, split: usize) -> String {
     }
 }
 
+/// Lowercase FQDN of a zone name
+pub(crate) fn zone_fqdn(name: &str) -> String {
+    let mut name = name.to_ascii_lowercase();
+    if !name.ends_with('.') {
+        name.push('.')
+    }
+    name
+}
+
+/// Gives every zone of a `group` the settings of the group where it has none
+/// of its own. Zones inheriting from it get them as the zone's own. Zones of
+/// an unknown group are reported together and keep their own settings.
+pub fn apply_groups(zones: &mut [Zone], groups: &IndexMap<String, ZoneGroup>) -> Result<()> {
+    let mut errors = Errors::default();
+    for zone in zones {
+        let Some(name) = &zone.group else {
+            continue;
+        };
+        let Some(group) = groups.get(name) else {
+            errors.push(
+                None,
+                anyhow!(
+                    "Zone {} is in the group {name}, which is not in the config",
+                    zone_fqdn(&zone.name)
+                ),
+            );
+            continue;
+        };
+        let base = &mut zone.base;
+        base.serial = base.serial.or(group.base.serial);
+        base.email = base.email.take().or_else(|| group.base.email.clone());
+        base.expire = base.expire.or(group.base.expire);
+        base.nameserver = base
+            .nameserver
+            .take()
+            .or_else(|| group.base.nameserver.clone());
+        base.nrc_ttl = base.nrc_ttl.or(group.base.nrc_ttl);
+        base.refresh = base.refresh.or(group.base.refresh);
+        base.retry = base.retry.or(group.base.retry);
+        base.ttl = base.ttl.take().or_else(|| group.base.ttl.clone());
+        base.nsd = base.nsd.take().or_else(|| group.base.nsd.clone());
+        zone.mx = zone.mx.take().or_else(|| group.mx.clone());
+        zone.mx_prio = zone.mx_prio.or(group.mx_prio);
+        zone.srv_prio = zone.srv_prio.or(group.srv_prio);
+        zone.srv_weight = zone.srv_weight.or(group.srv_weight);
+        zone.with_ptr = zone.with_ptr.or(group.with_ptr);
+        zone.ptr_policy = zone.ptr_policy.or(group.ptr_policy);
+        zone.alias_mode = zone.alias_mode.or(group.alias_mode);
+        zone.public = zone.public.or(group.public);
+        zone.ttl_min = zone.ttl_min.take().or_else(|| group.ttl_min.clone());
+        zone.ttl_max = zone.ttl_max.take().or_else(|| group.ttl_max.clone());
+    }
+    errors.finish()
+}
+
+/// Gives every zone with `inherit` the nameservers, email and SOA timers of
+/// the zone it inherits from, where it has none of its own. The parent may
+/// inherit in turn, its inherited values are passed on. Zones with a parent
+/// that is missing or part of a circle are reported together and inherit
+/// nothing from it.
+pub fn inherit_zones(zones: &mut [Zone]) -> Result<()> {
+    let mut errors = Errors::default();
+    let names: Vec<String> = zones.iter().map(|zone| zone_fqdn(&zone.name)).collect();
+    let mut parents: Vec<Option<usize>> = Vec::with_capacity(zones.len());
+    for (index, zone) in zones.iter().enumerate() {
+        let name = &names[index];
+        let parent = (|| match &zone.inherit {
+            None | Some(Inherit::Parent(false)) => Ok(None),
+            Some(Inherit::Parent(true)) => {
+                let parent = names
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, parent)| name.ends_with(&format!(".{parent}")))
+                    .max_by_key(|(_, parent)| parent.len())
+                    .map(|(parent, _)| parent);
+                match parent {
+                    Some(parent) => Ok(Some(parent)),
+                    None => bail!("Zone {name} has no parent zone in the config to inherit from"),
+                }
+            }
+            Some(Inherit::Zone(parent)) => {
+                let parent = zone_fqdn(parent);
+                match names.iter().position(|other| *other == parent) {
+                    Some(other) if other != index => Ok(Some(other)),
+                    Some(_) => bail!("Zone {name} cannot inherit from itself"),
+                    None => bail!("Zone {name} inherits from {parent}, which is not in the config"),
+                }
+            }
+        })();
+        parents.push(errors.check(parent).flatten());
+    }
+
+    // Parents first, so they have their inherited values already
+    let mut done = vec![false; zones.len()];
+    for start in 0..zones.len() {
+        let mut chain = vec![start];
+        let mut current = start;
+        while let Some(parent) = parents[current].filter(|parent| !done[*parent]) {
+            if chain.contains(&parent) {
+                let circle: Vec<&str> = chain
+                    .iter()
+                    .chain([&parent])
+                    .map(|i| names[*i].as_str())
+                    .collect();
+                errors.push(
+                    None,
+                    anyhow!("Zones inherit from each other: {}", circle.join(" -> ")),
+                );
+                // Broken up, so the circle is reported once
+                parents[current] = None;
+                break;
+            }
+            chain.push(parent);
+            current = parent;
+        }
+        for &index in chain.iter().rev() {
+            if let Some(parent) = parents[index].filter(|_| !done[index]) {
+                let parent = zones[parent].base.clone();
+                let base = &mut zones[index].base;
+                base.email = base.email.take().or(parent.email);
+                base.nameserver = base.nameserver.take().or(parent.nameserver);
+                base.refresh = base.refresh.or(parent.refresh);
+                base.retry = base.retry.or(parent.retry);
+                base.expire = base.expire.or(parent.expire);
+                base.nrc_ttl = base.nrc_ttl.or(parent.nrc_ttl);
+            }
+            done[index] = true;
+        }
+    }
+    errors.finish()
+}
+
+/// Fails if `name` of a record generated by `source` is already a host or a
+/// CNAME, which owns no other records (RFC 1034, section 3.6.2)
+fn name_free(name: &str, hosts: &[ARecord], cname: &[CnameRecord], source: &str) -> Result<()> {
+    if cname.iter().any(|c| c.name == name) {
+        bail!("{name} is both a CNAME and set by {source}")
+    }
+    if hosts.iter().any(|h| h.name == name) {
+        bail!("{name} is both a host and set by {source}")
+    }
+    Ok(())
+}
+
 pub fn parse_forward(
     raw: Zone,
     defaults: &SessionDefaults,
-) -> Result<(ForwardZone, Vec<PtrRecord>)> {
-    let mut zone_name = raw.name.clone();
-    if !zone_name.ends_with('.') {
-        zone_name.push('.')
-    }
+) -> Result<(ForwardZone, Vec<PtrClaim>)> {
+    let zone_name = zone_fqdn(&raw.name);
 
     let serial = raw.base.serial.unwrap_or(defaults.serial);
     let expire = raw.base.expire.unwrap_or(defaults.expire);
@@ -332,24 +1393,151 @@ pub fn parse_forward(
     let srv_weight = raw.srv_weight.unwrap_or(defaults.srv_weight);
     let ttl = parse_ttl(&raw.base.ttl, defaults.ttl);
     let with_ptr = raw.with_ptr.unwrap_or(defaults.with_ptr);
-
-    if retry >= refresh {
-        bail!("retry ({retry}) must be less than refresh {refresh}")
-    }
-
-    let email = match raw.base.email {
-        Some(mail) => parse_email(&mail)?,
-        None => match defaults.email.clone() {
-            Some(default_mail) => default_mail,
-            None => bail!("Email is required"),
-        },
+    let ptr_policy = raw.ptr_policy.unwrap_or(defaults.ptr_policy);
+    let alias_mode = raw.alias_mode.unwrap_or(defaults.alias_mode);
+
+    // Every part is checked, so all problems of the zone are reported at once
+    let mut errors = Errors::default();
+    errors.check(check_soa_timers(&zone_name, refresh, retry, expire));
+
+    let email = errors
+        .check(parse_zone_email(raw.base.email, defaults))
+        .unwrap_or_default();
+    let nsd = errors
+        .check(parse_nsd(raw.base.nsd, &defaults.nsd))
+        .unwrap_or_default();
+    let Hosts {
+        addresses: hosts,
+        aliases,
+        ptr,
+        acme,
+        parked,
+    } = errors
+        .check(parse_hosts(
+            raw.hosts,
+            &zone_name,
+            &raw.ip6_prefix
+                .map(SingleOrVecValue::to_vec)
+                .unwrap_or_default(),
+            ttl,
+            with_ptr,
+            ptr_policy,
+            alias_mode,
+        ))
+        .unwrap_or_default();
+    // The mail host replaces the default MX, not those of the zone
+    let default_mx = if raw.mail.is_some() {
+        &[]
+    } else {
+        &defaults.mx[..]
     };
-
-    let (hosts, ptr) = parse_hosts(raw.hosts, &zone_name, ttl, with_ptr)?;
-    let mx = parse_mx(raw.mx, &zone_name, ttl, mx_prio, &defaults.mx)?;
-    let nameserver = parse_ns(raw.base.nameserver, &zone_name, ttl, &defaults.nameserver)?;
-    let cname: Vec<CnameRecord> = parse_cname(raw.cname, &zone_name, ttl)?;
-    let srv: Vec<SrvRecord> = parse_srv(raw.srv, &zone_name, ttl, srv_prio, srv_weight)?;
+    let mut mx = errors
+        .check(parse_mx(raw.mx, &zone_name, ttl, mx_prio, default_mx))
+        .unwrap_or_default();
+    let nameserver = errors
+        .check(parse_ns(
+            raw.base.nameserver,
+            &zone_name,
+            ttl,
+            &defaults.nameserver,
+        ))
+        .unwrap_or_default();
+    let mut cname: Vec<CnameRecord> = errors
+        .check(parse_cname(raw.cname, &zone_name, ttl))
+        .unwrap_or_default();
+    for alias in aliases {
+        if cname.iter().any(|c| c.name == alias.name) {
+            errors.push(
+                None,
+                anyhow!("{} is both an alias of a host and a CNAME", alias.name),
+            );
+        } else {
+            cname.push(alias);
+        }
+    }
+    let pools = errors
+        .check(parse_pools(
+            raw.pool, &zone_name, ttl, &hosts, &cname, &parked,
+        ))
+        .unwrap_or_default();
+    let mut srv: Vec<SrvRecord> = errors
+        .check(parse_srv(raw.srv, &zone_name, ttl, srv_prio, srv_weight))
+        .unwrap_or_default();
+    srv.extend(
+        errors
+            .check(parse_services(
+                raw.services,
+                &zone_name,
+                ttl,
+                srv_prio,
+                srv_weight,
+            ))
+            .unwrap_or_default(),
+    );
+    let mail = errors
+        .check(parse_mail(
+            raw.mail, &zone_name, ttl, mx_prio, srv_prio, srv_weight,
+        ))
+        .unwrap_or_default();
+    mx.extend(mail.mx);
+    for record in mail.cname {
+        if errors
+            .check(name_free(&record.name, &hosts, &cname, "the mail block"))
+            .is_some()
+        {
+            cname.push(record);
+        }
+    }
+    srv.extend(mail.srv);
+    let mut challenges = Vec::new();
+    if let Some(delegate) = &raw.acme_delegate {
+        if let Some(delegate) = errors.check(parse_host_str(delegate, &zone_name)) {
+            challenges.push((zone_name.clone(), delegate));
+        }
+    }
+    challenges.extend(acme);
+    let acme_mode = raw.acme_mode.unwrap_or_default();
+    let (acme_cname, mut delegation) = errors
+        .check(parse_acme(challenges, acme_mode, ttl))
+        .unwrap_or_default();
+    for record in acme_cname {
+        if errors
+            .check(name_free(&record.name, &hosts, &cname, "acme-delegate"))
+            .is_some()
+        {
+            cname.push(record);
+        }
+    }
+    for record in &delegation {
+        errors.check(name_free(&record.name, &hosts, &cname, "acme-delegate"));
+    }
+    let (children, glue) = errors
+        .check(parse_child_delegations(raw.delegate, &zone_name, ttl))
+        .unwrap_or_default();
+    for record in &children {
+        errors.check(name_free(&record.name, &hosts, &cname, "delegate"));
+    }
+    // Names below a delegation are served by the child, only the glue stays
+    let hidden = hosts
+        .iter()
+        .map(|a| &a.name)
+        .chain(cname.iter().map(|c| &c.name));
+    for name in hidden {
+        if let Some(ns) = children
+            .iter()
+            .find(|ns| name.ends_with(&format!(".{}", ns.name)))
+        {
+            errors.push(
+                None,
+                anyhow!("{name} lies within the delegated zone {}", ns.name),
+            );
+        }
+    }
+    for record in &glue {
+        errors.check(name_free(&record.name, &hosts, &cname, "delegate"));
+    }
+    delegation.extend(children);
+    errors.finish()?;
 
     Ok((
         ForwardZone {
@@ -363,46 +1551,226 @@ pub fn parse_forward(
                 refresh,
                 retry,
                 ttl,
+                nsd,
             },
+            delegation,
             mx,
-            hosts,
+            hosts: hosts.into_iter().chain(pools).chain(glue).collect(),
             cname,
             srv,
+            txt: mail.txt,
+            ptr: ptr.iter().map(|claim| claim.record.clone()).collect(),
+            public: raw.public.unwrap_or(false),
+            origins: HashMap::new(),
         },
         ptr,
     ))
 }
 
+/// Clamps the TTLs of the records of a zone and of the PTR records its hosts
+/// claim into `min..=max`. Returns the records changed, like
+/// `www.example.com. A 60 -> 300`.
+pub fn clamp_ttls(
+    zone: &mut ForwardZone,
+    claims: &mut [PtrClaim],
+    min: Option<u32>,
+    max: Option<u32>,
+) -> Result<Vec<String>> {
+    let (min, max) = (min.unwrap_or(0), max.unwrap_or(u32::MAX));
+    if min > max {
+        bail!("ttl-min {min} is above ttl-max {max}");
+    }
+    let mut clamped = Vec::new();
+    let mut clamp = |name: &str, kind: &str, ttl: &mut u32| {
+        let bounded = (*ttl).clamp(min, max);
+        if bounded != *ttl {
+            clamped.push(format!("{name} {kind} {ttl} -> {bounded}"));
+            *ttl = bounded;
+        }
+    };
+    let apex = zone.base.name.clone();
+    clamp(&apex, "SOA", &mut zone.base.ttl);
+    for ns in &mut zone.base.nameserver {
+        clamp(&apex, "NS", &mut ns.ttl);
+    }
+    for ns in &mut zone.delegation {
+        clamp(&ns.name, "NS", &mut ns.ttl);
+    }
+    for mx in &mut zone.mx {
+        clamp(&apex, "MX", &mut mx.ttl);
+    }
+    for a in &mut zone.hosts {
+        clamp(
+            &a.name,
+            if a.ip.is_ipv4() { "A" } else { "AAAA" },
+            &mut a.ttl,
+        );
+    }
+    for cname in &mut zone.cname {
+        clamp(&cname.name, "CNAME", &mut cname.ttl);
+    }
+    for srv in &mut zone.srv {
+        clamp(&srv.name, "SRV", &mut srv.ttl);
+    }
+    for txt in &mut zone.txt {
+        clamp(&txt.name, "TXT", &mut txt.ttl);
+    }
+    for claim in claims.iter_mut() {
+        clamp(&claim.record.ip.to_string(), "PTR", &mut claim.record.ttl);
+    }
+    zone.ptr = claims.iter().map(|claim| claim.record.clone()).collect();
+    Ok(clamped)
+}
+
+/// `inner` lies completely within `outer`
+fn is_subnet(inner: &IpNetwork, outer: &IpNetwork) -> bool {
+    outer.contains(inner.network()) && inner.prefix() >= outer.prefix()
+}
+
+/// Largest network whose free addresses `fill-ptr` fills, in host bits
+const MAX_FILL_BITS: u8 = 16;
+
+fn host_bits(net: &IpNetwork) -> u8 {
+    match net {
+        IpNetwork::V4(n) => 32 - n.prefix(),
+        IpNetwork::V6(n) => 128 - n.prefix(),
+    }
+}
+
+/// Adds a PTR record named after `pattern` for every address of the reverse
+/// zone's network that has none. `{ip}` is replaced by the address with
+/// dashes for dots and colons, `{n}` by its position in the network `net`.
+/// The network and broadcast addresses of IPv4 networks are left out.
+fn fill_ptrs(
+    pattern: &str,
+    net: &IpNetwork,
+    zone_net: &IpNetwork,
+    exclude: &[IpNetwork],
+    ttl: u32,
+    ptr: &mut Vec<PtrRecord>,
+) -> Result<()> {
+    let assigned: HashSet<IpAddr> = ptr.iter().map(|ptr| ptr.ip).collect();
+    let first = ip_number(&net.network());
+    for ip in zone_net.iter() {
+        let special = match net {
+            IpNetwork::V4(n) if n.prefix() < 31 => ip == net.network() || ip == net.broadcast(),
+            _ => false,
+        };
+        if special || assigned.contains(&ip) || exclude.iter().any(|e| e.contains(ip)) {
+            continue;
+        }
+        let dashed = ip.to_string().replace(['.', ':'], "-");
+        let name = pattern
+            .replace("{ip}", &dashed)
+            .replace("{n}", &(ip_number(&ip) - first).to_string())
+            .to_ascii_lowercase();
+        if let Err(e) = validate_dns_name(&name) {
+            bail!("Invalid fill-ptr pattern {pattern}: {e}")
+        }
+        ptr.push(PtrRecord { name, ip, ttl });
+    }
+    Ok(())
+}
+
+fn ip_number(ip: &IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u32::from(*ip) as u128,
+        IpAddr::V6(ip) => u128::from(*ip),
+    }
+}
+
+/// NS records of the child zones delegated from the reverse zone `name` of
+/// `net`. Children shorter than a zone are split like reverse networks.
+fn parse_delegations(
+    delegate: &IndexMap<IpNetwork, SingleOrVecValue<StringOrTableValue<NameserverEntry>>>,
+    net: &IpNetwork,
+    name: &str,
+    ttl: u32,
+) -> Result<Vec<DelegationRecord>> {
+    let mut records = Vec::new();
+    for (child, nameserver) in delegate {
+        for child in reverse_zone_networks(child) {
+            // A reverse network split into several zones delegates from each
+            if !net.contains(child.network()) {
+                continue;
+            }
+            let aligned = match child {
+                IpNetwork::V4(n) => n.prefix() % 8 == 0,
+                IpNetwork::V6(n) => n.prefix() % 4 == 0,
+            };
+            let (child_name, _) = create_reverse_zone_name(&child);
+            if !aligned || child_name == name {
+                bail!("Delegated network {child} is no zone of its own below {name}, it must end at an octet (a nibble for IPv6)")
+            }
+            for ns in parse_ns(Some(nameserver.clone()), &child_name, ttl, &[])? {
+                records.push(DelegationRecord {
+                    name: child_name.clone(),
+                    target: ns.name,
+                    ttl: ns.ttl,
+                });
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Reverse networks may only overlap where one lies in a part the other
+/// excludes
+fn check_overlap(
+    seen: &[(IpNetwork, Vec<IpNetwork>)],
+    net: &IpNetwork,
+    exclude: &[IpNetwork],
+) -> Result<()> {
+    for (other, other_exclude) in seen {
+        let overlaps = match (net, other) {
+            (IpNetwork::V4(a), IpNetwork::V4(b)) => a.overlaps(*b),
+            (IpNetwork::V6(a), IpNetwork::V6(b)) => a.overlaps(*b),
+            _ => false,
+        };
+        let excluded = other_exclude.iter().any(|e| is_subnet(net, e))
+            || exclude.iter().any(|e| is_subnet(other, e));
+        if overlaps && !excluded {
+            bail!("Reverse zone networks overlap: {net} and {other}")
+        }
+    }
+    Ok(())
+}
+
+/// Builds the reverse zones and moves the PTR records of their networks out
+/// of `ptrs`. Left are the records outside all networks, records in excluded
+/// parts are dropped.
 pub fn parse_reverse(
     raw: Option<ReverseValue>,
     defaults: &SessionDefaults,
-    mut ptrs: HashMap<IpAddr, PtrRecord>,
+    ptrs: &mut HashMap<IpAddr, Vec<PtrRecord>>,
 ) -> Result<Vec<ReverseZone>> {
-    let mut net4: Vec<Ipv4Network> = vec![];
-    let mut net6: Vec<Ipv6Network> = vec![];
-    let zones: Result<Vec<ReverseZone>> = match raw {
+    let mut seen: Vec<(IpNetwork, Vec<IpNetwork>)> = vec![];
+    let zones: Vec<Result<Vec<ReverseZone>>> = match raw {
         Some(ReverseValue::Entry(entry)) => entry
             .into_iter()
             .map(|(net, entry)| {
-                match net {
-                    IpNetwork::V4(n4) => {
-                        for n in &net4 {
-                            if n.overlaps(n4) {
-                                bail!("Reverse zone networks overlap: {n4} and {n}")
-                            }
-                        }
-                        net4.push(n4)
+                let delegate = entry.delegate.unwrap_or_default();
+                for child in delegate.keys() {
+                    if !is_subnet(child, &net) || *child == net {
+                        bail!("Delegated network {child} is not part of {net}")
                     }
-                    IpNetwork::V6(n6) => {
-                        for n in &net6 {
-                            if n.overlaps(n6) {
-                                bail!("Reverse zone networks overlap: {n6} and {n}")
-                            }
-                        }
-                        net6.push(n6)
+                }
+                let mut exclude = entry.exclude.map(|e| e.to_vec()).unwrap_or_default();
+                // Delegated parts get their PTR records from the child zone
+                exclude.extend(delegate.keys());
+                for excluded in &exclude {
+                    if !is_subnet(excluded, &net) {
+                        bail!("Excluded network {excluded} is not part of {net}")
                     }
                 }
-                let (name, split) = create_reverse_zone_name(&net);
+                if entry.fill_ptr.is_some() && host_bits(&net) > MAX_FILL_BITS {
+                    bail!(
+                        "fill-ptr of {net} would create more than {} PTR records",
+                        1u32 << MAX_FILL_BITS
+                    )
+                }
+                check_overlap(&seen, &net, &exclude)?;
+                seen.push((net, exclude.clone()));
                 let serial = entry.base.serial.unwrap_or(defaults.serial);
                 let expire = entry.base.expire.unwrap_or(defaults.expire);
                 let nrc_ttl = entry.base.nrc_ttl.unwrap_or(defaults.nrc_ttl);
@@ -410,99 +1778,165 @@ pub fn parse_reverse(
                 let retry = entry.base.retry.unwrap_or(defaults.retry);
                 let ttl = parse_ttl(&entry.base.ttl, defaults.ttl);
 
-                if retry >= refresh {
-                    bail!("retry ({retry}) must be less than refresh {refresh}")
+                let mut zones = Vec::new();
+                for zone_net in reverse_zone_networks(&net) {
+                    let (name, split) = create_reverse_zone_name(&zone_net);
+                    let mut errors = Errors::default();
+                    errors.check(check_soa_timers(&name, refresh, retry, expire));
+                    let email = errors
+                        .check(parse_zone_email(entry.base.email.clone(), defaults))
+                        .unwrap_or_default();
+                    let nameserver = errors
+                        .check(parse_ns(
+                            entry.base.nameserver.clone(),
+                            &name,
+                            ttl,
+                            &defaults.nameserver,
+                        ))
+                        .unwrap_or_default();
+                    let delegation = errors
+                        .check(parse_delegations(&delegate, &zone_net, &name, ttl))
+                        .unwrap_or_default();
+                    let nsd = errors
+                        .check(parse_nsd(entry.base.nsd.clone(), &defaults.nsd))
+                        .unwrap_or_default();
+                    errors.finish().map_err(|e| Errors::at(&name, e))?;
+
+                    let mut ptr: Vec<PtrRecord> = ptrs
+                        .extract_if(|ip, _ptr| {
+                            zone_net.contains(*ip) && !exclude.iter().any(|e| e.contains(*ip))
+                        })
+                        .flat_map(|(_ip, ptrs)| ptrs)
+                        .collect();
+                    if let Some(pattern) = &entry.fill_ptr {
+                        fill_ptrs(pattern, &net, &zone_net, &exclude, ttl, &mut ptr)
+                            .map_err(|e| Errors::at(&name, e))?;
+                    }
+                    // The map has no stable order
+                    ptr.sort_by_key(|ptr| ptr.ip);
+
+                    zones.push(ReverseZone {
+                        base: ZoneBase {
+                            serial,
+                            name,
+                            email,
+                            expire,
+                            nameserver,
+                            nrc_ttl,
+                            refresh,
+                            retry,
+                            ttl,
+                            nsd,
+                        },
+                        ptr,
+                        split,
+                        delegation,
+                        origins: HashMap::new(),
+                    });
                 }
-
-                let email = match entry.base.email {
-                    Some(mail) => parse_email(&mail)?,
-                    None => match defaults.email.clone() {
-                        Some(default_mail) => default_mail,
-                        None => bail!("Email is required"),
-                    },
-                };
-
-                let nameserver = parse_ns(entry.base.nameserver, &name, ttl, &defaults.nameserver)?;
-
-                let ptr: Vec<PtrRecord> = ptrs
-                    .extract_if(|ip, _ptr| net.contains(*ip))
-                    .map(|(_ip, ptr)| ptr)
-                    .collect();
-
-                Ok(ReverseZone {
-                    base: ZoneBase {
-                        serial,
-                        name,
-                        email,
-                        expire,
-                        nameserver,
-                        nrc_ttl,
-                        refresh,
-                        retry,
-                        ttl,
-                    },
-                    ptr,
-                    split,
-                })
+                Ok(zones)
             })
             .collect(),
         Some(ReverseValue::Net(network)) => network
             .to_vec()
             .iter()
             .map(|net| {
-                match net {
-                    IpNetwork::V4(n4) => {
-                        for n in &net4 {
-                            if n.overlaps(*n4) {
-                                bail!("Reverse zone networks overlap: {n4} and {n}")
-                            }
-                        }
-                        net4.push(*n4)
-                    }
-                    IpNetwork::V6(n6) => {
-                        for n in &net6 {
-                            if n.overlaps(*n6) {
-                                bail!("Reverse zone networks overlap: {n6} and {n}")
-                            }
-                        }
-                        net6.push(*n6)
-                    }
+                check_overlap(&seen, net, &[])?;
+                seen.push((*net, vec![]));
+
+                let mut zones = Vec::new();
+                for net in reverse_zone_networks(net) {
+                    let (name, split) = create_reverse_zone_name(&net);
+                    let mut errors = Errors::default();
+                    let email = errors
+                        .check(parse_zone_email(None, defaults))
+                        .unwrap_or_default();
+                    let nameserver = errors
+                        .check(parse_ns(None, &name, defaults.ttl, &defaults.nameserver))
+                        .unwrap_or_default();
+                    errors.finish().map_err(|e| Errors::at(&name, e))?;
+
+                    let mut ptr: Vec<PtrRecord> = ptrs
+                        .extract_if(|ip, _ptr| net.contains(*ip))
+                        .flat_map(|(_ip, ptrs)| ptrs)
+                        .collect();
+                    // The map has no stable order
+                    ptr.sort_by_key(|ptr| ptr.ip);
+
+                    zones.push(ReverseZone {
+                        base: ZoneBase {
+                            serial: defaults.serial,
+                            name,
+                            email,
+                            expire: defaults.expire,
+                            nameserver,
+                            nrc_ttl: defaults.nrc_ttl,
+                            refresh: defaults.refresh,
+                            retry: defaults.retry,
+                            ttl: defaults.ttl,
+                            nsd: defaults.nsd.clone(),
+                        },
+                        ptr,
+                        split,
+                        delegation: Vec::new(),
+                        origins: HashMap::new(),
+                    });
                 }
-                let (name, split) = create_reverse_zone_name(net);
-
-                let email = match &defaults.email {
-                    Some(mail) => mail.clone(),
-                    None => bail!("Email is required"),
-                };
-
-                let nameserver = parse_ns(None, &name, defaults.ttl, &defaults.nameserver)?;
-
-                let ptr: Vec<PtrRecord> = ptrs
-                    .extract_if(|ip, _ptr| net.contains(*ip))
-                    .map(|(_ip, ptr)| ptr)
-                    .collect();
-
-                Ok(ReverseZone {
-                    base: ZoneBase {
-                        serial: defaults.serial,
-                        name,
-                        email,
-                        expire: defaults.expire,
-                        nameserver,
-                        nrc_ttl: defaults.nrc_ttl,
-                        refresh: defaults.refresh,
-                        retry: defaults.retry,
-                        ttl: defaults.ttl,
-                    },
-                    ptr,
-                    split,
-                })
+                Ok(zones)
             })
             .collect(),
-        None => Ok(Vec::new()),
+        None => Vec::new(),
     };
+    ptrs.retain(|ip, _ptrs| {
+        !seen
+            .iter()
+            .any(|(_net, exclude)| exclude.iter().any(|e| e.contains(*ip)))
+    });
 
-    zones
+    let mut errors = Errors::default();
+    let zones = zones
+        .into_iter()
+        .filter_map(|zones| errors.check(zones))
+        .flatten()
+        .collect();
+    // Kept as a collection, so the zone names survive even for a single error
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    Ok(zones)
+}
+
+/// Secondary zones, which may not be served from the config as well
+pub fn parse_secondaries(
+    raw: Option<SingleOrVecValue<SecondaryEntry>>,
+    forward: &[ForwardZone],
+    reverse: &[ReverseZone],
+) -> Result<Vec<SecondaryZone>> {
+    let mut zones: Vec<SecondaryZone> = Vec::new();
+    for entry in raw.map(SingleOrVecValue::to_vec).unwrap_or_default() {
+        let name = zone_fqdn(&entry.name);
+        validate_dns_name(&name)?;
+        let mut served = forward
+            .iter()
+            .map(|z| &z.base)
+            .chain(reverse.iter().map(|z| &z.base));
+        if served.any(|base| base.name == name) {
+            bail!("Zone {name} is both served from the config and a secondary zone")
+        }
+        if zones.iter().any(|zone| zone.name == name) {
+            bail!("Secondary zone {name} is defined more than once")
+        }
+        let masters = entry.masters.to_vec();
+        if masters.is_empty() {
+            bail!("Secondary zone {name} needs a master")
+        }
+        zones.push(SecondaryZone {
+            name,
+            masters,
+            tsig_key: entry.tsig_key,
+        });
+    }
+    Ok(zones)
 }
 
 #[cfg(test)]
@@ -533,6 +1967,12 @@ mod tests {
         assert_eq!(result, "host.example.com.");
     }
 
+    #[test]
+    fn test_parse_host_str_lowercase() {
+        let result = parse_host_str("WWW.Example.com.", "zone.com.").unwrap();
+        assert_eq!(result, "www.example.com.");
+    }
+
     #[test]
     fn test_parse_host_str_no_zone() {
         let result = parse_host_str("host", "");
@@ -611,6 +2051,49 @@ mod tests {
         assert_eq!(split, 2);
     }
 
+    #[test]
+    fn test_create_reverse_zone_name_ipv4_25() {
+        let net: IpNetwork = "192.168.1.128/25".parse().unwrap();
+        let (name, split) = create_reverse_zone_name(&net);
+        assert_eq!(name, "1.168.192.in-addr.arpa.");
+        assert_eq!(split, 1);
+    }
+
+    #[test]
+    fn test_reverse_zone_networks_ipv4() {
+        let networks = |net: &str| -> Vec<String> {
+            reverse_zone_networks(&net.parse().unwrap())
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+        assert_eq!(
+            networks("10.0.4.0/22"),
+            ["10.0.4.0/24", "10.0.5.0/24", "10.0.6.0/24", "10.0.7.0/24"]
+        );
+        assert_eq!(networks("172.16.0.0/12").len(), 16);
+        assert_eq!(networks("172.16.0.0/12")[15], "172.31.0.0/16");
+        assert_eq!(networks("10.0.0.0/16"), ["10.0.0.0/16"]);
+        assert_eq!(networks("10.0.1.128/25"), ["10.0.1.128/25"]);
+    }
+
+    #[test]
+    fn test_reverse_zone_networks_ipv6() {
+        let networks: Vec<String> = reverse_zone_networks(&"2001:db8:0:8::/61".parse().unwrap())
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(networks.len(), 8);
+        assert_eq!(networks[0], "2001:db8:0:8::/64");
+        assert_eq!(networks[7], "2001:db8:0:f::/64");
+
+        let net = "2001:db8::/126".parse().unwrap();
+        assert_eq!(reverse_zone_networks(&net), [net]);
+        let (name, split) = create_reverse_zone_name(&net);
+        assert_eq!(name.split('.').count(), 31 + 3);
+        assert_eq!(split, 1);
+    }
+
     #[test]
     fn test_create_reverse_zone_name_ipv6() {
         use ipnetwork::Ipv6Network;
@@ -643,4 +2126,43 @@ mod tests {
         let name = ip_name(&ip, 4);
         assert_eq!(name, "5.0.0.0");
     }
+
+    #[test]
+    fn test_check_soa_timers() {
+        assert!(check_soa_timers("example.com.", 7200, 3600, 1209600).is_ok());
+        assert_eq!(
+            check_soa_timers("example.com.", 3600, 3600, 5000)
+                .unwrap_err()
+                .to_string(),
+            "SOA timers of example.com.: retry (3600) must be less than refresh (3600), \
+             expire (5000) must be at least refresh + retry (7200)"
+        );
+    }
+
+    #[test]
+    fn test_eui64_with_prefix() {
+        let id = eui64("00:1A:2b:3c:4d:5e").unwrap();
+        assert_eq!(id, "::21a:2bff:fe3c:4d5e".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(eui64("00-1a-2b-3c-4d-5e").unwrap(), id);
+        let invalid = [
+            "00:1a:2b:3c:4d",
+            "00:1a:2b:3c:4d:5e:6f",
+            "0:1a:2b:3c:4d:5e",
+            "zz:1a:2b:3c:4d:5e",
+        ];
+        for mac in invalid {
+            assert!(eui64(mac).is_err(), "{mac}");
+        }
+
+        let prefix: Ipv6Network = "2001:db8:1::/64".parse().unwrap();
+        assert_eq!(
+            with_prefix(&prefix, id).unwrap().to_string(),
+            "2001:db8:1:0:21a:2bff:fe3c:4d5e"
+        );
+        let prefix: Ipv6Network = "2001:db8:1::/112".parse().unwrap();
+        assert_eq!(
+            with_prefix(&prefix, id).unwrap_err().to_string(),
+            "Suffix ::21a:2bff:fe3c:4d5e does not fit into the host part of 2001:db8:1::/112"
+        );
+    }
 }
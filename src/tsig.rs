@@ -0,0 +1,232 @@
+//! TSIG keys authenticating zone transfers and notifies between the primary
+//! and its secondaries. Secrets are stored in the BIND key file format, as
+//! written by `tsig-keygen`, so they can be handed to the secondaries as is.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::io::{self, Read, Write};
+use std::net::IpAddr;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::path::PathBuf;
+
+#[cfg(feature = "fs")]
+use anyhow::Context;
+use anyhow::{bail, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// HMAC of the TSIG keys
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TsigAlgorithm {
+    #[default]
+    HmacSha256,
+    HmacSha512,
+}
+
+impl TsigAlgorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            TsigAlgorithm::HmacSha256 => "hmac-sha256",
+            TsigAlgorithm::HmacSha512 => "hmac-sha512",
+        }
+    }
+
+    /// Secrets as long as the hash, as `tsig-keygen` creates them
+    pub fn secret_len(self) -> usize {
+        match self {
+            TsigAlgorithm::HmacSha256 => 32,
+            TsigAlgorithm::HmacSha512 => 64,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [TsigAlgorithm::HmacSha256, TsigAlgorithm::HmacSha512]
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// `tsig` section of the config
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TsigConfig {
+    /// Directory the secrets are stored in
+    pub key_dir: PathBuf,
+    /// Algorithm of new keys
+    #[serde(default, skip_serializing_if = "is_default_algorithm")]
+    pub algorithm: TsigAlgorithm,
+    /// Name of each key and the addresses of the secondaries using it
+    pub keys: BTreeMap<String, Vec<IpAddr>>,
+}
+
+fn is_default_algorithm(algorithm: &TsigAlgorithm) -> bool {
+    *algorithm == TsigAlgorithm::default()
+}
+
+/// A TSIG key and the secondaries transferring the zones with it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsigKey {
+    pub name: String,
+    pub algorithm: TsigAlgorithm,
+    /// Base64 of the secret
+    pub secret: String,
+    pub secondaries: Vec<IpAddr>,
+}
+
+impl TsigKey {
+    /// New key with a random secret from the operating system
+    #[cfg(feature = "fs")]
+    pub fn generate(name: &str, algorithm: TsigAlgorithm) -> Result<TsigKey> {
+        validate_key_name(name)?;
+        let mut secret = vec![0u8; algorithm.secret_len()];
+        fs::File::open("/dev/urandom")
+            .and_then(|mut random| random.read_exact(&mut secret))
+            .context("Cannot read random bytes for a new TSIG key")?;
+        Ok(TsigKey {
+            name: name.to_string(),
+            algorithm,
+            secret: BASE64.encode(&secret),
+            secondaries: Vec::new(),
+        })
+    }
+
+    /// Content of the key file
+    pub fn key_file(&self) -> String {
+        format!(
+            "key \"{}\" {{\n\talgorithm {};\n\tsecret \"{}\";\n}};\n",
+            self.name,
+            self.algorithm.name(),
+            self.secret
+        )
+    }
+
+    /// Reads the key `name` from a key file
+    pub fn parse(name: &str, content: &str) -> Result<TsigKey> {
+        validate_key_name(name)?;
+        let tokens: Vec<&str> = content
+            .split(|c: char| c.is_whitespace() || matches!(c, ';' | '{' | '}'))
+            .filter(|token| !token.is_empty())
+            .map(|token| token.trim_matches('"'))
+            .collect();
+        let value = |keyword: &str| {
+            tokens
+                .windows(2)
+                .find(|pair| pair[0] == keyword)
+                .map(|pair| pair[1])
+        };
+        if value("key") != Some(name) {
+            bail!("No key {name} in the key file");
+        }
+        let Some(algorithm) = value("algorithm") else {
+            bail!("Key {name} has no algorithm");
+        };
+        let Some(algorithm) = TsigAlgorithm::from_name(algorithm) else {
+            bail!("Key {name} uses {algorithm}, only hmac-sha256 and hmac-sha512 are supported");
+        };
+        let Some(secret) = value("secret") else {
+            bail!("Key {name} has no secret");
+        };
+        BASE64.decode(secret)?;
+        Ok(TsigKey {
+            name: name.to_string(),
+            algorithm,
+            secret: secret.to_string(),
+            secondaries: Vec::new(),
+        })
+    }
+
+    /// Reads the key `name` from `dir`, `None` if it has no key file yet
+    #[cfg(feature = "fs")]
+    pub fn load(dir: &Path, name: &str) -> Result<Option<TsigKey>> {
+        validate_key_name(name)?;
+        let path = key_path(dir, name);
+        match fs::read_to_string(&path) {
+            Ok(content) => TsigKey::parse(name, &content)
+                .map(Some)
+                .with_context(|| format!("Invalid TSIG key file {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Cannot read {}", path.display())),
+        }
+    }
+
+    /// Writes the key file to `dir`, only readable by the owner. Returns its
+    /// path.
+    #[cfg(feature = "fs")]
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create TSIG key directory {}", dir.display()))?;
+        let path = key_path(dir, &self.name);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(&path)
+            .and_then(|mut file| file.write_all(self.key_file().as_bytes()))
+            .with_context(|| format!("Cannot create {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Path of the key file of `name` in `dir`
+#[cfg(feature = "fs")]
+pub fn key_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.tsig"))
+}
+
+/// Key names end up in file names and the NSD config, so only letters,
+/// digits, `-`, `_` and inner dots are allowed
+fn validate_key_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        bail!("Invalid TSIG key name '{name}'");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_file() {
+        let content = "key \"transfer\" {\n\talgorithm hmac-sha256;\n\tsecret \"c2VjcmV0\";\n};\n";
+        let key = TsigKey::parse("transfer", content).unwrap();
+        assert_eq!(key.algorithm, TsigAlgorithm::HmacSha256);
+        assert_eq!(key.secret, "c2VjcmV0");
+        assert_eq!(key.key_file(), content);
+
+        let err = TsigKey::parse("other", content).unwrap_err();
+        assert!(err.to_string().contains("No key other"), "{err}");
+        let md5 = content.replace("hmac-sha256", "hmac-md5");
+        let err = TsigKey::parse("transfer", &md5).unwrap_err();
+        assert!(err.to_string().contains("hmac-md5"), "{err}");
+        assert!(TsigKey::parse("transfer", &content.replace("c2VjcmV0", "c2Vj!")).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_generate_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(TsigKey::load(dir.path(), "ns2").unwrap(), None);
+        let key = TsigKey::generate("ns2", TsigAlgorithm::HmacSha512).unwrap();
+        assert_eq!(BASE64.decode(&key.secret).unwrap().len(), 64);
+        key.save(dir.path()).unwrap();
+        assert_eq!(TsigKey::load(dir.path(), "ns2").unwrap(), Some(key.clone()));
+        // An existing key is never overwritten
+        assert!(key.save(dir.path()).is_err());
+
+        for name in ["", "../ns2", ".ns2", "ns 2"] {
+            assert!(TsigKey::generate(name, TsigAlgorithm::HmacSha256).is_err());
+        }
+    }
+}
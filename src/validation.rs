@@ -38,6 +38,12 @@ pub fn validate_dns_name(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Whether a name is a wildcard like `*.example.com.`, which `validate_dns_name`
+/// only allows with `*` as its whole leftmost label
+pub fn is_wildcard(name: &str) -> bool {
+    name == "*" || name.starts_with("*.")
+}
+
 pub fn validate_email(email: &str) -> Result<()> {
     // Validiere normale Email-Adresse (user@example.com)
     if email.len() > 254 {
@@ -59,11 +65,15 @@ pub fn validate_email(email: &str) -> Result<()> {
         bail!("Email local part cannot start or end with '.': {}", local);
     }
     if local.contains("..") {
-        bail!("Email local part cannot contain consecutive dots: {}", local);
-    }
-    if !local.chars().all(|c| {
-        c.is_alphanumeric() || c == '.' || c == '+' || c == '-' || c == '_'
-    }) {
+        bail!(
+            "Email local part cannot contain consecutive dots: {}",
+            local
+        );
+    }
+    if !local
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '.' || c == '+' || c == '-' || c == '_')
+    {
         bail!("Email local part contains invalid characters: {}", local);
     }
 
@@ -72,7 +82,10 @@ pub fn validate_email(email: &str) -> Result<()> {
         bail!("Email domain (after @) cannot be empty");
     }
     if !domain.contains('.') {
-        bail!("Email domain must contain at least one dot (e.g., 'example.com'): {}", domain);
+        bail!(
+            "Email domain must contain at least one dot (e.g., 'example.com'): {}",
+            domain
+        );
     }
 
     // Validiere Domain-Labels
@@ -129,6 +142,14 @@ mod tests {
         assert!(validate_dns_name("sub*.example.com.").is_err());
     }
 
+    #[test]
+    fn test_is_wildcard() {
+        assert!(is_wildcard("*.example.com."));
+        assert!(is_wildcard("*"));
+        assert!(!is_wildcard("*sub.example.com."));
+        assert!(!is_wildcard("sub.example.com."));
+    }
+
     #[test]
     fn test_validate_dns_name_missing_dot() {
         assert!(validate_dns_name("example.com").is_err());
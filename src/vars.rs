@@ -0,0 +1,352 @@
+//! Variables of the `vars` section, referenced as `{{vars.name}}` in names,
+//! addresses and emails. They are expanded when the config is resolved, so
+//! `fmt` and `convert` keep the references as written.
+
+use std::ops::Range;
+
+use anyhow::{anyhow, bail, Result};
+use indexmap::IndexMap;
+
+use crate::error::Errors;
+use crate::parser::{
+    Content, Email, HostAddress, HostValue, Inherit, MxEntry, NameserverEntry, RawDefaults,
+    ReverseEntry, ReverseValue, SingleOrVecValue, StringOrTableValue, Zone, ZoneBaseEntry,
+};
+use crate::validation::validate_email;
+
+/// Whether `text` refers to a variable
+pub fn has_vars(text: &str) -> bool {
+    references(text).next().is_some()
+}
+
+/// Positions and names of the `{{vars.name}}` references in `text`. Other
+/// `{{...}}` are left alone.
+fn references(text: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        while let Some(start) = text[pos..].find("{{").map(|i| pos + i) {
+            let end = text[start..].find("}}").map(|i| start + i + 2)?;
+            if let Some(name) = text[start + 2..end - 2].trim().strip_prefix("vars.") {
+                pos = end;
+                return Some((start..end, name));
+            }
+            pos = start + 2;
+        }
+        None
+    })
+}
+
+struct Vars<'a>(&'a IndexMap<String, String>);
+
+impl Vars<'_> {
+    fn expand(&self, text: &str) -> Result<String> {
+        let mut expanded = String::new();
+        let mut last = 0;
+        for (range, name) in references(text) {
+            let Some(value) = self.0.get(name) else {
+                bail!("Unknown variable vars.{name} in '{text}'");
+            };
+            expanded.push_str(&text[last..range.start]);
+            expanded.push_str(value);
+            last = range.end;
+        }
+        expanded.push_str(&text[last..]);
+        Ok(expanded)
+    }
+
+    fn string(&self, text: &mut String) -> Result<()> {
+        *text = self.expand(text)?;
+        Ok(())
+    }
+
+    fn strings(&self, texts: Option<&mut SingleOrVecValue<String>>) -> Result<()> {
+        texts
+            .into_iter()
+            .flat_map(SingleOrVecValue::iter_mut)
+            .try_for_each(|text| self.string(text))
+    }
+
+    /// Keys of a map, which must stay distinct
+    fn keys<V>(&self, map: &mut IndexMap<String, V>) -> Result<()> {
+        let mut expanded = IndexMap::with_capacity(map.len());
+        for (key, value) in map.drain(..) {
+            let name = self.expand(&key)?;
+            if let Some((first, _)) = expanded.insert(name.clone(), (key.clone(), value)) {
+                bail!("'{first}' and '{key}' are both {name}");
+            }
+        }
+        *map = expanded
+            .into_iter()
+            .map(|(name, (_, value))| (name, value))
+            .collect();
+        Ok(())
+    }
+
+    fn nameservers(
+        &self,
+        nameservers: Option<&mut SingleOrVecValue<StringOrTableValue<NameserverEntry>>>,
+    ) -> Result<()> {
+        for ns in nameservers.into_iter().flat_map(SingleOrVecValue::iter_mut) {
+            match ns {
+                StringOrTableValue::Entry(name) => self.string(name)?,
+                StringOrTableValue::Table(entry) => self.string(&mut entry.name)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn mx(&self, mx: Option<&mut SingleOrVecValue<StringOrTableValue<MxEntry>>>) -> Result<()> {
+        for mx in mx.into_iter().flat_map(SingleOrVecValue::iter_mut) {
+            match mx {
+                StringOrTableValue::Entry(name) => self.string(name)?,
+                StringOrTableValue::Table(entry) => self.string(&mut entry.name)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn base(&self, base: &mut ZoneBaseEntry) -> Result<()> {
+        if let Some(email) = &mut base.email {
+            self.string(email)?;
+        }
+        self.nameservers(base.nameserver.as_mut())
+    }
+
+    fn host(&self, host: &mut HostValue) -> Result<()> {
+        let (ips, suffix) = match host {
+            HostValue::Ip(ips) => (Some(ips), None),
+            HostValue::Entry(entry) => {
+                self.strings(entry.alias.as_mut())?;
+                if let Some(host) = &mut entry.same_as {
+                    self.string(host)?;
+                }
+                if let Some(delegate) = &mut entry.acme_delegate {
+                    self.string(delegate)?;
+                }
+                (entry.ip.as_mut(), entry.ip6_suffix.as_mut())
+            }
+        };
+        let ips = ips.into_iter().flat_map(SingleOrVecValue::iter_mut);
+        for ip in ips.chain(suffix) {
+            if let HostAddress::Vars(text) = ip {
+                *ip = self.expand(text)?.parse().map_err(|e: String| anyhow!(e))?;
+            }
+        }
+        Ok(())
+    }
+    fn defaults(&self, defaults: &mut RawDefaults) -> Result<()> {
+        if let Some(Email(email)) = &mut defaults.email {
+            self.string(email)?;
+            validate_email(email).map_err(|e| anyhow!("Invalid email: {e}"))?;
+        }
+        self.strings(defaults.nameserver.as_mut())?;
+        self.mx(defaults.mx.as_mut())
+    }
+
+    fn zone(&self, zone: &mut Zone) -> Result<()> {
+        self.string(&mut zone.name)?;
+        self.base(&mut zone.base)?;
+        if let Some(Inherit::Zone(parent)) = &mut zone.inherit {
+            self.string(parent)?;
+        }
+        self.mx(zone.mx.as_mut())?;
+        if let Some(hosts) = &mut zone.hosts {
+            self.keys(hosts)?;
+            hosts.values_mut().try_for_each(|host| self.host(host))?;
+        }
+        if let Some(cname) = &mut zone.cname {
+            self.keys(cname)?;
+            for target in cname.values_mut() {
+                match target {
+                    StringOrTableValue::Entry(target) => self.string(target)?,
+                    StringOrTableValue::Table(entry) => self.string(&mut entry.target)?,
+                }
+            }
+        }
+        if let Some(pool) = &mut zone.pool {
+            self.keys(pool)?;
+            for members in pool.values_mut() {
+                self.strings(Some(members))?;
+            }
+        }
+        if let Some(srv) = &mut zone.srv {
+            self.keys(&mut srv.0)?;
+            for entry in srv.0.values_mut() {
+                self.string(&mut entry.target)?;
+            }
+        }
+        for hosts in zone.services.iter_mut().flat_map(|s| s.values_mut()) {
+            self.strings(Some(hosts))?;
+        }
+        if let Some(delegate) = &mut zone.acme_delegate {
+            self.string(delegate)?;
+        }
+        if let Some(mail) = &mut zone.mail {
+            self.string(&mut mail.host)?;
+        }
+        if let Some(delegate) = &mut zone.delegate {
+            self.keys(delegate)?;
+            for ns in delegate.values_mut().flat_map(SingleOrVecValue::iter_mut) {
+                match ns {
+                    StringOrTableValue::Entry(name) => self.string(name)?,
+                    StringOrTableValue::Table(entry) => self.string(&mut entry.name)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn reverse(&self, entry: &mut ReverseEntry) -> Result<()> {
+        self.base(&mut entry.base)?;
+        for nameservers in entry.delegate.iter_mut().flat_map(|d| d.values_mut()) {
+            self.nameservers(Some(nameservers))?;
+        }
+        if let Some(pattern) = &mut entry.fill_ptr {
+            self.string(pattern)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_var(name: &str, value: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if !valid {
+        bail!("Invalid variable name '{name}', only letters, digits, '-' and '_' are allowed");
+    }
+    if has_vars(value) {
+        bail!("Variable {name} refers to another variable");
+    }
+    Ok(())
+}
+
+/// Replaces the references to variables in the config by their values. The
+/// sections, zones and networks are expanded independently, so the errors of
+/// all of them are reported. Zones that fail are kept for the zones inheriting
+/// from them but not resolved themselves, networks that fail are left out.
+pub fn expand_vars(content: &mut Content) -> Result<()> {
+    let mut errors = Errors::default();
+    let mut defined = content.vars.clone().unwrap_or_default();
+    defined.retain(|name, value| errors.check(check_var(name, value)).is_some());
+    let vars = Vars(&defined);
+
+    if let Some(defaults) = &mut content.defaults {
+        if let Err(err) = vars.defaults(defaults) {
+            errors.push(Some("defaults"), err);
+        }
+    }
+
+    for (name, group) in content.groups.iter_mut().flatten() {
+        if let Err(err) = vars
+            .base(&mut group.base)
+            .and_then(|_| vars.mx(group.mx.as_mut()))
+        {
+            errors.push(Some(name), err);
+        }
+    }
+
+    let mut failed = Vec::new();
+    if content.zone.is_some() {
+        for zone in content.zones_mut() {
+            if let Err(err) = vars.zone(zone) {
+                errors.push(Some(&zone.name), err);
+                failed.push(zone.name.clone());
+            }
+        }
+    }
+
+    if let Some(ReverseValue::Entry(entries)) = &mut content.reverse {
+        entries.retain(|network, entry| match vars.reverse(entry) {
+            Ok(()) => true,
+            Err(err) => {
+                errors.push(Some(&network.to_string()), err);
+                false
+            }
+        });
+    }
+
+    for name in &mut content.dropped_zones {
+        errors.check(vars.string(name));
+    }
+    content.dropped_zones.extend(failed);
+
+    // Keys with unknown variables fail above, unless they are outside of zones
+    for part in content.origins.0.iter_mut().flat_map(|(path, _)| path) {
+        if let Ok(expanded) = vars.expand(part) {
+            *part = expanded;
+        }
+    }
+    errors.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references() {
+        let refs: Vec<_> = references("{{vars.a}}-{{ vars.b }}.{{other}}{{vars.c").collect();
+        assert_eq!(refs, [(0..10, "a"), (11..23, "b")]);
+        assert!(has_vars("ns1.{{vars.domain}}"));
+        assert!(!has_vars("dhcp-{ip}.example.com."));
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_expand_vars() {
+        use crate::args::InputFormat;
+        use crate::parser::{format_config, parse, parse_config};
+
+        let config = |hosts: &str| {
+            format!(
+                "vars:\n  ns1: ns1.example.net.\n  lan: 10.0.2\n  admin: hostmaster@example.net\n\
+                 defaults:\n  email: '{{{{vars.admin}}}}'\n  nameserver: '{{{{ vars.ns1 }}}}'\n\
+                 zone:\n  example.com:\n    hosts:\n{hosts}reverse:\n  - 10.0.2.0/24\n"
+            )
+        };
+        let hosts =
+            "      www: '{{vars.lan}}.5'\n      node-[1-2]:\n        ip: '{{vars.lan}}.[10-11]'\n";
+        let zones = parse(&config(hosts), 2025060100, InputFormat::Yaml).unwrap();
+        let zone = &zones.forward[0];
+        assert_eq!(zone.base.email, "hostmaster.example.net.");
+        assert_eq!(zone.base.nameserver[0].name, "ns1.example.net.");
+        let records: Vec<String> = zone
+            .hosts
+            .iter()
+            .map(|host| format!("{} {}", host.name, host.ip))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                "www.example.com. 10.0.2.5",
+                "node-1.example.com. 10.0.2.10",
+                "node-2.example.com. 10.0.2.11"
+            ]
+        );
+
+        // The raw config keeps the references
+        let content = parse_config(&config(hosts), InputFormat::Yaml).unwrap();
+        let yaml = format_config(&content, InputFormat::Yaml).unwrap();
+        assert!(yaml.contains("{{vars.lan}}.[10-11]"), "{yaml}");
+
+        let err = |hosts: &str| {
+            parse(&config(hosts), 2025060100, InputFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("      www: '{{vars.wan}}.5'\n"),
+            "Unknown variable vars.wan in '{{vars.wan}}.5'"
+        );
+        assert_eq!(
+            err("      www: '{{vars.lan}}.500'\n"),
+            "'10.0.2.500' is not a valid IP address"
+        );
+        assert_eq!(
+            err("      '{{vars.ns1}}': 10.0.2.1\n      ns1.example.net.: 10.0.2.2\n"),
+            "'{{vars.ns1}}' and 'ns1.example.net.' are both ns1.example.net."
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::args::{ConfChecker, ZoneChecker};
+use crate::error::Errors;
+use tempfile::TempDir;
+
+impl ZoneChecker {
+    pub fn program(self) -> &'static str {
+        match self {
+            ZoneChecker::NsdCheckzone => "nsd-checkzone",
+            ZoneChecker::NamedCheckzone => "named-checkzone",
+            ZoneChecker::Kzonecheck => "kzonecheck",
+        }
+    }
+
+    fn command(self, zone: &str, path: &Path) -> Command {
+        let mut command = Command::new(self.program());
+        if self == ZoneChecker::Kzonecheck {
+            command.arg("--origin");
+        }
+        command.arg(zone).arg(path);
+        command
+    }
+}
+
+impl ConfChecker {
+    pub fn program(self) -> &'static str {
+        match self {
+            ConfChecker::UnboundCheckconf => "unbound-checkconf",
+            ConfChecker::NsdCheckconf => "nsd-checkconf",
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            ConfChecker::UnboundCheckconf => "unbound.conf",
+            ConfChecker::NsdCheckconf => "nsd.conf",
+        }
+    }
+
+    /// Settings appended to the checked config: the chroot and user of a
+    /// packaged Unbound reject a config in a temporary directory
+    fn overrides(self) -> &'static str {
+        match self {
+            ConfChecker::UnboundCheckconf => "server:\n    chroot: \"\"\n    username: \"\"\n",
+            ConfChecker::NsdCheckconf => "",
+        }
+    }
+}
+
+/// Runs `command` and fails with its output if it exits unsuccessfully
+fn run_checker(mut command: Command, path: &Path) -> Result<()> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .with_context(|| format!("Cannot run {program}"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let mut message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if message.is_empty() {
+        message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    }
+    Err(anyhow!(
+        "{program} rejected {} ({}): {message}",
+        path.display(),
+        output.status
+    ))
+}
+
+/// Checks the zone files with an external checker before they replace the
+/// deployed ones. `staged` maps the path of every file below `config_dir`,
+/// the NSD config directory, to the temporary file holding its content. The
+/// checker runs in a private copy of that directory, so the `$INCLUDE` paths
+/// of split zones find the new part files. The rejections of all zones are
+/// reported together.
+pub fn verify_zones<'a>(
+    checker: ZoneChecker,
+    config_dir: &Path,
+    staged: impl IntoIterator<Item = (&'a Path, &'a Path)>,
+    zone_files: impl IntoIterator<Item = (&'a str, &'a Path)>,
+) -> Result<()> {
+    let dir = stage_zones(config_dir, staged)?;
+    // The checker resolves the zone file in its own working directory
+    let root = fs::canonicalize(dir.path())?;
+    let mut errors = Errors::default();
+    for (zone, path) in zone_files {
+        let Some(staged) = path
+            .strip_prefix(config_dir)
+            .ok()
+            .map(|relative| root.join(relative))
+            .filter(|staged| staged.exists())
+        else {
+            bail!("Zone file {} is not staged", path.display());
+        };
+        let mut command = checker.command(zone, &staged);
+        command.current_dir(&root);
+        if let Err(err) = run_checker(command, path) {
+            // A missing checker fails every zone the same way
+            if err.chain().any(|e| e.is::<std::io::Error>()) {
+                return Err(err);
+            }
+            errors.push(Some(zone), err);
+        }
+    }
+    errors.finish()
+}
+
+/// Links the staged files into a new private directory below `config_dir`
+/// by their paths relative to it
+fn stage_zones<'a>(
+    config_dir: &Path,
+    staged: impl IntoIterator<Item = (&'a Path, &'a Path)>,
+) -> Result<TempDir> {
+    let dir = tempfile::Builder::new()
+        .prefix(".zonefile-rs-verify-")
+        .tempdir_in(config_dir)
+        .with_context(|| format!("Cannot stage zone files in {}", config_dir.display()))?;
+    for (path, tmp) in staged {
+        let Ok(relative) = path.strip_prefix(config_dir) else {
+            continue;
+        };
+        let link = dir.path().join(relative);
+        let stage = || -> io::Result<()> {
+            if let Some(parent) = link.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // A copy where hard links are not supported
+            fs::hard_link(tmp, &link).or_else(|_| fs::copy(tmp, &link).map(drop))
+        };
+        stage().with_context(|| format!("Cannot stage {} for the checker", path.display()))?;
+    }
+    Ok(dir)
+}
+
+/// Checks a generated server config with the checker of the server before
+/// it is written. The config is staged in a new private temporary directory,
+/// so nothing is deployed unless the checker accepts it. `target` names the config in
+/// the error. The config is read from `config`, e.g. the temporary file of
+/// the output, so it needs not be rendered again.
+pub fn check_config(checker: ConfChecker, config: impl Read, target: &str) -> Result<()> {
+    let dir = tempfile::Builder::new()
+        .prefix("zonefile-rs-check-")
+        .tempdir()
+        .context("Cannot create a directory to stage the config in")?;
+    stage_and_check(checker.program(), checker, config, dir.path(), target)
+}
+
+fn stage_and_check(
+    program: &str,
+    checker: ConfChecker,
+    mut config: impl Read,
+    dir: &Path,
+    target: &str,
+) -> Result<()> {
+    let path = dir.join(checker.file_name());
+    let mut stage = || -> io::Result<()> {
+        let mut file = File::create(&path)?;
+        io::copy(&mut config, &mut file)?;
+        file.write_all(checker.overrides().as_bytes())
+    };
+    stage().with_context(|| format!("Cannot stage {} for {program}", path.display()))?;
+    let mut command = Command::new(program);
+    command.arg(&path);
+    run_checker(command, Path::new(target))
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_checker() {
+        let path = Path::new("master/example.com.zone");
+        let mut accept = Command::new("sh");
+        accept.args(["-c", "exit 0"]);
+        assert!(run_checker(accept, path).is_ok());
+
+        let mut reject = Command::new("sh");
+        reject.args(["-c", "echo 'example.com.: bad record' >&2; exit 1"]);
+        let err = run_checker(reject, path).unwrap_err().to_string();
+        assert!(
+            err.starts_with("sh rejected master/example.com.zone"),
+            "{err}"
+        );
+        assert!(err.ends_with(": example.com.: bad record"), "{err}");
+
+        let missing = Command::new("zonefile-rs-missing-checker");
+        let err = run_checker(missing, path).unwrap_err();
+        assert!(err.chain().any(|e| e.is::<std::io::Error>()));
+        assert_eq!(err.to_string(), "Cannot run zonefile-rs-missing-checker");
+    }
+
+    #[test]
+    fn test_stage_and_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let stage = tempfile::tempdir().unwrap();
+        let stage = stage.path();
+        // The staged config is the only argument, with the overrides appended
+        let script = dir.path().join("checker");
+        fs::write(
+            &script,
+            "#!/bin/sh\ngrep -q 'username: \"\"' \"$1\" || exit 1\n",
+        )
+        .unwrap();
+        let mode = std::os::unix::fs::PermissionsExt::from_mode(0o755);
+        fs::set_permissions(&script, mode).unwrap();
+        let program = script.to_str().unwrap();
+
+        let config = "server:\n    local-zone: \"example.com.\" static\n";
+        let checker = ConfChecker::UnboundCheckconf;
+        let result = stage_and_check(program, checker, config.as_bytes(), stage, "unbound.conf");
+        assert!(result.is_ok());
+        let staged = fs::read_to_string(stage.join("unbound.conf")).unwrap();
+        assert!(staged.starts_with(config), "{staged}");
+
+        let checker = ConfChecker::NsdCheckconf;
+        let err = stage_and_check(program, checker, &b"zone:\n"[..], stage, "nsd/zones.conf");
+        let err = err.unwrap_err().to_string();
+        assert!(err.contains("rejected nsd/zones.conf"), "{err}");
+    }
+
+    #[test]
+    fn test_stage_zones() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let config_dir = config_dir.path();
+        fs::create_dir(config_dir.join("master")).unwrap();
+        let path = config_dir.join("master/example.com.zone");
+        let tmp = config_dir.join("master/example.com.zone.tmp");
+        fs::write(&tmp, "$INCLUDE master/example.com.zone.w\n").unwrap();
+
+        let dir = stage_zones(config_dir, [(path.as_path(), tmp.as_path())]).unwrap();
+        assert!(dir.path().starts_with(config_dir));
+        let staged = fs::read_to_string(dir.path().join("master/example.com.zone")).unwrap();
+        assert_eq!(staged, "$INCLUDE master/example.com.zone.w\n");
+        // Neither the deployed file nor the temporary one is touched
+        assert!(!path.exists() && tmp.exists());
+
+        let root = dir.path().to_path_buf();
+        drop(dir);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn test_checker_command() {
+        let path = Path::new("master/example.com.zone");
+        let command = ZoneChecker::Kzonecheck.command("example.com.", path);
+        assert_eq!(command.get_program(), "kzonecheck");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            ["--origin", "example.com.", "master/example.com.zone"]
+        );
+        let command = ZoneChecker::NsdCheckzone.command("example.com.", path);
+        assert_eq!(command.get_args().count(), 2);
+    }
+}
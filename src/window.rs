@@ -0,0 +1,283 @@
+//! Validity windows of entries, `not-before` and `not-after` dates between
+//! which their records are generated, e.g. for planned migrations or
+//! temporary services. Entries outside their window are parked as if they
+//! were `disabled`, with a warning.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::check::{Finding, Severity};
+use crate::parser::{Content, HostValue, MxEntry, SingleOrVecValue, StringOrTableValue};
+
+/// Calendar date of a validity window, written as `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Date, String> {
+        let leap =
+            year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+        let days = match month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => {
+                return Err(format!(
+                    "{year:04}-{month:02}-{day:02} has no month {month}"
+                ))
+            }
+        };
+        if !(1..=days).contains(&day) {
+            return Err(format!("{year:04}-{month:02}-{day:02} has no day {day}"));
+        }
+        Ok(Date { year, month, day })
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let invalid = || format!("'{text}' is not a date like 2025-06-01");
+        let parts: Vec<&str> = text.split('-').collect();
+        let [year, month, day] = parts[..] else {
+            return Err(invalid());
+        };
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return Err(invalid());
+        }
+        let number = |part: &str| part.parse::<u16>().map_err(|_| invalid());
+        Date::new(number(year)?, number(month)? as u8, number(day)? as u8)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Takes quoted dates and the bare dates of TOML, which its deserializer
+/// hands out as a map with the date as the only value
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DateVisitor;
+
+        impl<'de> Visitor<'de> for DateVisitor {
+            type Value = Date;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a date like 2025-06-01")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Date, E> {
+                text.parse().map_err(E::custom)
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Date, M::Error> {
+                match map.next_entry::<String, String>()? {
+                    Some((_, text)) => text.parse().map_err(de::Error::custom),
+                    None => Err(de::Error::custom("expected a date like 2025-06-01")),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(DateVisitor)
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Why an entry is outside its window on `today`, `None` inside of it
+fn outside(
+    what: &str,
+    not_before: Option<Date>,
+    not_after: Option<Date>,
+    today: Date,
+) -> Result<Option<String>> {
+    match (not_before, not_after) {
+        (Some(start), Some(end)) if end < start => {
+            bail!("{what} has a not-after date {end} before its not-before date {start}")
+        }
+        (Some(start), _) if today < start => Ok(Some(format!("Skipped {what} until {start}"))),
+        (_, Some(end)) if today > end => Ok(Some(format!("Skipped {what}, expired on {end}"))),
+        _ => Ok(None),
+    }
+}
+
+fn warning(zone: &str, message: String) -> Finding {
+    Finding {
+        rule: "validity-window",
+        zone: zone.to_string(),
+        severity: Severity::Warning,
+        message,
+    }
+}
+
+/// MX entries inside their window, with a warning for every other
+fn mx_in_window(
+    mx: &mut SingleOrVecValue<StringOrTableValue<MxEntry>>,
+    zone: &str,
+    today: Date,
+    warnings: &mut Vec<Finding>,
+) -> Result<()> {
+    let entries = std::mem::replace(mx, SingleOrVecValue::Multiple(Vec::new())).to_vec();
+    let mut kept = Vec::new();
+    for entry in entries {
+        if let StringOrTableValue::Table(t) = &entry {
+            let what = format!("MX {}", t.name);
+            if let Some(message) = outside(&what, t.not_before, t.not_after, today)? {
+                warnings.push(warning(zone, message));
+                continue;
+            }
+        }
+        kept.push(entry);
+    }
+    *mx = SingleOrVecValue::Multiple(kept);
+    Ok(())
+}
+
+/// Parks the hosts, CNAMEs and SRV entries outside their validity window on
+/// `today` and drops such MX entries, warning about each. A zone whose MX
+/// entries all expired has no MX records rather than the default ones.
+pub fn apply_windows(content: &mut Content, today: Date) -> Result<Vec<Finding>> {
+    let mut warnings = Vec::new();
+    if let Some(mx) = content.defaults.as_mut().and_then(|d| d.mx.as_mut()) {
+        mx_in_window(mx, "defaults", today, &mut warnings)?;
+    }
+    for (name, group) in content.groups.iter_mut().flatten() {
+        if let Some(mx) = &mut group.mx {
+            mx_in_window(mx, &format!("group {name}"), today, &mut warnings)?;
+        }
+    }
+    if content.zone.is_none() {
+        return Ok(warnings);
+    }
+    for zone in content.zones_mut() {
+        let zone_name = zone.name.clone();
+        if let Some(mx) = &mut zone.mx {
+            mx_in_window(mx, &zone_name, today, &mut warnings)?;
+        }
+        for (name, host) in zone.hosts.iter_mut().flat_map(|hosts| hosts.iter_mut()) {
+            let HostValue::Entry(entry) = host else {
+                continue;
+            };
+            let what = format!("host '{name}'");
+            if entry.disabled != Some(true) {
+                if let Some(message) = outside(&what, entry.not_before, entry.not_after, today)? {
+                    warnings.push(warning(&zone_name, message));
+                    entry.disabled = Some(true);
+                }
+            }
+        }
+        for (name, cname) in zone.cname.iter_mut().flat_map(|cname| cname.iter_mut()) {
+            let StringOrTableValue::Table(entry) = cname else {
+                continue;
+            };
+            let what = format!("CNAME '{name}'");
+            if entry.disabled != Some(true) {
+                if let Some(message) = outside(&what, entry.not_before, entry.not_after, today)? {
+                    warnings.push(warning(&zone_name, message));
+                    entry.disabled = Some(true);
+                }
+            }
+        }
+        for (name, entry) in zone.srv.iter_mut().flat_map(|srv| srv.0.iter_mut()) {
+            let what = format!("SRV '{name}'");
+            if entry.disabled != Some(true) {
+                if let Some(message) = outside(&what, entry.not_before, entry.not_after, today)? {
+                    warnings.push(warning(&zone_name, message));
+                    entry.disabled = Some(true);
+                }
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date() {
+        let date: Date = "2024-02-29".parse().unwrap();
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert!(date < "2024-03-01".parse().unwrap());
+        for text in [
+            "2025-02-29",
+            "2025-13-01",
+            "2025-6-1",
+            "01.06.2025",
+            "2025-06-01T00:00",
+        ] {
+            assert!(text.parse::<Date>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_outside() {
+        let date = |text: &str| text.parse::<Date>().ok();
+        let today = date("2025-06-01").unwrap();
+        let window = |start: &str, end: &str| outside("host 'db'", date(start), date(end), today);
+        assert_eq!(window("2025-06-01", "2025-06-01").unwrap(), None);
+        assert_eq!(
+            window("2025-06-02", "").unwrap().as_deref(),
+            Some("Skipped host 'db' until 2025-06-02")
+        );
+        assert_eq!(
+            window("", "2025-05-31").unwrap().as_deref(),
+            Some("Skipped host 'db', expired on 2025-05-31")
+        );
+        assert!(window("2025-07-01", "2025-06-30").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "yaml", feature = "toml"))]
+    fn test_apply_windows() {
+        use crate::args::InputFormat;
+        use crate::parser::{parse_config, resolve};
+
+        let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    mx:\n      - name: mx-old\n        not-after: 2025-05-31\n      - name: mx-new\n        not-before: 2025-06-01\n    hosts:\n      ns1: 10.0.0.1\n      promo:\n        ip: 10.0.0.9\n        not-before: 2025-12-01\n    cname:\n      shop:\n        target: promo\n        not-before: 2025-12-01\n";
+        let mut content = parse_config(yaml, InputFormat::Yaml).unwrap();
+        let warnings = apply_windows(&mut content, "2025-06-01".parse().unwrap()).unwrap();
+        let messages: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "example.com: Skipped MX mx-old, expired on 2025-05-31",
+                "example.com: Skipped host 'promo' until 2025-12-01",
+                "example.com: Skipped CNAME 'shop' until 2025-12-01"
+            ]
+        );
+        let zones = resolve(content, 2025060100).unwrap();
+        let zone = &zones.forward[0];
+        assert_eq!(zone.mx.len(), 1);
+        assert_eq!(zone.mx[0].name, "mx-new.example.com.");
+        assert_eq!(zone.hosts.len(), 1);
+        assert!(zone.cname.is_empty());
+
+        // Bare TOML dates
+        let toml =
+            "[zone.\"example.com\".hosts]\npromo = { ip = \"10.0.0.9\", not-after = 2025-05-31 }\n";
+        let mut content = parse_config(toml, InputFormat::Toml).unwrap();
+        let warnings = apply_windows(&mut content, "2025-06-01".parse().unwrap()).unwrap();
+        assert_eq!(
+            warnings[0].message,
+            "Skipped host 'promo', expired on 2025-05-31"
+        );
+    }
+}
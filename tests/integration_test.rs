@@ -1,5 +1,9 @@
 #[cfg(any(feature = "toml", feature = "yaml"))]
 use std::fs;
+#[cfg(all(feature = "fs", feature = "toml", feature = "yaml"))]
+use zonefile_rs::parser::parse_dir;
+#[cfg(all(feature = "toml", feature = "yaml"))]
+use zonefile_rs::parser::{format_config, parse_config};
 #[cfg(any(feature = "toml", feature = "yaml"))]
 use zonefile_rs::{args::InputFormat, parser::parse};
 
@@ -15,7 +19,8 @@ fn test_parse_zones_toml() {
         result.err()
     );
 
-    let (forward, reverse) = result.unwrap();
+    let zones = result.unwrap();
+    let (forward, reverse) = (zones.forward, zones.reverse);
 
     // Verify we have zones
     assert!(!forward.is_empty(), "No forward zones parsed");
@@ -34,7 +39,9 @@ fn test_parse_zones_toml() {
 #[cfg(feature = "toml")]
 fn test_example_com_zone() {
     let content = fs::read_to_string("zones.toml").expect("Failed to read zones.toml");
-    let (forward, _) = parse(&content, 2025012500, InputFormat::Toml).unwrap();
+    let forward = parse(&content, 2025012500, InputFormat::Toml)
+        .unwrap()
+        .forward;
 
     let example_com = forward
         .iter()
@@ -69,7 +76,9 @@ fn test_example_com_zone() {
 #[cfg(feature = "toml")]
 fn test_reverse_zones() {
     let content = fs::read_to_string("zones.toml").expect("Failed to read zones.toml");
-    let (_, reverse) = parse(&content, 2025012500, InputFormat::Toml).unwrap();
+    let reverse = parse(&content, 2025012500, InputFormat::Toml)
+        .unwrap()
+        .reverse;
 
     // Check we have both IPv4 and IPv6 reverse zones
     let has_ipv4 = reverse.iter().any(|z| z.base.name.contains("in-addr.arpa"));
@@ -92,7 +101,9 @@ fn test_reverse_zones() {
 #[cfg(feature = "toml")]
 fn test_wildcard_host() {
     let content = fs::read_to_string("zones.toml").expect("Failed to read zones.toml");
-    let (forward, _) = parse(&content, 2025012500, InputFormat::Toml).unwrap();
+    let forward = parse(&content, 2025012500, InputFormat::Toml)
+        .unwrap()
+        .forward;
 
     let example_com = forward
         .iter()
@@ -112,7 +123,9 @@ fn test_wildcard_host() {
 #[cfg(feature = "toml")]
 fn test_cname_records() {
     let content = fs::read_to_string("zones.toml").expect("Failed to read zones.toml");
-    let (forward, _) = parse(&content, 2025012500, InputFormat::Toml).unwrap();
+    let forward = parse(&content, 2025012500, InputFormat::Toml)
+        .unwrap()
+        .forward;
 
     let devices_zone = forward
         .iter()
@@ -130,7 +143,9 @@ fn test_cname_records() {
 #[cfg(feature = "toml")]
 fn test_ipv6_addresses() {
     let content = fs::read_to_string("zones.toml").expect("Failed to read zones.toml");
-    let (forward, _) = parse(&content, 2025012500, InputFormat::Toml).unwrap();
+    let forward = parse(&content, 2025012500, InputFormat::Toml)
+        .unwrap()
+        .forward;
 
     let example_com = forward
         .iter()
@@ -164,7 +179,8 @@ fn test_parse_zones_yaml() {
         result.err()
     );
 
-    let (forward, reverse) = result.unwrap();
+    let zones = result.unwrap();
+    let (forward, reverse) = (zones.forward, zones.reverse);
 
     // Verify we have zones
     assert!(!forward.is_empty(), "No forward zones parsed");
@@ -180,7 +196,9 @@ fn test_parse_zones_yaml() {
 #[cfg(feature = "yaml")]
 fn test_example_com_zone_yaml() {
     let content = fs::read_to_string("zones.yaml").expect("Failed to read zones.yaml");
-    let (forward, _) = parse(&content, 2025012500, InputFormat::Yaml).unwrap();
+    let forward = parse(&content, 2025012500, InputFormat::Yaml)
+        .unwrap()
+        .forward;
 
     let example_com = forward
         .iter()
@@ -215,7 +233,9 @@ fn test_example_com_zone_yaml() {
 #[cfg(feature = "yaml")]
 fn test_reverse_zones_yaml() {
     let content = fs::read_to_string("zones.yaml").expect("Failed to read zones.yaml");
-    let (_, reverse) = parse(&content, 2025012500, InputFormat::Yaml).unwrap();
+    let reverse = parse(&content, 2025012500, InputFormat::Yaml)
+        .unwrap()
+        .reverse;
 
     // Check we have both IPv4 and IPv6 reverse zones
     let has_ipv4 = reverse.iter().any(|z| z.base.name.contains("in-addr.arpa"));
@@ -238,7 +258,9 @@ fn test_reverse_zones_yaml() {
 #[cfg(feature = "yaml")]
 fn test_wildcard_host_yaml() {
     let content = fs::read_to_string("zones.yaml").expect("Failed to read zones.yaml");
-    let (forward, _) = parse(&content, 2025012500, InputFormat::Yaml).unwrap();
+    let forward = parse(&content, 2025012500, InputFormat::Yaml)
+        .unwrap()
+        .forward;
 
     let apps_zone = forward
         .iter()
@@ -258,7 +280,9 @@ fn test_wildcard_host_yaml() {
 #[cfg(feature = "yaml")]
 fn test_cname_records_yaml() {
     let content = fs::read_to_string("zones.yaml").expect("Failed to read zones.yaml");
-    let (forward, _) = parse(&content, 2025012500, InputFormat::Yaml).unwrap();
+    let forward = parse(&content, 2025012500, InputFormat::Yaml)
+        .unwrap()
+        .forward;
 
     let apps_zone = forward
         .iter()
@@ -276,7 +300,9 @@ fn test_cname_records_yaml() {
 #[cfg(feature = "yaml")]
 fn test_ipv6_addresses_yaml() {
     let content = fs::read_to_string("zones.yaml").expect("Failed to read zones.yaml");
-    let (forward, _) = parse(&content, 2025012500, InputFormat::Yaml).unwrap();
+    let forward = parse(&content, 2025012500, InputFormat::Yaml)
+        .unwrap()
+        .forward;
 
     let example_com = forward
         .iter()
@@ -295,3 +321,987 @@ fn test_ipv6_addresses_yaml() {
         .collect();
     assert!(!ipv6_hosts.is_empty(), "No IPv6 address for router");
 }
+
+// Config directory tests
+
+#[test]
+#[cfg(all(feature = "fs", feature = "toml", feature = "yaml"))]
+fn test_parse_dir_merges_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("00-defaults.yaml"),
+        "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("10-example.yaml"),
+        "zone:\n  example.com:\n    hosts:\n      www: 192.168.1.2\nreverse: 192.168.1.0/24\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("20-other.toml"),
+        "[[zone]]\nname = \"other.com\"\n[zone.hosts]\nwww = \"192.168.2.2\"\n\n[reverse]\n\"192.168.2.0/24\" = {}\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("README.md"), "ignored").unwrap();
+
+    let zones = parse_dir(dir.path(), 2025012500).unwrap();
+    assert_eq!(zones.source.as_deref(), Some(dir.path()));
+    let (forward, reverse) = (zones.forward, zones.reverse);
+
+    let zone_names: Vec<&str> = forward.iter().map(|z| z.base.name.as_str()).collect();
+    assert_eq!(zone_names, vec!["example.com.", "other.com."]);
+    assert_eq!(reverse.len(), 2);
+    assert!(reverse.iter().all(|z| z.ptr.len() == 1));
+}
+
+#[test]
+#[cfg(all(feature = "fs", feature = "toml", feature = "yaml"))]
+fn test_parse_dir_duplicate_zone() {
+    let dir = tempfile::tempdir().unwrap();
+    let zone = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com: {}\n";
+    fs::write(dir.path().join("a.yaml"), zone).unwrap();
+    fs::write(
+        dir.path().join("b.toml"),
+        "[[zone]]\nname = \"example.com.\"\n",
+    )
+    .unwrap();
+
+    let err = parse_dir(dir.path(), 2025012500).unwrap_err().to_string();
+    assert!(err.contains("b.toml"), "{err}");
+    assert!(err.contains("defined more than once"), "{err}");
+}
+
+#[test]
+#[cfg(all(feature = "toml", feature = "yaml"))]
+fn test_convert_round_trip() {
+    let yaml = fs::read_to_string("zones.yaml").expect("Failed to read zones.yaml");
+    let toml = format_config(
+        &parse_config(&yaml, InputFormat::Yaml).unwrap(),
+        InputFormat::Toml,
+    )
+    .unwrap();
+    let back = format_config(
+        &parse_config(&toml, InputFormat::Toml).unwrap(),
+        InputFormat::Yaml,
+    )
+    .unwrap();
+
+    let zones = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let (forward, reverse) = (zones.forward, zones.reverse);
+    let converted = parse(&toml, 2025012500, InputFormat::Toml).unwrap();
+    let (converted, converted_reverse) = (converted.forward, converted.reverse);
+    let names = |zones: &[zonefile_rs::parser::ForwardZone]| {
+        zones
+            .iter()
+            .map(|z| {
+                (
+                    z.base.name.clone(),
+                    z.hosts.len(),
+                    z.cname.len(),
+                    z.srv.len(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&converted), names(&forward));
+    assert_eq!(converted_reverse.len(), reverse.len());
+
+    let forward_back = parse(&back, 2025012500, InputFormat::Yaml).unwrap().forward;
+    assert_eq!(names(&forward_back), names(&forward));
+    assert_eq!(forward[0].base.name, "internal.example.com.");
+}
+
+#[test]
+#[cfg(all(feature = "toml", feature = "yaml"))]
+fn test_serialized_config_resolves_identically() {
+    // TOML writes plain values before tables, so only the record order may differ
+    let resolved = |raw: &str, format: InputFormat| {
+        let mut zones = parse(raw, 2025012500, format).unwrap();
+        for zone in &mut zones.forward {
+            zone.hosts
+                .sort_by(|a, b| (&a.name, a.ip).cmp(&(&b.name, b.ip)));
+            zone.cname.sort_by(|a, b| a.name.cmp(&b.name));
+            zone.srv.sort_by(|a, b| a.name.cmp(&b.name));
+            zone.ptr.sort_by_key(|ptr| ptr.ip);
+        }
+        for zone in &mut zones.reverse {
+            zone.ptr.sort_by_key(|ptr| ptr.ip);
+        }
+        serde_json::to_value(zones).unwrap()
+    };
+
+    for (file, format) in [
+        ("zones.yaml", InputFormat::Yaml),
+        ("zones.toml", InputFormat::Toml),
+    ] {
+        let raw = fs::read_to_string(file).unwrap();
+        let expected = resolved(&raw, format.clone());
+        let config = parse_config(&raw, format).unwrap();
+
+        for target in [InputFormat::Yaml, InputFormat::Toml] {
+            let written = format_config(&config, target.clone()).unwrap();
+            assert_eq!(resolved(&written, target), expected, "{file}");
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_modify_config_before_resolve() {
+    use zonefile_rs::parser::{parse_config, resolve, HostValue, SingleOrVecValue};
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com: {}\n";
+    let mut config = parse_config(yaml, InputFormat::Yaml).unwrap();
+
+    let zone = config
+        .zones_mut()
+        .iter_mut()
+        .find(|z| z.name == "example.com")
+        .unwrap();
+    zone.hosts.get_or_insert_default().insert(
+        "printer".to_string(),
+        HostValue::Ip(SingleOrVecValue::Single("192.168.1.9".parse().unwrap())),
+    );
+
+    let zones = resolve(config, 2025012500).unwrap();
+    assert_eq!(zones.forward[0].hosts[0].name, "printer.example.com.");
+    assert_eq!(zones.forward[0].ptr.len(), 1);
+}
+
+#[test]
+#[cfg(all(feature = "fs", feature = "yaml", feature = "unbound", feature = "nsd"))]
+fn test_streamed_output_matches_generated() {
+    use zonefile_rs::output::nsd::{generate_nsd, write_nsd, ZoneLayout};
+    use zonefile_rs::output::unbound::{generate_unbound, write_unbound};
+
+    let yaml = fs::read_to_string("zones.yaml").unwrap();
+    let zones = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap();
+
+    let mut streamed = Vec::new();
+    write_unbound(&mut streamed, &zones.forward, &zones.reverse, &[], 46).unwrap();
+    assert_eq!(
+        String::from_utf8(streamed).unwrap(),
+        generate_unbound(&zones.forward, &zones.reverse, &[], 46).unwrap()
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    write_nsd(
+        dir.path(),
+        &zones.forward,
+        &zones.reverse,
+        32,
+        ZoneLayout::default(),
+    )
+    .unwrap();
+    let files = generate_nsd(
+        dir.path(),
+        &zones.forward,
+        &zones.reverse,
+        32,
+        ZoneLayout::default(),
+    );
+    for file in files.unwrap() {
+        assert_eq!(fs::read_to_string(&file.path).unwrap(), file.content);
+    }
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "unbound", feature = "nsd"))]
+fn test_generators_reject_zone_without_nameserver() {
+    use zonefile_rs::output::nsd::{generate_nsd, ZoneLayout};
+    use zonefile_rs::output::unbound::generate_unbound;
+
+    let yaml = fs::read_to_string("zones.yaml").unwrap();
+    let mut zones = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap();
+    zones.forward[0].base.nameserver.clear();
+
+    let err = generate_unbound(&zones.forward, &zones.reverse, &[], 46).unwrap_err();
+    assert!(err.to_string().contains("needs a nameserver"), "{err}");
+    let dir = tempfile::tempdir().unwrap();
+    let files = generate_nsd(
+        dir.path(),
+        &zones.forward,
+        &zones.reverse,
+        32,
+        ZoneLayout::default(),
+    );
+    assert!(files.is_err());
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd"))]
+fn test_zones_conf_with_tsig() {
+    use zonefile_rs::output::nsd::{render_zones_conf, ZoneLayout};
+    use zonefile_rs::tsig::{TsigAlgorithm, TsigKey};
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let key = TsigKey {
+        name: "ns2".to_string(),
+        algorithm: TsigAlgorithm::HmacSha256,
+        secret: "c2VjcmV0".to_string(),
+        secondaries: vec![
+            "192.0.2.53".parse().unwrap(),
+            "2001:db8::53".parse().unwrap(),
+        ],
+    };
+    let layout = ZoneLayout::default();
+    let conf = render_zones_conf(&zones.forward, &zones.reverse, &[], &[key], layout).unwrap();
+    assert_eq!(
+        conf,
+        "key:\n    name: \"ns2\"\n    algorithm: hmac-sha256\n    secret: \"c2VjcmV0\"\n\n\
+         zone:\n    name: example.com.\n    zonefile: master/example.com.zone\n\
+         \x20   notify: 192.0.2.53 ns2\n    provide-xfr: 192.0.2.53 ns2\n\
+         \x20   notify: 2001:db8::53 ns2\n    provide-xfr: 2001:db8::53 ns2\n\n"
+    );
+    let plain = render_zones_conf(&zones.forward, &zones.reverse, &[], &[], layout).unwrap();
+    assert_eq!(
+        plain,
+        "zone:\n    name: example.com.\n    zonefile: master/example.com.zone\n\n"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd"))]
+fn test_zones_conf_nsd_options() {
+    use zonefile_rs::output::nsd::{render_zones_conf, ZoneLayout};
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\n  nsd:\n    notify: 192.0.2.2 NOKEY\nzone:\n  example.com:\n    nsd:\n      pattern: secondaries\n      allow-transfer: [192.0.2.2 NOKEY, 192.0.2.3 NOKEY]\n      outgoing-interface: 192.0.2.1\n    hosts:\n      ns1: 10.0.1.1\nreverse: 10.0.1.0/24\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let layout = ZoneLayout::default();
+    let conf = render_zones_conf(&zones.forward, &zones.reverse, &[], &[], layout).unwrap();
+    assert_eq!(
+        conf,
+        "zone:\n    name: example.com.\n    zonefile: master/example.com.zone\n\
+         \x20   include-pattern: secondaries\n\
+         \x20   provide-xfr: 192.0.2.2 NOKEY\n    provide-xfr: 192.0.2.3 NOKEY\n\
+         \x20   outgoing-interface: 192.0.2.1\n\n\
+         zone:\n    name: 1.0.10.in-addr.arpa.\n    zonefile: master/1.0.10.in-addr.arpa.zone\n\
+         \x20   notify: 192.0.2.2 NOKEY\n\n"
+    );
+
+    let yaml = yaml.replace("pattern: secondaries", "pattern: two words");
+    let err = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap_err();
+    assert!(err.to_string().contains("more than one name"), "{err}");
+    let yaml = yaml.replace("pattern: two words", "notify: \"192.0.2.2 NOKEY # x\"");
+    let err = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap_err();
+    assert!(err.to_string().contains("NSD option notify"), "{err}");
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd"))]
+fn test_zone_file_template() {
+    use zonefile_rs::output::nsd::{check_file_template, generate_nsd, zone_file, ZoneLayout};
+
+    assert_eq!(zone_file("{zone}zone", "example.com."), "example.com.zone");
+    assert_eq!(zone_file("db.{zone}", "example.com."), "db.example.com");
+    assert_eq!(zone_file("{name}.db", "example.com."), "example.com.db");
+    assert!(check_file_template("db.{name}").is_ok());
+    for (template, error) in [
+        ("zone.db", "neither {zone} nor {name}"),
+        ("{name}/db", "or a '/'"),
+        ("{origin}.{name}", "braces other than"),
+    ] {
+        let err = check_file_template(template).unwrap_err();
+        assert!(err.to_string().contains(error), "{err}");
+    }
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\nreverse: 10.0.1.0/24\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let layout = ZoneLayout {
+        file_template: "db.{name}",
+        ..ZoneLayout::default()
+    };
+    let files = generate_nsd(dir.path(), &zones.forward, &zones.reverse, 32, layout);
+    let files = files.unwrap();
+    let paths: Vec<_> = files
+        .iter()
+        .map(|file| file.path.strip_prefix(dir.path()).unwrap())
+        .collect();
+    let expected = [
+        "zones.conf",
+        "master/db.1.0.10.in-addr.arpa",
+        "master/db.example.com",
+    ];
+    assert_eq!(paths, expected.map(std::path::Path::new));
+    assert!(files[0]
+        .content
+        .contains("    zonefile: master/db.example.com\n"));
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd"))]
+fn test_split_zones() {
+    use zonefile_rs::output::nsd::{generate_nsd, part_key, split_zone, ZoneLayout};
+
+    assert_eq!(part_key("@", false), None);
+    assert_eq!(part_key("Www.sub", false).as_deref(), Some("w"));
+    assert_eq!(part_key("_sip._tcp", false).as_deref(), Some("_"));
+    assert_eq!(part_key("5.2", true).as_deref(), Some("2"));
+    assert_eq!(part_key("5", true), None);
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      \"@\": 10.0.1.1\n      ns1: 10.0.1.2\n      alpha: [10.0.1.3, 10.0.2.3]\n      beta: 10.0.2.4\nreverse: 10.0.0.0/16\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let (forward, reverse) = (&zones.forward, &zones.reverse);
+    let dir = tempfile::tempdir().unwrap();
+    let layout = ZoneLayout::default();
+    let whole = generate_nsd(dir.path(), forward, reverse, 32, layout).unwrap();
+    assert_eq!(whole.len(), 3);
+
+    let mut files = vec![whole[0].clone()];
+    files.extend(split_zone(whole[1].clone(), &reverse[0].base, true, layout));
+    files.extend(split_zone(
+        whole[2].clone(),
+        &forward[0].base,
+        false,
+        layout,
+    ));
+    let names: Vec<_> = files
+        .iter()
+        .map(|file| {
+            file.path
+                .strip_prefix(dir.path())
+                .unwrap()
+                .to_str()
+                .unwrap()
+        })
+        .collect();
+    let expected = [
+        "zones.conf",
+        "master/0.10.in-addr.arpa.zone",
+        "master/0.10.in-addr.arpa.zone.1",
+        "master/0.10.in-addr.arpa.zone.2",
+        "master/example.com.zone",
+        "master/example.com.zone.a",
+        "master/example.com.zone.b",
+        "master/example.com.zone.n",
+    ];
+    assert_eq!(names, expected);
+
+    let zone = &files[4].content;
+    assert!(
+        zone.contains("@                               A       10.0.1.1\n"),
+        "{zone}"
+    );
+    assert!(zone.ends_with(
+        "\n$INCLUDE master/example.com.zone.a\n$INCLUDE master/example.com.zone.b\n\
+         $INCLUDE master/example.com.zone.n\n"
+    ));
+    assert_eq!(
+        files[5].content,
+        "$ORIGIN example.com.\n$TTL 10800\n\n\
+         alpha                           A       10.0.1.3\n\
+         \x20                               A       10.0.2.3\n"
+    );
+    // The parts hold the same records as the whole zone file
+    let records = |content: &str| {
+        let mut lines: Vec<String> = content
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('$'))
+            .map(str::to_string)
+            .collect();
+        lines.sort();
+        lines
+    };
+    let parts: String = files[1..4]
+        .iter()
+        .map(|file| file.content.as_str())
+        .collect();
+    assert_eq!(records(&parts), records(&whole[1].content));
+
+    // A zone file without records below the apex stays whole
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.net.\nzone:\n  example.com:\n    hosts:\n      \"@\": 10.0.1.1\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let files = generate_nsd(dir.path(), &zones.forward, &[], 32, layout).unwrap();
+    let apex = files[1].clone();
+    assert_eq!(
+        split_zone(apex.clone(), &zones.forward[0].base, false, layout),
+        [apex]
+    );
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd"))]
+fn test_zone_dir() {
+    use zonefile_rs::output::nsd::{generate_nsd, split_zone, ZoneLayout};
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\n      www: 10.0.1.2\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let layout = ZoneLayout {
+        dir: "zones/primary",
+        ..ZoneLayout::default()
+    };
+    assert!(layout.check().is_ok());
+    let files = generate_nsd(dir.path(), &zones.forward, &zones.reverse, 32, layout).unwrap();
+    assert_eq!(
+        files[1].path,
+        dir.path().join("zones/primary/example.com.zone")
+    );
+    assert!(files[0]
+        .content
+        .contains("    zonefile: zones/primary/example.com.zone\n"));
+
+    // The parts are included by the same path zones.conf names the zone by
+    let split = split_zone(files[1].clone(), &zones.forward[0].base, false, layout);
+    assert_eq!(
+        split[1].path,
+        dir.path().join("zones/primary/example.com.zone.n")
+    );
+    assert!(split[0].content.ends_with(
+        "\n$INCLUDE zones/primary/example.com.zone.n\n$INCLUDE zones/primary/example.com.zone.w\n"
+    ));
+
+    for dir in ["", "/srv/zones"] {
+        let err = ZoneLayout { dir, ..layout }.check().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("relative to the NSD config directory"),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "tar", feature = "fs"))]
+fn test_tar_archive() {
+    use zonefile_rs::output::nsd::{generate_nsd, ZoneLayout};
+    use zonefile_rs::output::tar::{archive_change, gzip, render_tar, write_archive};
+    use zonefile_rs::output::FileChange;
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("nsd");
+    let files = generate_nsd(
+        &root,
+        &zones.forward,
+        &zones.reverse,
+        32,
+        ZoneLayout::default(),
+    );
+    let files = files.unwrap();
+    let archive = render_tar(&files, &root, 1_737_763_200).unwrap();
+    // Files are packed in order below the output directory, the same files
+    // always give the same archive
+    assert_eq!(&archive[..11], b"zones.conf\0");
+    assert_eq!(&archive[1024..1048], b"master/example.com.zone\0");
+    assert_eq!(archive, render_tar(&files, &root, 1_737_763_200).unwrap());
+
+    let path = dir.path().join("nsd.tar.gz");
+    let compressed = gzip(&archive);
+    assert_eq!(
+        write_archive(&path, &compressed, None).unwrap(),
+        FileChange::Create
+    );
+    assert_eq!(archive_change(&path, &compressed), FileChange::Unchanged);
+    assert_eq!(
+        write_archive(&path, &archive, None).unwrap(),
+        FileChange::Modify
+    );
+    assert_eq!(std::fs::read(&path).unwrap(), archive);
+    assert!(!root.exists());
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "toml", feature = "unbound"))]
+fn test_unbound_server_options() {
+    use zonefile_rs::output::unbound::generate_unbound;
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nunbound:\n  prefetch: true\n  cache-min-ttl: 300\n  access-control: [10.0.0.0/8 allow, 0.0.0.0/0 refuse]\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let config = generate_unbound(&zones.forward, &zones.reverse, &zones.unbound, 46).unwrap();
+    assert!(
+        config.starts_with(
+            "server:\nprefetch: yes\ncache-min-ttl: 300\n\
+             access-control: 10.0.0.0/8 allow\naccess-control: 0.0.0.0/0 refuse\n\n\
+             local-zone:  example.com. static\n"
+        ),
+        "{config}"
+    );
+
+    let toml = "unbound = { prefetch = false, cache-max-ttl = 86400 }\n";
+    let zones = parse(toml, 2025012500, InputFormat::Toml).unwrap();
+    let config = generate_unbound(&zones.forward, &zones.reverse, &zones.unbound, 46).unwrap();
+    assert_eq!(config, "server:\nprefetch: no\ncache-max-ttl: 86400\n\n");
+
+    let err = parse(
+        &yaml.replace("prefetch", "Prefetch"),
+        2025012500,
+        InputFormat::Yaml,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("not a name like cache-min-ttl"),
+        "{err}"
+    );
+    let yaml = yaml.replace("10.0.0.0/8 allow", "\"10.0.0.0/8 allow # lan\"");
+    let err = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap_err();
+    assert!(
+        err.to_string().contains("Unbound option access-control"),
+        "{err}"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd"))]
+fn test_zones_conf_secondary_zones() {
+    use zonefile_rs::output::nsd::{render_zones_conf, ZoneLayout};
+    use zonefile_rs::tsig::{TsigAlgorithm, TsigKey};
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\nsecondary:\n  - name: example.net\n    masters: [192.0.2.1, 2001:db8::1]\n    tsig-key: partner\n  - name: example.org.\n    masters: 192.0.2.2\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let key = TsigKey {
+        name: "partner".to_string(),
+        algorithm: TsigAlgorithm::HmacSha256,
+        secret: "c2VjcmV0".to_string(),
+        secondaries: vec![],
+    };
+    let secondary = &zones.secondary;
+    let (forward, reverse) = (&zones.forward, &zones.reverse);
+    let layout = ZoneLayout::default();
+    let conf = render_zones_conf(forward, reverse, secondary, &[key], layout).unwrap();
+    assert!(
+        conf.ends_with(
+            "zone:\n    name: example.net.\n    zonefile: secondary/example.net.zone\n\
+             \x20   allow-notify: 192.0.2.1 partner\n    request-xfr: 192.0.2.1 partner\n\
+             \x20   allow-notify: 2001:db8::1 partner\n    request-xfr: 2001:db8::1 partner\n\n\
+             zone:\n    name: example.org.\n    zonefile: secondary/example.org.zone\n\
+             \x20   allow-notify: 192.0.2.2 NOKEY\n    request-xfr: 192.0.2.2 NOKEY\n\n"
+        ),
+        "{conf}"
+    );
+    let err = render_zones_conf(forward, reverse, secondary, &[], layout).unwrap_err();
+    assert!(err.to_string().contains("not in the tsig section"), "{err}");
+
+    let yaml = yaml.replace("example.org.", "Example.COM");
+    let err = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("both served from the config and a secondary"),
+        "{err}"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd", feature = "fs"))]
+fn test_conf_fragments() {
+    use zonefile_rs::output::nsd::{
+        render_conf_fragments, render_zones_conf, stale_fragments, ZoneLayout, KEYS_FRAGMENT,
+    };
+    use zonefile_rs::tsig::{TsigAlgorithm, TsigKey};
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\nreverse: 10.0.1.0/24\nsecondary:\n  - name: example.net\n    masters: 192.0.2.1\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let key = TsigKey {
+        name: "partner".to_string(),
+        algorithm: TsigAlgorithm::HmacSha256,
+        secret: "c2VjcmV0".to_string(),
+        secondaries: vec!["192.0.2.9".parse().unwrap()],
+    };
+    let (forward, reverse, secondary) = (&zones.forward, &zones.reverse, &zones.secondary);
+    let dir = tempfile::tempdir().unwrap();
+    let keys = [key];
+    let layout = ZoneLayout::default();
+    let fragments =
+        render_conf_fragments(dir.path(), forward, reverse, secondary, &keys, layout).unwrap();
+    let names: Vec<_> = fragments
+        .iter()
+        .map(|file| {
+            file.path
+                .strip_prefix(dir.path())
+                .unwrap()
+                .to_str()
+                .unwrap()
+        })
+        .collect();
+    let expected = [
+        KEYS_FRAGMENT,
+        "example.com.conf",
+        "1.0.10.in-addr.arpa.conf",
+        "example.net.conf",
+    ];
+    assert_eq!(names, expected);
+    // Together the fragments are zones.conf
+    let joined: String = fragments.iter().map(|file| file.content.as_str()).collect();
+    let conf = render_zones_conf(forward, reverse, secondary, &keys, layout).unwrap();
+    assert_eq!(joined, conf);
+    assert!(fragments[1]
+        .content
+        .contains("    provide-xfr: 192.0.2.9 partner\n"));
+
+    zonefile_rs::output::write_files(&fragments, None).unwrap();
+    fs::write(dir.path().join("old.example.conf"), "zone:\n").unwrap();
+    fs::write(dir.path().join("README"), "").unwrap();
+    let keep: Vec<_> = fragments.iter().map(|file| file.path.clone()).collect();
+    let stale = stale_fragments(dir.path(), &keep).unwrap();
+    assert_eq!(stale, [dir.path().join("old.example.conf")]);
+    assert!(stale_fragments(&dir.path().join("missing"), &keep)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "unbound", feature = "nsd"))]
+fn test_render_single_zone() {
+    use zonefile_rs::output::nsd::ZoneLayout;
+    use zonefile_rs::output::{nsd, unbound};
+
+    let yaml = fs::read_to_string("zones.yaml").unwrap();
+    let zones = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let zone = &zones.forward[0];
+
+    let preview = unbound::render_forward_zone(zone, 46).unwrap();
+    assert!(preview.starts_with(&format!("local-zone:  {} static\n", zone.base.name)));
+    let full = unbound::generate_unbound(&zones.forward, &zones.reverse, &[], 46).unwrap();
+    assert!(full.contains(&preview));
+
+    let preview = nsd::render_reverse_zone(&zones.reverse[0], 32).unwrap();
+    assert!(preview.starts_with(&format!("$ORIGIN {}\n", zones.reverse[0].base.name)));
+    let dir = tempfile::tempdir().unwrap();
+    let (forward, reverse) = (&zones.forward, &zones.reverse);
+    let files = nsd::generate_nsd(dir.path(), forward, reverse, 32, ZoneLayout::default()).unwrap();
+    assert!(files.iter().any(|file| file.content == preview));
+}
+
+#[test]
+#[cfg(all(feature = "toml", feature = "yaml"))]
+fn test_examples_pass_checks() {
+    use zonefile_rs::check::{mx_targets, soa_timers, srv_targets, Severity};
+
+    for (file, format) in [
+        ("zones.yaml", InputFormat::Yaml),
+        ("zones.toml", InputFormat::Toml),
+    ] {
+        let raw = fs::read_to_string(file).unwrap();
+        let zones = parse(&raw, 2025012500, format).unwrap();
+        assert!(zones.warnings.is_empty(), "{file}: {:?}", zones.warnings);
+        let mut findings = mx_targets(&zones, Severity::Error);
+        findings.extend(srv_targets(&zones));
+        findings.extend(soa_timers(&zones));
+        assert!(findings.is_empty(), "{file}: {findings:?}");
+    }
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "unbound"))]
+fn test_unbound_private_domains() {
+    use zonefile_rs::output::unbound::write_private_domains;
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com: {}\n  lab.home.arpa: {}\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let mut output = Vec::new();
+    write_private_domains(&mut output, &zones.forward).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "private-domain:  \"lab.home.arpa.\"\ndomain-insecure: \"lab.home.arpa.\"\n\n"
+    );
+
+    let mut output = Vec::new();
+    write_private_domains(&mut output, &zones.forward[..1]).unwrap();
+    assert!(output.is_empty());
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd", feature = "unbound"))]
+fn test_reverse_delegation() {
+    use zonefile_rs::output::{nsd, unbound};
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      www: 10.0.1.2\n      lab: 10.0.2.5\nreverse:\n  10.0.0.0/16:\n    delegate:\n      10.0.2.0/24: [ns1.lab.example., ns2.lab.example.]\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    // The PTR of the delegated host belongs to the child zone
+    assert_eq!(zones.reverse[0].ptr.len(), 1);
+    assert!(zones.warnings.is_empty(), "{:?}", zones.warnings);
+
+    let zone = nsd::render_reverse_zone(&zones.reverse[0], 32).unwrap();
+    assert!(
+        zone.contains("\n2                               NS      ns1.lab.example.\n"),
+        "{zone}"
+    );
+    assert!(
+        zone.contains("\n2                               NS      ns2.lab.example.\n"),
+        "{zone}"
+    );
+
+    let config = unbound::generate_unbound(&zones.forward, &zones.reverse, &[], 32).unwrap();
+    assert!(config.contains("local-zone:      2.0.10.in-addr.arpa. transparent\n"));
+    assert!(config.ends_with("stub-zone:\n    name: \"2.0.10.in-addr.arpa.\"\n    stub-host: \"ns1.lab.example.\"\n    stub-host: \"ns2.lab.example.\"\n\n"), "{config}");
+
+    for (delegate, error) in [
+        (
+            "10.0.0.0/16",
+            "Delegated network 10.0.0.0/16 is not part of 10.0.0.0/16",
+        ),
+        (
+            "10.0.2.128/25",
+            "Delegated network 10.0.2.128/25 is no zone of its own below 0.10.in-addr.arpa.",
+        ),
+    ] {
+        let yaml = yaml.replace("10.0.2.0/24", delegate);
+        let err = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap_err();
+        assert!(err.to_string().contains(error), "{err}");
+    }
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd", feature = "unbound"))]
+fn test_record_comments() {
+    use zonefile_rs::output::{nsd, unbound};
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    mx:\n      name: mail\n      comment: relay until 2026\n    hosts:\n      mail: 10.0.0.25\n      www:\n        ip: 10.0.0.80\n        comment: rack 4, ticket OPS-12\n    cname:\n      web:\n        target: www\n        comment: old name\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+
+    let zone = nsd::render_forward_zone(&zones.forward[0], 32).unwrap();
+    for line in [
+        "                                MX    0 mail.example.com. ; relay until 2026\n",
+        "www                             A       10.0.0.80 ; rack 4, ticket OPS-12\n",
+        "web                             CNAME   www.example.com. ; old name\n",
+    ] {
+        assert!(zone.contains(line), "{zone}");
+    }
+    assert!(
+        zone.contains("mail                            A       10.0.0.25\n"),
+        "{zone}"
+    );
+
+    let config = unbound::render_forward_zone(&zones.forward[0], 46).unwrap();
+    assert!(
+        config.contains("IN A    10.0.0.80\" # rack 4, ticket OPS-12\n"),
+        "{config}"
+    );
+    assert!(
+        config.contains("CNAME   www.example.com.\" # old name\n"),
+        "{config}"
+    );
+
+    let yaml = yaml.replace("comment: old name", "comment: \"old\\nname\"");
+    let err = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap_err();
+    assert!(err.to_string().contains("spans several lines"), "{err}");
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "toml"))]
+fn test_annotate_origins() {
+    use zonefile_rs::origin::annotate;
+    use zonefile_rs::output::{nsd, unbound};
+    use zonefile_rs::parser::{parse_config, resolve};
+
+    let yaml = "vars:\n  lan: 10.0.0\n  web: www\ndefaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: '{{vars.lan}}.1'\n      '{{vars.web}}': '{{vars.lan}}.5'\n      node-[1-2]:\n        ip: 10.0.0.[10-11]\n        comment: rack 4\n    cname:\n      web: www\nreverse:\n  - 10.0.0.0/24\n";
+    let content = parse_config(yaml, InputFormat::Yaml).unwrap();
+    let mut zones = resolve(content.clone(), 2025012500).unwrap();
+    annotate(&mut zones, &content).unwrap();
+
+    let zone = nsd::render_forward_zone(&zones.forward[0], 32).unwrap();
+    for line in [
+        "                                NS      ns1.example.com. ; input:8\n",
+        "node-2                          A       10.0.0.11 ; rack 4 (input:12)\n",
+        "ns1                             A       10.0.0.1 ; input:10\n",
+        "web                             CNAME   www.example.com. ; input:16\n",
+        "www                             A       10.0.0.5 ; input:11\n",
+    ] {
+        assert!(zone.contains(line), "{zone}");
+    }
+    let reverse = unbound::render_reverse_zone(&zones.reverse[0], 46).unwrap();
+    assert!(
+        reverse.contains("www.example.com.\" # input:11\n"),
+        "{reverse}"
+    );
+
+    let toml = "[defaults]\nemail = \"admin@example.com\"\nnameserver = \"ns1.example.com.\"\n\n[[zone]]\nname = \"example.com\"\n\n[zone.hosts]\nns1 = \"10.0.0.1\"\n";
+    let content = parse_config(toml, InputFormat::Toml).unwrap();
+    let mut zones = resolve(content.clone(), 2025012500).unwrap();
+    annotate(&mut zones, &content).unwrap();
+    let zone = nsd::render_forward_zone(&zones.forward[0], 32).unwrap();
+    assert!(
+        zone.contains("NS      ns1.example.com. ; input:5\n"),
+        "{zone}"
+    );
+    assert!(zone.contains("ns1                             A       10.0.0.1 ; input:9\n"));
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "dnssec"))]
+fn test_sign_zones() {
+    use zonefile_rs::dnssec::keys::{FLAGS_KSK, FLAGS_ZSK};
+    use zonefile_rs::dnssec::{sign_forward, sign_reverse, Key, Window};
+    use zonefile_rs::output::nsd;
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\n      www: 10.0.1.2\n      lab: 10.0.2.5\nreverse:\n  10.0.0.0/16:\n    delegate:\n      10.0.2.0/24: ns1.lab.example.\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let ksk = Key::from_seed("example.com.", FLAGS_KSK, [1; 32]);
+    let zsk = Key::from_seed("example.com.", FLAGS_ZSK, [2; 32]);
+    let window = Window {
+        inception: 1737763200,
+        expiration: 1740355200,
+    };
+
+    let signed = sign_forward(&zones.forward[0], &[ksk, zsk], window, window.inception).unwrap();
+    let records = signed.records;
+    let count = |record_type| {
+        records
+            .iter()
+            .filter(|r| r.record_type == record_type)
+            .count()
+    };
+    assert_eq!(count("DNSKEY"), 2);
+    // CDS and CDNSKEY of the KSK only
+    assert_eq!(count("CDS"), 1);
+    assert_eq!(count("CDNSKEY"), 1);
+    let cds = records.iter().find(|r| r.record_type == "CDS").unwrap();
+    assert_eq!(cds.data, signed.ds[0].data());
+    // apex, ns1, www and lab, each with one NSEC
+    assert_eq!(count("NSEC"), 4);
+    // The ZSK signs SOA, NS and NSEC of the apex and A and NSEC of each
+    // host, the KSK the DNSKEY, CDS and CDNSKEY records
+    assert_eq!(count("RRSIG"), 12);
+    let nsec: Vec<_> = records
+        .iter()
+        .filter(|r| r.record_type == "NSEC")
+        .map(|r| format!("{} {}", r.owner, r.data))
+        .collect();
+    assert_eq!(
+        nsec,
+        [
+            "example.com. lab.example.com. NS SOA RRSIG NSEC DNSKEY CDS CDNSKEY",
+            "lab.example.com. ns1.example.com. A RRSIG NSEC",
+            "ns1.example.com. www.example.com. A RRSIG NSEC",
+            "www.example.com. example.com. A RRSIG NSEC",
+        ]
+    );
+    let dnskey_sig = records
+        .iter()
+        .find(|r| r.record_type == "RRSIG" && r.data.starts_with("DNSKEY "))
+        .unwrap();
+    let ksk_tag = Key::from_seed("example.com.", FLAGS_KSK, [1; 32]).tag;
+    assert!(
+        dnskey_sig.data.starts_with(&format!(
+            "DNSKEY 15 2 10800 20250224000000 20250125000000 {ksk_tag} example.com. "
+        )),
+        "{}",
+        dnskey_sig.data
+    );
+
+    // The NS records of the delegation stay unsigned
+    let reverse = &zones.reverse[0];
+    let key = Key::from_seed(&reverse.base.name, FLAGS_KSK, [3; 32]);
+    let signed = sign_reverse(reverse, &[key], window, window.inception).unwrap();
+    let delegation: Vec<_> = signed
+        .records
+        .iter()
+        .filter(|r| r.owner == "2.0.10.in-addr.arpa.")
+        .map(|r| r.data.split(' ').next().unwrap().to_string())
+        .collect();
+    // Last name in canonical order, so its NSEC leads back to the apex
+    assert_eq!(delegation, ["0.10.in-addr.arpa.", "NSEC"]);
+
+    let zone = nsd::render_signatures(&reverse.base, &signed, 32).unwrap();
+    assert!(
+        zone.starts_with(&format!(
+            "\n; DNSSEC\n; DS records for the parent zone:\n;   {}\n@                               DNSKEY  257 3 15 ",
+            signed.ds[0]
+        )),
+        "{zone}"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "dnssec"))]
+fn test_external_keys() {
+    use zonefile_rs::dnssec::external_keys;
+    use zonefile_rs::dnssec::keys::parse_dnskeys;
+    use zonefile_rs::output::nsd;
+
+    let yaml = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    hosts:\n      ns1: 10.0.1.1\n";
+    let zones = parse(yaml, 2025012500, InputFormat::Yaml).unwrap();
+    let keys = parse_dnskeys(
+        "example.com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=\n\
+         example.com. 3600 IN DNSKEY 256 3 15 zPnZ/QwEe7S8C5SPz2OfS5RR40ATk2/rYnE9xHIEijs=",
+    )
+    .unwrap();
+    let base = &zones.forward[0].base;
+    let zone = nsd::render_signatures(base, &external_keys(base, &keys), 32).unwrap();
+    // Only the DNSKEY records, the external signer adds everything else
+    assert_eq!(
+        zone,
+        "\n; DNSSEC\n; DS records for the parent zone:\n\
+         ;   example.com. IN DS 3613 15 2 3aa5ab37efce57f737fc1627013fee07bdf241bd10f3b1964ab55c78e79a304b\n\
+         @                               DNSKEY  257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=\n\
+         @                               DNSKEY  256 3 15 zPnZ/QwEe7S8C5SPz2OfS5RR40ATk2/rYnE9xHIEijs=\n"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd", feature = "unbound"))]
+fn test_output_independent_of_config_order() {
+    use zonefile_rs::output::{nsd, unbound};
+
+    let head = "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n";
+    let first = format!(
+        "{head}    mx: [{{ name: mx2, prio: 20 }}, {{ name: mx1, prio: 10 }}]\n    hosts:\n      www: [10.0.0.2, 10.0.0.1]\n      mx1: 10.0.0.25\n      mx2: 10.0.0.26\n    cname:\n      web: www\n      blog: www\n    srv:\n      _sip._udp: {{ port: 5060, target: www }}\n      _ldap._tcp: {{ port: 389, target: www }}\n"
+    );
+    let second = format!(
+        "{head}    mx: [{{ name: mx1, prio: 10 }}, {{ name: mx2, prio: 20 }}]\n    hosts:\n      mx2: 10.0.0.26\n      mx1: 10.0.0.25\n      www: [10.0.0.1, 10.0.0.2]\n    cname:\n      blog: www\n      web: www\n    srv:\n      _ldap._tcp: {{ port: 389, target: www }}\n      _sip._udp: {{ port: 5060, target: www }}\n"
+    );
+    let first = parse(&first, 2025012500, InputFormat::Yaml).unwrap();
+    let second = parse(&second, 2025012500, InputFormat::Yaml).unwrap();
+
+    let zone = nsd::render_forward_zone(&first.forward[0], 32).unwrap();
+    assert_eq!(
+        zone,
+        nsd::render_forward_zone(&second.forward[0], 32).unwrap()
+    );
+    assert!(
+        zone.find("MX   10").unwrap() < zone.find("MX   20").unwrap(),
+        "{zone}"
+    );
+    assert!(
+        zone.find("blog").unwrap() < zone.find("web ").unwrap(),
+        "{zone}"
+    );
+    assert_eq!(
+        unbound::render_forward_zone(&first.forward[0], 46).unwrap(),
+        unbound::render_forward_zone(&second.forward[0], 46).unwrap()
+    );
+}
+
+#[test]
+#[cfg(all(feature = "yaml", feature = "nsd", feature = "unbound"))]
+fn test_output_independent_of_list_order() {
+    use zonefile_rs::output::{nsd, unbound};
+
+    // Every order of the apex addresses and of the nameservers of a child zone
+    let apex = [
+        ["10.0.0.1", "10.0.0.2", "2001:db8::1"],
+        ["2001:db8::1", "10.0.0.2", "10.0.0.1"],
+    ];
+    let ns = [
+        ["ns1.example.net.", "ns2.example.net."],
+        ["ns2.example.net.", "ns1.example.net."],
+    ];
+    let render = |apex: &[&str; 3], ns: &[&str; 2]| {
+        let yaml = format!(
+            "defaults:\n  email: admin@example.com\n  nameserver: ns1.example.com.\nzone:\n  example.com:\n    mail:\n      host: mx\n    hosts:\n      \"@\": [{}]\n      mx: 10.0.0.25\n    delegate:\n      lab: [{}]\nreverse: 10.0.0.0/24\n",
+            apex.join(", "),
+            ns.join(", ")
+        );
+        let zones = parse(&yaml, 2025012500, InputFormat::Yaml).unwrap();
+        let (forward, reverse) = (&zones.forward, &zones.reverse);
+        (
+            nsd::render_forward_zone(&forward[0], 32).unwrap(),
+            nsd::render_reverse_zone(&reverse[0], 32).unwrap(),
+            unbound::generate_unbound(forward, reverse, &[], 46).unwrap(),
+        )
+    };
+    let expected = render(&apex[0], &ns[0]);
+    assert!(expected.0.contains(
+        "@                               A       10.0.0.1\n\
+         \x20                               A       10.0.0.2\n"
+    ));
+    for apex in &apex {
+        for ns in &ns {
+            assert_eq!(render(apex, ns), expected, "{apex:?} {ns:?}");
+        }
+    }
+}